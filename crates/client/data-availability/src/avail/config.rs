@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
+use crate::compression::Compression;
 use crate::DaMode;
 
 const DEFAULT_AVAIL_WS: &str = "wss://kate.avail.tools:443/ws";
@@ -22,6 +23,11 @@ pub struct AvailConfig {
     pub seed: String,
     #[serde(default)]
     pub mode: DaMode,
+    #[serde(default)]
+    pub compression: Compression,
+    /// Avail block from which the published state diff headers are looked up, usually the best
+    /// block when the chain started publishing to the application
+    pub first_block: u32,
 }
 
 impl TryFrom<&PathBuf> for AvailConfig {
@@ -47,15 +53,3 @@ fn default_validate_codegen() -> bool {
 fn default_seed() -> String {
     DEFAULT_AVAIL_SEED.to_string()
 }
-
-impl Default for AvailConfig {
-    fn default() -> Self {
-        Self {
-            ws_provider: default_ws(),
-            app_id: default_app_id(),
-            mode: DaMode::default(),
-            validate_codegen: default_validate_codegen(),
-            seed: default_seed(),
-        }
-    }
-}
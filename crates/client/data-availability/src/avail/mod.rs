@@ -1,5 +1,7 @@
 pub mod config;
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use avail_subxt::api::runtime_types::avail_core::AppId;
@@ -11,8 +13,10 @@ use avail_subxt::{api as AvailApi, build_client, AvailConfig, Call};
 use ethers::types::{I256, U256};
 use sp_core::H256;
 use subxt::ext::sp_core::sr25519::Pair;
+use tokio::sync::Mutex;
 
-use crate::utils::get_bytes_from_state_diff;
+use crate::compression::{compress, Compression};
+use crate::header::StateDiffHeader;
 use crate::{DaClient, DaMode};
 
 type AvailPairSigner = subxt::tx::PairSigner<AvailConfig, Pair>;
@@ -23,12 +27,21 @@ pub struct AvailClient {
     app_id: AppId,
     signer: AvailPairSigner,
     mode: DaMode,
+    compression: Compression,
+    /// Latest state diff header found for our app id, so that blocks are only scanned once
+    last_header: Arc<Mutex<HeaderScan>>,
+}
+
+struct HeaderScan {
+    /// Last Avail block scanned for state diff headers
+    scanned_block: u32,
+    header: Option<StateDiffHeader>,
 }
 
 #[async_trait]
 impl DaClient for AvailClient {
-    async fn publish_state_diff(&self, state_diff: Vec<U256>) -> Result<()> {
-        let bytes = get_bytes_from_state_diff(&state_diff);
+    async fn publish_state_diff(&self, header: StateDiffHeader, state_diff: Vec<U256>) -> Result<()> {
+        let bytes = header.prefix(&compress(&state_diff, self.compression)?);
         let bytes = BoundedVec(bytes);
 
         let submitted_block_hash = self.publish_data(&bytes).await?;
@@ -37,15 +50,47 @@ impl DaClient for AvailClient {
         Ok(())
     }
 
-    // state diff can be published w/o verification of last state for the time being
-    // may change in subsequent DaMode implementations
     async fn last_published_state(&self) -> Result<I256> {
-        Ok(I256::from(1))
+        let finalized_head = self.ws_client.rpc().finalized_head().await?;
+        let head_number = self
+            .ws_client
+            .rpc()
+            .header(Some(finalized_head))
+            .await?
+            .ok_or(anyhow::anyhow!("Finalized block header not found"))?
+            .number;
+
+        let mut last_header = self.last_header.lock().await;
+        for number in last_header.scanned_block + 1..=head_number {
+            let block_hash = self
+                .ws_client
+                .rpc()
+                .block_hash(Some(number.into()))
+                .await?
+                .ok_or(anyhow::anyhow!("Block #{number} not found"))?;
+
+            // Data without a header was not published by Madara
+            for data in self.submitted_data(block_hash).await? {
+                if let Ok((header, _)) = StateDiffHeader::split(&data.0) {
+                    last_header.header = Some(header);
+                }
+            }
+            last_header.scanned_block = number;
+        }
+
+        match last_header.header {
+            Some(header) => Ok(I256::from(header.block_number)),
+            None => Ok(I256::minus_one()),
+        }
     }
 
     fn get_mode(&self) -> DaMode {
         self.mode
     }
+
+    fn checks_publication_gaps(&self) -> bool {
+        true
+    }
 }
 
 impl AvailClient {
@@ -63,6 +108,28 @@ impl AvailClient {
         Ok(events.block_hash())
     }
 
+    /// Return the data submitted with our app id in the given block, in extrinsic order
+    async fn submitted_data(&self, block_hash: H256) -> Result<Vec<BoundedVec<u8>>> {
+        let block = self
+            .ws_client
+            .rpc()
+            .block(Some(block_hash))
+            .await?
+            .ok_or(anyhow::anyhow!("Invalid hash, block not found"))?;
+
+        Ok(block
+            .block
+            .extrinsics
+            .into_iter()
+            .filter_map(|chain_block_ext| AppUncheckedExtrinsic::try_from(chain_block_ext).ok())
+            .filter(|ext| ext.app_id().0 == self.app_id.0)
+            .filter_map(|ext| match ext.function {
+                Call::DataAvailability(DaCall::submit_data { data }) => Some(data),
+                _ => None,
+            })
+            .collect())
+    }
+
     async fn verify_bytes_inclusion(&self, block_hash: H256, bytes: &BoundedVec<u8>) -> Result<()> {
         let submitted_block = self
             .ws_client
@@ -98,7 +165,10 @@ impl TryFrom<config::AvailConfig> for AvailClient {
             futures::executor::block_on(async { build_client(conf.ws_provider.as_str(), conf.validate_codegen).await })
                 .map_err(|e| anyhow::anyhow!("could not initialize ws endpoint {e}"))?;
 
-        Ok(Self { ws_client, app_id, signer, mode: conf.mode })
+        let last_header =
+            Arc::new(Mutex::new(HeaderScan { scanned_block: conf.first_block.saturating_sub(1), header: None }));
+
+        Ok(Self { ws_client, app_id, signer, mode: conf.mode, compression: conf.compression, last_header })
     }
 }
 
@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+pub const DEFAULT_BATCH_SIZE: usize = 1;
+
+/// Batching of the state diffs submitted to the DA layer
+///
+/// Read from the DA config, alongside the settings of the DA layer. The state diffs of
+/// consecutive blocks are merged and submitted together once `batch_size` blocks are waiting, or
+/// once the oldest of them has been waiting for `batch_interval` seconds.
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct BatchConfig {
+    /// Maximum number of blocks covered by a submission
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Maximum number of seconds to wait for a batch to be full, no limit if 0
+    #[serde(default)]
+    pub batch_interval: u64,
+}
+
+impl BatchConfig {
+    pub fn interval(&self) -> Option<Duration> {
+        (self.batch_interval > 0).then(|| Duration::from_secs(self.batch_interval))
+    }
+}
+
+impl TryFrom<&PathBuf> for BatchConfig {
+    type Error = String;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        let file = File::open(path).map_err(|e| format!("error opening da config: {e}"))?;
+        let conf: Self = serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))?;
+
+        if conf.batch_size == 0 {
+            return Err(String::from("error parsing da config: batch_size must be at least 1"));
+        }
+
+        Ok(conf)
+    }
+}
+
+fn default_batch_size() -> usize {
+    DEFAULT_BATCH_SIZE
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self { batch_size: default_batch_size(), batch_interval: 0 }
+    }
+}
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
+use crate::compression::Compression;
 use crate::DaMode;
 
 pub const DEFAULT_CELESTIA_NODE: &str = "127.0.0.1:26658";
@@ -20,6 +21,11 @@ pub struct CelestiaConfig {
     pub auth_token: Option<String>,
     #[serde(default)]
     pub mode: DaMode,
+    #[serde(default)]
+    pub compression: Compression,
+    /// Celestia height from which the published state diffs and their headers are read back,
+    /// usually the network head when the chain started publishing to the namespace
+    pub first_height: u64,
 }
 
 impl TryFrom<&PathBuf> for CelestiaConfig {
@@ -42,15 +48,3 @@ fn default_ws() -> String {
 fn default_nid() -> String {
     DEFAULT_NID.to_string()
 }
-
-impl Default for CelestiaConfig {
-    fn default() -> Self {
-        Self {
-            http_provider: default_http(),
-            ws_provider: default_ws(),
-            nid: default_nid(),
-            mode: DaMode::default(),
-            auth_token: None,
-        }
-    }
-}
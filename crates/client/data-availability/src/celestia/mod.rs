@@ -1,27 +1,48 @@
 pub mod config;
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use celestia_rpc::client::new_http;
 use celestia_rpc::{BlobClient, HeaderClient};
 use celestia_types::nmt::Namespace;
-use celestia_types::{Blob, Result as CelestiaTypesResult};
+use celestia_types::Blob;
 use ethers::types::{I256, U256};
 use jsonrpsee::http_client::HttpClient;
+use tokio::sync::Mutex;
+
+use crate::compression::{compress, decompress, Compression};
+use crate::header::StateDiffHeader;
+use crate::{DaClient, DaMode, DaReader};
 
-use crate::{DaClient, DaMode};
+/// Message of the error returned by the Celestia node for a height without any blob in the
+/// requested namespaces
+const BLOB_NOT_FOUND: &str = "blob: not found";
 
 #[derive(Clone, Debug)]
 pub struct CelestiaClient {
     http_client: HttpClient,
     nid: Namespace,
     mode: DaMode,
+    first_height: u64,
+    compression: Compression,
+    /// Latest state diff header found in the namespace, so that heights are only scanned once
+    last_header: Arc<Mutex<HeaderScan>>,
+}
+
+#[derive(Debug)]
+struct HeaderScan {
+    /// Last Celestia height scanned for state diff headers, if any has been scanned yet
+    scanned_height: Option<u64>,
+    header: Option<StateDiffHeader>,
 }
 
 #[async_trait]
 impl DaClient for CelestiaClient {
-    async fn publish_state_diff(&self, state_diff: Vec<U256>) -> Result<()> {
-        let blob = self.get_blob_from_state_diff(state_diff).map_err(|e| anyhow::anyhow!("celestia error: {e}"))?;
+    async fn publish_state_diff(&self, header: StateDiffHeader, state_diff: Vec<U256>) -> Result<()> {
+        let blob =
+            self.get_blob_from_state_diff(header, state_diff).map_err(|e| anyhow::anyhow!("celestia error: {e}"))?;
         let submitted_height = self.publish_data(&blob).await.map_err(|e| anyhow::anyhow!("celestia error: {e}"))?;
 
         // blocking call, awaiting on server side (Celestia Node) that a block with our data is included
@@ -38,30 +59,103 @@ impl DaClient for CelestiaClient {
     }
 
     async fn last_published_state(&self) -> Result<I256> {
-        Ok(I256::from(1))
+        let network_head = self.network_head().await?;
+
+        let mut last_header = self.last_header.lock().await;
+        let scanned_height = match last_header.scanned_height {
+            Some(scanned_height) => scanned_height,
+            // The latest header is looked up backwards from the network head, so that the whole
+            // history of the namespace is only scanned while nothing is published
+            None => {
+                for height in (self.first_height..=network_head).rev() {
+                    if let Some(header) = self.last_header_at(height).await? {
+                        last_header.header = Some(header);
+                        break;
+                    }
+                }
+                network_head
+            }
+        };
+        for height in scanned_height + 1..=network_head {
+            if let Some(header) = self.last_header_at(height).await? {
+                last_header.header = Some(header);
+            }
+        }
+        last_header.scanned_height = Some(network_head);
+
+        match last_header.header {
+            Some(header) => Ok(I256::from(header.block_number)),
+            None => Ok(I256::minus_one()),
+        }
     }
 
     fn get_mode(&self) -> DaMode {
         self.mode
     }
+
+    fn checks_publication_gaps(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl DaReader for CelestiaClient {
+    async fn published_state_diffs(&self) -> Result<Vec<Vec<U256>>> {
+        let network_head = self.network_head().await?;
+
+        let mut state_diffs = Vec::new();
+        for height in self.first_height..=network_head {
+            for blob in self.blobs_at(height).await? {
+                let (_, payload) = StateDiffHeader::split(&blob.data)?;
+                state_diffs.push(decompress(payload, self.compression)?);
+            }
+        }
+
+        Ok(state_diffs)
+    }
 }
 
 impl CelestiaClient {
+    async fn network_head(&self) -> Result<u64> {
+        Ok(self
+            .http_client
+            .header_network_head()
+            .await
+            .map_err(|e| anyhow::anyhow!("celestia da error: {e}"))?
+            .height()
+            .value())
+    }
+
+    /// Return the blobs of our namespace at the given height, in submission order
+    async fn blobs_at(&self, height: u64) -> Result<Vec<Blob>> {
+        match self.http_client.blob_get_all(height, &[self.nid]).await {
+            Ok(blobs) => Ok(blobs),
+            // Heights without any blob in our namespace, the error being possibly wrapped by the node
+            Err(jsonrpsee::core::Error::Call(e)) if e.message().ends_with(BLOB_NOT_FOUND) => Ok(Vec::new()),
+            Err(e) => Err(anyhow::anyhow!("celestia da error at height {height}: {e}")),
+        }
+    }
+
+    /// Return the last state diff header published at the given height, if any
+    async fn last_header_at(&self, height: u64) -> Result<Option<StateDiffHeader>> {
+        // Blobs without a header were not published by Madara
+        Ok(self
+            .blobs_at(height)
+            .await?
+            .iter()
+            .filter_map(|blob| StateDiffHeader::split(&blob.data).ok())
+            .last()
+            .map(|(header, _)| header))
+    }
+
     async fn publish_data(&self, blob: &Blob) -> Result<u64> {
         self.http_client.blob_submit(&[blob.clone()]).await.map_err(|e| anyhow::anyhow!("could not submit blob {e}"))
     }
 
-    fn get_blob_from_state_diff(&self, state_diff: Vec<U256>) -> CelestiaTypesResult<Blob> {
-        let state_diff_bytes: Vec<u8> = state_diff
-            .iter()
-            .flat_map(|item| {
-                let mut bytes = [0_u8; 32];
-                item.to_big_endian(&mut bytes);
-                bytes.to_vec()
-            })
-            .collect();
-
-        Blob::new(self.nid, state_diff_bytes)
+    fn get_blob_from_state_diff(&self, header: StateDiffHeader, state_diff: Vec<U256>) -> Result<Blob> {
+        let state_diff_bytes = compress(&state_diff, self.compression)?;
+
+        Ok(Blob::new(self.nid, header.prefix(&state_diff_bytes))?)
     }
 
     async fn verify_blob_was_included(&self, submitted_height: u64, blob: Blob) -> Result<()> {
@@ -84,6 +178,15 @@ impl TryFrom<config::CelestiaConfig> for CelestiaClient {
         // Create a new Namespace from these bytes
         let nid = Namespace::new_v0(bytes).map_err(|e| anyhow::anyhow!("could not init namespace: {e}"))?;
 
-        Ok(Self { http_client, nid, mode: conf.mode })
+        let last_header = Arc::new(Mutex::new(HeaderScan { scanned_height: None, header: None }));
+
+        Ok(Self {
+            http_client,
+            nid,
+            mode: conf.mode,
+            first_height: conf.first_height,
+            compression: conf.compression,
+            last_header,
+        })
     }
 }
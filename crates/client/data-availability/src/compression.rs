@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Result};
+use ethers::types::U256;
+use serde::Deserialize;
+
+use crate::utils::{get_bytes_from_state_diff, get_state_diff_from_bytes};
+
+/// zstd level used to compress state diffs
+const ZSTD_LEVEL: i32 = 19;
+/// Maximum size of a decompressed state diff, in bytes, so that a small malicious payload can't
+/// exhaust the memory of its reader
+const MAX_DECOMPRESSED_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Compression applied to the state diffs before they are submitted to a DA layer.
+///
+/// Compressed data starts with a version byte, the discriminant of the compression, so that
+/// readers know how to decode it. Uncompressed data has no version byte, and is published as it
+/// was before compression was introduced. Only the blob based DA layers compress state diffs, as
+/// the Ethereum core contract expects the program output as is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Default)]
+pub enum Compression {
+    /// The 32 bytes words of the state diff, as is
+    #[serde(rename = "none")]
+    #[default]
+    None = 0,
+    /// zstd over the 32 bytes words of the state diff
+    #[serde(rename = "zstd")]
+    Zstd = 1,
+    /// Stateful compression, followed by zstd
+    ///
+    /// Every distinct word of the state diff is stored once, in its shortest big endian form, and
+    /// the state diff is encoded as the indices of its words. Repeated addresses and storage keys
+    /// then only take a few bytes each.
+    #[serde(rename = "stateful")]
+    Stateful = 2,
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = anyhow::Error;
+
+    fn try_from(version: u8) -> Result<Self> {
+        match version {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Zstd),
+            2 => Ok(Compression::Stateful),
+            version => Err(anyhow!("unknown state diff compression version {version}")),
+        }
+    }
+}
+
+/// Compress a state diff, prefixing it with the version byte of the compression unless it is
+/// [`Compression::None`]
+pub fn compress(state_diff: &[U256], compression: Compression) -> Result<Vec<u8>> {
+    let payload = match compression {
+        Compression::None => return Ok(get_bytes_from_state_diff(state_diff)),
+        Compression::Zstd => zstd::encode_all(get_bytes_from_state_diff(state_diff).as_slice(), ZSTD_LEVEL)?,
+        Compression::Stateful => zstd::encode_all(stateful_encode(state_diff).as_slice(), ZSTD_LEVEL)?,
+    };
+
+    Ok([&[compression as u8], payload.as_slice()].concat())
+}
+
+/// Decompress a state diff compressed with [`compress`], by a DA client configured with
+/// `compression`
+///
+/// Compressed state diffs are decoded according to their version byte, whichever compression
+/// is configured, but uncompressed ones can only be told apart by the configuration.
+pub fn decompress(data: &[u8], compression: Compression) -> Result<Vec<U256>> {
+    if compression == Compression::None {
+        return get_state_diff_from_bytes(data);
+    }
+    let (version, payload) = data.split_first().ok_or_else(|| anyhow!("compressed state diff is empty"))?;
+
+    match Compression::try_from(*version)? {
+        Compression::None => bail!("uncompressed state diffs have no version byte"),
+        Compression::Zstd => get_state_diff_from_bytes(&zstd_decode(payload)?),
+        Compression::Stateful => stateful_decode(&zstd_decode(payload)?),
+    }
+}
+
+/// Decode zstd compressed data, failing if it decompresses to more than `MAX_DECOMPRESSED_SIZE`
+fn zstd_decode(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    zstd::stream::read::Decoder::new(payload)?.take(MAX_DECOMPRESSED_SIZE + 1).read_to_end(&mut decoded)?;
+    if decoded.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        bail!("state diff decompresses to more than {MAX_DECOMPRESSED_SIZE} bytes");
+    }
+
+    Ok(decoded)
+}
+
+/// Encode the state diff as a dictionary of its distinct words followed by the indices of its words
+///
+/// Layout: `n_words_in_dict, (len, word)*, n_words, index*`, where counts and indices are LEB128
+/// encoded, and each dictionary word is written in `len` big endian bytes.
+fn stateful_encode(state_diff: &[U256]) -> Vec<u8> {
+    let mut dictionary: Vec<U256> = Vec::new();
+    let mut indices: HashMap<U256, u64> = HashMap::new();
+    let word_indices: Vec<u64> = state_diff
+        .iter()
+        .map(|word| {
+            *indices.entry(*word).or_insert_with(|| {
+                dictionary.push(*word);
+                dictionary.len() as u64 - 1
+            })
+        })
+        .collect();
+
+    let mut encoded = Vec::new();
+    write_leb128(&mut encoded, dictionary.len() as u64);
+    for word in dictionary {
+        let mut bytes = [0_u8; 32];
+        word.to_big_endian(&mut bytes);
+        let len = 32 - word.leading_zeros() as usize / 8;
+        encoded.push(len as u8);
+        encoded.extend_from_slice(&bytes[32 - len..]);
+    }
+    write_leb128(&mut encoded, word_indices.len() as u64);
+    word_indices.into_iter().for_each(|index| write_leb128(&mut encoded, index));
+
+    encoded
+}
+
+fn stateful_decode(mut encoded: &[u8]) -> Result<Vec<U256>> {
+    let dictionary_len = read_leb128(&mut encoded)?;
+    let mut dictionary = Vec::new();
+    for _ in 0..dictionary_len {
+        let (len, rest) = encoded.split_first().ok_or_else(|| anyhow!("truncated state diff dictionary"))?;
+        let len = *len as usize;
+        if len > 32 || rest.len() < len {
+            bail!("invalid state diff dictionary word");
+        }
+        dictionary.push(U256::from_big_endian(&rest[..len]));
+        encoded = &rest[len..];
+    }
+
+    let n_words = read_leb128(&mut encoded)?;
+    let mut state_diff = Vec::new();
+    for _ in 0..n_words {
+        let index = read_leb128(&mut encoded)?;
+        let word = dictionary.get(index as usize).ok_or_else(|| anyhow!("invalid state diff word index {index}"))?;
+        state_diff.push(*word);
+    }
+
+    if !encoded.is_empty() {
+        bail!("unexpected data after the end of the state diff");
+    }
+
+    Ok(state_diff)
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(input: &mut &[u8]) -> Result<u64> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = input.split_first().ok_or_else(|| anyhow!("truncated LEB128 integer"))?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(anyhow!("LEB128 integer overflows 64 bits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_diff() -> Vec<U256> {
+        let address =
+            U256::from_dec_str("2087021424722619777119509474943472645767659996348769578120564519014510906823").unwrap();
+        vec![
+            U256::from(1),
+            address,
+            (U256::from(2) << 64) | U256::from(2),
+            U256::from(7),
+            U256::MAX,
+            address,
+            U256::zero(),
+        ]
+    }
+
+    #[test]
+    fn decompress_reverts_compress() {
+        for compression in [Compression::None, Compression::Zstd, Compression::Stateful] {
+            let compressed = compress(&state_diff(), compression).unwrap();
+
+            assert_eq!(decompress(&compressed, compression).unwrap(), state_diff());
+        }
+    }
+
+    #[test]
+    fn only_compressed_state_diffs_have_a_version_byte() {
+        assert_eq!(compress(&state_diff(), Compression::None).unwrap(), get_bytes_from_state_diff(&state_diff()));
+
+        let compressed = compress(&state_diff(), Compression::Zstd).unwrap();
+        assert_eq!(compressed[0], Compression::Zstd as u8);
+        // The compression of the published state diffs can change
+        assert_eq!(decompress(&compressed, Compression::Stateful).unwrap(), state_diff());
+    }
+
+    #[test]
+    fn decompressed_size_is_bounded() {
+        let zeros = std::io::repeat(0).take(MAX_DECOMPRESSED_SIZE + 32);
+        let bomb = [&[Compression::Zstd as u8], zstd::encode_all(zeros, 1).unwrap().as_slice()].concat();
+
+        assert!(decompress(&bomb, Compression::Zstd).is_err());
+    }
+
+    #[test]
+    fn stateful_encoding_shrinks_small_and_repeated_words() {
+        let encoded = stateful_encode(&state_diff());
+
+        assert!(encoded.len() < get_bytes_from_state_diff(&state_diff()).len() / 2);
+        assert_eq!(stateful_decode(&encoded).unwrap(), state_diff());
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        assert!(decompress(&[], Compression::Zstd).is_err());
+        assert!(decompress(&[3, 0], Compression::Zstd).is_err());
+        assert!(decompress(&[0, 0], Compression::Zstd).is_err());
+    }
+}
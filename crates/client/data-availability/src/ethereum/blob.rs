@@ -0,0 +1,222 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use c_kzg::{Blob, KzgCommitment, KzgProof, KzgSettings, BYTES_PER_BLOB};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Bytes, H256, U256};
+use ethers::utils::keccak256;
+use ethers::utils::rlp::RlpStream;
+
+/// EIP-2718 type of the EIP-4844 blob transactions
+pub const BLOB_TX_TYPE: u8 = 0x03;
+/// Version byte of the versioned hashes of KZG commitments
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+/// Number of 32 bytes field elements in a blob
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+
+/// Settings of the blob submission mode
+pub struct BlobSettings {
+    pub kzg_settings: KzgSettings,
+    pub gas_limit: u64,
+}
+
+impl std::fmt::Debug for BlobSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobSettings").field("gas_limit", &self.gas_limit).finish_non_exhaustive()
+    }
+}
+
+/// Blob holding a state diff, along with its KZG commitment and proof
+///
+/// Each word of the state diff is one field element of the blob, in big endian. Starknet felts are
+/// below the BLS12-381 scalar field modulus, so that words never need to be split. The blob is
+/// padded with zeros.
+///
+/// A state diff is held by a single blob, whose versioned hash the core contract can check against
+/// `BLOBHASH(0)`.
+pub struct BlobSidecar {
+    pub blobs: Vec<Vec<u8>>,
+    pub commitments: Vec<[u8; 48]>,
+    pub proofs: Vec<[u8; 48]>,
+}
+
+impl BlobSidecar {
+    pub fn new(state_diff: &[U256], kzg_settings: &KzgSettings) -> Result<Self> {
+        let bytes = blob_from_state_diff(state_diff)?;
+        let blob = Blob::from_bytes(&bytes).map_err(|e| anyhow!("invalid blob: {e:?}"))?;
+        let commitment = KzgCommitment::blob_to_kzg_commitment(&blob, kzg_settings)
+            .map_err(|e| anyhow!("could not compute the blob commitment: {e:?}"))?
+            .to_bytes();
+        let proof = KzgProof::compute_blob_kzg_proof(&blob, &commitment, kzg_settings)
+            .map_err(|e| anyhow!("could not compute the blob proof: {e:?}"))?
+            .to_bytes();
+
+        Ok(Self { blobs: vec![bytes], commitments: vec![*commitment], proofs: vec![*proof] })
+    }
+
+    /// Versioned hashes of the blob commitments, as exposed by the `BLOBHASH` opcode
+    pub fn versioned_hashes(&self) -> Vec<H256> {
+        self.commitments.iter().map(|commitment| kzg_to_versioned_hash(commitment)).collect()
+    }
+
+    /// Versioned hash of the blob, passed to the core contract as the on-chain data hash
+    pub fn data_hash(&self) -> U256 {
+        self.versioned_hashes().first().map_or_else(U256::zero, |hash| U256::from_big_endian(hash.as_bytes()))
+    }
+}
+
+/// Load the KZG trusted setup, in the format of the `c-kzg-4844` repository
+pub fn load_trusted_setup(path: &Path) -> Result<KzgSettings> {
+    KzgSettings::load_trusted_setup_file(path)
+        .map_err(|e| anyhow!("could not load the KZG trusted setup {}: {e:?}", path.display()))
+}
+
+/// Pack the words of a state diff into a zero padded blob
+pub fn blob_from_state_diff(state_diff: &[U256]) -> Result<Vec<u8>> {
+    if state_diff.len() > FIELD_ELEMENTS_PER_BLOB {
+        bail!(
+            "state diff of {} words doesn't fit in a blob of {FIELD_ELEMENTS_PER_BLOB} field elements, use a smaller \
+             batch size",
+            state_diff.len()
+        );
+    }
+
+    let mut blob = vec![0_u8; BYTES_PER_BLOB];
+    for (word, field_element) in state_diff.iter().zip(blob.chunks_exact_mut(32)) {
+        word.to_big_endian(field_element);
+    }
+
+    Ok(blob)
+}
+
+/// Versioned hash of a KZG commitment, as defined by EIP-4844
+pub fn kzg_to_versioned_hash(commitment: &[u8]) -> H256 {
+    let mut versioned_hash = sp_io::hashing::sha2_256(commitment);
+    versioned_hash[0] = VERSIONED_HASH_VERSION_KZG;
+    H256(versioned_hash)
+}
+
+/// EIP-4844 blob transaction
+///
+/// ethers doesn't support blob transactions yet, so that they are encoded and signed here.
+pub struct BlobTransaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: U256,
+    pub to: Address,
+    pub data: Bytes,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+}
+
+impl BlobTransaction {
+    /// Sign the transaction and return it in its network form, wrapped with its blobs, to be sent
+    /// with `eth_sendRawTransaction`
+    pub fn sign(&self, wallet: &LocalWallet, sidecar: &BlobSidecar) -> Result<Bytes> {
+        let mut unsigned = RlpStream::new_list(11);
+        self.rlp_append_fields(&mut unsigned);
+        let sighash = keccak256([&[BLOB_TX_TYPE], unsigned.as_raw()].concat());
+
+        let signature = wallet.sign_hash(H256(sighash)).map_err(|e| anyhow!("could not sign the blob tx: {e}"))?;
+        // `sign_hash` returns a legacy `v`, without any chain id
+        let y_parity = signature.v - 27;
+
+        let mut network = RlpStream::new_list(4);
+        network.begin_list(14);
+        self.rlp_append_fields(&mut network);
+        network.append(&y_parity);
+        network.append(&signature.r);
+        network.append(&signature.s);
+        network.begin_list(sidecar.blobs.len());
+        sidecar.blobs.iter().for_each(|blob| {
+            network.append(&blob.as_slice());
+        });
+        network.begin_list(sidecar.commitments.len());
+        sidecar.commitments.iter().for_each(|commitment| {
+            network.append(&commitment.as_slice());
+        });
+        network.begin_list(sidecar.proofs.len());
+        sidecar.proofs.iter().for_each(|proof| {
+            network.append(&proof.as_slice());
+        });
+
+        Ok([&[BLOB_TX_TYPE], network.as_raw()].concat().into())
+    }
+
+    fn rlp_append_fields(&self, s: &mut RlpStream) {
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.max_priority_fee_per_gas);
+        s.append(&self.max_fee_per_gas);
+        s.append(&self.gas_limit);
+        s.append(&self.to);
+        // No value is transferred, and the access list is empty
+        s.append(&U256::zero());
+        s.append(&self.data.as_ref());
+        s.begin_list(0);
+        s.append(&self.max_fee_per_blob_gas);
+        s.append_list(&self.blob_versioned_hashes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_diff_words_are_blob_field_elements() {
+        let state_diff = vec![U256::from(1), U256::from(2)];
+        let blob = blob_from_state_diff(&state_diff).unwrap();
+
+        assert_eq!(blob.len(), BYTES_PER_BLOB);
+        assert_eq!(blob[31], 1);
+        assert_eq!(blob[63], 2);
+        assert!(blob[64..].iter().all(|byte| *byte == 0));
+
+        assert!(blob_from_state_diff(&vec![U256::one(); FIELD_ELEMENTS_PER_BLOB]).is_ok());
+        assert!(blob_from_state_diff(&vec![U256::one(); FIELD_ELEMENTS_PER_BLOB + 1]).is_err());
+    }
+
+    #[test]
+    fn versioned_hash_is_the_versioned_commitment_sha256() {
+        let commitment = [0xc0_u8; 48];
+        let versioned_hash = kzg_to_versioned_hash(&commitment);
+
+        assert_eq!(versioned_hash[0], VERSIONED_HASH_VERSION_KZG);
+        assert_eq!(versioned_hash[1..], sp_io::hashing::sha2_256(&commitment)[1..]);
+    }
+
+    #[test]
+    fn blob_transaction_is_signed_and_wrapped() {
+        // First anvil account, 0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266
+        let wallet: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".parse().unwrap();
+        let mut versioned_hash = H256::repeat_byte(0x11);
+        versioned_hash[0] = VERSIONED_HASH_VERSION_KZG;
+        let tx = BlobTransaction {
+            chain_id: 31337,
+            nonce: U256::one(),
+            max_priority_fee_per_gas: U256::from(1_000_000_000),
+            max_fee_per_gas: U256::from(2_000_000_000),
+            gas_limit: U256::from(1_000_000),
+            to: "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse().unwrap(),
+            data: vec![1, 2].into(),
+            max_fee_per_blob_gas: U256::from(3),
+            blob_versioned_hashes: vec![versioned_hash],
+        };
+        let sidecar =
+            BlobSidecar { blobs: vec![vec![0xbb; 4]], commitments: vec![[0xc0; 48]], proofs: vec![[0xd0; 48]] };
+
+        let raw_tx = tx.sign(&wallet, &sidecar).unwrap();
+
+        // Signed with an independent implementation of the EIP-4844 signing scheme
+        let expected = "03f8fef892827a6901843b9aca008477359400830f4240945fbdb2315678afecb367f032d93f642f64180aa38082\
+                        0102c003e1a0011111111111111111111111111111111111111111111111111111111111111101a04ee695296f95\
+                        79e465df8512d3a8325fd4705a9eb89780c7fd14310ada2ff8fda02320921373fbd83cd9f59ffb1ff753b94cf94d\
+                        1eef69d4237f164e6fa4acb67ec584bbbbbbbbf1b0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0\
+                        c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0f1b0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0\
+                        d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0";
+        assert_eq!(ethers::utils::hex::encode(&raw_tx), expected);
+    }
+}
@@ -12,6 +12,20 @@ pub const DEFAULT_ETHEREUM_NODE: &str = "127.0.0.1:8545";
 pub const DEFAULT_SEQUENCER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
 pub const DEFAULT_STARKNET_CORE_CONTRACTS: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
 pub const DEFAULT_CHAIN_ID: u64 = 31337;
+pub const DEFAULT_BLOB_TX_GAS_LIMIT: u64 = 1_000_000;
+
+/// How state diffs are submitted to the Starknet core contract
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Default)]
+pub enum SubmissionMode {
+    /// The state diff is the `programOutput` calldata of `updateState`
+    #[serde(rename = "calldata")]
+    #[default]
+    Calldata,
+    /// The state diff is sent as an EIP-4844 blob, and the core contract receives its versioned
+    /// hash as the on-chain data hash
+    #[serde(rename = "blob")]
+    Blob,
+}
 
 #[derive(Clone, PartialEq, Deserialize, Debug)]
 pub struct EthereumConfig {
@@ -25,6 +39,16 @@ pub struct EthereumConfig {
     pub chain_id: u64,
     #[serde(default)]
     pub mode: DaMode,
+    #[serde(default)]
+    pub submission: SubmissionMode,
+    /// KZG trusted setup, required in blob submission mode: the `trusted_setup.txt` of the
+    /// c-kzg-4844 v0.4.0 release, matching the `c-kzg` crate. Relative to the DA config file if not
+    /// absolute
+    #[serde(default)]
+    pub trusted_setup: Option<PathBuf>,
+    /// Gas limit of the blob transactions
+    #[serde(default = "default_blob_tx_gas_limit")]
+    pub blob_tx_gas_limit: u64,
 }
 
 impl TryFrom<&PathBuf> for EthereumConfig {
@@ -32,7 +56,13 @@ impl TryFrom<&PathBuf> for EthereumConfig {
 
     fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
         let file = File::open(path).map_err(|e| format!("error opening da config: {e}"))?;
-        serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))
+        let mut conf: Self = serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))?;
+
+        if let (Some(trusted_setup), Some(config_dir)) = (&conf.trusted_setup, path.parent()) {
+            conf.trusted_setup = Some(config_dir.join(trusted_setup));
+        }
+
+        Ok(conf)
     }
 }
 
@@ -52,6 +82,10 @@ fn default_chain_id() -> u64 {
     DEFAULT_CHAIN_ID
 }
 
+fn default_blob_tx_gas_limit() -> u64 {
+    DEFAULT_BLOB_TX_GAS_LIMIT
+}
+
 impl Default for EthereumConfig {
     fn default() -> Self {
         Self {
@@ -60,6 +94,9 @@ impl Default for EthereumConfig {
             core_contracts: default_core_contracts(),
             sequencer_key: default_sequencer_key(),
             chain_id: default_chain_id(),
+            submission: SubmissionMode::default(),
+            trusted_setup: None,
+            blob_tx_gas_limit: default_blob_tx_gas_limit(),
         }
     }
 }
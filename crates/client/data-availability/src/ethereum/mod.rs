@@ -1,34 +1,40 @@
+pub mod blob;
 pub mod config;
 
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use ethers::abi::AbiDecode;
 use ethers::prelude::{abigen, SignerMiddleware};
-use ethers::providers::{Http, Provider};
+use ethers::providers::{Http, Middleware, Provider};
 use ethers::signers::{LocalWallet, Signer};
-use ethers::types::{Address, I256, U256};
+use ethers::types::{Address, BlockNumber, TransactionReceipt, I256, U256, U64};
 
+use self::blob::{BlobSettings, BlobSidecar, BlobTransaction};
+use self::config::SubmissionMode;
+use crate::header::StateDiffHeader;
 use crate::utils::is_valid_http_endpoint;
-use crate::{DaClient, DaMode};
+use crate::{DaClient, DaMode, DaReader};
 
 #[derive(Clone, Debug)]
 pub struct EthereumClient {
     http_provider: Provider<Http>,
     signer: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
     cc_address: Address,
+    chain_id: u64,
     mode: DaMode,
+    /// Set in blob submission mode
+    blob: Option<Arc<BlobSettings>>,
 }
 
 #[async_trait]
 impl DaClient for EthereumClient {
-    async fn publish_state_diff(&self, state_diff: Vec<U256>) -> Result<()> {
-        abigen!(
-            STARKNET,
-            r#"[
-                function updateState(uint256[] calldata programOutput, uint256 onchainDataHash, uint256 onchainDataSize) external
-            ]"#,
-        );
+    /// The core contract keeps track of the state itself, so that the header is not published
+    async fn publish_state_diff(&self, _header: StateDiffHeader, state_diff: Vec<U256>) -> Result<()> {
+        if let Some(blob_settings) = &self.blob {
+            return self.publish_state_diff_as_blobs(state_diff, blob_settings).await;
+        }
 
         let core_contracts = STARKNET::new(self.cc_address, self.signer.clone());
 
@@ -39,6 +45,7 @@ impl DaClient for EthereumClient {
             .map_err(|e| anyhow::anyhow!("ethereum send update err: {e}"))?
             .await
             .map_err(|e| anyhow::anyhow!("ethereum poll update err: {e}"))?;
+        let tx = check_receipt(tx)?;
 
         log::info!("State Update: {:?}", tx);
         Ok(())
@@ -61,6 +68,115 @@ impl DaClient for EthereumClient {
     }
 }
 
+abigen!(
+    STARKNET,
+    r#"[
+        function updateState(uint256[] calldata programOutput, uint256 onchainDataHash, uint256 onchainDataSize) external
+        event LogStateUpdate(uint256 globalRoot, int256 blockNumber, uint256 blockHash)
+    ]"#,
+);
+
+#[async_trait]
+impl DaReader for EthereumClient {
+    /// The state diffs are read back from the calldata of the core contract updates, found through
+    /// the `LogStateUpdate` events of the contract
+    async fn published_state_diffs(&self) -> Result<Vec<Vec<U256>>> {
+        if self.blob.is_some() {
+            bail!("reading state diffs back from blobs is not supported");
+        }
+
+        let core_contracts = STARKNET::new(self.cc_address, self.signer.clone());
+        let updates = core_contracts
+            .log_state_update_filter()
+            .from_block(BlockNumber::Earliest)
+            .query_with_meta()
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum state update logs err: {e}"))?;
+
+        let mut state_diffs = Vec::new();
+        for (_, meta) in updates {
+            let tx = self
+                .http_provider
+                .get_transaction(meta.transaction_hash)
+                .await
+                .map_err(|e| anyhow::anyhow!("ethereum transaction err: {e}"))?
+                .ok_or_else(|| anyhow::anyhow!("state update transaction {:?} not found", meta.transaction_hash))?;
+            let call = UpdateStateCall::decode(&tx.input)
+                .map_err(|e| anyhow::anyhow!("invalid state update transaction {:?}: {e}", meta.transaction_hash))?;
+            state_diffs.push(call.program_output);
+        }
+
+        Ok(state_diffs)
+    }
+}
+
+impl EthereumClient {
+    /// Send the state diff as EIP-4844 blobs, along with the core contract update committing to
+    /// them
+    async fn publish_state_diff_as_blobs(&self, state_diff: Vec<U256>, blob_settings: &BlobSettings) -> Result<()> {
+        let sidecar = BlobSidecar::new(&state_diff, &blob_settings.kzg_settings)?;
+
+        let core_contracts = STARKNET::new(self.cc_address, self.signer.clone());
+        let data = core_contracts
+            .update_state(Vec::new(), sidecar.data_hash(), U256::from(state_diff.len()))
+            .calldata()
+            .ok_or_else(|| anyhow::anyhow!("could not encode the core contract update"))?;
+
+        let sender = self.signer.address();
+        let nonce = self
+            .http_provider
+            .get_transaction_count(sender, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum nonce err: {e}"))?;
+        let (max_fee_per_gas, max_priority_fee_per_gas) = self
+            .http_provider
+            .estimate_eip1559_fees(None)
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum fee estimation err: {e}"))?;
+        let blob_base_fee: U256 = self
+            .http_provider
+            .request("eth_blobBaseFee", ())
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum blob fee err: {e}"))?;
+
+        let tx = BlobTransaction {
+            chain_id: self.chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit: U256::from(blob_settings.gas_limit),
+            to: self.cc_address,
+            data,
+            // Leave room for the blob base fee to increase before the tx is included
+            max_fee_per_blob_gas: blob_base_fee * 2,
+            blob_versioned_hashes: sidecar.versioned_hashes(),
+        };
+        let raw_tx = tx.sign(self.signer.signer(), &sidecar)?;
+
+        let receipt = self
+            .http_provider
+            .send_raw_transaction(raw_tx)
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum send blob update err: {e}"))?
+            .await
+            .map_err(|e| anyhow::anyhow!("ethereum poll blob update err: {e}"))?;
+        let receipt = check_receipt(receipt)?;
+
+        log::info!("State Update (blob): {:?}", receipt);
+        Ok(())
+    }
+}
+
+/// Check that a core contract update was included without reverting
+fn check_receipt(receipt: Option<TransactionReceipt>) -> Result<TransactionReceipt> {
+    let receipt = receipt.ok_or_else(|| anyhow::anyhow!("ethereum update dropped from the mempool"))?;
+    if receipt.status != Some(U64::one()) {
+        bail!("ethereum update {:?} reverted", receipt.transaction_hash);
+    }
+
+    Ok(receipt)
+}
+
 impl TryFrom<config::EthereumConfig> for EthereumClient {
     type Error = String;
 
@@ -81,6 +197,18 @@ impl TryFrom<config::EthereumConfig> for EthereumClient {
 
         let cc_address: Address = conf.core_contracts.parse().map_err(|e| format!("ethereum error: {e}"))?;
 
-        Ok(Self { http_provider: provider, signer, cc_address, mode: conf.mode })
+        let blob = match conf.submission {
+            SubmissionMode::Calldata => None,
+            SubmissionMode::Blob => {
+                let trusted_setup = conf
+                    .trusted_setup
+                    .ok_or_else(|| "ethereum error: blob submission requires a KZG trusted setup".to_string())?;
+                let kzg_settings =
+                    blob::load_trusted_setup(&trusted_setup).map_err(|e| format!("ethereum error: {e}"))?;
+                Some(Arc::new(BlobSettings { kzg_settings, gas_limit: conf.blob_tx_gas_limit }))
+            }
+        };
+
+        Ok(Self { http_provider: provider, signer, cc_address, chain_id: conf.chain_id, mode: conf.mode, blob })
     }
 }
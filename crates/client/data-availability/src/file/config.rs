@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::compression::Compression;
+use crate::DaMode;
+
+pub const DEFAULT_DA_DIRECTORY: &str = "da";
+
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct FileConfig {
+    /// Directory the state diffs are written to, relative to the DA config file if not absolute
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub mode: DaMode,
+    #[serde(default)]
+    pub compression: Compression,
+}
+
+impl TryFrom<&PathBuf> for FileConfig {
+    type Error = String;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        let file = File::open(path).map_err(|e| format!("error opening da config: {e}"))?;
+        let mut conf: Self = serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))?;
+
+        if let Some(config_dir) = path.parent() {
+            conf.path = config_dir.join(conf.path);
+        }
+
+        Ok(conf)
+    }
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from(DEFAULT_DA_DIRECTORY)
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        Self { path: default_path(), mode: DaMode::default(), compression: Compression::default() }
+    }
+}
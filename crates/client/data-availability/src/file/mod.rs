@@ -0,0 +1,182 @@
+pub mod config;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{I256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::compression::{compress, decompress, Compression};
+use crate::header::StateDiffHeader;
+use crate::{DaClient, DaMode, DaReader};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Local filesystem DA layer
+///
+/// Each state diff is written to its own sequentially numbered file, and a manifest lists the
+/// published files in order. Meant to run the DA pipeline without any external endpoint, in
+/// development and tests.
+#[derive(Clone, Debug)]
+pub struct FileClient {
+    path: PathBuf,
+    mode: DaMode,
+    compression: Compression,
+}
+
+/// List of the state diffs published to the DA directory, in publication order
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub state_diffs: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Sequence number of the state diff, starting from 0
+    pub index: u64,
+    /// Name of the file holding the state diff, relative to the DA directory
+    pub file: String,
+    /// Size of the file, in bytes
+    pub size: usize,
+    /// Number of the last block covered by the state diff
+    pub block_number: u64,
+    /// Global state root after that block
+    pub state_root: U256,
+}
+
+#[async_trait]
+impl DaClient for FileClient {
+    async fn publish_state_diff(&self, header: StateDiffHeader, state_diff: Vec<U256>) -> Result<()> {
+        let mut manifest = self.manifest().await?;
+
+        let index = manifest.state_diffs.len() as u64;
+        let content = compress(&state_diff, self.compression)?;
+        let entry = ManifestEntry {
+            index,
+            file: format!("{index:010}.bin"),
+            size: content.len(),
+            block_number: header.block_number,
+            state_root: header.state_root,
+        };
+
+        // The manifest is only updated once the state diff file is written, so that it never
+        // references a partial file
+        self.write_atomically(&entry.file, &content).await?;
+        manifest.state_diffs.push(entry);
+        self.write_atomically(MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?).await?;
+
+        Ok(())
+    }
+
+    async fn last_published_state(&self) -> Result<I256> {
+        match self.manifest().await?.state_diffs.last() {
+            Some(entry) => Ok(I256::from(entry.block_number)),
+            None => Ok(I256::minus_one()),
+        }
+    }
+
+    fn get_mode(&self) -> DaMode {
+        self.mode
+    }
+}
+
+#[async_trait]
+impl DaReader for FileClient {
+    async fn published_state_diffs(&self) -> Result<Vec<Vec<U256>>> {
+        let mut state_diffs = Vec::new();
+        for entry in self.manifest().await?.state_diffs {
+            state_diffs.push(self.read_state_diff(&entry).await?);
+        }
+
+        Ok(state_diffs)
+    }
+}
+
+impl FileClient {
+    /// Return the manifest of the DA directory, empty if nothing has been published yet
+    pub async fn manifest(&self) -> Result<Manifest> {
+        match tokio::fs::read(self.path.join(MANIFEST_FILE)).await {
+            Ok(raw) => {
+                serde_json::from_slice(&raw).map_err(|e| anyhow::anyhow!("could not parse the DA manifest: {e}"))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(e) => Err(anyhow::anyhow!("could not read the DA manifest: {e}")),
+        }
+    }
+
+    /// Read back the state diff with the given sequence number
+    pub async fn state_diff(&self, index: u64) -> Result<Vec<U256>> {
+        let manifest = self.manifest().await?;
+        let entry = manifest
+            .state_diffs
+            .iter()
+            .find(|entry| entry.index == index)
+            .ok_or_else(|| anyhow::anyhow!("state diff #{index} has not been published"))?;
+
+        self.read_state_diff(entry).await
+    }
+
+    async fn read_state_diff(&self, entry: &ManifestEntry) -> Result<Vec<U256>> {
+        let raw = tokio::fs::read(self.path.join(&entry.file)).await?;
+        if raw.len() != entry.size {
+            return Err(anyhow::anyhow!("state diff file {} is corrupted", entry.file));
+        }
+
+        decompress(&raw, self.compression)
+    }
+
+    async fn write_atomically(&self, file: &str, content: &[u8]) -> Result<()> {
+        let tmp_path = self.path.join(format!("{file}.tmp"));
+
+        tokio::fs::write(&tmp_path, content).await?;
+        tokio::fs::rename(&tmp_path, self.path.join(file)).await?;
+
+        Ok(())
+    }
+}
+
+impl TryFrom<config::FileConfig> for FileClient {
+    type Error = anyhow::Error;
+
+    fn try_from(conf: config::FileConfig) -> Result<Self, Self::Error> {
+        std::fs::create_dir_all(&conf.path)
+            .map_err(|e| anyhow::anyhow!("could not create the DA directory {}: {e}", conf.path.display()))?;
+
+        Ok(Self { path: conf.path, mode: conf.mode, compression: conf.compression })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn published_state_diffs_are_read_back() {
+        let path = std::env::temp_dir().join(format!("madara-da-{}", uuid::Uuid::new_v4()));
+        let conf =
+            config::FileConfig { path: path.clone(), mode: DaMode::Validium, compression: Compression::Stateful };
+        let client = FileClient::try_from(conf.clone()).unwrap();
+
+        assert_eq!(client.last_published_state().await.unwrap(), I256::minus_one());
+
+        let first_state_diff = vec![U256::from(1), U256::MAX];
+        let second_state_diff = vec![U256::from(2)];
+        let header =
+            |block_number| StateDiffHeader { block_number, state_root: U256::from(block_number), ..Default::default() };
+        client.publish_state_diff(header(1), first_state_diff.clone()).await.unwrap();
+        client.publish_state_diff(header(3), second_state_diff.clone()).await.unwrap();
+
+        assert_eq!(client.last_published_state().await.unwrap(), I256::from(3));
+        assert_eq!(client.state_diff(0).await.unwrap(), first_state_diff);
+        assert_eq!(client.state_diff(1).await.unwrap(), second_state_diff);
+        assert!(client.state_diff(2).await.is_err());
+        assert_eq!(client.published_state_diffs().await.unwrap(), vec![first_state_diff, second_state_diff]);
+
+        // A new client resumes from the manifest
+        let client = FileClient::try_from(conf).unwrap();
+        assert_eq!(client.manifest().await.unwrap().state_diffs[1].file, "0000000001.bin");
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+use ethers::types::U256;
+
+/// Magic prefixing the header of a whole state diff, which also versions its layout
+const HEADER_MAGIC: &[u8; 4] = b"MDA1";
+/// Magic prefixing the header of the on-chain part of a state diff, in Volition mode
+const ONCHAIN_HEADER_MAGIC: &[u8; 4] = b"MDN1";
+/// Magic prefixing the header of the off-chain part of a state diff, in Volition mode
+const OFFCHAIN_HEADER_MAGIC: &[u8; 4] = b"MDF1";
+/// Length of the encoded header: magic, block number and state root
+pub const HEADER_LEN: usize = HEADER_MAGIC.len() + 8 + 32;
+
+/// Part of the state of the covered blocks that a state diff holds
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum StateDiffPart {
+    /// The whole state diff
+    #[default]
+    Full,
+    /// The part published to Ethereum, in Volition mode
+    Onchain,
+    /// The part published to the DA layer, in Volition mode
+    Offchain,
+}
+
+impl StateDiffPart {
+    fn magic(&self) -> &'static [u8; 4] {
+        match self {
+            Self::Full => HEADER_MAGIC,
+            Self::Onchain => ONCHAIN_HEADER_MAGIC,
+            Self::Offchain => OFFCHAIN_HEADER_MAGIC,
+        }
+    }
+}
+
+/// Header published along with each state diff to the blob DA layers
+///
+/// It tells which block the state diff brings the state to, so that the last published block can
+/// be read back from the DA layer itself.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct StateDiffHeader {
+    /// Number of the last block covered by the state diff
+    pub block_number: u64,
+    /// Global state root after that block
+    pub state_root: U256,
+    /// Part of the state diff of the covered blocks following the header
+    pub part: StateDiffPart,
+}
+
+impl StateDiffHeader {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut state_root = [0_u8; 32];
+        self.state_root.to_big_endian(&mut state_root);
+
+        [self.part.magic().as_slice(), &self.block_number.to_be_bytes(), &state_root].concat()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != HEADER_LEN {
+            bail!("invalid state diff header");
+        }
+
+        let (magic, rest) = bytes.split_at(HEADER_MAGIC.len());
+        let part = [StateDiffPart::Full, StateDiffPart::Onchain, StateDiffPart::Offchain]
+            .into_iter()
+            .find(|part| part.magic() == magic)
+            .ok_or_else(|| anyhow::anyhow!("invalid state diff header"))?;
+        let (block_number, state_root) = rest.split_at(8);
+        Ok(Self {
+            block_number: u64::from_be_bytes(block_number.try_into()?),
+            state_root: U256::from_big_endian(state_root),
+            part,
+        })
+    }
+
+    /// Prefix the data published to a DA layer with the header
+    pub fn prefix(&self, payload: &[u8]) -> Vec<u8> {
+        [self.encode().as_slice(), payload].concat()
+    }
+
+    /// Split data published to a DA layer into its header and payload
+    pub fn split(data: &[u8]) -> Result<(Self, &[u8])> {
+        if data.len() < HEADER_LEN {
+            bail!("published data is too short to hold a state diff header");
+        }
+
+        let (header, payload) = data.split_at(HEADER_LEN);
+        Ok((Self::decode(header)?, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_reverts_prefix() {
+        let header = StateDiffHeader { block_number: 42, state_root: U256::MAX - 1, part: StateDiffPart::Full };
+        let data = header.prefix(&[1, 2, 3]);
+
+        assert_eq!(data.len(), HEADER_LEN + 3);
+        assert_eq!(StateDiffHeader::split(&data).unwrap(), (header, [1_u8, 2, 3].as_slice()));
+        assert!(StateDiffHeader::split(&data[..HEADER_LEN - 1]).is_err());
+        assert!(StateDiffHeader::split(&[b"MDA0".as_slice(), &data[4..]].concat()).is_err());
+    }
+
+    #[test]
+    fn partial_state_diffs_have_their_own_header() {
+        let header = StateDiffHeader { block_number: 42, state_root: U256::one(), part: StateDiffPart::Offchain };
+        let data = header.prefix(&[1, 2, 3]);
+
+        assert!(data.starts_with(b"MDF1"));
+        assert_eq!(StateDiffHeader::split(&data).unwrap().0, header);
+        assert_ne!(data, StateDiffHeader { part: StateDiffPart::Full, ..header }.prefix(&[1, 2, 3]));
+    }
+}
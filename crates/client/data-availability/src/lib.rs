@@ -1,23 +1,53 @@
 pub mod avail;
+pub mod batch;
 pub mod celestia;
+pub mod compression;
 pub mod ethereum;
-mod sharp;
+pub mod file;
+pub mod header;
+pub mod prover;
+pub mod sharp;
+pub mod state_diff;
 pub mod utils;
+pub mod volition;
 
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use batch::BatchConfig;
 use ethers::types::{I256, U256};
 use futures::StreamExt;
+use header::{StateDiffHeader, StateDiffPart};
+use mc_db::ProofStatus;
+use mp_digest_log::find_starknet_block;
+use prover::config::ProverConfig;
+use prover::Prover;
+use sc_client_api::backend::{Backend, StorageProvider};
 use sc_client_api::client::BlockchainEvents;
 use serde::Deserialize;
+use sharp::CairoJobStatus;
 use sp_api::ProvideRuntimeApi;
-use sp_runtime::traits::Block as BlockT;
+use sp_blockchain::HeaderBackend;
+use sp_core::storage::StorageKey;
+use sp_io::hashing::blake2_128;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+use state_diff::StateDiff;
+use volition::Volition;
 
-pub type StorageWrites<'a> = Vec<(&'a [u8], &'a [u8])>;
+/// Delay before retrying a failed publication, doubled after each consecutive failure
+const MIN_PUBLISH_BACKOFF: Duration = Duration::from_secs(1);
+/// Maximum delay between two publication attempts
+const MAX_PUBLISH_BACKOFF: Duration = Duration::from_secs(300);
+/// Maximum number of blocks being proved at the same time, in Validity mode
+const MAX_PROVING_JOBS: usize = 16;
+/// Delay between two checks for newly proved blocks to publish, in Validity mode
+const PROOF_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of blocks whose legacy DA db entries are removed in a single db transaction
+const LEGACY_ENTRIES_BATCH_SIZE: usize = 1024;
 
 pub struct DataAvailabilityWorker<B, C>(PhantomData<(B, C)>);
 
@@ -26,6 +56,7 @@ pub enum DaLayer {
     Celestia,
     Ethereum,
     Avail,
+    File,
 }
 
 /// Data availability modes in which Madara can be initialized.
@@ -45,9 +76,8 @@ pub enum DaMode {
     /// Hybrid Volition
     ///
     /// Volitions allow applications and users to interoperate between on-chain data and off-chain
-    /// da. Although full specs are not currently available, this mode will entail generating
-    /// a StarknetOS execution trace for data elected to be on-chain and interaction w/ the prover
-    /// will be necessary.
+    /// da. The state diffs of the contracts and storage slots elected to be on-chain in the
+    /// [`volition::VolitionConfig`] are published to Ethereum, and the rest to the DA layer.
     #[serde(rename = "volition")]
     Volition,
     /// Sovereign Validium
@@ -64,8 +94,22 @@ pub enum DaMode {
 #[async_trait]
 pub trait DaClient: Send + Sync {
     fn get_mode(&self) -> DaMode;
+    /// Return the number of the last block whose state diff is published, or -1 if none is
     async fn last_published_state(&self) -> Result<I256>;
-    async fn publish_state_diff(&self, state_diff: Vec<U256>) -> Result<()>;
+    /// Publish the state diff bringing the state to the block described by `header`
+    async fn publish_state_diff(&self, header: StateDiffHeader, state_diff: Vec<U256>) -> Result<()>;
+    /// Whether the last published block is read back from the headers of the published state
+    /// diffs, so that nothing is published past a gap in the DA history
+    fn checks_publication_gaps(&self) -> bool {
+        false
+    }
+}
+
+/// Read back the state diffs published to a DA layer
+#[async_trait]
+pub trait DaReader: Send + Sync {
+    /// Return the published state diffs, in publication order
+    async fn published_state_diffs(&self) -> Result<Vec<Vec<U256>>>;
 }
 
 impl<B, C> DataAvailabilityWorker<B, C>
@@ -74,74 +118,152 @@ where
     C: ProvideRuntimeApi<B>,
     C: BlockchainEvents<B> + 'static,
 {
-    pub async fn prove_current_block(da_mode: DaMode, client: Arc<C>, madara_backend: Arc<mc_db::Backend<B>>) {
+    /// Store the state diff of every imported block, queued for publication
+    ///
+    /// In Validity mode, the blocks are then proved by [`Self::prove_state`] before being
+    /// published.
+    pub async fn prove_current_block<BE>(client: Arc<C>, madara_backend: Arc<mc_db::Backend<B>>)
+    where
+        BE: Backend<B>,
+        C: HeaderBackend<B> + StorageProvider<B, BE>,
+    {
         let mut storage_event_st = client
             .storage_changes_notification_stream(None, None)
             .expect("node has been initialized to prove state change, but can't read from notification stream");
 
         while let Some(storage_event) = storage_event_st.next().await {
-            // Locate and encode the storage change
-            let mut nonces: HashMap<&[u8], &[u8]> = HashMap::new();
-            let mut storage_diffs: HashMap<&[u8], StorageWrites> = HashMap::new();
-
-            // Locate and encode the storage change
-            for event in storage_event.changes.iter() {
-                let mut prefix = event.1.0.as_slice();
-                let mut key: &[u8] = &[];
-                if prefix.len() > 32 {
-                    let raw_split = prefix.split_at(32);
-                    prefix = raw_split.0;
-                    key = raw_split.1;
-                }
-
-                if prefix == *utils::SN_NONCE_PREFIX {
-                    if let Some(data) = event.2 {
-                        nonces.insert(key, data.0.as_slice());
-                    }
+            let block_number: u64 = match client.number(storage_event.block) {
+                Ok(Some(block_number)) => block_number.unique_saturated_into(),
+                _ => {
+                    log::error!("block {:?} not found", storage_event.block);
+                    continue;
                 }
+            };
 
-                if prefix == *utils::SN_STORAGE_PREFIX {
-                    if let Some(data) = event.2 {
-                        // first 32 bytes = contract address, second 32 bytes = storage variable
-                        let write_split = key.split_at(32);
+            let mut state_diff = StateDiff::default();
+            // Contracts whose nonce changed in this block
+            let mut updated_nonces = HashSet::new();
 
-                        storage_diffs
-                            .entry(write_split.0)
-                            .and_modify(|v| v.push((write_split.1, data.0.as_slice())))
-                            .or_insert(vec![(write_split.1, data.0.as_slice())]);
-                    }
+            let changes: Vec<(&[u8], Option<&[u8]>)> = storage_event
+                .changes
+                .iter()
+                .map(|(_, key, data)| (key.0.as_slice(), data.map(|data| data.0.as_slice())))
+                .collect();
+            // The storage migration only moves entries, which doesn't change the state
+            let moves = schema_v1_entry_moves(client.as_ref(), storage_event.block, &changes);
+
+            for (key, data) in changes.into_iter().filter(|(key, _)| !moves.contains(*key)) {
+                if let Some(address) = state_diff.record_storage_change(key, data) {
+                    updated_nonces.insert(address);
                 }
             }
 
-            let state_diff = utils::pre_0_11_0_state_diff(storage_diffs, nonces);
+            // The encoding carries the nonce of every contract in the diff, even when it didn't change
+            for (address, diff) in state_diff.contracts.iter_mut() {
+                if !updated_nonces.contains(address) {
+                    diff.nonce = current_nonce(client.as_ref(), storage_event.block, address);
+                }
+            }
+
+            let state_diff = match state_diff.encode() {
+                Ok(state_diff) => state_diff,
+                Err(e) => {
+                    log::error!("can't encode the state diff of block #{block_number}: {e}");
+                    continue;
+                }
+            };
 
             // Store the DA output from the SN OS
-            if let Err(db_err) = madara_backend.da().store_state_diff(&storage_event.block, state_diff) {
+            if let Err(db_err) = madara_backend.da().store_state_diff(block_number, &storage_event.block, state_diff) {
                 log::error!("db err: {db_err}");
             };
+        }
+    }
 
-            match da_mode {
-                DaMode::Validity => {
-                    // Submit the StarkNet OS PIE
-                    // TODO: Validity Impl
-                    // run the Starknet OS with the Cairo VM
-                    // extract the PIE from the Cairo VM run
-                    // pass the PIE to `submit_pie` and zip/base64 internal
-                    if let Ok(job_resp) = sharp::submit_pie("TODO") {
-                        log::info!("Job Submitted: {}", job_resp.cairo_job_key);
-                        // Store the cairo job key
-                        if let Err(db_err) =
-                            madara_backend.da().update_cairo_job(&storage_event.block, job_resp.cairo_job_key)
-                        {
-                            log::error!("db err: {db_err}");
-                        };
+    /// Prove the queued blocks, in Validity mode
+    ///
+    /// A Cairo job is submitted for every queued block without one, and the jobs in progress are
+    /// polled until their proof is onchain, on every imported block and every poll interval. Jobs
+    /// which failed, or that the prover doesn't know about anymore, are submitted again.
+    pub async fn prove_state(
+        prover: Box<dyn Prover>,
+        client: Arc<C>,
+        madara_backend: Arc<mc_db::Backend<B>>,
+        prover_config: ProverConfig,
+    ) {
+        let mut notification_st = client.import_notification_stream();
+
+        loop {
+            if let Err(e) = Self::update_proofs(prover.as_ref(), &madara_backend).await {
+                log::error!("prover error: {e}");
+            }
+
+            tokio::select! {
+                notification = notification_st.next() => if notification.is_none() { break },
+                _ = tokio::time::sleep(prover_config.poll_interval()) => {}
+            }
+        }
+    }
+
+    /// Update the proofs of the queued blocks
+    ///
+    /// A failure to update the proof of a block is logged, and the next blocks are updated anyway.
+    async fn update_proofs(prover: &dyn Prover, madara_backend: &mc_db::Backend<B>) -> Result<()> {
+        for (block_number, block_hash) in
+            madara_backend.da().queued_blocks(MAX_PROVING_JOBS).map_err(|e| anyhow::anyhow!(e))?
+        {
+            if let Err(e) = Self::update_proof(prover, madara_backend, block_number, &block_hash).await {
+                log::error!("failed to update the proof of block #{block_number}: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn update_proof(
+        prover: &dyn Prover,
+        madara_backend: &mc_db::Backend<B>,
+        block_number: u64,
+        block_hash: &B::Hash,
+    ) -> Result<()> {
+        let da = madara_backend.da();
+
+        match da.proof_status(block_hash).map_err(|e| anyhow::anyhow!(e))? {
+            None => {
+                let job_key = prover.submit_pie(&Self::cairo_pie(madara_backend, block_hash)?).await?;
+                log::info!("Job Submitted for block #{block_number}: {job_key}");
+                da.update_cairo_job(block_hash, job_key).map_err(|e| anyhow::anyhow!(e))?;
+            }
+            Some(ProofStatus::InProgress) => {
+                let job_key = da.cairo_job(block_hash).map_err(|e| anyhow::anyhow!(e))?;
+                match prover.job_status(job_key).await? {
+                    CairoJobStatus::Onchain => {
+                        let fact = prover.fact(job_key).await?;
+                        log::info!("block #{block_number} proved onchain, fact {fact:?}");
+                        da.mark_proved(block_hash, fact.to_fixed_bytes()).map_err(|e| anyhow::anyhow!(e))?;
+                    }
+                    CairoJobStatus::InProgress | CairoJobStatus::Processed => {}
+                    status => {
+                        log::warn!("job {job_key} of block #{block_number} is {status}, submitting it again");
+                        da.remove_cairo_job(block_hash).map_err(|e| anyhow::anyhow!(e))?;
                     }
-                }
-                _ => {
-                    log::info!("don't prove in remaining DA modes")
                 }
             }
+            Some(ProofStatus::Onchain(_)) => {}
         }
+
+        Ok(())
+    }
+
+    /// Return the Cairo PIE of the given block
+    ///
+    /// TODO: run the Starknet OS over the block with the Cairo VM, and extract the PIE from the
+    /// run. Until then, the PIE is the encoded state diff of the block, which only the local prover
+    /// accepts: the DA config can't select SHARP.
+    fn cairo_pie(madara_backend: &mc_db::Backend<B>, block_hash: &B::Hash) -> Result<Vec<u8>> {
+        let state_diff = madara_backend.da().state_diff(block_hash).map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(utils::get_bytes_from_state_diff(&state_diff))
     }
 }
 
@@ -151,40 +273,424 @@ where
     C: ProvideRuntimeApi<B>,
     C: BlockchainEvents<B> + 'static,
 {
+    /// Publish the stored state diffs to the DA layer
+    ///
+    /// State diffs are published from the persistent publish queue in strict block order, batched
+    /// according to `batch_config`. The queue is drained when the worker starts, so publication
+    /// resumes after a restart, and then on every imported block or when the batch interval
+    /// elapses. In Validity mode, only the blocks whose proof is onchain are published, and the
+    /// queue is also checked every `PROOF_CHECK_INTERVAL` as proofs land independently of the
+    /// block production. In Volition mode, the on-chain part of each batch is published to
+    /// Ethereum with `volition` beforehand, and the rest to `da_client`.
     pub async fn update_state(
         da_client: Box<dyn DaClient + Send + Sync>,
+        volition: Option<Volition>,
         client: Arc<C>,
         madara_backend: Arc<mc_db::Backend<B>>,
-    ) {
+        batch_config: BatchConfig,
+    ) where
+        C: HeaderBackend<B>,
+    {
+        if let Err(e) = Self::remove_legacy_da_entries(client.as_ref(), &madara_backend) {
+            log::error!("could not remove the legacy DA db entries: {e}");
+        }
+
         let mut notification_st = client.import_notification_stream();
+        // When the blocks of the current batch started waiting for publication
+        let mut batch_started_at = None;
+
+        loop {
+            match (da_client.get_mode(), &volition) {
+                (DaMode::Volition, None) => log::error!("volition da mode requires an on-chain DA client"),
+                (_, volition) => {
+                    Self::publish_queued_state_diffs(
+                        da_client.as_ref(),
+                        volition.as_ref(),
+                        client.as_ref(),
+                        &madara_backend,
+                        &batch_config,
+                        &mut batch_started_at,
+                    )
+                    .await
+                }
+            }
+
+            let mut deadline = match (batch_started_at, batch_config.interval()) {
+                (Some(started_at), Some(interval)) => Some(started_at + interval),
+                _ => None,
+            };
+            if da_client.get_mode() == DaMode::Validity {
+                let next_check = Instant::now() + PROOF_CHECK_INTERVAL;
+                deadline = Some(deadline.map_or(next_check, |deadline| deadline.min(next_check)));
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    let deadline = tokio::time::Instant::from_std(deadline);
+                    tokio::select! {
+                        notification = notification_st.next() => if notification.is_none() { break },
+                        _ = tokio::time::sleep_until(deadline) => {}
+                    }
+                }
+                None => {
+                    if notification_st.next().await.is_none() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Publish the queued state diffs, batch by batch, until no batch is ready
+    ///
+    /// The state diffs of a batch are merged, later writes winning, and submitted at once. A failed
+    /// publication is retried with an exponential backoff, as skipping a block would leave a gap in
+    /// the DA history. Only the blocks whose state diff was never stored are skipped, with an
+    /// error.
+    ///
+    /// Before each submission, the last block published to the DA layer is checked against the
+    /// batch: blocks the DA layer already holds, e.g. when the node stopped right after publishing
+    /// them, are not published twice, and nothing is published past a gap on the DA layers reading
+    /// their last published block back from the state diff headers.
+    async fn publish_queued_state_diffs(
+        da_client: &(dyn DaClient + Send + Sync),
+        volition: Option<&Volition>,
+        client: &C,
+        madara_backend: &mc_db::Backend<B>,
+        batch_config: &BatchConfig,
+        batch_started_at: &mut Option<Instant>,
+    ) where
+        C: HeaderBackend<B>,
+    {
+        let mut backoff = MIN_PUBLISH_BACKOFF;
+
+        loop {
+            match madara_backend.da().skip_missing_blocks() {
+                Ok(Some((first_skipped, last_skipped))) => log::error!(
+                    "the state diffs of blocks #{first_skipped} to #{last_skipped} were never stored, skipping them"
+                ),
+                Ok(None) => {}
+                Err(db_err) => {
+                    log::error!("db err: {db_err}");
+                    return;
+                }
+            }
+            let mut batch = match madara_backend.da().queued_blocks(batch_config.batch_size) {
+                Ok(batch) => batch,
+                Err(db_err) => {
+                    log::error!("db err: {db_err}");
+                    return;
+                }
+            };
+            // In Validity mode, blocks are published once their proof is onchain
+            if da_client.get_mode() == DaMode::Validity {
+                let n_proved = batch
+                    .iter()
+                    .take_while(|(_, block_hash)| {
+                        matches!(madara_backend.da().proof_status(block_hash), Ok(Some(ProofStatus::Onchain(_))))
+                    })
+                    .count();
+                batch.truncate(n_proved);
+            }
+            if batch.is_empty() {
+                *batch_started_at = None;
+                return;
+            }
 
-        while let Some(notification) = notification_st.next().await {
-            // Query last written state
-            // TODO: this value will be used to ensure the correct state diff is being written in Validity mode
-            let _last_published_state = match da_client.last_published_state().await {
-                Ok(last_published_state) => last_published_state,
+            let started_at = *batch_started_at.get_or_insert_with(Instant::now);
+            let interval_elapsed = batch_config.interval().map_or(false, |interval| started_at.elapsed() >= interval);
+            if batch.len() < batch_config.batch_size && !interval_elapsed {
+                return;
+            }
+
+            let (first_block, last_block) = (batch[0].0, batch[batch.len() - 1].0);
+            let last_published = match da_client.last_published_state().await {
+                Ok(last_published) => last_published,
                 Err(e) => {
-                    log::error!("da provider error: {e}");
+                    log::error!("da provider error, retrying in {}s: {e}", backoff.as_secs());
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_PUBLISH_BACKOFF);
                     continue;
                 }
             };
 
-            match da_client.get_mode() {
-                DaMode::Validity => {
-                    // Check the SHARP status of last_proved + 1
-                    // Write the publish state diff of last_proved + 1
-                    log::info!("validity da mode not implemented");
+            let n_already_published =
+                batch.iter().take_while(|(block_number, _)| I256::from(*block_number) <= last_published).count();
+            if n_already_published > 0 {
+                let already_published = &batch[..n_already_published];
+                log::warn!(
+                    "blocks #{first_block} to #{} are already published to the DA layer, not publishing them again",
+                    already_published[n_already_published - 1].0
+                );
+                match madara_backend.da().mark_batch_published(already_published) {
+                    Ok(submission) => Self::purge_previous_submission(client, madara_backend, submission),
+                    Err(db_err) => {
+                        log::error!("db err: {db_err}");
+                        return;
+                    }
+                }
+                continue;
+            }
+            if da_client.checks_publication_gaps()
+                && last_published >= I256::zero()
+                && last_published < I256::from(first_block) - 1
+            {
+                log::error!(
+                    "the DA layer is at block #{last_published} but the next block to publish is #{first_block}, not \
+                     publishing past the gap"
+                );
+                return;
+            }
+
+            let header = match Self::state_diff_header(client, batch[batch.len() - 1]) {
+                Ok(header) => header,
+                Err(e) => {
+                    log::error!("could not build the state diff header: {e}");
+                    return;
                 }
-                DaMode::Validium => match madara_backend.da().state_diff(&notification.hash) {
-                    Ok(state_diff) => {
-                        if let Err(e) = da_client.publish_state_diff(state_diff).await {
-                            log::error!("DA PUBLISH ERROR: {}", e);
+            };
+            let state_diff = match Self::merge_state_diffs(madara_backend, &batch) {
+                Ok(state_diff) => state_diff,
+                Err(e) => {
+                    log::error!("could not pull state diff: {e}");
+                    return;
+                }
+            };
+            let (header, state_diff) = match volition {
+                Some(volition) => {
+                    match Self::publish_onchain_state_diff(volition, madara_backend, &batch, header, state_diff).await {
+                        Ok(offchain_state_diff) => {
+                            (StateDiffHeader { part: StateDiffPart::Offchain, ..header }, offchain_state_diff)
+                        }
+                        Err(e) => {
+                            log::error!(
+                                "ONCHAIN DA PUBLISH ERROR for blocks #{first_block} to #{last_block}, retrying in \
+                                 {}s: {e}",
+                                backoff.as_secs()
+                            );
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(MAX_PUBLISH_BACKOFF);
+                            continue;
+                        }
+                    }
+                }
+                None => (header, state_diff),
+            };
+
+            let published = match state_diff.encode() {
+                Ok(state_diff) => da_client.publish_state_diff(header, state_diff).await,
+                Err(e) => Err(e),
+            };
+            match published {
+                Ok(()) => {
+                    log::info!("published the state diff of blocks #{first_block} to #{last_block}");
+                    match madara_backend.da().mark_batch_published(&batch) {
+                        Ok(submission) => Self::purge_previous_submission(client, madara_backend, submission),
+                        Err(db_err) => {
+                            log::error!("db err: {db_err}");
+                            return;
                         }
                     }
-                    Err(e) => log::error!("could not pull state diff: {e}"),
-                },
-                DaMode::Volition => log::info!("volition da mode not implemented"),
+                    *batch_started_at = None;
+                    backoff = MIN_PUBLISH_BACKOFF;
+                }
+                Err(e) => {
+                    log::error!(
+                        "DA PUBLISH ERROR for blocks #{first_block} to #{last_block}, retrying in {}s: {e}",
+                        backoff.as_secs()
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_PUBLISH_BACKOFF);
+                }
+            }
+        }
+    }
+
+    /// Purge the DA db entries of the blocks published by the submission preceding the given one
+    ///
+    /// The publication status of a block is only kept until the next submission.
+    fn purge_previous_submission(client: &C, madara_backend: &mc_db::Backend<B>, submission: u64)
+    where
+        C: HeaderBackend<B>,
+    {
+        let Some(previous_submission) = submission.checked_sub(1) else {
+            return;
+        };
+        let (first_block, last_block) = match madara_backend.da().submission(previous_submission) {
+            Ok(Some(blocks)) => blocks,
+            Ok(None) => return,
+            Err(db_err) => {
+                log::error!("db err: {db_err}");
+                return;
+            }
+        };
+
+        for block_number in first_block..=last_block {
+            match client.hash(block_number.unique_saturated_into()) {
+                Ok(Some(block_hash)) => {
+                    if let Err(db_err) = madara_backend.da().purge(&block_hash) {
+                        log::error!("db err: {db_err}");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("could not get the hash of block #{block_number}: {e}"),
+            }
+        }
+    }
+
+    /// Remove the DA db entries stored under the bare block hashes, before the per block
+    /// sub-spaces, going once through the canonical chain
+    ///
+    /// The entries of the blocks of retracted forks are left behind.
+    fn remove_legacy_da_entries(client: &C, madara_backend: &mc_db::Backend<B>) -> Result<()>
+    where
+        C: HeaderBackend<B>,
+    {
+        let da = madara_backend.da();
+        if da.legacy_entries_removed().map_err(|e| anyhow::anyhow!(e))? {
+            return Ok(());
+        }
+
+        let best_number: u64 = client.info().best_number.unique_saturated_into();
+        let mut block_hashes = Vec::with_capacity(LEGACY_ENTRIES_BATCH_SIZE);
+        for block_number in 0..=best_number {
+            if let Some(block_hash) = client.hash(block_number.unique_saturated_into())? {
+                block_hashes.push(block_hash);
+            }
+            if block_hashes.len() == LEGACY_ENTRIES_BATCH_SIZE {
+                da.remove_legacy_entries(&block_hashes, false).map_err(|e| anyhow::anyhow!(e))?;
+                block_hashes.clear();
+            }
+        }
+        da.remove_legacy_entries(&block_hashes, true).map_err(|e| anyhow::anyhow!(e))?;
+        log::info!("removed the legacy DA db entries of blocks #0 to #{best_number}");
+
+        Ok(())
+    }
+
+    /// Publish the on-chain part of the state diff of a batch, in Volition mode, and return its
+    /// off-chain part
+    ///
+    /// Blocks whose on-chain part has already been dealt with, before a failed off-chain
+    /// publication, are not published on-chain again.
+    async fn publish_onchain_state_diff(
+        volition: &Volition,
+        madara_backend: &mc_db::Backend<B>,
+        batch: &[(u64, B::Hash)],
+        header: StateDiffHeader,
+        state_diff: StateDiff,
+    ) -> Result<StateDiff> {
+        let da = madara_backend.da();
+        let last_onchain_published = da.last_onchain_published_block().map_err(|e| anyhow::anyhow!(e))?;
+        let pending: Vec<_> =
+            batch.iter().filter(|(block_number, _)| last_onchain_published < Some(*block_number)).copied().collect();
+
+        if let (Some(first_pending), Some(last_pending)) = (pending.first(), pending.last()) {
+            let (onchain_state_diff, _) = volition.config.split(Self::merge_state_diffs(madara_backend, &pending)?);
+            let submitted = !onchain_state_diff.is_empty();
+            if submitted {
+                let onchain_header = StateDiffHeader { part: StateDiffPart::Onchain, ..header };
+                volition.client.publish_state_diff(onchain_header, onchain_state_diff.encode()?).await?;
+                log::info!("published the on-chain state diff of blocks #{} to #{}", first_pending.0, last_pending.0);
             }
+            da.mark_onchain_published(&pending, submitted).map_err(|e| anyhow::anyhow!(e))?;
+        }
+
+        Ok(volition.config.split(state_diff).1)
+    }
+
+    /// Return the header of a state diff ending at the given block
+    fn state_diff_header(client: &C, (block_number, block_hash): (u64, B::Hash)) -> Result<StateDiffHeader>
+    where
+        C: HeaderBackend<B>,
+    {
+        let header = client.header(block_hash)?.ok_or_else(|| anyhow::anyhow!("block #{block_number} not found"))?;
+        let block = find_starknet_block(header.digest())
+            .map_err(|e| anyhow::anyhow!("block #{block_number} has no Starknet block: {e}"))?;
+
+        Ok(StateDiffHeader {
+            block_number,
+            state_root: U256::from_big_endian(block.header().global_state_root.bytes()),
+            part: StateDiffPart::Full,
+        })
+    }
+
+    /// Merge the stored state diffs of the given blocks, in block order
+    fn merge_state_diffs(madara_backend: &mc_db::Backend<B>, blocks: &[(u64, B::Hash)]) -> Result<StateDiff> {
+        let mut state_diff = StateDiff::default();
+        for (block_number, block_hash) in blocks {
+            let encoded = madara_backend.da().state_diff(block_hash).map_err(|e| anyhow::anyhow!(e))?;
+            let block_state_diff = StateDiff::decode(&encoded)
+                .map_err(|e| anyhow::anyhow!("invalid state diff for block #{block_number}: {e}"))?;
+            state_diff.merge(block_state_diff);
+        }
+
+        Ok(state_diff)
+    }
+}
+
+/// Return the keys of the storage changes of a block which only move map entries from their
+/// storage schema V1 location, as the storage migration does
+///
+/// A moved entry is removed from its schema V1 key and inserted at its schema V2 key with the
+/// value it held in the parent block. The removal is left out of the state diff whenever the
+/// schema V2 key is written in the same block.
+fn schema_v1_entry_moves<B, BE, C>(client: &C, block: B::Hash, changes: &[(&[u8], Option<&[u8]>)]) -> HashSet<Vec<u8>>
+where
+    B: BlockT,
+    BE: Backend<B>,
+    C: HeaderBackend<B> + StorageProvider<B, BE>,
+{
+    let removed_v1_keys: HashSet<&[u8]> = changes
+        .iter()
+        .filter(|(key, data)| data.is_none() && utils::is_schema_v1_map_key(key))
+        .map(|(key, _)| *key)
+        .collect();
+    // Only the blocks running the migration remove schema V1 entries
+    if removed_v1_keys.is_empty() {
+        return HashSet::new();
+    }
+    let parent_hash = match client.header(block) {
+        Ok(Some(header)) => *header.parent_hash(),
+        _ => {
+            log::error!("block {block:?} not found");
+            return HashSet::new();
+        }
+    };
+
+    let mut moves = HashSet::new();
+    for (key, data) in changes {
+        let Some(v1_key) = utils::schema_v1_map_key(key) else { continue };
+        if !removed_v1_keys.contains(v1_key.as_slice()) {
+            continue;
         }
+        let parent_value = client.storage(parent_hash, &StorageKey(v1_key.clone())).ok().flatten();
+        if parent_value.as_ref().map(|value| value.0.as_slice()) == *data {
+            moves.insert(key.to_vec());
+        }
+        moves.insert(v1_key);
     }
+
+    moves
+}
+
+/// Read the nonce of a contract from the state at the given block
+///
+/// Both the schema V2 `Blake2_128Concat` key and the schema V1 `Identity` key are tried.
+fn current_nonce<B, BE, C>(client: &C, block: B::Hash, address: &U256) -> U256
+where
+    B: BlockT,
+    BE: Backend<B>,
+    C: StorageProvider<B, BE>,
+{
+    let mut address_bytes = [0_u8; 32];
+    address.to_big_endian(&mut address_bytes);
+
+    [[blake2_128(&address_bytes).as_slice(), &address_bytes].concat(), address_bytes.to_vec()]
+        .into_iter()
+        .find_map(|key| {
+            client.storage(block, &StorageKey([utils::SN_NONCE_PREFIX.as_slice(), &key].concat())).ok().flatten()
+        })
+        .map(|data| U256::from_big_endian(&data.0))
+        .unwrap_or_default()
 }
@@ -0,0 +1,98 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+pub const DEFAULT_PROOF_POLL_INTERVAL: u64 = 10;
+
+/// Provers of the Validity mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Default)]
+pub enum ProverKind {
+    /// Local stub prover, for development and tests
+    #[serde(rename = "local")]
+    #[default]
+    Local,
+    /// SHARP, which isn't available yet: it must be submitted the Cairo PIE of a Starknet OS run
+    /// over the block, and the fact of its jobs computed from the output of the run, which Madara
+    /// doesn't do. A DA config selecting it is rejected.
+    #[serde(rename = "sharp")]
+    Sharp,
+}
+
+/// Proving settings of the Validity mode, read from the DA config
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct ProverConfig {
+    #[serde(default)]
+    pub prover: ProverKind,
+    /// Number of seconds between two polls of the proving jobs
+    #[serde(default = "default_proof_poll_interval")]
+    pub proof_poll_interval: u64,
+    /// Number of seconds the local prover takes to prove a job
+    #[serde(default)]
+    pub local_proving_time: u64,
+}
+
+impl ProverConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.proof_poll_interval)
+    }
+}
+
+impl TryFrom<&PathBuf> for ProverConfig {
+    type Error = String;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        let file = File::open(path).map_err(|e| format!("error opening da config: {e}"))?;
+        let conf: Self = serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))?;
+
+        if conf.proof_poll_interval == 0 {
+            return Err(String::from("error parsing da config: proof_poll_interval must be at least 1"));
+        }
+        if conf.prover == ProverKind::Sharp {
+            return Err(String::from(
+                "error parsing da config: the sharp prover isn't supported yet, as Madara doesn't produce the Cairo \
+                 PIEs of its blocks; use the local prover",
+            ));
+        }
+
+        Ok(conf)
+    }
+}
+
+fn default_proof_poll_interval() -> u64 {
+    DEFAULT_PROOF_POLL_INTERVAL
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            prover: ProverKind::default(),
+            proof_poll_interval: default_proof_poll_interval(),
+            local_proving_time: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(config: &str) -> Result<ProverConfig, String> {
+        let path = std::env::temp_dir().join(format!("madara-prover-config-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, config).unwrap();
+        let conf = ProverConfig::try_from(&path);
+        std::fs::remove_file(path).unwrap();
+        conf
+    }
+
+    #[test]
+    fn the_local_prover_is_the_default() {
+        assert_eq!(parse("{}").unwrap(), ProverConfig::default());
+    }
+
+    #[test]
+    fn the_sharp_prover_is_rejected() {
+        assert!(parse(r#"{ "prover": "sharp" }"#).unwrap_err().contains("sharp prover isn't supported"));
+    }
+}
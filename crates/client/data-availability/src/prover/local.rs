@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use uuid::Uuid;
+
+use super::Prover;
+use crate::sharp::CairoJobStatus;
+
+/// Stub prover, which considers every job proved onchain once `proving_time` has elapsed
+///
+/// The fact of a job is the keccak of its PIE. Jobs are kept in memory only, so that they are
+/// unknown after a restart and have to be submitted again.
+#[derive(Debug, Default)]
+pub struct LocalProver {
+    proving_time: Duration,
+    jobs: Mutex<HashMap<Uuid, (Instant, H256)>>,
+}
+
+impl LocalProver {
+    pub fn new(proving_time: Duration) -> Self {
+        Self { proving_time, jobs: Mutex::default() }
+    }
+}
+
+#[async_trait]
+impl Prover for LocalProver {
+    async fn submit_pie(&self, pie: &[u8]) -> Result<Uuid> {
+        let job_key = Uuid::new_v4();
+        self.jobs.lock().unwrap().insert(job_key, (Instant::now(), H256(keccak256(pie))));
+
+        Ok(job_key)
+    }
+
+    async fn job_status(&self, job_key: Uuid) -> Result<CairoJobStatus> {
+        Ok(match self.jobs.lock().unwrap().get(&job_key) {
+            Some((submitted_at, _)) if submitted_at.elapsed() >= self.proving_time => CairoJobStatus::Onchain,
+            Some(_) => CairoJobStatus::InProgress,
+            None => CairoJobStatus::Unknown,
+        })
+    }
+
+    async fn fact(&self, job_key: Uuid) -> Result<H256> {
+        match self.job_status(job_key).await? {
+            CairoJobStatus::Onchain => Ok(self.jobs.lock().unwrap()[&job_key].1),
+            status => Err(anyhow!("job {job_key} is not onchain, its status is {status}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn jobs_are_onchain_after_the_proving_time() {
+        let prover = LocalProver::new(Duration::from_millis(50));
+        let job_key = prover.submit_pie(b"pie").await.unwrap();
+
+        assert_eq!(prover.job_status(job_key).await.unwrap(), CairoJobStatus::InProgress);
+        assert!(prover.fact(job_key).await.is_err());
+        assert_eq!(prover.job_status(Uuid::new_v4()).await.unwrap(), CairoJobStatus::Unknown);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(prover.job_status(job_key).await.unwrap(), CairoJobStatus::Onchain);
+        assert_eq!(prover.fact(job_key).await.unwrap(), H256(keccak256(b"pie")));
+    }
+}
@@ -0,0 +1,23 @@
+pub mod config;
+pub mod local;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::H256;
+use uuid::Uuid;
+
+use crate::sharp::CairoJobStatus;
+
+/// Proves the execution of the Starknet OS over blocks, in Validity mode
+///
+/// A Cairo PIE is submitted for each block, and the resulting job is polled until its proof is
+/// onchain. The fact registered by the proof is what the state update is checked against.
+#[async_trait]
+pub trait Prover: Send + Sync {
+    /// Submit a Cairo PIE to be proved, and return the key of the proving job
+    async fn submit_pie(&self, pie: &[u8]) -> Result<Uuid>;
+    /// Return the status of a proving job
+    async fn job_status(&self, job_key: Uuid) -> Result<CairoJobStatus>;
+    /// Return the fact registered onchain by the proof of a job
+    async fn fact(&self, job_key: Uuid) -> Result<H256>;
+}
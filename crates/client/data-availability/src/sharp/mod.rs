@@ -1,13 +1,19 @@
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use ethers::types::H256;
 use serde::Deserialize;
 use uuid::Uuid;
 
-#[allow(dead_code)]
+use crate::prover::Prover;
+
 pub const LAMBDA_URL: &str = "https://testnet.provingservice.io";
 pub const _LAMBDA_MAX_PIE_MB: u64 = 20_971_520;
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CairoJobStatus {
     Unknown,
     NotCreated,
@@ -18,7 +24,6 @@ pub enum CairoJobStatus {
     Failed,
 }
 
-#[allow(dead_code)]
 impl CairoJobStatus {
     fn as_str(&self) -> &'static str {
         match self {
@@ -33,29 +38,33 @@ impl CairoJobStatus {
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-pub struct CairoJobResponse {
-    pub cairo_job_key: Uuid,
-    pub version: u64,
+impl fmt::Display for CairoJobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-// Send zipped CairoPie to SHARP
-// - PIE Submission format base64.b64encode(cairo_pie.serialize()).decode("ascii")
-pub fn submit_pie(pie: &str) -> Result<CairoJobResponse> {
-    let data = serde_json::json!({ "cairo_pie": pie });
-    let data = serde_json::json!({ "action": "add_job", "request": data });
-    let _payload: serde_json::Value = serde_json::from_value(data).unwrap();
-
-    // CAREFUL NOT TO OVERWHELM SHARP DUE TO SHORT BLOCK TIMES
-    // TODO: uncomment w/ Validity DaMode impl
-    // let resp = reqwest::blocking::Client::new().post(LAMBDA_URL).json(&payload).send().unwrap();
+impl FromStr for CairoJobStatus {
+    type Err = anyhow::Error;
 
-    // match resp.status() {
-    //     reqwest::StatusCode::OK => Ok(resp.json::<CairoJobResponse>().unwrap()),
-    //     _ => Err(String::from("could not submit pie")),
-    // }
+    fn from_str(status: &str) -> Result<Self> {
+        match status {
+            "UNKNOWN" => Ok(CairoJobStatus::Unknown),
+            "NOT_CREATED" => Ok(CairoJobStatus::NotCreated),
+            "IN_PROGRESS" => Ok(CairoJobStatus::InProgress),
+            "PROCESSED" => Ok(CairoJobStatus::Processed),
+            "ONCHAIN" => Ok(CairoJobStatus::Onchain),
+            "INVALID" => Ok(CairoJobStatus::Invalid),
+            "FAILED" => Ok(CairoJobStatus::Failed),
+            status => Err(anyhow::anyhow!("unknown cairo job status {status}")),
+        }
+    }
+}
 
-    Ok(CairoJobResponse { cairo_job_key: Uuid::new_v4(), version: 1_u64 })
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct CairoJobResponse {
+    pub cairo_job_key: Uuid,
+    pub version: u64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
@@ -67,17 +76,69 @@ pub struct CairoStatusResponse {
     pub version: Option<u64>,
 }
 
-// Fetch Cairo Job Status from SHARP
-// TODO: function will be needed in Validity DaMode impl
-fn _get_status(job_key: &str) -> Result<CairoStatusResponse> {
-    let data = serde_json::json!({ "cairo_job_key": job_key });
-    let data = serde_json::json!({ "action": "get_status", "request": data });
-    let payload: serde_json::Value = serde_json::from_value(data).unwrap();
+/// Prover submitting the Cairo PIEs to SHARP
+#[derive(Clone, Debug, Default)]
+pub struct SharpProver {
+    http_client: reqwest::Client,
+}
+
+#[async_trait]
+impl Prover for SharpProver {
+    // Send zipped CairoPie to SHARP
+    // - PIE Submission format base64.b64encode(cairo_pie.serialize()).decode("ascii")
+    async fn submit_pie(&self, pie: &[u8]) -> Result<Uuid> {
+        let pie = base64::engine::general_purpose::STANDARD.encode(pie);
+        let data = serde_json::json!({ "cairo_pie": pie });
+        let payload = serde_json::json!({ "action": "add_job", "request": data });
+
+        // CAREFUL NOT TO OVERWHELM SHARP DUE TO SHORT BLOCK TIMES
+        let resp = self.http_client.post(LAMBDA_URL).json(&payload).send().await?;
+
+        match resp.status() {
+            reqwest::StatusCode::OK => Ok(resp.json::<CairoJobResponse>().await?.cairo_job_key),
+            status => Err(anyhow::anyhow!("could not submit pie: {status}")),
+        }
+    }
 
-    let resp = reqwest::blocking::Client::new().post(LAMBDA_URL).json(&payload).send().unwrap();
+    async fn job_status(&self, job_key: Uuid) -> Result<CairoJobStatus> {
+        let data = serde_json::json!({ "cairo_job_key": job_key.to_string() });
+        let payload = serde_json::json!({ "action": "get_status", "request": data });
 
-    match resp.status() {
-        reqwest::StatusCode::OK => Ok(resp.json::<CairoStatusResponse>().unwrap()),
-        _ => Err(anyhow::anyhow!("could not get job status")),
+        let resp = self.http_client.post(LAMBDA_URL).json(&payload).send().await?;
+
+        match resp.status() {
+            reqwest::StatusCode::OK => match resp.json::<CairoStatusResponse>().await?.status {
+                Some(status) => status.parse(),
+                None => Ok(CairoJobStatus::Unknown),
+            },
+            status => Err(anyhow::anyhow!("could not get job status: {status}")),
+        }
+    }
+
+    async fn fact(&self, job_key: Uuid) -> Result<H256> {
+        // TODO: SHARP doesn't return the facts of its jobs. The fact is the hash of the program
+        // hash and program output of the Starknet OS, which Madara doesn't run yet
+        Err(anyhow::anyhow!("can't retrieve the fact of SHARP job {job_key}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_status_parses_its_string_form() {
+        for status in [
+            CairoJobStatus::Unknown,
+            CairoJobStatus::NotCreated,
+            CairoJobStatus::InProgress,
+            CairoJobStatus::Processed,
+            CairoJobStatus::Onchain,
+            CairoJobStatus::Invalid,
+            CairoJobStatus::Failed,
+        ] {
+            assert_eq!(status.to_string().parse::<CairoJobStatus>().unwrap(), status);
+        }
+        assert!("DONE".parse::<CairoJobStatus>().is_err());
     }
 }
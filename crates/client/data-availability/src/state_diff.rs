@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use ethers::types::U256;
+use mp_commitments::{
+    calculate_classes_tree_root, calculate_contract_state_hash, calculate_global_state_root, calculate_state_tree_root,
+};
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// Number of bits of the nonce and of the number of storage updates in the packed contract word
+const PACKED_FIELD_BITS: usize = 64;
+
+/// State changes of a single contract
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractStateDiff {
+    /// Nonce of the contract after the block
+    pub nonce: U256,
+    /// New class hash, if the contract was deployed or its class replaced
+    pub class_hash: Option<U256>,
+    /// Storage updates, sorted by storage key
+    pub storage_updates: BTreeMap<U256, U256>,
+}
+
+/// State diff of one or more blocks
+///
+/// Every collection is key-sorted so that a state diff always has the same encoding.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// Contracts whose state changed, sorted by address
+    pub contracts: BTreeMap<U256, ContractStateDiff>,
+    /// Compiled class hashes of the declared Cairo 1 classes, sorted by class hash
+    pub declared_classes: BTreeMap<U256, U256>,
+}
+
+impl StateDiff {
+    /// Encode the state diff in the Starknet on-chain data format, as of v0.11.0
+    ///
+    /// - <https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/on-chain-data/#v0.11.0_format>
+    ///
+    /// For each contract, the nonce and the number of storage updates are packed in a single word,
+    /// along with a flag telling whether the contract's class hash follows. Fails if a nonce
+    /// doesn't fit in its packed field.
+    pub fn encode(&self) -> Result<Vec<U256>> {
+        let mut encoded = vec![U256::from(self.contracts.len())];
+
+        for (address, diff) in &self.contracts {
+            let class_flag = U256::from(diff.class_hash.is_some() as u8);
+            if diff.nonce.bits() > PACKED_FIELD_BITS {
+                bail!("nonce {:#x} of contract {address:#x} doesn't fit in {PACKED_FIELD_BITS} bits", diff.nonce);
+            }
+            let n_updates = U256::from(diff.storage_updates.len());
+
+            encoded.push(*address);
+            encoded.push((class_flag << (2 * PACKED_FIELD_BITS)) | (diff.nonce << PACKED_FIELD_BITS) | n_updates);
+            if let Some(class_hash) = diff.class_hash {
+                encoded.push(class_hash);
+            }
+            for (key, value) in &diff.storage_updates {
+                encoded.push(*key);
+                encoded.push(*value);
+            }
+        }
+
+        encoded.push(U256::from(self.declared_classes.len()));
+        for (class_hash, compiled_class_hash) in &self.declared_classes {
+            encoded.push(*class_hash);
+            encoded.push(*compiled_class_hash);
+        }
+
+        Ok(encoded)
+    }
+
+    /// Return the state diff writing the given Starknet state from the empty state
+    ///
+    /// The state is given as raw storage entries, e.g. the genesis storage built from the chain
+    /// spec. Entries of other pallets are ignored.
+    pub fn from_storage<'a>(storage: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> Self {
+        let mut state_diff = StateDiff::default();
+        for (key, value) in storage {
+            state_diff.record_storage_change(key, Some(value));
+        }
+
+        state_diff
+    }
+
+    /// Record a change of the Starknet pallet storage, from its storage key and new value
+    ///
+    /// Returns the address of the contract whose nonce changed, if the change is the one of a
+    /// nonce.
+    pub fn record_storage_change(&mut self, key: &[u8], value: Option<&[u8]>) -> Option<U256> {
+        let (prefix, key) = if key.len() > 32 { key.split_at(32) } else { (key, &[][..]) };
+        let data = value.map(U256::from_big_endian);
+
+        if prefix == *utils::SN_NONCE_PREFIX {
+            if let (Some(nonce), Some(address)) = (data, utils::storage_map_key(key, 32)) {
+                let address = U256::from_big_endian(address);
+                self.contracts.entry(address).or_default().nonce = nonce;
+                return Some(address);
+            }
+        } else if prefix == *utils::SN_STORAGE_PREFIX {
+            if let Some(key) = utils::storage_map_key(key, 64) {
+                // first 32 bytes = contract address, second 32 bytes = storage variable
+                let (address, storage_key) = key.split_at(32);
+
+                // A removed storage value reads as zero
+                self.contracts
+                    .entry(U256::from_big_endian(address))
+                    .or_default()
+                    .storage_updates
+                    .insert(U256::from_big_endian(storage_key), data.unwrap_or_default());
+            }
+        } else if prefix == *utils::SN_CONTRACT_CLASS_HASH_PREFIX {
+            // The contract has been deployed or its class replaced
+            if let (Some(class_hash), Some(address)) = (data, utils::storage_map_key(key, 32)) {
+                self.contracts.entry(U256::from_big_endian(address)).or_default().class_hash = Some(class_hash);
+            }
+        } else if prefix == *utils::SN_COMPILED_CLASS_HASH_PREFIX {
+            if let (Some(compiled_class_hash), Some(class_hash)) = (data, utils::storage_map_key(key, 32)) {
+                self.declared_classes.insert(U256::from_big_endian(class_hash), compiled_class_hash);
+            }
+        }
+
+        None
+    }
+
+    /// Return whether the state diff doesn't change anything
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty() && self.declared_classes.is_empty()
+    }
+
+    /// Apply a following state diff on top of this one
+    ///
+    /// Merging every state diff from the genesis rebuilds the whole state, each contract holding
+    /// its class hash, nonce and full storage.
+    pub fn merge(&mut self, other: StateDiff) {
+        for (address, diff) in other.contracts {
+            let contract = self.contracts.entry(address).or_default();
+            contract.nonce = diff.nonce;
+            if diff.class_hash.is_some() {
+                contract.class_hash = diff.class_hash;
+            }
+            contract.storage_updates.extend(diff.storage_updates);
+        }
+        self.declared_classes.extend(other.declared_classes);
+    }
+
+    /// Return the global state root of the state rebuilt by merging every state diff from the
+    /// genesis
+    pub fn state_root(&self) -> Result<Felt252Wrapper> {
+        let contracts = self
+            .contracts
+            .iter()
+            .map(|(address, contract)| {
+                let storage = contract
+                    .storage_updates
+                    .iter()
+                    .map(|(key, value)| Ok((to_felt(*key)?, to_felt(*value)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                let storage_root = calculate_state_tree_root::<PedersenHasher>(&storage);
+                let class_hash = contract.class_hash.map(to_felt).transpose()?.unwrap_or_default();
+                let contract_state_hash =
+                    calculate_contract_state_hash::<PedersenHasher>(class_hash, storage_root, to_felt(contract.nonce)?);
+
+                Ok((to_felt(*address)?, contract_state_hash))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let classes = self
+            .declared_classes
+            .iter()
+            .map(|(class_hash, compiled_class_hash)| Ok((to_felt(*class_hash)?, to_felt(*compiled_class_hash)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(calculate_global_state_root::<PoseidonHasher>(
+            calculate_state_tree_root::<PedersenHasher>(&contracts),
+            calculate_classes_tree_root::<PoseidonHasher>(&classes),
+        ))
+    }
+
+    /// Decode a state diff encoded with [`StateDiff::encode`]
+    pub fn decode(encoded: &[U256]) -> Result<Self> {
+        let mut words = encoded.iter().copied();
+        let mut next = || words.next().ok_or_else(|| anyhow!("state diff is truncated"));
+        let mut state_diff = StateDiff::default();
+
+        let n_contracts = count(next()?)?;
+        for _ in 0..n_contracts {
+            let address = next()?;
+            let packed = next()?;
+            let field_mask = U256::from(u64::MAX);
+
+            let mut diff = ContractStateDiff {
+                nonce: (packed >> PACKED_FIELD_BITS) & field_mask,
+                class_hash: None,
+                storage_updates: BTreeMap::new(),
+            };
+            match count(packed >> (2 * PACKED_FIELD_BITS)) {
+                Ok(0) => {}
+                Ok(1) => diff.class_hash = Some(next()?),
+                _ => bail!("invalid class information flag for contract {address:#x}"),
+            }
+            for _ in 0..count(packed & field_mask)? {
+                let key = next()?;
+                diff.storage_updates.insert(key, next()?);
+            }
+
+            state_diff.contracts.insert(address, diff);
+        }
+
+        let n_declared_classes = count(next()?)?;
+        for _ in 0..n_declared_classes {
+            let class_hash = next()?;
+            state_diff.declared_classes.insert(class_hash, next()?);
+        }
+
+        if words.next().is_some() {
+            bail!("unexpected data after the end of the state diff");
+        }
+
+        Ok(state_diff)
+    }
+}
+
+fn to_felt(word: U256) -> Result<Felt252Wrapper> {
+    let mut bytes = [0_u8; 32];
+    word.to_big_endian(&mut bytes);
+    Felt252Wrapper::try_from(&bytes).map_err(|e| anyhow!("{word:#x} is not a valid felt: {e}"))
+}
+
+fn count(word: U256) -> Result<u64> {
+    u64::try_from(word).map_err(|_| anyhow!("invalid count {word:#x} in state diff"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_diff() -> StateDiff {
+        let mut state_diff = StateDiff::default();
+        state_diff.contracts.insert(
+            U256::from(2),
+            ContractStateDiff {
+                nonce: U256::from(1),
+                class_hash: None,
+                storage_updates: [(U256::from(9), U256::from(10)), (U256::from(7), U256::from(8))].into(),
+            },
+        );
+        state_diff.contracts.insert(
+            U256::from(1),
+            ContractStateDiff {
+                nonce: U256::zero(),
+                class_hash: Some(U256::from(3)),
+                storage_updates: BTreeMap::new(),
+            },
+        );
+        state_diff.declared_classes.insert(U256::from(4), U256::from(5));
+        state_diff
+    }
+
+    #[test]
+    fn encoding_is_key_sorted() {
+        let packed = |class_flag: u64, nonce: u64, n_updates: u64| {
+            (U256::from(class_flag) << 128) | (U256::from(nonce) << 64) | U256::from(n_updates)
+        };
+
+        assert_eq!(
+            state_diff().encode().unwrap(),
+            vec![
+                U256::from(2),
+                // Deployed contract
+                U256::from(1),
+                packed(1, 0, 0),
+                U256::from(3),
+                // Contract with storage updates
+                U256::from(2),
+                packed(0, 1, 2),
+                U256::from(7),
+                U256::from(8),
+                U256::from(9),
+                U256::from(10),
+                // Declared classes
+                U256::from(1),
+                U256::from(4),
+                U256::from(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_reverts_encode() {
+        let state_diff = state_diff();
+        let encoded = state_diff.encode().unwrap();
+
+        assert_eq!(StateDiff::decode(&encoded).unwrap(), state_diff);
+        assert!(StateDiff::decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(StateDiff::decode(&[encoded.as_slice(), &[U256::one()]].concat()).is_err());
+    }
+
+    #[test]
+    fn encode_rejects_a_nonce_overflowing_its_field() {
+        let mut state_diff = state_diff();
+        state_diff.contracts.get_mut(&U256::from(2)).unwrap().nonce = U256::from(u64::MAX);
+        assert!(state_diff.encode().is_ok());
+
+        state_diff.contracts.get_mut(&U256::from(2)).unwrap().nonce = U256::from(u64::MAX) + 1;
+        assert!(state_diff.encode().is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_latest_values() {
+        let mut state = state_diff();
+        let mut next = StateDiff::default();
+        next.contracts.insert(
+            U256::from(1),
+            ContractStateDiff {
+                nonce: U256::from(1),
+                class_hash: None,
+                storage_updates: [(U256::from(7), U256::from(11))].into(),
+            },
+        );
+        next.contracts.insert(
+            U256::from(2),
+            ContractStateDiff {
+                nonce: U256::from(2),
+                class_hash: Some(U256::from(6)),
+                storage_updates: [(U256::from(7), U256::from(12))].into(),
+            },
+        );
+
+        state.merge(next);
+
+        let first_contract = &state.contracts[&U256::from(1)];
+        assert_eq!(first_contract.nonce, U256::from(1));
+        assert_eq!(first_contract.class_hash, Some(U256::from(3)));
+        assert_eq!(first_contract.storage_updates, [(U256::from(7), U256::from(11))].into());
+
+        let second_contract = &state.contracts[&U256::from(2)];
+        assert_eq!(second_contract.nonce, U256::from(2));
+        assert_eq!(second_contract.class_hash, Some(U256::from(6)));
+        assert_eq!(
+            second_contract.storage_updates,
+            [(U256::from(7), U256::from(12)), (U256::from(9), U256::from(10))].into()
+        );
+        assert_eq!(state.declared_classes, [(U256::from(4), U256::from(5))].into());
+    }
+
+    #[test]
+    fn state_diff_is_built_from_the_starknet_storage() {
+        let word = |n: u8| {
+            let mut bytes = [0_u8; 32];
+            bytes[31] = n;
+            bytes
+        };
+        // Keys of maps using the `Blake2_128Concat` hasher
+        let map_key = |prefix: &[u8], key: &[u8]| [prefix, &sp_io::hashing::blake2_128(key), key].concat();
+
+        let storage = [
+            (map_key(&utils::SN_CONTRACT_CLASS_HASH_PREFIX, &word(1)), word(3).to_vec()),
+            (map_key(&utils::SN_NONCE_PREFIX, &word(2)), word(1).to_vec()),
+            (map_key(&utils::SN_STORAGE_PREFIX, &[word(2), word(7)].concat()), word(8).to_vec()),
+            (map_key(&utils::SN_STORAGE_PREFIX, &[word(2), word(9)].concat()), word(10).to_vec()),
+            (map_key(&utils::SN_COMPILED_CLASS_HASH_PREFIX, &word(4)), word(5).to_vec()),
+            // Not part of the Starknet state
+            (sp_io::hashing::twox_128(b"System").to_vec(), word(6).to_vec()),
+        ];
+
+        let storage = storage.iter().map(|(key, value)| (key.as_slice(), value.as_slice()));
+        assert_eq!(StateDiff::from_storage(storage), state_diff());
+    }
+}
@@ -1,9 +1,9 @@
-use std::collections::HashMap;
-
+use anyhow::{anyhow, Result};
 use ethers::types::U256;
 use lazy_static::lazy_static;
 use mp_storage::{
-    PALLET_STARKNET, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE,
+    PALLET_STARKNET, STARKNET_COMPILED_CLASS_HASH, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH,
+    STARKNET_NONCE, STARKNET_STORAGE,
 };
 use sp_io::hashing::twox_128;
 use url::{ParseError, Url};
@@ -14,33 +14,42 @@ lazy_static! {
         [twox_128(PALLET_STARKNET), twox_128(STARKNET_CONTRACT_CLASS_HASH)].concat();
     pub static ref SN_CONTRACT_CLASS_PREFIX: Vec<u8> =
         [twox_128(PALLET_STARKNET), twox_128(STARKNET_CONTRACT_CLASS)].concat();
+    pub static ref SN_COMPILED_CLASS_HASH_PREFIX: Vec<u8> =
+        [twox_128(PALLET_STARKNET), twox_128(STARKNET_COMPILED_CLASS_HASH)].concat();
     pub static ref SN_STORAGE_PREFIX: Vec<u8> = [twox_128(PALLET_STARKNET), twox_128(STARKNET_STORAGE)].concat();
 }
 
-// encode calldata:
-// - https://docs.starknet.io/documentation/architecture_and_concepts/Data_Availability/on-chain-data/#pre_v0.11.0_example
-pub fn pre_0_11_0_state_diff(
-    storage_diffs: HashMap<&[u8], crate::StorageWrites>,
-    nonces: HashMap<&[u8], &[u8]>,
-) -> Vec<U256> {
-    let mut state_diff: Vec<U256> = Vec::new();
+/// Returns the encoded key of a storage map entry, from the part of its storage key following the
+/// map prefix.
+///
+/// The encoded key ends the storage key with both the `Identity` and `Blake2_128Concat` hashers,
+/// so the schema version doesn't have to be known.
+pub fn storage_map_key(hashed_key: &[u8], key_len: usize) -> Option<&[u8]> {
+    hashed_key.len().checked_sub(key_len).map(|start| &hashed_key[start..])
+}
 
-    state_diff.push(U256::from(storage_diffs.len()));
+/// The maps whose entries are moved from the `Identity` hasher of the storage schema V1 to the
+/// `Blake2_128Concat` hasher, with the length of their encoded keys
+fn schema_v1_maps() -> [(&'static [u8], usize); 3] {
+    [
+        (SN_NONCE_PREFIX.as_slice(), 32),
+        (SN_CONTRACT_CLASS_HASH_PREFIX.as_slice(), 32),
+        (SN_STORAGE_PREFIX.as_slice(), 64),
+    ]
+}
 
-    for (addr, writes) in storage_diffs {
-        state_diff.push(U256::from_big_endian(addr));
-        state_diff.push(U256::from(writes.len()));
-        for write in writes {
-            state_diff.push(U256::from_big_endian(write.0));
-            state_diff.push(U256::from_big_endian(write.1));
-        }
-    }
+/// Returns whether a storage key is the key of a map entry at its storage schema V1 location
+pub fn is_schema_v1_map_key(key: &[u8]) -> bool {
+    schema_v1_maps().iter().any(|(prefix, key_len)| key.len() == prefix.len() + key_len && key.starts_with(prefix))
+}
 
-    for (addr, nonce) in nonces {
-        state_diff.push(U256::from_big_endian(addr));
-        state_diff.push(U256::from_big_endian(nonce));
-    }
-    state_diff
+/// Returns the storage schema V1 location of a map entry stored with the `Blake2_128Concat`
+/// hasher, if the map was migrated from the `Identity` hasher.
+pub fn schema_v1_map_key(key: &[u8]) -> Option<Vec<u8>> {
+    schema_v1_maps()
+        .into_iter()
+        .find(|(prefix, key_len)| key.len() == prefix.len() + 16 + key_len && key.starts_with(prefix))
+        .map(|(prefix, key_len)| [prefix, &key[key.len() - key_len..]].concat())
 }
 
 pub fn get_bytes_from_state_diff(state_diff: &[U256]) -> Vec<u8> {
@@ -56,6 +65,14 @@ pub fn get_bytes_from_state_diff(state_diff: &[U256]) -> Vec<u8> {
     state_diff_bytes
 }
 
+pub fn get_state_diff_from_bytes(state_diff_bytes: &[u8]) -> Result<Vec<U256>> {
+    if state_diff_bytes.len() % 32 != 0 {
+        return Err(anyhow!("state diff length {} is not a multiple of 32 bytes", state_diff_bytes.len()));
+    }
+
+    Ok(state_diff_bytes.chunks(32).map(U256::from_big_endian).collect())
+}
+
 pub fn get_valid_url(endpoint: &str) -> Result<Url, ParseError> {
     Url::parse(endpoint)
 }
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::PathBuf;
+
+use ethers::types::U256;
+use serde::Deserialize;
+
+use crate::ethereum::config::EthereumConfig;
+use crate::state_diff::{ContractStateDiff, StateDiff};
+use crate::DaClient;
+
+/// Storage slot whose updates go on-chain, in Volition mode
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct OnchainStorageKey {
+    pub contract_address: U256,
+    pub key: U256,
+}
+
+/// Selection of the state going on-chain in Volition mode
+///
+/// Read from the `volition` section of the DA config, along with the settings of the Ethereum
+/// client the on-chain state diffs are published with, which are both required. Addresses and keys
+/// are hex strings.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct VolitionConfig {
+    /// Contracts whose whole state diff goes on-chain
+    #[serde(default)]
+    pub onchain_contracts: BTreeSet<U256>,
+    /// Storage slots whose updates go on-chain, whatever their contract
+    #[serde(default)]
+    pub onchain_storage_keys: BTreeSet<OnchainStorageKey>,
+    pub ethereum: EthereumConfig,
+}
+
+impl TryFrom<&PathBuf> for VolitionConfig {
+    type Error = String;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        #[derive(Deserialize)]
+        struct DaConfig {
+            volition: Option<VolitionConfig>,
+        }
+
+        let file = File::open(path).map_err(|e| format!("error opening da config: {e}"))?;
+        let conf: DaConfig = serde_json::from_reader(file).map_err(|e| format!("error parsing da config: {e}"))?;
+        let mut volition = conf.volition.ok_or_else(|| "volition da mode requires a volition section".to_string())?;
+
+        if let (Some(trusted_setup), Some(config_dir)) = (&volition.ethereum.trusted_setup, path.parent()) {
+            volition.ethereum.trusted_setup = Some(config_dir.join(trusted_setup));
+        }
+
+        Ok(volition)
+    }
+}
+
+impl VolitionConfig {
+    /// Split a state diff into its on-chain and off-chain parts
+    ///
+    /// A contract appears in each part holding some of its storage updates, along with its nonce
+    /// and class hash, so that each part can be decoded on its own. Contracts without storage
+    /// updates and declared classes go off-chain, unless the contract is an on-chain one.
+    pub fn split(&self, state_diff: StateDiff) -> (StateDiff, StateDiff) {
+        let mut onchain = StateDiff::default();
+        let mut offchain = StateDiff { declared_classes: state_diff.declared_classes, ..Default::default() };
+
+        for (address, diff) in state_diff.contracts {
+            if self.onchain_contracts.contains(&address) {
+                onchain.contracts.insert(address, diff);
+                continue;
+            }
+
+            let (onchain_updates, offchain_updates): (BTreeMap<_, _>, BTreeMap<_, _>) =
+                diff.storage_updates.into_iter().partition(|(key, _)| {
+                    self.onchain_storage_keys.contains(&OnchainStorageKey { contract_address: address, key: *key })
+                });
+            let part = |storage_updates: BTreeMap<U256, U256>| ContractStateDiff {
+                nonce: diff.nonce,
+                class_hash: diff.class_hash,
+                storage_updates,
+            };
+
+            let has_onchain_updates = !onchain_updates.is_empty();
+            if has_onchain_updates {
+                onchain.contracts.insert(address, part(onchain_updates));
+            }
+            if !offchain_updates.is_empty() || !has_onchain_updates {
+                offchain.contracts.insert(address, part(offchain_updates));
+            }
+        }
+
+        (onchain, offchain)
+    }
+}
+
+/// On-chain side of the Volition mode
+pub struct Volition {
+    /// Client publishing the on-chain state diffs to Ethereum
+    pub client: Box<dyn DaClient + Send + Sync>,
+    pub config: VolitionConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(nonce: u64, storage_updates: &[(u64, u64)]) -> ContractStateDiff {
+        ContractStateDiff {
+            nonce: U256::from(nonce),
+            class_hash: None,
+            storage_updates: storage_updates.iter().map(|(k, v)| (U256::from(*k), U256::from(*v))).collect(),
+        }
+    }
+
+    #[test]
+    fn split_follows_the_contract_and_storage_key_rules() {
+        let config = VolitionConfig {
+            onchain_contracts: [U256::from(1)].into(),
+            onchain_storage_keys: [OnchainStorageKey { contract_address: U256::from(2), key: U256::from(20) }].into(),
+            ethereum: EthereumConfig::default(),
+        };
+        let state_diff = StateDiff {
+            contracts: [
+                (U256::from(1), contract(1, &[(10, 1), (11, 2)])),
+                (U256::from(2), contract(2, &[(20, 3), (21, 4)])),
+                (U256::from(3), contract(3, &[])),
+            ]
+            .into(),
+            declared_classes: [(U256::from(4), U256::from(5))].into(),
+        };
+
+        let (onchain, offchain) = config.split(state_diff);
+
+        assert_eq!(
+            onchain.contracts,
+            BTreeMap::from([
+                (U256::from(1), contract(1, &[(10, 1), (11, 2)])),
+                (U256::from(2), contract(2, &[(20, 3)])),
+            ])
+        );
+        assert!(onchain.declared_classes.is_empty());
+        assert_eq!(
+            offchain.contracts,
+            BTreeMap::from([(U256::from(2), contract(2, &[(21, 4)])), (U256::from(3), contract(3, &[]))])
+        );
+        assert_eq!(offchain.declared_classes, [(U256::from(4), U256::from(5))].into());
+    }
+
+    #[test]
+    fn config_requires_the_volition_section() {
+        let dir = std::env::temp_dir().join(format!("madara-volition-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("da-config.json");
+
+        std::fs::write(&path, r#"{"mode": "volition"}"#).unwrap();
+        assert!(VolitionConfig::try_from(&path).is_err());
+
+        std::fs::write(&path, r#"{"volition": {"onchain_contracts": ["0x1"]}}"#).unwrap();
+        assert!(VolitionConfig::try_from(&path).is_err());
+
+        std::fs::write(
+            &path,
+            r#"{"volition": {"onchain_contracts": ["0x1"], "ethereum": {"trusted_setup": "trusted_setup.txt"}}}"#,
+        )
+        .unwrap();
+        let config = VolitionConfig::try_from(&path).unwrap();
+        assert_eq!(config.onchain_contracts, [U256::from(1)].into());
+        assert_eq!(config.ethereum.trusted_setup, Some(dir.join("trusted_setup.txt")));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
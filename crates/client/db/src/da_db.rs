@@ -10,25 +10,92 @@ use uuid::Uuid;
 
 use crate::DbHash;
 
+/// Prefixes of the per block sub-spaces of the `DA` column.
+///
+/// Each block hash is stored under one key per sub-space, so that entries of different
+/// kinds can't overwrite each other.
+mod prefixes {
+    pub const STATE_DIFF: &[u8] = b"STATE_DIFF";
+    pub const CAIRO_JOB: &[u8] = b"CAIRO_JOB";
+    pub const PUBLISH_STATUS: &[u8] = b"PUBLISH_STATUS";
+    pub const PROOF_STATUS: &[u8] = b"PROOF_STATUS";
+    /// Publish queue, mapping a block number to the hash of the block to publish at this height
+    pub const PUBLISH_QUEUE: &[u8] = b"PUBLISH_QUEUE";
+    /// Range of block numbers covered by each DA submission, indexed by submission number
+    pub const SUBMISSION: &[u8] = b"SUBMISSION";
+    /// Same as `SUBMISSION`, for the on-chain submissions of the Volition mode
+    pub const ONCHAIN_SUBMISSION: &[u8] = b"ONCHAIN_SUBMISSION";
+}
+
+/// The publication status of the state diff of a block on the DA layer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum PublishStatus {
+    /// The state diff is stored and waiting to be published
+    Pending,
+    /// The state diff has been published to the DA layer
+    Published,
+}
+
+/// The proving status of a block, in Validity mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum ProofStatus {
+    /// The Cairo job of the block has been submitted to the prover
+    InProgress,
+    /// The proof of the block is onchain, and registered the given fact
+    Onchain([u8; 32]),
+}
+
 // The fact db stores DA facts that need to be written to L1
+//
+// State diffs are published in strict block order through a persistent queue: the queue head is
+// the number of the next block to publish, and only moves forward once this block is published.
+// Consecutive blocks can be published together, each DA submission recording the blocks it covers.
 pub struct DaDb<B: BlockT> {
     pub(crate) db: Arc<dyn Database<DbHash>>,
     pub(crate) _marker: PhantomData<B>,
 }
 
-// TODO: purge old cairo job keys
 impl<B: BlockT> DaDb<B> {
+    /// Return the state diff of the given block, or an empty one if it has not been stored
     pub fn state_diff(&self, block_hash: &B::Hash) -> Result<Vec<U256>, String> {
-        match self.db.get(crate::columns::DA, &block_hash.encode()) {
+        match self.db.get(crate::columns::DA, &block_key(prefixes::STATE_DIFF, block_hash)) {
             Some(raw) => Ok(Vec::<U256>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
             None => Ok(Vec::new()),
         }
     }
 
-    pub fn store_state_diff(&self, block_hash: &B::Hash, diffs: Vec<U256>) -> Result<(), String> {
+    /// Store the state diff of the given block, mark it as pending publication and queue it
+    ///
+    /// A block queued at the same height is replaced, and its entries are purged. A block below the
+    /// head of the queue, whose height was already published or skipped, is not stored.
+    pub fn store_state_diff(&self, block_number: u64, block_hash: &B::Hash, diffs: Vec<U256>) -> Result<(), String> {
+        if self.publish_queue_head()?.is_some_and(|head| block_number < head) {
+            return Ok(());
+        }
+
         let mut transaction = sp_database::Transaction::new();
 
-        transaction.set(crate::columns::DA, &block_hash.encode(), &diffs.encode());
+        if let Some(replaced_hash) = self.queued_block(block_number)? {
+            if replaced_hash != *block_hash {
+                for prefix in BLOCK_PREFIXES {
+                    transaction.remove(crate::columns::DA, &block_key(prefix, &replaced_hash));
+                }
+            }
+        }
+
+        transaction.set(crate::columns::DA, &block_key(prefixes::STATE_DIFF, block_hash), &diffs.encode());
+        transaction.set(
+            crate::columns::DA,
+            &block_key(prefixes::PUBLISH_STATUS, block_hash),
+            &PublishStatus::Pending.encode(),
+        );
+        transaction.set(crate::columns::DA, &queue_key(block_number), &block_hash.encode());
+        if self.publish_queue_head()?.is_none() {
+            transaction.set(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_HEAD, &block_number.encode());
+        }
+        if self.publish_queue_tail()?.map_or(true, |tail| tail < block_number) {
+            transaction.set(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_TAIL, &block_number.encode());
+        }
 
         self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
 
@@ -36,16 +103,299 @@ impl<B: BlockT> DaDb<B> {
     }
 
     pub fn cairo_job(&self, block_hash: &B::Hash) -> Result<Uuid, String> {
-        match self.db.get(crate::columns::DA, &block_hash.encode()) {
+        match self.db.get(crate::columns::DA, &block_key(prefixes::CAIRO_JOB, block_hash)) {
             Some(raw) => Ok(Uuid::from_slice(&raw[..]).map_err(|e| format!("{:?}", e))?),
             None => Err(String::from("can't locate cairo job")),
         }
     }
 
+    /// Record the Cairo job proving the given block, whose proof is then in progress
     pub fn update_cairo_job(&self, block_hash: &B::Hash, job_id: Uuid) -> Result<(), String> {
         let mut transaction = sp_database::Transaction::new();
 
-        transaction.set(crate::columns::DA, &block_hash.encode(), &job_id.into_bytes());
+        transaction.set(crate::columns::DA, &block_key(prefixes::CAIRO_JOB, block_hash), &job_id.into_bytes());
+        transaction.set(
+            crate::columns::DA,
+            &block_key(prefixes::PROOF_STATUS, block_hash),
+            &ProofStatus::InProgress.encode(),
+        );
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Forget the Cairo job of the given block, so that a new one can be submitted
+    pub fn remove_cairo_job(&self, block_hash: &B::Hash) -> Result<(), String> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.remove(crate::columns::DA, &block_key(prefixes::CAIRO_JOB, block_hash));
+        transaction.remove(crate::columns::DA, &block_key(prefixes::PROOF_STATUS, block_hash));
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Return the proving status of the given block, if a Cairo job has been submitted for it
+    pub fn proof_status(&self, block_hash: &B::Hash) -> Result<Option<ProofStatus>, String> {
+        match self.db.get(crate::columns::DA, &block_key(prefixes::PROOF_STATUS, block_hash)) {
+            Some(raw) => Ok(Some(ProofStatus::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record that the proof of the given block is onchain, and make it the last proved block
+    pub fn mark_proved(&self, block_hash: &B::Hash, fact: [u8; 32]) -> Result<(), String> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(
+            crate::columns::DA,
+            &block_key(prefixes::PROOF_STATUS, block_hash),
+            &ProofStatus::Onchain(fact).encode(),
+        );
+        transaction.set(crate::columns::DA, crate::static_keys::LAST_PROVED_BLOCK, &block_hash.encode());
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Return the publication status of the given block, if it is tracked
+    pub fn publish_status(&self, block_hash: &B::Hash) -> Result<Option<PublishStatus>, String> {
+        match self.db.get(crate::columns::DA, &block_key(prefixes::PUBLISH_STATUS, block_hash)) {
+            Some(raw) => Ok(Some(PublishStatus::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the number of the next block to publish, if any block has been queued
+    pub fn publish_queue_head(&self) -> Result<Option<u64>, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_HEAD) {
+            Some(raw) => Ok(Some(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the number of the highest block queued so far, if any
+    fn publish_queue_tail(&self) -> Result<Option<u64>, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_TAIL) {
+            Some(raw) => Ok(Some(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Move the head of the queue past the blocks whose state diff was never stored while later
+    /// blocks were, e.g. when storing it failed, and return the numbers of the first and last
+    /// skipped blocks
+    ///
+    /// Without this, the queue would wait forever for these blocks.
+    pub fn skip_missing_blocks(&self) -> Result<Option<(u64, u64)>, String> {
+        let (head, tail) = match (self.publish_queue_head()?, self.publish_queue_tail()?) {
+            (Some(head), Some(tail)) if head < tail => (head, tail),
+            _ => return Ok(None),
+        };
+        if self.queued_block(head)?.is_some() {
+            return Ok(None);
+        }
+
+        for block_number in head + 1..=tail {
+            if self.queued_block(block_number)?.is_some() {
+                let mut transaction = sp_database::Transaction::new();
+                for skipped in head..block_number {
+                    transaction.remove(crate::columns::DA, &queue_key(skipped));
+                }
+                transaction.set(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_HEAD, &block_number.encode());
+                self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+                return Ok(Some((head, block_number - 1)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Return the number and hash of the next block to publish
+    ///
+    /// Returns `Ok(None)` while the state diff of the block at the head of the queue has not been
+    /// stored, as blocks are never published out of order. See [`Self::skip_missing_blocks`] for
+    /// blocks whose state diff will never be stored.
+    pub fn next_to_publish(&self) -> Result<Option<(u64, B::Hash)>, String> {
+        match self.publish_queue_head()? {
+            Some(block_number) => Ok(self.queued_block(block_number)?.map(|block_hash| (block_number, block_hash))),
+            None => Ok(None),
+        }
+    }
+
+    /// Return up to `max_blocks` consecutive blocks from the head of the queue, in block order
+    pub fn queued_blocks(&self, max_blocks: usize) -> Result<Vec<(u64, B::Hash)>, String> {
+        let mut blocks = Vec::new();
+        if let Some(head) = self.publish_queue_head()? {
+            for block_number in (head..).take(max_blocks) {
+                match self.queued_block(block_number)? {
+                    Some(block_hash) => blocks.push((block_number, block_hash)),
+                    None => break,
+                }
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// Record that the state diff of the given block has been published on its own
+    pub fn mark_published(&self, block_number: u64, block_hash: &B::Hash) -> Result<(), String> {
+        self.mark_batch_published(&[(block_number, *block_hash)]).map(|_| ())
+    }
+
+    /// Record that the state diffs of the given consecutive blocks have been published in a single
+    /// DA submission, and return the number of this submission
+    ///
+    /// The last block becomes the last published one and the queue moves to the following block.
+    /// The state diffs, cairo jobs and proof statuses of the blocks are purged as they are not
+    /// needed anymore.
+    pub fn mark_batch_published(&self, blocks: &[(u64, B::Hash)]) -> Result<u64, String> {
+        let (first_block, last_block) = match (blocks.first(), blocks.last()) {
+            (Some(first_block), Some(last_block)) => (first_block, last_block),
+            _ => return Err(String::from("can't publish an empty batch")),
+        };
+        let submission = self.submission_count()?;
+
+        let mut transaction = sp_database::Transaction::new();
+
+        for (block_number, block_hash) in blocks {
+            transaction.set(
+                crate::columns::DA,
+                &block_key(prefixes::PUBLISH_STATUS, block_hash),
+                &PublishStatus::Published.encode(),
+            );
+            transaction.remove(crate::columns::DA, &queue_key(*block_number));
+            transaction.remove(crate::columns::DA, &block_key(prefixes::STATE_DIFF, block_hash));
+            transaction.remove(crate::columns::DA, &block_key(prefixes::CAIRO_JOB, block_hash));
+            transaction.remove(crate::columns::DA, &block_key(prefixes::PROOF_STATUS, block_hash));
+        }
+        transaction.set(crate::columns::DA, crate::static_keys::LAST_PUBLISHED_BLOCK, &last_block.1.encode());
+        transaction.set(crate::columns::DA, crate::static_keys::PUBLISH_QUEUE_HEAD, &(last_block.0 + 1).encode());
+        transaction.set(crate::columns::DA, &submission_key(submission), &(first_block.0, last_block.0).encode());
+        transaction.set(crate::columns::DA, crate::static_keys::DA_SUBMISSION_COUNT, &(submission + 1).encode());
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(submission)
+    }
+
+    /// Return the number of DA submissions made so far
+    pub fn submission_count(&self) -> Result<u64, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::DA_SUBMISSION_COUNT) {
+            Some(raw) => Ok(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Ok(0),
+        }
+    }
+
+    /// Return the numbers of the first and last blocks covered by the given DA submission
+    pub fn submission(&self, submission: u64) -> Result<Option<(u64, u64)>, String> {
+        match self.db.get(crate::columns::DA, &submission_key(submission)) {
+            Some(raw) => Ok(Some(<(u64, u64)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the number of the last block whose on-chain state diff has been published, in
+    /// Volition mode
+    pub fn last_onchain_published_block(&self) -> Result<Option<u64>, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::LAST_ONCHAIN_PUBLISHED_BLOCK) {
+            Some(raw) => Ok(Some(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record that the on-chain part of the state diffs of the given consecutive blocks has been
+    /// dealt with, in Volition mode
+    ///
+    /// If it was submitted, which it is not when empty, the submission is recorded and its number
+    /// returned. The blocks stay queued until their off-chain part is published.
+    pub fn mark_onchain_published(&self, blocks: &[(u64, B::Hash)], submitted: bool) -> Result<Option<u64>, String> {
+        let (first_block, last_block) = match (blocks.first(), blocks.last()) {
+            (Some(first_block), Some(last_block)) => (first_block.0, last_block.0),
+            _ => return Err(String::from("can't publish an empty batch")),
+        };
+
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(crate::columns::DA, crate::static_keys::LAST_ONCHAIN_PUBLISHED_BLOCK, &last_block.encode());
+        let submission = if submitted {
+            let submission = self.onchain_submission_count()?;
+            transaction.set(
+                crate::columns::DA,
+                &onchain_submission_key(submission),
+                &(first_block, last_block).encode(),
+            );
+            transaction.set(
+                crate::columns::DA,
+                crate::static_keys::ONCHAIN_SUBMISSION_COUNT,
+                &(submission + 1).encode(),
+            );
+            Some(submission)
+        } else {
+            None
+        };
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(submission)
+    }
+
+    /// Return the number of on-chain submissions made so far, in Volition mode
+    pub fn onchain_submission_count(&self) -> Result<u64, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::ONCHAIN_SUBMISSION_COUNT) {
+            Some(raw) => Ok(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Ok(0),
+        }
+    }
+
+    /// Return the numbers of the first and last blocks covered by the given on-chain submission
+    pub fn onchain_submission(&self, submission: u64) -> Result<Option<(u64, u64)>, String> {
+        match self.db.get(crate::columns::DA, &onchain_submission_key(submission)) {
+            Some(raw) => Ok(Some(<(u64, u64)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove every entry stored for the given block
+    ///
+    /// Used for blocks that will never be published, or whose publication status is not needed
+    /// anymore.
+    pub fn purge(&self, block_hash: &B::Hash) -> Result<(), String> {
+        let mut transaction = sp_database::Transaction::new();
+
+        for prefix in BLOCK_PREFIXES {
+            transaction.remove(crate::columns::DA, &block_key(prefix, block_hash));
+        }
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Return whether the entries stored under the bare block hashes, before the per block
+    /// sub-spaces, have all been removed
+    pub fn legacy_entries_removed(&self) -> Result<bool, String> {
+        Ok(self.db.contains(crate::columns::DA, crate::static_keys::DA_LEGACY_ENTRIES_REMOVED))
+    }
+
+    /// Remove the entries stored under the bare hash of the given blocks, before the per block
+    /// sub-spaces
+    ///
+    /// These entries, a state diff or a cairo job id, are never read anymore. Once every block has
+    /// been dealt with, `done` records it so that they aren't looked for again.
+    pub fn remove_legacy_entries(&self, block_hashes: &[B::Hash], done: bool) -> Result<(), String> {
+        let mut transaction = sp_database::Transaction::new();
+
+        for block_hash in block_hashes {
+            transaction.remove(crate::columns::DA, &block_hash.encode());
+        }
+        if done {
+            transaction.set(crate::columns::DA, crate::static_keys::DA_LEGACY_ENTRIES_REMOVED, &[]);
+        }
 
         self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
 
@@ -68,4 +418,295 @@ impl<B: BlockT> DaDb<B> {
 
         Ok(())
     }
+
+    /// Return the last block whose state diff has been published, if any
+    pub fn last_published_block(&self) -> Result<Option<B::Hash>, String> {
+        match self.db.get(crate::columns::DA, crate::static_keys::LAST_PUBLISHED_BLOCK) {
+            Some(raw) => Ok(Some(B::Hash::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    fn queued_block(&self, block_number: u64) -> Result<Option<B::Hash>, String> {
+        match self.db.get(crate::columns::DA, &queue_key(block_number)) {
+            Some(raw) => Ok(Some(B::Hash::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Every per block sub-space
+const BLOCK_PREFIXES: [&[u8]; 4] =
+    [prefixes::STATE_DIFF, prefixes::CAIRO_JOB, prefixes::PUBLISH_STATUS, prefixes::PROOF_STATUS];
+
+fn block_key<H: Encode>(prefix: &[u8], block_hash: &H) -> Vec<u8> {
+    [prefix, &block_hash.encode()].concat()
+}
+
+fn queue_key(block_number: u64) -> Vec<u8> {
+    [prefixes::PUBLISH_QUEUE, &block_number.to_be_bytes()].concat()
+}
+
+fn submission_key(submission: u64) -> Vec<u8> {
+    [prefixes::SUBMISSION, &submission.to_be_bytes()].concat()
+}
+
+fn onchain_submission_key(submission: u64) -> Vec<u8> {
+    [prefixes::ONCHAIN_SUBMISSION, &submission.to_be_bytes()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::H256;
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block, Header};
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+
+    use super::*;
+
+    type TestBlock = Block<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    fn da_db() -> DaDb<TestBlock> {
+        DaDb { db: Arc::new(MemDb::default()), _marker: PhantomData }
+    }
+
+    fn da_db_with(db: &MemDb) -> DaDb<TestBlock> {
+        DaDb { db: Arc::new(db.clone()), _marker: PhantomData }
+    }
+
+    #[test]
+    fn cairo_job_does_not_overwrite_state_diff() {
+        let da = da_db();
+        let block_hash = H256::repeat_byte(1);
+        let state_diff = vec![U256::from(1), U256::from(2)];
+        let job_id = Uuid::from_u128(42);
+
+        da.store_state_diff(1, &block_hash, state_diff.clone()).unwrap();
+        da.update_cairo_job(&block_hash, job_id).unwrap();
+
+        assert_eq!(da.state_diff(&block_hash).unwrap(), state_diff);
+        assert_eq!(da.cairo_job(&block_hash).unwrap(), job_id);
+        assert_eq!(da.publish_status(&block_hash).unwrap(), Some(PublishStatus::Pending));
+    }
+
+    #[test]
+    fn block_lifecycle() {
+        let da = da_db();
+        let first_block = H256::repeat_byte(1);
+        let second_block = H256::repeat_byte(2);
+
+        assert_eq!(da.publish_status(&first_block).unwrap(), None);
+        assert_eq!(da.last_published_block().unwrap(), None);
+
+        da.store_state_diff(1, &first_block, vec![U256::from(1)]).unwrap();
+        da.store_state_diff(2, &second_block, vec![U256::from(2)]).unwrap();
+        da.update_cairo_job(&first_block, Uuid::from_u128(1)).unwrap();
+
+        da.mark_published(1, &first_block).unwrap();
+
+        assert_eq!(da.publish_status(&first_block).unwrap(), Some(PublishStatus::Published));
+        assert_eq!(da.last_published_block().unwrap(), Some(first_block));
+        assert!(da.state_diff(&first_block).unwrap().is_empty());
+        assert!(da.cairo_job(&first_block).is_err());
+        // Other blocks are left untouched
+        assert_eq!(da.state_diff(&second_block).unwrap(), vec![U256::from(2)]);
+        assert_eq!(da.publish_status(&second_block).unwrap(), Some(PublishStatus::Pending));
+
+        da.mark_published(2, &second_block).unwrap();
+        assert_eq!(da.last_published_block().unwrap(), Some(second_block));
+    }
+
+    #[test]
+    fn proof_lifecycle() {
+        let da = da_db();
+        let block_hash = H256::repeat_byte(1);
+
+        da.store_state_diff(1, &block_hash, vec![U256::from(1)]).unwrap();
+        assert_eq!(da.proof_status(&block_hash).unwrap(), None);
+
+        // A failed job is dropped and submitted again
+        da.update_cairo_job(&block_hash, Uuid::from_u128(1)).unwrap();
+        assert_eq!(da.proof_status(&block_hash).unwrap(), Some(ProofStatus::InProgress));
+        da.remove_cairo_job(&block_hash).unwrap();
+        assert_eq!(da.proof_status(&block_hash).unwrap(), None);
+        assert!(da.cairo_job(&block_hash).is_err());
+
+        da.update_cairo_job(&block_hash, Uuid::from_u128(2)).unwrap();
+        da.mark_proved(&block_hash, [7; 32]).unwrap();
+        assert_eq!(da.proof_status(&block_hash).unwrap(), Some(ProofStatus::Onchain([7; 32])));
+        assert_eq!(da.cairo_job(&block_hash).unwrap(), Uuid::from_u128(2));
+        assert_eq!(da.last_proved_block().unwrap(), block_hash);
+
+        da.mark_published(1, &block_hash).unwrap();
+        assert_eq!(da.proof_status(&block_hash).unwrap(), None);
+    }
+
+    #[test]
+    fn purge_removes_every_block_entry() {
+        let da = da_db();
+        let block_hash = H256::repeat_byte(1);
+
+        da.store_state_diff(1, &block_hash, vec![U256::from(1)]).unwrap();
+        da.update_cairo_job(&block_hash, Uuid::from_u128(1)).unwrap();
+        da.update_last_proved_block(&block_hash).unwrap();
+
+        da.purge(&block_hash).unwrap();
+
+        assert!(da.state_diff(&block_hash).unwrap().is_empty());
+        assert!(da.cairo_job(&block_hash).is_err());
+        assert_eq!(da.publish_status(&block_hash).unwrap(), None);
+        // Chain wide keys are not affected
+        assert_eq!(da.last_proved_block().unwrap(), block_hash);
+    }
+
+    #[test]
+    fn legacy_entries_are_removed() {
+        let da = da_db();
+        let block_hash = H256::repeat_byte(1);
+        let mut transaction = sp_database::Transaction::new();
+        transaction.set(crate::columns::DA, &block_hash.encode(), &vec![U256::from(1)].encode());
+        da.db.commit(transaction).unwrap();
+        da.store_state_diff(1, &block_hash, vec![U256::from(2)]).unwrap();
+
+        assert!(!da.legacy_entries_removed().unwrap());
+        da.remove_legacy_entries(&[block_hash], false).unwrap();
+        assert!(!da.legacy_entries_removed().unwrap());
+        da.remove_legacy_entries(&[], true).unwrap();
+
+        assert!(da.legacy_entries_removed().unwrap());
+        assert_eq!(da.db.get(crate::columns::DA, &block_hash.encode()), None);
+        assert_eq!(da.state_diff(&block_hash).unwrap(), vec![U256::from(2)]);
+    }
+
+    #[test]
+    fn publish_queue_is_in_block_order() {
+        let da = da_db();
+        let first_block = H256::repeat_byte(1);
+        let second_block = H256::repeat_byte(2);
+        let retracted_block = H256::repeat_byte(3);
+
+        assert_eq!(da.next_to_publish().unwrap(), None);
+
+        da.store_state_diff(5, &first_block, vec![U256::from(1)]).unwrap();
+        da.store_state_diff(6, &retracted_block, vec![U256::from(3)]).unwrap();
+        // A new best block at the same height replaces the retracted one
+        da.store_state_diff(6, &second_block, vec![U256::from(2)]).unwrap();
+
+        assert_eq!(da.publish_queue_head().unwrap(), Some(5));
+        assert_eq!(da.publish_status(&retracted_block).unwrap(), None);
+        assert!(da.state_diff(&retracted_block).unwrap().is_empty());
+
+        // The head of the queue stays the same until it is published
+        assert_eq!(da.next_to_publish().unwrap(), Some((5, first_block)));
+        assert_eq!(da.next_to_publish().unwrap(), Some((5, first_block)));
+
+        da.mark_published(5, &first_block).unwrap();
+        assert_eq!(da.next_to_publish().unwrap(), Some((6, second_block)));
+
+        da.mark_published(6, &second_block).unwrap();
+        // Block #7 has not been stored yet
+        assert_eq!(da.publish_queue_head().unwrap(), Some(7));
+        assert_eq!(da.next_to_publish().unwrap(), None);
+    }
+
+    #[test]
+    fn missing_blocks_are_skipped() {
+        let da = da_db();
+        let first_block = H256::repeat_byte(1);
+        let fourth_block = H256::repeat_byte(4);
+
+        da.store_state_diff(1, &first_block, vec![U256::from(1)]).unwrap();
+        da.mark_published(1, &first_block).unwrap();
+        // Blocks #2 and #3 never made it to the queue
+        da.store_state_diff(4, &fourth_block, vec![U256::from(4)]).unwrap();
+        assert_eq!(da.next_to_publish().unwrap(), None);
+
+        assert_eq!(da.skip_missing_blocks().unwrap(), Some((2, 3)));
+        assert_eq!(da.next_to_publish().unwrap(), Some((4, fourth_block)));
+        assert_eq!(da.skip_missing_blocks().unwrap(), None);
+
+        // The head of the queue is not skipped while it is the highest queued block
+        da.mark_published(4, &fourth_block).unwrap();
+        assert_eq!(da.skip_missing_blocks().unwrap(), None);
+        assert_eq!(da.publish_queue_head().unwrap(), Some(5));
+    }
+
+    #[test]
+    fn no_block_entry_is_left_after_skipping_and_purging() {
+        let db = MemDb::default();
+        let da = da_db_with(&db);
+        let first_block = H256::repeat_byte(1);
+        let third_block = H256::repeat_byte(3);
+        let fourth_block = H256::repeat_byte(4);
+
+        da.store_state_diff(1, &first_block, vec![U256::from(1)]).unwrap();
+        da.mark_published(1, &first_block).unwrap();
+        da.store_state_diff(4, &fourth_block, vec![U256::from(4)]).unwrap();
+        assert_eq!(da.skip_missing_blocks().unwrap(), Some((2, 3)));
+
+        // Blocks below the head of the queue are not stored anymore
+        da.store_state_diff(3, &third_block, vec![U256::from(3)]).unwrap();
+        da.store_state_diff(1, &H256::repeat_byte(5), vec![U256::from(5)]).unwrap();
+        assert_eq!(da.publish_status(&third_block).unwrap(), None);
+        assert_eq!(da.next_to_publish().unwrap(), Some((4, fourth_block)));
+
+        da.mark_published(4, &fourth_block).unwrap();
+        da.purge(&first_block).unwrap();
+        da.purge(&fourth_block).unwrap();
+
+        // Only the queue head and tail, the last published block, the submission count and the
+        // two submissions are left
+        assert_eq!(db.count(crate::columns::DA), 6);
+        assert_eq!(da.publish_queue_head().unwrap(), Some(5));
+        assert_eq!(da.submission_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn batch_publication_records_its_coverage() {
+        let da = da_db();
+        let blocks: Vec<(u64, H256)> = (1..=4).map(|n| (n, H256::repeat_byte(n as u8))).collect();
+
+        for (block_number, block_hash) in &blocks[..3] {
+            da.store_state_diff(*block_number, block_hash, vec![U256::from(*block_number)]).unwrap();
+        }
+
+        // Only consecutive stored blocks are returned
+        assert_eq!(da.queued_blocks(2).unwrap(), blocks[..2].to_vec());
+        assert_eq!(da.queued_blocks(10).unwrap(), blocks[..3].to_vec());
+
+        assert_eq!(da.mark_batch_published(&blocks[..2]).unwrap(), 0);
+        assert_eq!(da.submission(0).unwrap(), Some((1, 2)));
+        assert_eq!(da.publish_status(&blocks[1].1).unwrap(), Some(PublishStatus::Published));
+        assert_eq!(da.last_published_block().unwrap(), Some(blocks[1].1));
+        assert_eq!(da.queued_blocks(10).unwrap(), blocks[2..3].to_vec());
+
+        da.mark_published(3, &blocks[2].1).unwrap();
+        assert_eq!(da.submission_count().unwrap(), 2);
+        assert_eq!(da.submission(1).unwrap(), Some((3, 3)));
+        assert!(da.queued_blocks(10).unwrap().is_empty());
+        assert!(da.mark_batch_published(&[]).is_err());
+    }
+
+    #[test]
+    fn onchain_publication_is_tracked_separately() {
+        let da = da_db();
+        let blocks: Vec<(u64, H256)> = (1..=3).map(|n| (n, H256::repeat_byte(n as u8))).collect();
+
+        for (block_number, block_hash) in &blocks {
+            da.store_state_diff(*block_number, block_hash, vec![U256::from(*block_number)]).unwrap();
+        }
+        assert_eq!(da.last_onchain_published_block().unwrap(), None);
+
+        assert_eq!(da.mark_onchain_published(&blocks[..2], true).unwrap(), Some(0));
+        // Nothing to publish on-chain for the last block
+        assert_eq!(da.mark_onchain_published(&blocks[2..], false).unwrap(), None);
+
+        assert_eq!(da.last_onchain_published_block().unwrap(), Some(3));
+        assert_eq!(da.onchain_submission_count().unwrap(), 1);
+        assert_eq!(da.onchain_submission(0).unwrap(), Some((1, 2)));
+        // The off-chain publication is still pending
+        assert_eq!(da.queued_blocks(10).unwrap(), blocks);
+        assert_eq!(da.submission_count().unwrap(), 0);
+    }
 }
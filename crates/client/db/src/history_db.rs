@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+// Substrate
+use scale_codec::{Decode, Encode};
+use sp_database::Database;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::DbHash;
+
+/// Prefixes of the sub-spaces of the `STORAGE_HISTORY` column.
+mod prefixes {
+    /// Indices of the first and next change of a storage key
+    pub const INDICES: &[u8] = b"INDICES";
+    /// Block number of a change of a storage key, by storage key and change index
+    pub const CHANGE: &[u8] = b"CHANGE";
+    /// Value of a storage key as set by a change, by storage key and block number
+    pub const VALUE: &[u8] = b"VALUE";
+    /// Storage keys changed by a recorded block, by block number
+    pub const BLOCK_KEYS: &[u8] = b"BLOCK_KEYS";
+    /// Block number and storage changes of an imported block which is not finalized yet, by
+    /// block hash
+    pub const UNFINALIZED: &[u8] = b"UNFINALIZED";
+}
+
+/// A storage change: the raw storage key and its new value, `None` if it has been removed
+pub type StorageChange = (Vec<u8>, Option<Vec<u8>>);
+
+/// Allow interaction with the storage history db
+///
+/// The storage history db records the successive values of the `pallet-starknet` storage keys,
+/// indexed by the number of the block that changed them. It makes it possible to read the storage
+/// at an old block once its Substrate state has been pruned, without the disk cost of an archive
+/// node as only the changes are stored.
+///
+/// The history covers a single range of consecutive finalized blocks. It is seeded once with the
+/// state of its first block, and then only records the changes of the next blocks, as notified on
+/// their import. Each change is written once, under its storage key and change index, so that the
+/// last change of a key at a given block is found by a binary search over its change indices.
+/// The history can be pruned from its start, keeping the values needed by the blocks left.
+pub struct StorageHistoryDb<B: BlockT> {
+    db: Arc<dyn Database<DbHash>>,
+    write_lock: Arc<Mutex<()>>,
+    _marker: PhantomData<B>,
+}
+
+impl<B: BlockT> StorageHistoryDb<B> {
+    /// Creates a new instance of the storage history database.
+    pub fn new(db: Arc<dyn Database<DbHash>>) -> Self {
+        Self { db, write_lock: Arc::new(Mutex::new(())), _marker: PhantomData }
+    }
+
+    /// Return the number of the first block recorded in the history, if any
+    pub fn indexed_from(&self) -> Result<Option<u64>, String> {
+        Ok(self.blocks()?.map(|(first_block, _)| first_block))
+    }
+
+    /// Return the number of the last block recorded in the history, if any
+    pub fn last_indexed_block(&self) -> Result<Option<u64>, String> {
+        Ok(self.blocks()?.map(|(_, last_block)| last_block))
+    }
+
+    /// Start the history at the block with the given number, from the whole Starknet storage at
+    /// this block
+    ///
+    /// The history can only be seeded once.
+    pub fn seed(&self, block_number: u64, state: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>) -> Result<(), String> {
+        let _lock = self.write_lock.lock();
+
+        if self.blocks()?.is_some() {
+            return Err(String::from("the storage history is already seeded"));
+        }
+
+        let changes: Vec<StorageChange> = state.into_iter().map(|(key, value)| (key, Some(value))).collect();
+        let mut transaction = sp_database::Transaction::new();
+        self.record_block(&mut transaction, block_number, &changes)?;
+        transaction.set(
+            crate::columns::STORAGE_HISTORY,
+            crate::static_keys::STORAGE_HISTORY_BLOCKS,
+            &(block_number, block_number).encode(),
+        );
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Record the storage changes made by the block with the given number and hash, and forget
+    /// its unfinalized changes
+    ///
+    /// The block must directly follow the last recorded one.
+    pub fn write_changes(
+        &self,
+        block_number: u64,
+        block_hash: &B::Hash,
+        changes: &[StorageChange],
+    ) -> Result<(), String> {
+        let _lock = self.write_lock.lock();
+
+        match self.blocks()? {
+            Some((first_block, last_block)) if last_block + 1 == block_number => {
+                let mut transaction = sp_database::Transaction::new();
+                self.record_block(&mut transaction, block_number, changes)?;
+                transaction.remove(crate::columns::STORAGE_HISTORY, &unfinalized_key(block_hash));
+                transaction.set(
+                    crate::columns::STORAGE_HISTORY,
+                    crate::static_keys::STORAGE_HISTORY_BLOCKS,
+                    &(first_block, block_number).encode(),
+                );
+
+                self.db.commit(transaction).map_err(|e| format!("{:?}", e))
+            }
+            _ => Err(format!("block #{block_number} doesn't follow the last block of the storage history")),
+        }
+    }
+
+    /// Return the value of a storage key at the block with the given number
+    ///
+    /// Returns `Ok(None)` if the key had no value at this block, and an error if the block is not
+    /// covered by the history.
+    pub fn value_at(&self, key: &[u8], block_number: u64) -> Result<Option<Vec<u8>>, String> {
+        match self.blocks()? {
+            Some((first_block, last_block)) if (first_block..=last_block).contains(&block_number) => {}
+            _ => return Err(format!("block #{block_number} is not covered by the storage history")),
+        }
+
+        // Binary search of the last change at or before the block
+        let (first_index, next_index) = self.change_indices(key)?;
+        let (mut low, mut high) = (first_index, next_index);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.change(key, middle)? <= block_number {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        if low == first_index {
+            return Ok(None);
+        }
+        let last_change = self.change(key, low - 1)?;
+
+        match self.db.get(crate::columns::STORAGE_HISTORY, &value_key(key, last_change)) {
+            Some(raw) => Ok(Option::<Vec<u8>>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Err(format!("missing value of a storage key changed at block #{last_change}")),
+        }
+    }
+
+    /// Prune the history so that it starts at the block with the given number
+    ///
+    /// The changes superseded by a later change made at or before this block are removed, along
+    /// with the keys changed by the pruned blocks.
+    pub fn prune(&self, first_block: u64) -> Result<(), String> {
+        let _lock = self.write_lock.lock();
+
+        let (pruned_from, last_block) = match self.blocks()? {
+            Some((pruned_from, last_block)) if pruned_from < first_block && first_block <= last_block => {
+                (pruned_from, last_block)
+            }
+            _ => return Ok(()),
+        };
+
+        let mut transaction = sp_database::Transaction::new();
+        let mut indices = HashMap::new();
+        for block_number in pruned_from + 1..=first_block {
+            for key in self.block_keys(block_number)? {
+                let (mut first_index, next_index) = match indices.get(&key) {
+                    Some(indices) => *indices,
+                    None => self.change_indices(&key)?,
+                };
+                while first_index < next_index {
+                    let change = self.change(&key, first_index)?;
+                    if change >= block_number {
+                        break;
+                    }
+                    transaction.remove(crate::columns::STORAGE_HISTORY, &change_key(&key, first_index));
+                    transaction.remove(crate::columns::STORAGE_HISTORY, &value_key(&key, change));
+                    first_index += 1;
+                }
+                indices.insert(key, (first_index, next_index));
+            }
+        }
+        for (key, indices) in indices {
+            transaction.set(crate::columns::STORAGE_HISTORY, &change_indices_key(&key), &indices.encode());
+        }
+        for block_number in pruned_from..first_block {
+            transaction.remove(crate::columns::STORAGE_HISTORY, &block_keys_key(block_number));
+        }
+        transaction.set(
+            crate::columns::STORAGE_HISTORY,
+            crate::static_keys::STORAGE_HISTORY_BLOCKS,
+            &(first_block, last_block).encode(),
+        );
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Keep the storage changes of an imported block until it is finalized
+    pub fn write_unfinalized_changes(
+        &self,
+        block_number: u64,
+        block_hash: &B::Hash,
+        changes: &[StorageChange],
+    ) -> Result<(), String> {
+        let _lock = self.write_lock.lock();
+
+        let mut unfinalized_blocks = self.unfinalized_blocks()?;
+        unfinalized_blocks.push((block_number, *block_hash));
+
+        let mut transaction = sp_database::Transaction::new();
+        transaction.set(
+            crate::columns::STORAGE_HISTORY,
+            &unfinalized_key(block_hash),
+            &(block_number, changes).encode(),
+        );
+        transaction.set(
+            crate::columns::STORAGE_HISTORY,
+            crate::static_keys::STORAGE_HISTORY_UNFINALIZED_BLOCKS,
+            &unfinalized_blocks.encode(),
+        );
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Return the storage changes of an imported block which is not finalized yet, if known
+    pub fn unfinalized_changes(&self, block_hash: &B::Hash) -> Result<Option<Vec<StorageChange>>, String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, &unfinalized_key(block_hash)) {
+            Some(raw) => {
+                let (_, changes) =
+                    <(u64, Vec<StorageChange>)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?;
+                Ok(Some(changes))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Forget the storage changes of the unfinalized blocks at or below the given number, which are
+    /// either recorded or retracted once this block is finalized
+    pub fn discard_unfinalized_changes(&self, finalized_number: u64) -> Result<(), String> {
+        let _lock = self.write_lock.lock();
+
+        let (discarded, kept): (Vec<_>, Vec<_>) =
+            self.unfinalized_blocks()?.into_iter().partition(|(block_number, _)| *block_number <= finalized_number);
+
+        let mut transaction = sp_database::Transaction::new();
+        for (_, block_hash) in discarded {
+            transaction.remove(crate::columns::STORAGE_HISTORY, &unfinalized_key(&block_hash));
+        }
+        transaction.set(
+            crate::columns::STORAGE_HISTORY,
+            crate::static_keys::STORAGE_HISTORY_UNFINALIZED_BLOCKS,
+            &kept.encode(),
+        );
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Add the changes of a block to the transaction
+    fn record_block(
+        &self,
+        transaction: &mut sp_database::Transaction<DbHash>,
+        block_number: u64,
+        changes: &[StorageChange],
+    ) -> Result<(), String> {
+        let mut changed_keys = HashMap::new();
+        for (key, value) in changes {
+            // A key changed twice keeps its last value, under a single change index
+            let (first_index, next_index) = match changed_keys.get(key) {
+                Some(indices) => *indices,
+                None => {
+                    let (first_index, next_index) = self.change_indices(key)?;
+                    transaction.set(
+                        crate::columns::STORAGE_HISTORY,
+                        &change_key(key, next_index),
+                        &block_number.encode(),
+                    );
+                    (first_index, next_index + 1)
+                }
+            };
+            transaction.set(crate::columns::STORAGE_HISTORY, &value_key(key, block_number), &value.encode());
+            transaction.set(
+                crate::columns::STORAGE_HISTORY,
+                &change_indices_key(key),
+                &(first_index, next_index).encode(),
+            );
+            changed_keys.insert(key, (first_index, next_index));
+        }
+        let keys: Vec<&Vec<u8>> = changed_keys.into_keys().collect();
+        transaction.set(crate::columns::STORAGE_HISTORY, &block_keys_key(block_number), &keys.encode());
+
+        Ok(())
+    }
+
+    /// First and last block numbers covered by the history, if it is seeded
+    fn blocks(&self) -> Result<Option<(u64, u64)>, String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, crate::static_keys::STORAGE_HISTORY_BLOCKS) {
+            Some(raw) => Ok(Some(<(u64, u64)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Indices of the first and next change of a storage key
+    fn change_indices(&self, key: &[u8]) -> Result<(u64, u64), String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, &change_indices_key(key)) {
+            Some(raw) => Ok(<(u64, u64)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Ok((0, 0)),
+        }
+    }
+
+    /// Block number of the change of a storage key with the given index
+    fn change(&self, key: &[u8], index: u64) -> Result<u64, String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, &change_key(key, index)) {
+            Some(raw) => Ok(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Err(format!("missing change #{index} of a storage key")),
+        }
+    }
+
+    fn block_keys(&self, block_number: u64) -> Result<Vec<Vec<u8>>, String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, &block_keys_key(block_number)) {
+            Some(raw) => Ok(Vec::<Vec<u8>>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn unfinalized_blocks(&self) -> Result<Vec<(u64, B::Hash)>, String> {
+        match self.db.get(crate::columns::STORAGE_HISTORY, crate::static_keys::STORAGE_HISTORY_UNFINALIZED_BLOCKS) {
+            Some(raw) => Ok(Vec::<(u64, B::Hash)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn change_indices_key(key: &[u8]) -> Vec<u8> {
+    [prefixes::INDICES, key].concat()
+}
+
+fn change_key(key: &[u8], index: u64) -> Vec<u8> {
+    [prefixes::CHANGE, key, &index.to_be_bytes()].concat()
+}
+
+fn value_key(key: &[u8], block_number: u64) -> Vec<u8> {
+    [prefixes::VALUE, key, &block_number.to_be_bytes()].concat()
+}
+
+fn block_keys_key(block_number: u64) -> Vec<u8> {
+    [prefixes::BLOCK_KEYS, &block_number.to_be_bytes()[..]].concat()
+}
+
+fn unfinalized_key<H: Encode>(block_hash: &H) -> Vec<u8> {
+    [prefixes::UNFINALIZED, &block_hash.encode()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::H256;
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block, Header};
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+
+    use super::*;
+
+    type TestBlock = Block<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    fn history_db() -> StorageHistoryDb<TestBlock> {
+        StorageHistoryDb::new(Arc::new(MemDb::default()))
+    }
+
+    fn block_hash(block_number: u64) -> H256 {
+        H256::from_low_u64_be(block_number)
+    }
+
+    fn write_changes(history: &StorageHistoryDb<TestBlock>, block_number: u64, changes: &[StorageChange]) {
+        history.write_changes(block_number, &block_hash(block_number), changes).unwrap();
+    }
+
+    #[test]
+    fn value_at_returns_the_last_change() {
+        let history = history_db();
+        let key = b"key".to_vec();
+
+        history.seed(1, []).unwrap();
+        write_changes(&history, 2, &[(key.clone(), Some(vec![1]))]);
+        for block_number in 3..=4 {
+            write_changes(&history, block_number, &[]);
+        }
+        write_changes(&history, 5, &[(key.clone(), Some(vec![2]))]);
+        for block_number in 6..=7 {
+            write_changes(&history, block_number, &[]);
+        }
+        write_changes(&history, 8, &[(key.clone(), None)]);
+
+        assert_eq!(history.indexed_from().unwrap(), Some(1));
+        assert_eq!(history.last_indexed_block().unwrap(), Some(8));
+        assert_eq!(history.value_at(&key, 1).unwrap(), None);
+        assert_eq!(history.value_at(&key, 2).unwrap(), Some(vec![1]));
+        assert_eq!(history.value_at(&key, 4).unwrap(), Some(vec![1]));
+        assert_eq!(history.value_at(&key, 5).unwrap(), Some(vec![2]));
+        assert_eq!(history.value_at(&key, 7).unwrap(), Some(vec![2]));
+        assert_eq!(history.value_at(&key, 8).unwrap(), None);
+        assert_eq!(history.value_at(b"other_key", 8).unwrap(), None);
+    }
+
+    #[test]
+    fn history_is_seeded_once_with_the_state_of_its_first_block() {
+        let history = history_db();
+        let key = b"key".to_vec();
+
+        assert!(history.value_at(&key, 0).is_err());
+
+        // The key was set before the history starts
+        history.seed(3, [(key.clone(), vec![1])]).unwrap();
+        write_changes(&history, 4, &[(b"other_key".to_vec(), Some(vec![2]))]);
+
+        assert!(history.seed(5, [(key.clone(), vec![5])]).is_err());
+        assert!(history.value_at(&key, 2).is_err());
+        assert_eq!(history.value_at(&key, 3).unwrap(), Some(vec![1]));
+        assert_eq!(history.value_at(&key, 4).unwrap(), Some(vec![1]));
+        assert!(history.value_at(&key, 5).is_err());
+    }
+
+    #[test]
+    fn blocks_are_recorded_in_order() {
+        let history = history_db();
+        let key = b"key".to_vec();
+
+        // Changes can't be recorded without the state they apply to
+        assert!(history.write_changes(1, &block_hash(1), &[(key.clone(), Some(vec![1]))]).is_err());
+
+        history.seed(1, [(key.clone(), vec![1])]).unwrap();
+        assert!(history.write_changes(3, &block_hash(3), &[(key.clone(), Some(vec![3]))]).is_err());
+        write_changes(&history, 2, &[(key.clone(), Some(vec![2]))]);
+
+        // Recorded blocks are never rewritten
+        assert!(history.write_changes(2, &block_hash(2), &[(key.clone(), Some(vec![4]))]).is_err());
+        assert_eq!(history.value_at(&key, 2).unwrap(), Some(vec![2]));
+    }
+
+    #[test]
+    fn pruning_keeps_the_values_of_the_blocks_left() {
+        let history = history_db();
+        let key = b"key".to_vec();
+        let stable_key = b"stable_key".to_vec();
+
+        history.seed(1, [(key.clone(), vec![1]), (stable_key.clone(), vec![1])]).unwrap();
+        for block_number in 2..=6 {
+            write_changes(&history, block_number, &[(key.clone(), Some(vec![block_number as u8]))]);
+        }
+
+        history.prune(4).unwrap();
+
+        assert_eq!(history.indexed_from().unwrap(), Some(4));
+        assert!(history.value_at(&key, 3).is_err());
+        assert_eq!(history.value_at(&key, 4).unwrap(), Some(vec![4]));
+        assert_eq!(history.value_at(&key, 6).unwrap(), Some(vec![6]));
+        assert_eq!(history.value_at(&stable_key, 6).unwrap(), Some(vec![1]));
+        // The superseded changes are removed
+        assert_eq!(history.change_indices(&key).unwrap(), (3, 6));
+        assert_eq!(history.db.get(crate::columns::STORAGE_HISTORY, &value_key(&key, 3)), None);
+        assert_eq!(history.db.get(crate::columns::STORAGE_HISTORY, &block_keys_key(3)), None);
+
+        history.prune(6).unwrap();
+        assert_eq!(history.value_at(&key, 6).unwrap(), Some(vec![6]));
+        assert_eq!(history.value_at(&stable_key, 6).unwrap(), Some(vec![1]));
+        assert_eq!(history.change_indices(&key).unwrap(), (5, 6));
+    }
+
+    #[test]
+    fn unfinalized_changes_are_kept_until_finalization() {
+        let history = history_db();
+        let changes = vec![(b"key".to_vec(), Some(vec![2]))];
+        let retracted_changes = vec![(b"key".to_vec(), Some(vec![3]))];
+        let retracted_hash = H256::repeat_byte(0xff);
+
+        history.seed(1, []).unwrap();
+        history.write_unfinalized_changes(2, &block_hash(2), &changes).unwrap();
+        history.write_unfinalized_changes(2, &retracted_hash, &retracted_changes).unwrap();
+        assert_eq!(history.unfinalized_changes(&block_hash(2)).unwrap(), Some(changes.clone()));
+
+        write_changes(&history, 2, &changes);
+        history.discard_unfinalized_changes(2).unwrap();
+
+        assert_eq!(history.unfinalized_changes(&block_hash(2)).unwrap(), None);
+        assert_eq!(history.unfinalized_changes(&retracted_hash).unwrap(), None);
+        assert!(history.unfinalized_blocks().unwrap().is_empty());
+        assert_eq!(history.value_at(b"key", 2).unwrap(), Some(vec![2]));
+    }
+}
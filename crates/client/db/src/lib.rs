@@ -14,17 +14,23 @@
 mod mapping_db;
 pub use mapping_db::MappingCommitment;
 mod da_db;
+pub use da_db::{ProofStatus, PublishStatus};
 mod db_opening_utils;
+mod history_db;
+pub use history_db::StorageChange;
 mod meta_db;
+mod sierra_classes_db;
 
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use da_db::DaDb;
+use history_db::StorageHistoryDb;
 use mapping_db::MappingDb;
 use meta_db::MetaDb;
 use sc_client_db::DatabaseSource;
+use sierra_classes_db::SierraClassesDb;
 use sp_database::Database;
 use sp_runtime::traits::Block as BlockT;
 
@@ -42,7 +48,7 @@ pub(crate) mod columns {
     // ===== /!\ ===================================================================================
     // MUST BE INCREMENTED WHEN A NEW COLUMN IN ADDED
     // ===== /!\ ===================================================================================
-    pub const NUM_COLUMNS: u32 = 6;
+    pub const NUM_COLUMNS: u32 = 8;
 
     pub const META: u32 = 0;
     pub const BLOCK_MAPPING: u32 = 1;
@@ -54,22 +60,40 @@ pub(crate) mod columns {
     ///
     /// This column should only be accessed if the `--cache` flag is enabled.
     pub const STARKNET_TRANSACTION_HASHES_CACHE: u32 = 5;
+    /// This column stores the successive values of the `pallet-starknet` storage keys, indexed by
+    /// block number, so that old states can be read once pruned from the Substrate db.
+    pub const STORAGE_HISTORY: u32 = 6;
+    /// This column stores the declared Sierra classes, compressed, indexed by class hash.
+    pub const SIERRA_CLASSES: u32 = 7;
 }
 
 pub mod static_keys {
     pub const CURRENT_SYNCING_TIPS: &[u8] = b"CURRENT_SYNCING_TIPS";
     pub const LAST_PROVED_BLOCK: &[u8] = b"LAST_PROVED_BLOCK";
+    pub const LAST_PUBLISHED_BLOCK: &[u8] = b"LAST_PUBLISHED_BLOCK";
+    pub const PUBLISH_QUEUE_HEAD: &[u8] = b"PUBLISH_QUEUE_HEAD";
+    pub const PUBLISH_QUEUE_TAIL: &[u8] = b"PUBLISH_QUEUE_TAIL";
+    pub const DA_SUBMISSION_COUNT: &[u8] = b"DA_SUBMISSION_COUNT";
+    pub const LAST_ONCHAIN_PUBLISHED_BLOCK: &[u8] = b"LAST_ONCHAIN_PUBLISHED_BLOCK";
+    pub const ONCHAIN_SUBMISSION_COUNT: &[u8] = b"ONCHAIN_SUBMISSION_COUNT";
+    pub const DA_LEGACY_ENTRIES_REMOVED: &[u8] = b"DA_LEGACY_ENTRIES_REMOVED";
+    pub const STORAGE_HISTORY_BLOCKS: &[u8] = b"STORAGE_HISTORY_BLOCKS";
+    pub const STORAGE_HISTORY_UNFINALIZED_BLOCKS: &[u8] = b"STORAGE_HISTORY_UNFINALIZED_BLOCKS";
 }
 
 /// The Madara client database backend
 ///
-/// Contains two distinct databases: `meta` and `mapping`.
+/// Contains distinct databases: `meta`, `mapping`, `da`, `history` and `sierra_classes`.
 /// `mapping` is used to map Starknet blocks to Substrate ones.
 /// `meta` is used to store data about the current state of the chain
+/// `history` is used to read the Starknet storage at blocks whose state has been pruned
+/// `sierra_classes` is used to serve the Sierra classes as they were declared
 pub struct Backend<B: BlockT> {
     meta: Arc<MetaDb<B>>,
     mapping: Arc<MappingDb<B>>,
     da: Arc<DaDb<B>>,
+    history: Arc<StorageHistoryDb<B>>,
+    sierra_classes: Arc<SierraClassesDb<B>>,
 }
 
 /// Returns the Starknet database directory.
@@ -110,6 +134,8 @@ impl<B: BlockT> Backend<B> {
             mapping: Arc::new(MappingDb::new(db.clone(), cache_more_things)),
             meta: Arc::new(MetaDb { db: db.clone(), _marker: PhantomData }),
             da: Arc::new(DaDb { db: db.clone(), _marker: PhantomData }),
+            history: Arc::new(StorageHistoryDb::new(db.clone())),
+            sierra_classes: Arc::new(SierraClassesDb { db: db.clone(), _marker: PhantomData }),
         })
     }
 
@@ -127,4 +153,14 @@ impl<B: BlockT> Backend<B> {
     pub fn da(&self) -> &Arc<DaDb<B>> {
         &self.da
     }
+
+    /// Return the storage history database manager
+    pub fn history(&self) -> &Arc<StorageHistoryDb<B>> {
+        &self.history
+    }
+
+    /// Return the Sierra classes database manager
+    pub fn sierra_classes(&self) -> &Arc<SierraClassesDb<B>> {
+        &self.sierra_classes
+    }
 }
@@ -0,0 +1,64 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+// Substrate
+use scale_codec::Encode;
+use sp_core::H256;
+use sp_database::Database;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::DbHash;
+
+/// Allow interaction with the Sierra classes db
+///
+/// The runtime only stores the compiled CASM of Cairo 1 classes. The Sierra classes, as declared,
+/// are kept here indexed by class hash so that they can be served back verbatim.
+/// Classes are stored compressed, their encoding is left to the caller.
+pub struct SierraClassesDb<B: BlockT> {
+    pub(crate) db: Arc<dyn Database<DbHash>>,
+    pub(crate) _marker: PhantomData<B>,
+}
+
+impl<B: BlockT> SierraClassesDb<B> {
+    /// Return the compressed Sierra class with the given class hash, if known
+    pub fn sierra_class(&self, class_hash: &H256) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.db.get(crate::columns::SIERRA_CLASSES, &class_hash.encode()))
+    }
+
+    /// Store the compressed Sierra class with the given class hash
+    pub fn store_sierra_class(&self, class_hash: &H256, compressed_class: &[u8]) -> Result<(), String> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(crate::columns::SIERRA_CLASSES, &class_hash.encode(), compressed_class);
+
+        self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block, Header};
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+
+    use super::*;
+
+    type TestBlock = Block<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    #[test]
+    fn sierra_class_is_served_verbatim() {
+        let sierra_classes = SierraClassesDb::<TestBlock> { db: Arc::new(MemDb::default()), _marker: PhantomData };
+        let class_hash = H256::repeat_byte(1);
+        let compressed_class = vec![31, 139, 8, 0, 42];
+
+        assert_eq!(sierra_classes.sierra_class(&class_hash).unwrap(), None);
+
+        sierra_classes.store_sierra_class(&class_hash, &compressed_class).unwrap();
+
+        assert_eq!(sierra_classes.sierra_class(&class_hash).unwrap(), Some(compressed_class));
+        assert_eq!(sierra_classes.sierra_class(&H256::repeat_byte(2)).unwrap(), None);
+    }
+}
@@ -5,10 +5,16 @@
 //! `pallet-starknet` logs. Those logs should contain the data necessary to update the Madara
 //! mapping db: a starknet block header.
 //!
+//! The `sync_storage_history` task listen to the storage changes of new Substrate blocks and
+//! record the ones of `pallet-starknet` in the Madara storage history db, once the blocks are
+//! finalized.
+//!
 //! # Usage
-//! The madara node should spawn a `MappingSyncWorker` among it's services.
+//! The madara node should spawn a `MappingSyncWorker` and the `sync_storage_history` task among
+//! it's services.
 
 mod sync_blocks;
+mod sync_history;
 
 use std::marker::PhantomData;
 use std::pin::Pin;
@@ -26,6 +32,7 @@ use sc_client_api::client::ImportNotifications;
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+pub use sync_history::sync_storage_history;
 
 /// The worker in charge of syncing the Madara db when it receive a new Substrate block
 pub struct MappingSyncWorker<B: BlockT, C, BE, H> {
@@ -1,4 +1,4 @@
-use mc_rpc_core::utils::get_block_by_block_hash;
+use mc_rpc_core::utils::{decompress_sierra_class, get_block_by_block_hash};
 use mp_digest_log::{find_starknet_block, FindLogError};
 use mp_hashers::HasherT;
 use mp_transactions::compute_hash::ComputeTransactionHash;
@@ -9,7 +9,12 @@ use sp_blockchain::{Backend as _, HeaderBackend};
 use sp_core::H256;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, Zero};
 
-fn sync_block<B: BlockT, C, BE, H>(client: &C, backend: &mc_db::Backend<B>, header: &B::Header) -> Result<(), String>
+fn sync_block<B: BlockT, C, BE, H>(
+    client: &C,
+    substrate_backend: &BE,
+    backend: &mc_db::Backend<B>,
+    header: &B::Header,
+) -> Result<(), String>
 where
     C: HeaderBackend<B> + StorageProvider<B, BE>,
     C: ProvideRuntimeApi<B>,
@@ -53,6 +58,7 @@ where
                                 .collect(),
                         };
 
+                        sync_sierra_classes(client, substrate_backend, backend, substrate_block_hash)?;
                         backend.mapping().write_hashes(mapping_commitment)
                     }
                 }
@@ -66,6 +72,46 @@ where
     }
 }
 
+/// Store the Sierra classes declared in the given block
+///
+/// The Sierra class of a Cairo 1 class is lost when compiled, so declare transactions carry it for
+/// the nodes to serve it back. A Sierra class which doesn't hash to the class hash it was declared
+/// with is ignored.
+fn sync_sierra_classes<B: BlockT, C, BE>(
+    client: &C,
+    substrate_backend: &BE,
+    backend: &mc_db::Backend<B>,
+    substrate_block_hash: B::Hash,
+) -> Result<(), String>
+where
+    C: ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+    BE: Backend<B>,
+{
+    let extrinsics = substrate_backend
+        .blockchain()
+        .body(substrate_block_hash)
+        .map_err(|e| format!("{:?}", e))?
+        .ok_or("Block body not found")?;
+    let sierra_classes = client
+        .runtime_api()
+        .declared_sierra_classes(substrate_block_hash, extrinsics)
+        .map_err(|_| "Failed to fetch the declared Sierra classes through the runtime api")?;
+
+    for (class_hash, compressed_sierra_class) in sierra_classes {
+        match decompress_sierra_class(&compressed_sierra_class) {
+            Ok(sierra_class) if sierra_class.class_hash() == class_hash.0 => {
+                backend.sierra_classes().store_sierra_class(&H256::from(class_hash), &compressed_sierra_class)?
+            }
+            _ => log::warn!(
+                "Ignoring the Sierra class declared with class hash {class_hash:?}, which doesn't hash to it"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
 fn sync_genesis_block<B: BlockT, C, H>(
     _client: &C,
     backend: &mc_db::Backend<B>,
@@ -141,7 +187,7 @@ where
         madara_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
         Ok(true)
     } else {
-        sync_block::<_, _, _, H>(client, madara_backend, &operating_header)?;
+        sync_block::<_, _, _, H>(client, substrate_backend, madara_backend, &operating_header)?;
 
         current_syncing_tips.push(*operating_header.parent_hash());
         madara_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
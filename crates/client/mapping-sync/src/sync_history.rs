@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use log::{debug, error, info};
+use mp_storage::{
+    PALLET_STARKNET, PALLET_STARKNET_SCHEMA, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE,
+    STARKNET_STORAGE,
+};
+use sc_client_api::backend::{Backend, StorageProvider};
+use sc_client_api::client::{BlockchainEvents, FinalityNotification};
+use sp_blockchain::HeaderBackend;
+use sp_core::hashing::twox_128;
+use sp_core::storage::StorageKey;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+
+/// Record the changes of the `pallet-starknet` storage in the Madara storage history db
+///
+/// Only the storage items read by the storage overrides are recorded, alongside the storage
+/// schema version.
+///
+/// The history is seeded once, with the state of the last finalized block when the task first
+/// runs, which is the genesis block for a new node. The storage changes of the imported blocks are
+/// then kept in the db until the blocks are finalized, and only then recorded, so that the history
+/// never holds the changes of a retracted block. A finalized block whose changes are not known,
+/// as the blocks imported during a major sync, ends the history at the previous block: its state
+/// is never read to fill the gap.
+///
+/// When `retained_blocks` is set, the history is pruned so that it only covers this number of
+/// blocks before the last finalized one.
+pub async fn sync_storage_history<B, C, BE>(
+    client: Arc<C>,
+    madara_backend: Arc<mc_db::Backend<B>>,
+    retained_blocks: Option<u64>,
+) where
+    B: BlockT,
+    C: BlockchainEvents<B> + HeaderBackend<B> + StorageProvider<B, BE>,
+    BE: Backend<B>,
+{
+    let prefixes: Vec<Vec<u8>> =
+        [STARKNET_STORAGE, STARKNET_NONCE, STARKNET_CONTRACT_CLASS_HASH, STARKNET_CONTRACT_CLASS]
+            .into_iter()
+            .map(|item| [twox_128(PALLET_STARKNET), twox_128(item)].concat())
+            .collect();
+
+    let mut storage_event_st = match client.storage_changes_notification_stream(None, None) {
+        Ok(storage_event_st) => storage_event_st.fuse(),
+        Err(e) => {
+            error!(target: "storage-history", "Can't read from the storage notification stream: {e}");
+            return;
+        }
+    };
+    let mut finality_st = client.finality_notification_stream().fuse();
+
+    if let Err(e) = seed_history(client.as_ref(), &madara_backend, &prefixes) {
+        error!(target: "storage-history", "Failed to seed the storage history: {e}");
+        return;
+    }
+
+    loop {
+        futures::select! {
+            storage_event = storage_event_st.next() => {
+                let Some(storage_event) = storage_event else { break };
+                let block_number: u64 = match client.number(storage_event.block) {
+                    Ok(Some(block_number)) => block_number.unique_saturated_into(),
+                    _ => {
+                        error!(target: "storage-history", "Block {:?} not found", storage_event.block);
+                        continue;
+                    }
+                };
+
+                let changes: Vec<mc_db::StorageChange> = storage_event
+                    .changes
+                    .iter()
+                    // Only keep the top level storage changes
+                    .filter(|(child_key, _, _)| child_key.is_none())
+                    .filter(|(_, key, _)| is_recorded_key(&prefixes, &key.0))
+                    .map(|(_, key, value)| (key.0.clone(), value.map(|value| value.0.clone())))
+                    .collect();
+                let history = madara_backend.history();
+                if let Err(e) = history.write_unfinalized_changes(block_number, &storage_event.block, &changes) {
+                    error!(target: "storage-history", "Failed to store the changes of block #{block_number}: {e}");
+                }
+            },
+            notification = finality_st.next() => {
+                let Some(notification) = notification else { break };
+                let result = record_finalized_blocks(client.as_ref(), &madara_backend, &notification, retained_blocks);
+                if let Err(e) = result {
+                    error!(target: "storage-history", "Stopped recording the storage history: {e}");
+                    return;
+                }
+            },
+        }
+    }
+}
+
+/// Seed the storage history with the state of the last finalized block, unless it is already
+/// seeded
+///
+/// Fails if the state of this block is no longer available.
+fn seed_history<B, C, BE>(client: &C, madara_backend: &mc_db::Backend<B>, prefixes: &[Vec<u8>]) -> Result<(), String>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + StorageProvider<B, BE>,
+    BE: Backend<B>,
+{
+    let history = madara_backend.history();
+    if history.indexed_from()?.is_some() {
+        return Ok(());
+    }
+
+    let info = client.info();
+    let block_number: u64 = info.finalized_number.unique_saturated_into();
+    info!(target: "storage-history", "Seeding the storage history with the state of block #{block_number}");
+    let state = starknet_state(client, prefixes, info.finalized_hash)
+        .map_err(|e| format!("the state of block #{block_number} is not available: {e}"))?;
+
+    history.seed(block_number, state)
+}
+
+/// Record the changes of the newly finalized blocks in the storage history, then forget the
+/// changes of the retracted blocks and prune the history
+///
+/// Fails if the changes of a finalized block which is not recorded yet are not known.
+fn record_finalized_blocks<B, C, BE>(
+    client: &C,
+    madara_backend: &mc_db::Backend<B>,
+    notification: &FinalityNotification<B>,
+    retained_blocks: Option<u64>,
+) -> Result<(), String>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + StorageProvider<B, BE>,
+    BE: Backend<B>,
+{
+    let history = madara_backend.history();
+
+    // The blocks finalized along with the notified one come first, in block order
+    for block_hash in notification.tree_route.iter().chain([&notification.hash]) {
+        let block_number: u64 = match client.number(*block_hash) {
+            Ok(Some(block_number)) => block_number.unique_saturated_into(),
+            _ => return Err(format!("block {block_hash:?} not found")),
+        };
+        // Already recorded, or the seed
+        if history.last_indexed_block()?.is_some_and(|last_indexed_block| block_number <= last_indexed_block) {
+            continue;
+        }
+
+        let changes = history
+            .unfinalized_changes(block_hash)?
+            .ok_or_else(|| format!("the storage changes of block #{block_number} are not known"))?;
+        history.write_changes(block_number, block_hash, &changes)?;
+        debug!(target: "storage-history", "Recorded {} changes of block #{block_number}", changes.len());
+    }
+
+    let finalized_number: u64 = (*notification.header.number()).unique_saturated_into();
+    history.discard_unfinalized_changes(finalized_number)?;
+    if let Some(retained_blocks) = retained_blocks {
+        history.prune(finalized_number.saturating_sub(retained_blocks))?;
+    }
+
+    Ok(())
+}
+
+/// Return the recorded storage entries of the state of the given block
+fn starknet_state<B, C, BE>(
+    client: &C,
+    prefixes: &[Vec<u8>],
+    block_hash: B::Hash,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String>
+where
+    B: BlockT,
+    C: StorageProvider<B, BE>,
+    BE: Backend<B>,
+{
+    let mut state = Vec::new();
+    for prefix in prefixes {
+        let pairs =
+            client.storage_pairs(block_hash, Some(&StorageKey(prefix.clone())), None).map_err(|e| e.to_string())?;
+        state.extend(pairs.map(|(key, value)| (key.0, value.0)));
+    }
+    let schema_key = StorageKey(PALLET_STARKNET_SCHEMA.to_vec());
+    if let Some(schema) = client.storage(block_hash, &schema_key).map_err(|e| e.to_string())? {
+        state.push((schema_key.0, schema.0));
+    }
+
+    Ok(state)
+}
+
+fn is_recorded_key(prefixes: &[Vec<u8>], key: &[u8]) -> bool {
+    key == PALLET_STARKNET_SCHEMA || prefixes.iter().any(|prefix| key.starts_with(prefix))
+}
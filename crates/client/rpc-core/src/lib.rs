@@ -9,6 +9,10 @@ mod tests;
 
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
+use mp_transactions::from_broadcasted_transactions::{
+    BroadcastedDeclareTransactionV3, BroadcastedDeployAccountTransactionV3, BroadcastedInvokeTransactionV3,
+    BroadcastedTransactionV3,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
@@ -18,7 +22,7 @@ use starknet_core::serde::unsigned_field_element::UfeHex;
 use starknet_core::types::{
     BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
     BroadcastedInvokeTransaction, BroadcastedTransaction, ContractClass, DeclareTransactionResult,
-    DeployAccountTransactionResult, EventFilterWithPage, EventsPage, FeeEstimate, FieldElement, FunctionCall,
+    DeployAccountTransactionResult, EventFilterWithPage, EventsPage, FieldElement, FunctionCall,
     InvokeTransactionResult, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt,
     StateUpdate, SyncStatusType, Transaction,
 };
@@ -27,6 +31,79 @@ use starknet_core::types::{
 #[derive(Serialize, Deserialize)]
 pub struct Felt(#[serde_as(as = "UfeHex")] pub FieldElement);
 
+/// An invoke transaction broadcasted to the RPC, of any supported version.
+///
+/// The v3 transactions are not part of the starknet-core version we depend on, so they are
+/// tried first and the previous versions are deserialized as starknet-core defines them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BroadcastedInvokeTxn {
+    V3(BroadcastedInvokeTransactionV3),
+    Legacy(BroadcastedInvokeTransaction),
+}
+
+/// A declare transaction broadcasted to the RPC, of any supported version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BroadcastedDeclareTxn {
+    V3(BroadcastedDeclareTransactionV3),
+    Legacy(BroadcastedDeclareTransaction),
+}
+
+/// A deploy account transaction broadcasted to the RPC, of any supported version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BroadcastedDeployAccountTxn {
+    V3(BroadcastedDeployAccountTransactionV3),
+    Legacy(BroadcastedDeployAccountTransaction),
+}
+
+/// A transaction broadcasted to the RPC, of any supported version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BroadcastedTxn {
+    V3(BroadcastedTransactionV3),
+    Legacy(BroadcastedTransaction),
+}
+
+/// The price of a resource, as given in the header of a block.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourcePrice {
+    /// The price of one unit of the resource, in wei.
+    #[serde_as(as = "UfeHex")]
+    pub price_in_wei: FieldElement,
+}
+
+/// The estimate of the fee of a transaction.
+///
+/// The amounts are felts, as in the specification, while the starknet-core version we depend on
+/// limits them to `u64`.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// The L1 gas consumed by the transaction.
+    #[serde_as(as = "UfeHex")]
+    pub gas_consumed: FieldElement,
+    /// The L1 gas price, in wei, used to compute the fee.
+    #[serde_as(as = "UfeHex")]
+    pub gas_price: FieldElement,
+    /// The estimated fee, in wei.
+    #[serde_as(as = "UfeHex")]
+    pub overall_fee: FieldElement,
+}
+
+/// A block returned by the RPC, along with the L1 gas price of its header.
+///
+/// The L1 gas price is not part of the blocks of the starknet-core version we depend on, so it is
+/// added to the block as starknet-core defines it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockWithL1GasPrice<B> {
+    #[serde(flatten)]
+    pub block: B,
+    pub l1_gas_price: ResourcePrice,
+}
+
 /// Starknet rpc interface.
 #[rpc(server, namespace = "starknet")]
 pub trait StarknetRpcApi {
@@ -69,7 +146,10 @@ pub trait StarknetRpcApi {
 
     /// Get block information with transaction hashes given the block id
     #[method(name = "getBlockWithTxHashes")]
-    fn get_block_with_tx_hashes(&self, block_id: BlockId) -> RpcResult<MaybePendingBlockWithTxHashes>;
+    fn get_block_with_tx_hashes(
+        &self,
+        block_id: BlockId,
+    ) -> RpcResult<BlockWithL1GasPrice<MaybePendingBlockWithTxHashes>>;
 
     /// Get the nonce associated with the given address at the given block
     #[method(name = "getNonce")]
@@ -77,7 +157,7 @@ pub trait StarknetRpcApi {
 
     /// Get block information with full transactions given the block id
     #[method(name = "getBlockWithTxs")]
-    fn get_block_with_txs(&self, block_id: BlockId) -> RpcResult<MaybePendingBlockWithTxs>;
+    fn get_block_with_txs(&self, block_id: BlockId) -> RpcResult<BlockWithL1GasPrice<MaybePendingBlockWithTxs>>;
 
     /// Get the chain id
     #[method(name = "chainId")]
@@ -87,23 +167,19 @@ pub trait StarknetRpcApi {
     #[method(name = "addInvokeTransaction")]
     async fn add_invoke_transaction(
         &self,
-        invoke_transaction: BroadcastedInvokeTransaction,
+        invoke_transaction: BroadcastedInvokeTxn,
     ) -> RpcResult<InvokeTransactionResult>;
 
     /// Add a Deploy Account Transaction
     #[method(name = "addDeployAccountTransaction")]
     async fn add_deploy_account_transaction(
         &self,
-        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+        deploy_account_transaction: BroadcastedDeployAccountTxn,
     ) -> RpcResult<DeployAccountTransactionResult>;
 
     /// Estimate the fee associated with transaction
     #[method(name = "estimateFee")]
-    async fn estimate_fee(
-        &self,
-        request: Vec<BroadcastedTransaction>,
-        block_id: BlockId,
-    ) -> RpcResult<Vec<FeeEstimate>>;
+    async fn estimate_fee(&self, request: Vec<BroadcastedTxn>, block_id: BlockId) -> RpcResult<Vec<FeeEstimate>>;
 
     /// Get the details of a transaction by a given block id and index
     #[method(name = "getTransactionByBlockIdAndIndex")]
@@ -125,7 +201,7 @@ pub trait StarknetRpcApi {
     #[method(name = "addDeclareTransaction")]
     async fn add_declare_transaction(
         &self,
-        declare_transaction: BroadcastedDeclareTransaction,
+        declare_transaction: BroadcastedDeclareTxn,
     ) -> RpcResult<DeclareTransactionResult>;
 
     /// Returns the information about a transaction by transaction hash.
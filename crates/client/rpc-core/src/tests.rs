@@ -1,4 +1,5 @@
-use starknet_core::types::BlockTag;
+use assert_matches::assert_matches;
+use starknet_core::types::{BlockTag, EntryPointsByType, FlattenedSierraClass, SierraEntryPoint};
 
 use super::*;
 
@@ -38,3 +39,86 @@ fn block_id_deserialization() {
         BlockId::Number(42)
     );
 }
+
+#[test]
+fn sierra_class_compression_roundtrip() {
+    let sierra_class = FlattenedSierraClass {
+        sierra_program: vec![FieldElement::ONE, FieldElement::TWO],
+        contract_class_version: "0.1.0".into(),
+        entry_points_by_type: EntryPointsByType {
+            constructor: vec![],
+            external: vec![SierraEntryPoint { selector: FieldElement::from_hex_be("0x3").unwrap(), function_idx: 0 }],
+            l1_handler: vec![],
+        },
+        abi: "[{\"type\":\"function\",\"name\":\"foo\"}]".into(),
+    };
+
+    let compressed = utils::compress_sierra_class(&sierra_class).unwrap();
+
+    assert_eq!(utils::decompress_sierra_class(&compressed).unwrap(), sierra_class);
+}
+
+#[test]
+fn broadcasted_invoke_transaction_versions_deserialization() {
+    let v1 = serde_json::json!({
+        "type": "INVOKE",
+        "version": "0x1",
+        "sender_address": "0x1",
+        "calldata": ["0x2"],
+        "max_fee": "0x3",
+        "signature": [],
+        "nonce": "0x0"
+    });
+    assert_matches!(serde_json::from_value(v1).unwrap(), BroadcastedInvokeTxn::Legacy(_));
+
+    let v3 = serde_json::json!({
+        "type": "INVOKE",
+        "version": "0x3",
+        "sender_address": "0x1",
+        "calldata": ["0x2"],
+        "signature": [],
+        "nonce": "0x0",
+        "resource_bounds": {
+            "l1_gas": { "max_amount": "0x3", "max_price_per_unit": "0x4" },
+            "l2_gas": { "max_amount": "0x0", "max_price_per_unit": "0x0" }
+        },
+        "tip": "0x0",
+        "paymaster_data": [],
+        "account_deployment_data": [],
+        "nonce_data_availability_mode": "L1",
+        "fee_data_availability_mode": "L1"
+    });
+    assert_matches!(serde_json::from_value(v3.clone()).unwrap(), BroadcastedInvokeTxn::V3(_));
+    assert_matches!(serde_json::from_value(v3).unwrap(), BroadcastedTxn::V3(BroadcastedTransactionV3::Invoke(_)));
+}
+
+#[test]
+fn block_with_l1_gas_price_serialization() {
+    let block = BlockWithL1GasPrice {
+        block: MaybePendingBlockWithTxHashes::Block(starknet_core::types::BlockWithTxHashes {
+            status: starknet_core::types::BlockStatus::AcceptedOnL2,
+            block_hash: FieldElement::ONE,
+            parent_hash: FieldElement::ZERO,
+            block_number: 1,
+            new_root: FieldElement::TWO,
+            timestamp: 42,
+            sequencer_address: FieldElement::THREE,
+            transactions: vec![],
+        }),
+        l1_gas_price: ResourcePrice { price_in_wei: FieldElement::from(30_000_000_000u64) },
+    };
+
+    let value = serde_json::to_value(block).unwrap();
+    assert_eq!(value["block_number"], 1);
+    assert_eq!(value["l1_gas_price"], serde_json::json!({ "price_in_wei": "0x6fc23ac00" }));
+}
+
+#[test]
+fn fee_estimate_above_u64_serialization() {
+    let overall_fee = FieldElement::from(u64::MAX) + FieldElement::ONE;
+    let estimate =
+        FeeEstimate { gas_consumed: FieldElement::TWO, gas_price: FieldElement::from(u64::MAX), overall_fee };
+
+    let value = serde_json::to_value(estimate).unwrap();
+    assert_eq!(value["overall_fee"], "0x10000000000000000");
+}
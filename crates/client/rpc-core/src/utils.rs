@@ -12,6 +12,7 @@ use cairo_lang_starknet::contract_class_into_casm_contract_class::StarknetSierra
 use cairo_lang_utils::bigint::BigUintAsHex;
 use mp_block::Block as StarknetBlock;
 use mp_digest_log::find_starknet_block;
+pub use mp_transactions::from_broadcasted_transactions::{compress_sierra_class, decompress_sierra_class};
 use num_bigint::{BigInt, BigUint, Sign};
 use sp_api::{BlockT, HeaderT};
 use sp_blockchain::HeaderBackend;
@@ -11,12 +11,16 @@ mod types;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
 use errors::StarknetRpcApiError;
 use jsonrpsee::core::{async_trait, RpcResult};
 use log::error;
 pub use mc_rpc_core::utils::*;
-use mc_rpc_core::Felt;
 pub use mc_rpc_core::StarknetRpcApiServer;
+use mc_rpc_core::{
+    BlockWithL1GasPrice, BroadcastedDeclareTxn, BroadcastedDeployAccountTxn, BroadcastedInvokeTxn, BroadcastedTxn,
+    FeeEstimate, Felt, ResourcePrice,
+};
 use mc_storage::OverrideHandle;
 use mc_transaction_pool::{ChainApi, Pool};
 use mp_felt::Felt252Wrapper;
@@ -38,13 +42,13 @@ use sp_runtime::generic::BlockId as SPBlockId;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use sp_runtime::transaction_validity::InvalidTransaction;
 use sp_runtime::DispatchError;
+use starknet_api::api_core::ClassHash;
 use starknet_api::transaction::Calldata;
 use starknet_core::types::{
     BlockHashAndNumber, BlockId, BlockStatus, BlockTag, BlockWithTxHashes, BlockWithTxs, BroadcastedDeclareTransaction,
-    BroadcastedDeployAccountTransaction, BroadcastedInvokeTransaction, BroadcastedTransaction, ContractClass,
-    DeclareTransactionReceipt, DeclareTransactionResult, DeployAccountTransactionReceipt,
-    DeployAccountTransactionResult, EventFilterWithPage, EventsPage, ExecutionResult, FeeEstimate, FieldElement,
-    FunctionCall, InvokeTransactionReceipt, InvokeTransactionResult, L1HandlerTransactionReceipt,
+    BroadcastedTransaction, ContractClass, DeclareTransactionReceipt, DeclareTransactionResult,
+    DeployAccountTransactionReceipt, DeployAccountTransactionResult, EventFilterWithPage, EventsPage, ExecutionResult,
+    FieldElement, FunctionCall, InvokeTransactionReceipt, InvokeTransactionResult, L1HandlerTransactionReceipt,
     MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt, StateDiff, StateUpdate,
     SyncStatus, SyncStatusType, Transaction, TransactionFinalityStatus, TransactionReceipt,
 };
@@ -169,6 +173,32 @@ where
             None
         })
     }
+
+    /// Returns the RPC contract class of a class stored in the runtime.
+    ///
+    /// Cairo 1 classes are served as they were declared if their Sierra class is known, as it
+    /// can't be recovered from the compiled class.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_hash` - The hash of the class
+    /// * `contract_class` - The class, as stored in the runtime
+    fn to_rpc_contract_class(
+        &self,
+        class_hash: ClassHash,
+        contract_class: BlockifierContractClass,
+    ) -> anyhow::Result<ContractClass> {
+        if let BlockifierContractClass::V1(_) = contract_class {
+            let class_hash = H256::from(Felt252Wrapper::from(class_hash));
+            if let Some(compressed_sierra_class) =
+                self.backend.sierra_classes().sierra_class(&class_hash).map_err(|e| anyhow::anyhow!(e))?
+            {
+                return Ok(ContractClass::Sierra(decompress_sierra_class(&compressed_sierra_class)?));
+            }
+        }
+
+        to_rpc_contract_class(contract_class)
+    }
 }
 
 /// Taken from https://github.com/paritytech/substrate/blob/master/client/rpc/src/author/mod.rs#L78
@@ -272,16 +302,20 @@ where
         })?;
 
         let contract_address_wrapped = Felt252Wrapper(contract_address).into();
-        let contract_class = self
-            .overrides
-            .for_block_hash(self.client.as_ref(), substrate_block_hash)
-            .contract_class_by_address(substrate_block_hash, contract_address_wrapped)
+        let storage_override = self.overrides.for_block_hash(self.client.as_ref(), substrate_block_hash);
+        let (class_hash, contract_class) = storage_override
+            .contract_class_hash_by_address(substrate_block_hash, contract_address_wrapped)
+            .and_then(|class_hash| {
+                storage_override
+                    .contract_class_by_class_hash(substrate_block_hash, class_hash)
+                    .map(|contract_class| (class_hash, contract_class))
+            })
             .ok_or_else(|| {
                 error!("Failed to retrieve contract class at '{contract_address}'");
                 StarknetRpcApiError::ContractNotFound
             })?;
 
-        Ok(to_rpc_contract_class(contract_class).map_err(|e| {
+        Ok(self.to_rpc_contract_class(class_hash, contract_class).map_err(|e| {
             error!("Failed to convert contract class at '{contract_address}' to RPC contract class: {e}");
             StarknetRpcApiError::InvalidContractClass
         })?)
@@ -398,14 +432,17 @@ where
                 StarknetRpcApiError::ClassHashNotFound
             })?;
 
-        Ok(to_rpc_contract_class(contract_class).map_err(|e| {
+        Ok(self.to_rpc_contract_class(class_hash, contract_class).map_err(|e| {
             error!("Failed to convert contract class from hash '{class_hash}' to RPC contract class: {e}");
             StarknetRpcApiError::InternalServerError
         })?)
     }
 
     /// Returns the specified block with transaction hashes.
-    fn get_block_with_tx_hashes(&self, block_id: BlockId) -> RpcResult<MaybePendingBlockWithTxHashes> {
+    fn get_block_with_tx_hashes(
+        &self,
+        block_id: BlockId,
+    ) -> RpcResult<BlockWithL1GasPrice<MaybePendingBlockWithTxHashes>> {
         let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
@@ -438,7 +475,10 @@ where
             sequencer_address: Felt252Wrapper::from(block.header().sequencer_address).into(),
         };
 
-        Ok(MaybePendingBlockWithTxHashes::Block(block_with_tx_hashes))
+        Ok(BlockWithL1GasPrice {
+            block: MaybePendingBlockWithTxHashes::Block(block_with_tx_hashes),
+            l1_gas_price: l1_gas_price(block.header()),
+        })
     }
 
     /// Get the nonce associated with the given address at the given block
@@ -485,16 +525,20 @@ where
     /// * `declare_transaction_result` - the result of the declare transaction
     async fn add_declare_transaction(
         &self,
-        declare_transaction: BroadcastedDeclareTransaction,
+        declare_transaction: BroadcastedDeclareTxn,
     ) -> RpcResult<DeclareTransactionResult> {
         let best_block_hash = self.client.info().best_hash;
 
-        let transaction: UserTransaction = declare_transaction.try_into().map_err(|e| {
+        let transaction: UserTransaction = match declare_transaction {
+            BroadcastedDeclareTxn::V3(tx) => tx.try_into(),
+            BroadcastedDeclareTxn::Legacy(tx) => tx.try_into(),
+        }
+        .map_err(|e| {
             error!("{e}");
             StarknetRpcApiError::InternalServerError
         })?;
         let class_hash = match transaction {
-            UserTransaction::Declare(ref tx, _) => tx.class_hash(),
+            UserTransaction::Declare(ref tx, _, _) => tx.class_hash(),
             _ => Err(StarknetRpcApiError::InternalServerError)?,
         };
 
@@ -531,11 +575,15 @@ where
     /// * `transaction_hash` - transaction hash corresponding to the invocation
     async fn add_invoke_transaction(
         &self,
-        invoke_transaction: BroadcastedInvokeTransaction,
+        invoke_transaction: BroadcastedInvokeTxn,
     ) -> RpcResult<InvokeTransactionResult> {
         let best_block_hash = self.client.info().best_hash;
 
-        let transaction: UserTransaction = invoke_transaction.try_into().map_err(|e| {
+        let transaction: UserTransaction = match invoke_transaction {
+            BroadcastedInvokeTxn::V3(tx) => tx.try_into(),
+            BroadcastedInvokeTxn::Legacy(tx) => tx.try_into(),
+        }
+        .map_err(|e| {
             error!("{e}");
             StarknetRpcApiError::InternalServerError
         })?;
@@ -561,11 +609,15 @@ where
     /// * `contract_address` - address of the deployed contract account
     async fn add_deploy_account_transaction(
         &self,
-        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+        deploy_account_transaction: BroadcastedDeployAccountTxn,
     ) -> RpcResult<DeployAccountTransactionResult> {
         let best_block_hash = self.client.info().best_hash;
 
-        let transaction: UserTransaction = deploy_account_transaction.try_into().map_err(|e| {
+        let transaction: UserTransaction = match deploy_account_transaction {
+            BroadcastedDeployAccountTxn::V3(tx) => tx.try_into(),
+            BroadcastedDeployAccountTxn::Legacy(tx) => tx.try_into(),
+        }
+        .map_err(|e| {
             error!("{e}");
             StarknetRpcApiError::InternalServerError
         })?;
@@ -577,6 +629,7 @@ where
         let chain_id = Felt252Wrapper(self.chain_id()?.0);
         let account_address = match &transaction {
             UserTransaction::DeployAccount(tx) => tx.account_address(),
+            UserTransaction::DeployAccountV3(tx) => tx.account_address(),
             _ => Err(StarknetRpcApiError::InternalServerError)?,
         };
 
@@ -596,16 +649,17 @@ where
     /// # Returns
     ///
     /// * `fee_estimate` - fee estimate in gwei
-    async fn estimate_fee(
-        &self,
-        request: Vec<BroadcastedTransaction>,
-        block_id: BlockId,
-    ) -> RpcResult<Vec<FeeEstimate>> {
+    async fn estimate_fee(&self, request: Vec<BroadcastedTxn>, block_id: BlockId) -> RpcResult<Vec<FeeEstimate>> {
         let is_invalid_query_transaction = request.iter().any(|tx| match tx {
-            BroadcastedTransaction::Invoke(invoke_tx) => !invoke_tx.is_query,
-            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V1(tx_v1)) => !tx_v1.is_query,
-            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx_v2)) => !tx_v2.is_query,
-            BroadcastedTransaction::DeployAccount(deploy_tx) => !deploy_tx.is_query,
+            BroadcastedTxn::Legacy(BroadcastedTransaction::Invoke(invoke_tx)) => !invoke_tx.is_query,
+            BroadcastedTxn::Legacy(BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V1(tx_v1))) => {
+                !tx_v1.is_query
+            }
+            BroadcastedTxn::Legacy(BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx_v2))) => {
+                !tx_v2.is_query
+            }
+            BroadcastedTxn::Legacy(BroadcastedTransaction::DeployAccount(deploy_tx)) => !deploy_tx.is_query,
+            BroadcastedTxn::V3(tx) => !tx.is_query(),
         });
         if is_invalid_query_transaction {
             return Err(StarknetRpcApiError::UnsupportedTxVersion.into());
@@ -617,10 +671,18 @@ where
         })?;
         let best_block_hash = self.client.info().best_hash;
         let chain_id = Felt252Wrapper(self.chain_id()?.0);
+        // The fees are estimated with the L1 gas price of the block the transactions are executed on
+        let gas_price = get_block_by_block_hash(self.client.as_ref(), substrate_block_hash)
+            .map(|block| block.header().l1_gas_price)
+            .unwrap_or_default();
 
         let mut estimates = vec![];
         for tx in request {
-            let tx = tx.try_into().map_err(|e| {
+            let tx: UserTransaction = match tx {
+                BroadcastedTxn::V3(tx) => tx.try_into(),
+                BroadcastedTxn::Legacy(tx) => tx.try_into(),
+            }
+            .map_err(|e| {
                 error!("{e}");
                 StarknetRpcApiError::InternalServerError
             })?;
@@ -637,7 +699,11 @@ where
                     StarknetRpcApiError::ContractError
                 })?;
 
-            estimates.push(FeeEstimate { gas_price: 0, gas_consumed: gas_usage, overall_fee: actual_fee });
+            estimates.push(FeeEstimate {
+                gas_price: Felt252Wrapper::from(gas_price).into(),
+                gas_consumed: FieldElement::from(gas_usage),
+                overall_fee: Felt252Wrapper::from(actual_fee).into(),
+            });
         }
         Ok(estimates)
     }
@@ -659,11 +725,12 @@ where
             .map(|tx_hashes| h256_to_felt(*tx_hashes.get(index as usize).ok_or(StarknetRpcApiError::InvalidTxnIndex)?))
             .unwrap_or_else(|| Ok(transaction.compute_hash::<H>(chain_id.0.into(), false).0))?;
 
-        Ok(to_starknet_core_tx(transaction.clone(), transaction_hash))
+        to_starknet_core_tx(transaction.clone(), transaction_hash)
+            .ok_or(StarknetRpcApiError::UnsupportedTxVersion.into())
     }
 
     /// Get block information with full transactions given the block id
-    fn get_block_with_txs(&self, block_id: BlockId) -> RpcResult<MaybePendingBlockWithTxs> {
+    fn get_block_with_txs(&self, block_id: BlockId) -> RpcResult<BlockWithL1GasPrice<MaybePendingBlockWithTxs>> {
         let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
@@ -681,7 +748,7 @@ where
                 .as_ref()
                 .map(|tx_hashes| h256_to_felt(*tx_hashes.get(index).ok_or(StarknetRpcApiError::InternalServerError)?))
                 .unwrap_or_else(|| Ok(tx.compute_hash::<H>(chain_id.0.into(), false).0))?;
-            transactions.push(to_starknet_core_tx(tx.clone(), hash));
+            transactions.push(to_starknet_core_tx(tx.clone(), hash).ok_or(StarknetRpcApiError::UnsupportedTxVersion)?);
         }
 
         let block_with_txs = BlockWithTxs {
@@ -696,7 +763,10 @@ where
             transactions,
         };
 
-        Ok(MaybePendingBlockWithTxs::Block(block_with_txs))
+        Ok(BlockWithL1GasPrice {
+            block: MaybePendingBlockWithTxs::Block(block_with_txs),
+            l1_gas_price: l1_gas_price(block.header()),
+        })
     }
 
     /// Get the information about the result of executing the requested block
@@ -766,9 +836,9 @@ where
             .into_iter()
             .map(|tx| {
                 let hash = tx.compute_hash::<H>(chain_id.0.into(), false).into();
-                to_starknet_core_tx(tx, hash)
+                to_starknet_core_tx(tx, hash).ok_or(StarknetRpcApiError::UnsupportedTxVersion)
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
         Ok(transactions)
     }
 
@@ -865,7 +935,7 @@ where
                 .map(|tx| to_starknet_core_tx(tx.clone(), transaction_hash))
         };
 
-        find_tx.ok_or(StarknetRpcApiError::TxnHashNotFound.into())
+        find_tx.ok_or(StarknetRpcApiError::TxnHashNotFound)?.ok_or(StarknetRpcApiError::UnsupportedTxVersion.into())
     }
 
     /// Returns the receipt of a transaction by transaction hash.
@@ -1055,6 +1125,11 @@ where
     }
 }
 
+/// Returns the L1 gas price of a block, as given in its header by the RPC.
+fn l1_gas_price(header: &mp_block::Header) -> ResourcePrice {
+    ResourcePrice { price_in_wei: Felt252Wrapper::from(header.l1_gas_price).into() }
+}
+
 fn h256_to_felt(h256: H256) -> Result<FieldElement, StarknetRpcApiError> {
     match Felt252Wrapper::try_from(h256) {
         Ok(felt) => Ok(felt.0),
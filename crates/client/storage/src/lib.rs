@@ -5,6 +5,9 @@
 //! The `OverrideHandle` make it possible to use the later, more efficient way, while keeping the
 //! first one as a fallback.
 //! It can also support multiple versions of the pallet storage.
+//!
+//! When the state of a block has been pruned from the Substrate db, the storage is read from the
+//! Madara storage history db instead.
 
 mod overrides;
 
@@ -18,11 +21,11 @@ use sc_client_api::backend::{Backend, StorageProvider};
 use scale_codec::Decode;
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
-use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
 use sp_storage::StorageKey;
 
 /// Create and return a handle of the starknet schema overrides
-pub fn overrides_handle<B, C, BE>(client: Arc<C>) -> Arc<OverrideHandle<B>>
+pub fn overrides_handle<B, C, BE>(client: Arc<C>, madara_backend: Arc<mc_db::Backend<B>>) -> Arc<OverrideHandle<B>>
 where
     B: BlockT,
     C: ProvideRuntimeApi<B>,
@@ -33,24 +36,57 @@ where
     let mut overrides_map = BTreeMap::new();
     overrides_map.insert(
         StarknetStorageSchemaVersion::V1,
-        Box::new(SchemaV1Override::new(client.clone())) as Box<dyn StorageOverride<_>>,
+        Box::new(SchemaV1Override::new(client.clone(), madara_backend.clone())) as Box<dyn StorageOverride<_>>,
+    );
+    overrides_map.insert(
+        StarknetStorageSchemaVersion::V2,
+        Box::new(SchemaV2Override::new(client.clone(), madara_backend.clone())) as Box<dyn StorageOverride<_>>,
     );
 
     Arc::new(OverrideHandle {
         schemas: overrides_map,
         fallback: Box::new(RuntimeApiStorageOverride::<B, C>::new(client)),
+        madara_backend,
     })
 }
 
 /// Retrieve the current `pallet-starknet` storage schema version
-pub fn onchain_storage_schema<B, C, BE>(client: &C, hash: B::Hash) -> StarknetStorageSchemaVersion
+pub fn onchain_storage_schema<B, C, BE>(
+    client: &C,
+    madara_backend: &mc_db::Backend<B>,
+    hash: B::Hash,
+) -> StarknetStorageSchemaVersion
+where
+    B: BlockT,
+    C: HeaderBackend<B> + StorageProvider<B, BE>,
+    BE: Backend<B>,
+{
+    match storage_at(client, madara_backend, hash, &StorageKey(PALLET_STARKNET_SCHEMA.to_vec())) {
+        Some(bytes) => Decode::decode(&mut &bytes[..]).ok().unwrap_or(StarknetStorageSchemaVersion::Undefined),
+        None => StarknetStorageSchemaVersion::Undefined,
+    }
+}
+
+/// Read the raw value of a storage key at the given block
+///
+/// If the state of the block is not available anymore, the value is read from the Madara storage
+/// history db.
+pub fn storage_at<B, C, BE>(
+    client: &C,
+    madara_backend: &mc_db::Backend<B>,
+    hash: B::Hash,
+    key: &StorageKey,
+) -> Option<Vec<u8>>
 where
     B: BlockT,
     C: HeaderBackend<B> + StorageProvider<B, BE>,
     BE: Backend<B>,
 {
-    match client.storage(hash, &StorageKey(PALLET_STARKNET_SCHEMA.to_vec())) {
-        Ok(Some(bytes)) => Decode::decode(&mut &bytes.0[..]).ok().unwrap_or(StarknetStorageSchemaVersion::Undefined),
-        _ => StarknetStorageSchemaVersion::Undefined,
+    match client.storage(hash, key) {
+        Ok(data) => data.map(|data| data.0),
+        Err(_) => {
+            let block_number: u64 = client.number(hash).ok()??.unique_saturated_into();
+            madara_backend.history().value_at(&key.0, block_number).ok()?
+        }
     }
 }
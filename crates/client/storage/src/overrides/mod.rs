@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 
 use blockifier::execution::contract_class::ContractClass;
-use frame_support::{Identity, StorageHasher};
+use frame_support::{Blake2_128Concat, Identity, StorageHasher};
 use mp_storage::StarknetStorageSchemaVersion;
 use pallet_starknet::runtime_api::StarknetRuntimeApi;
 use sc_client_api::{Backend, HeaderBackend, StorageProvider};
@@ -15,8 +15,10 @@ use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
 
 mod schema_v1_override;
+mod schema_v2_override;
 
 pub use self::schema_v1_override::SchemaV1Override;
+pub use self::schema_v2_override::SchemaV2Override;
 use crate::onchain_storage_schema;
 
 /// A handle containing multiple entities implementing `StorageOverride`
@@ -25,6 +27,8 @@ pub struct OverrideHandle<B: BlockT> {
     pub schemas: BTreeMap<StarknetStorageSchemaVersion, Box<dyn StorageOverride<B>>>,
     /// A non-failing way to retrieve the storage data
     pub fallback: Box<dyn StorageOverride<B>>,
+    /// The Madara backend, used to find the schema version of pruned blocks
+    pub madara_backend: Arc<mc_db::Backend<B>>,
 }
 
 #[allow(clippy::borrowed_box)]
@@ -44,7 +48,7 @@ impl<B: BlockT> OverrideHandle<B> {
         client: &C,
         block_hash: B::Hash,
     ) -> &Box<dyn StorageOverride<B>> {
-        let schema_version = onchain_storage_schema(client, block_hash);
+        let schema_version = onchain_storage_schema(client, &self.madara_backend, block_hash);
         self.for_schema_version(&schema_version)
     }
 }
@@ -87,6 +91,11 @@ fn storage_key_build(prefix: Vec<u8>, key: &[u8]) -> Vec<u8> {
     [prefix, Identity::hash(key)].concat()
 }
 
+/// Returns the storage key for single key maps using the Blake2_128Concat storage hasher.
+fn storage_key_build_blake2_128_concat(prefix: Vec<u8>, key: &[u8]) -> Vec<u8> {
+    [prefix, Blake2_128Concat::hash(key)].concat()
+}
+
 /// A wrapper type for the Runtime API.
 ///
 /// This type implements `StorageOverride`, so it can be used when calling the runtime API is
@@ -181,3 +190,127 @@ where
         self.client.runtime_api().nonce(block_hash, contract_address).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use mp_storage::{
+        PALLET_STARKNET, PALLET_STARKNET_SCHEMA, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE,
+    };
+    use sc_client_db::DatabaseSource;
+    use scale_codec::Encode;
+    use starknet_api::api_core::PatriciaKey;
+    use substrate_test_runtime_client::runtime::Block as TestBlock;
+    use substrate_test_runtime_client::{Backend as TestBackend, TestClientBuilder, TestClientBuilderExt};
+
+    use super::*;
+
+    /// The raw storage of a contract, along with the schema version, for a given layout
+    fn contract_storage(
+        schema: StarknetStorageSchemaVersion,
+        class_hash_key: fn(Vec<u8>, &[u8]) -> Vec<u8>,
+        nonce_key: fn(Vec<u8>, &[u8]) -> Vec<u8>,
+        storage_key: fn(Vec<u8>, &[u8]) -> Vec<u8>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let (address, key) = (address(), StorageKey(PatriciaKey(StarkFelt::from(2_u128))));
+        vec![
+            (PALLET_STARKNET_SCHEMA.to_vec(), schema.encode()),
+            (
+                class_hash_key(storage_prefix_build(PALLET_STARKNET, STARKNET_CONTRACT_CLASS_HASH), &address.encode()),
+                ClassHash(StarkFelt::from(3_u128)).encode(),
+            ),
+            (
+                nonce_key(storage_prefix_build(PALLET_STARKNET, STARKNET_NONCE), &address.encode()),
+                Nonce(StarkFelt::from(4_u128)).encode(),
+            ),
+            (
+                storage_key(storage_prefix_build(PALLET_STARKNET, STARKNET_STORAGE), &(address, key).encode()),
+                StarkFelt::from(5_u128).encode(),
+            ),
+        ]
+    }
+
+    fn address() -> ContractAddress {
+        ContractAddress(PatriciaKey(StarkFelt::from(1_u128)))
+    }
+
+    /// Reads the contract storage at genesis through the overrides of a chain with the given
+    /// storage
+    fn assert_contract_storage_is_read(storage: Vec<(Vec<u8>, Vec<u8>)>) {
+        let client = Arc::new(
+            storage
+                .into_iter()
+                .fold(TestClientBuilder::new(), |builder, (k, v)| builder.add_extra_storage(k, v))
+                .build(),
+        );
+        let db_dir = tempfile::tempdir().unwrap();
+        let madara_backend = Arc::new(
+            mc_db::Backend::<TestBlock>::open(
+                &DatabaseSource::RocksDb { path: db_dir.path().to_path_buf(), cache_size: 0 },
+                db_dir.path(),
+                false,
+            )
+            .unwrap(),
+        );
+
+        let mut schemas = BTreeMap::new();
+        schemas.insert(
+            StarknetStorageSchemaVersion::V1,
+            Box::new(SchemaV1Override::<_, _, TestBackend>::new(client.clone(), madara_backend.clone()))
+                as Box<dyn StorageOverride<_>>,
+        );
+        schemas.insert(
+            StarknetStorageSchemaVersion::V2,
+            Box::new(SchemaV2Override::<_, _, TestBackend>::new(client.clone(), madara_backend.clone()))
+                as Box<dyn StorageOverride<_>>,
+        );
+        let overrides = OverrideHandle {
+            schemas,
+            fallback: Box::new(SchemaV2Override::<_, _, TestBackend>::new(client.clone(), madara_backend.clone())),
+            madara_backend,
+        };
+
+        let genesis_hash = client.info().genesis_hash;
+        let storage_override = overrides.for_block_hash(client.as_ref(), genesis_hash);
+        let key = StorageKey(PatriciaKey(StarkFelt::from(2_u128)));
+        assert_eq!(
+            storage_override.contract_class_hash_by_address(genesis_hash, address()),
+            Some(ClassHash(StarkFelt::from(3_u128)))
+        );
+        assert_eq!(storage_override.nonce(genesis_hash, address()), Some(Nonce(StarkFelt::from(4_u128))));
+        assert_eq!(
+            storage_override.get_storage_by_storage_key(genesis_hash, address(), key),
+            Some(StarkFelt::from(5_u128))
+        );
+    }
+
+    #[test]
+    fn storage_is_read_before_the_schema_v2_migration() {
+        assert_contract_storage_is_read(contract_storage(
+            StarknetStorageSchemaVersion::V1,
+            storage_key_build,
+            storage_key_build,
+            storage_key_build,
+        ));
+    }
+
+    #[test]
+    fn storage_is_read_during_the_schema_v2_migration() {
+        // The class hashes are already moved to their schema V2 key, but not the storage
+        assert_contract_storage_is_read(contract_storage(
+            StarknetStorageSchemaVersion::V1,
+            storage_key_build_blake2_128_concat,
+            storage_key_build_blake2_128_concat,
+            storage_key_build,
+        ));
+    }
+
+    #[test]
+    fn storage_is_read_after_the_schema_v2_migration() {
+        assert_contract_storage_is_read(contract_storage(
+            StarknetStorageSchemaVersion::V2,
+            storage_key_build_blake2_128_concat,
+            storage_key_build_blake2_128_concat,
+            storage_key_build_blake2_128_concat,
+        ));
+    }
+}
@@ -15,17 +15,19 @@ use starknet_api::api_core::{ClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey as StarknetStorageKey;
 
-use super::{storage_key_build, storage_prefix_build, StorageOverride};
+use super::{storage_key_build, storage_key_build_blake2_128_concat, storage_prefix_build, StorageOverride};
+use crate::storage_at;
 
 /// An override for runtimes that use Schema V1
 pub struct SchemaV1Override<B: BlockT, C, BE> {
     client: Arc<C>,
-    _marker: PhantomData<(B, BE)>,
+    madara_backend: Arc<mc_db::Backend<B>>,
+    _marker: PhantomData<BE>,
 }
 
 impl<B: BlockT, C, BE> SchemaV1Override<B, C, BE> {
-    pub fn new(client: Arc<C>) -> Self {
-        Self { client, _marker: PhantomData }
+    pub fn new(client: Arc<C>, madara_backend: Arc<mc_db::Backend<B>>) -> Self {
+        Self { client, madara_backend, _marker: PhantomData }
     }
 }
 
@@ -36,13 +38,22 @@ where
     BE: Backend<B> + 'static,
 {
     fn query_storage<T: Decode>(&self, block_hash: B::Hash, key: &StorageKey) -> Option<T> {
-        if let Ok(Some(data)) = self.client.storage(block_hash, key) {
-            if let Ok(result) = Decode::decode(&mut &data.0[..]) {
+        if let Some(data) = storage_at(self.client.as_ref(), self.madara_backend.as_ref(), block_hash, key) {
+            if let Ok(result) = Decode::decode(&mut &data[..]) {
                 return Some(result);
             }
         }
         None
     }
+    /// Query an entry of a map using the `Identity` hasher in the schema V1.
+    ///
+    /// The schema V1 entries are moved to their schema V2 key over several blocks when upgrading
+    /// the runtime, while the schema version stays V1, so the schema V2 key is read when the entry
+    /// isn't at its schema V1 key.
+    fn query_migrated_storage<T: Decode>(&self, block_hash: B::Hash, prefix: Vec<u8>, key: &[u8]) -> Option<T> {
+        self.query_storage(block_hash, &StorageKey(storage_key_build(prefix.clone(), key)))
+            .or_else(|| self.query_storage(block_hash, &StorageKey(storage_key_build_blake2_128_concat(prefix, key))))
+    }
     fn encode_storage_key<T: Encode>(&self, key: &T) -> Vec<u8> {
         Encode::encode(key)
     }
@@ -69,9 +80,10 @@ where
             None => return None,
         }
 
-        let storage = self.query_storage::<StarkFelt>(
+        let storage = self.query_migrated_storage::<StarkFelt>(
             block_hash,
-            &StorageKey(storage_key_build(storage_storage_prefix, &self.encode_storage_key(&key))),
+            storage_storage_prefix,
+            &self.encode_storage_key(&key),
         );
 
         match storage {
@@ -95,9 +107,10 @@ where
         address: ContractAddress,
     ) -> Option<ClassHash> {
         let storage_contract_class_hash_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_CONTRACT_CLASS_HASH);
-        self.query_storage::<ClassHash>(
+        self.query_migrated_storage::<ClassHash>(
             block_hash,
-            &StorageKey(storage_key_build(storage_contract_class_hash_prefix, &self.encode_storage_key(&address))),
+            storage_contract_class_hash_prefix,
+            &self.encode_storage_key(&address),
         )
     }
 
@@ -118,10 +131,8 @@ where
 
     fn nonce(&self, block_hash: <B as BlockT>::Hash, address: ContractAddress) -> Option<Nonce> {
         let storage_nonce_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_NONCE);
-        let nonce = self.query_storage::<Nonce>(
-            block_hash,
-            &StorageKey(storage_key_build(storage_nonce_prefix, &self.encode_storage_key(&address))),
-        );
+        let nonce =
+            self.query_migrated_storage::<Nonce>(block_hash, storage_nonce_prefix, &self.encode_storage_key(&address));
 
         match nonce {
             Some(nonce) => Some(nonce),
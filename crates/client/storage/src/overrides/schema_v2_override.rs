@@ -0,0 +1,139 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use blockifier::execution::contract_class::ContractClass;
+use mp_storage::{
+    PALLET_STARKNET, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE,
+};
+// Substrate
+use sc_client_api::backend::{Backend, StorageProvider};
+use scale_codec::{Decode, Encode};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use sp_storage::StorageKey;
+use starknet_api::api_core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey as StarknetStorageKey;
+
+use super::{storage_key_build, storage_key_build_blake2_128_concat, storage_prefix_build, StorageOverride};
+use crate::storage_at;
+
+/// An override for runtimes that use Schema V2
+///
+/// Contract class hashes, nonces and contract storage are stored using the `Blake2_128Concat`
+/// hasher.
+pub struct SchemaV2Override<B: BlockT, C, BE> {
+    client: Arc<C>,
+    madara_backend: Arc<mc_db::Backend<B>>,
+    _marker: PhantomData<BE>,
+}
+
+impl<B: BlockT, C, BE> SchemaV2Override<B, C, BE> {
+    pub fn new(client: Arc<C>, madara_backend: Arc<mc_db::Backend<B>>) -> Self {
+        Self { client, madara_backend, _marker: PhantomData }
+    }
+}
+
+impl<B, C, BE> SchemaV2Override<B, C, BE>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + StorageProvider<B, BE> + 'static,
+    BE: Backend<B> + 'static,
+{
+    fn query_storage<T: Decode>(&self, block_hash: B::Hash, key: &StorageKey) -> Option<T> {
+        if let Some(data) = storage_at(self.client.as_ref(), self.madara_backend.as_ref(), block_hash, key) {
+            if let Ok(result) = Decode::decode(&mut &data[..]) {
+                return Some(result);
+            }
+        }
+        None
+    }
+    fn encode_storage_key<T: Encode>(&self, key: &T) -> Vec<u8> {
+        Encode::encode(key)
+    }
+}
+
+impl<B, C, BE> StorageOverride<B> for SchemaV2Override<B, C, BE>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + StorageProvider<B, BE> + 'static,
+    BE: Backend<B> + 'static,
+{
+    fn get_storage_by_storage_key(
+        &self,
+        block_hash: <B as BlockT>::Hash,
+        address: ContractAddress,
+        key: StarknetStorageKey,
+    ) -> Option<StarkFelt> {
+        let storage_storage_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_STORAGE);
+        let key = (address, key);
+
+        // check if contract exists
+        match self.contract_class_hash_by_address(block_hash, address) {
+            Some(_) => (),
+            None => return None,
+        }
+
+        let storage = self.query_storage::<StarkFelt>(
+            block_hash,
+            &StorageKey(storage_key_build_blake2_128_concat(storage_storage_prefix, &self.encode_storage_key(&key))),
+        );
+
+        match storage {
+            Some(storage) => Some(storage),
+            None => Some(Default::default()),
+        }
+    }
+
+    fn contract_class_by_address(
+        &self,
+        block_hash: <B as BlockT>::Hash,
+        address: ContractAddress,
+    ) -> Option<ContractClass> {
+        let class_hash = self.contract_class_hash_by_address(block_hash, address)?;
+        self.contract_class_by_class_hash(block_hash, class_hash)
+    }
+
+    fn contract_class_hash_by_address(
+        &self,
+        block_hash: <B as BlockT>::Hash,
+        address: ContractAddress,
+    ) -> Option<ClassHash> {
+        let storage_contract_class_hash_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_CONTRACT_CLASS_HASH);
+        self.query_storage::<ClassHash>(
+            block_hash,
+            &StorageKey(storage_key_build_blake2_128_concat(
+                storage_contract_class_hash_prefix,
+                &self.encode_storage_key(&address),
+            )),
+        )
+    }
+
+    fn contract_class_by_class_hash(
+        &self,
+        block_hash: <B as BlockT>::Hash,
+        contract_class_hash: ClassHash,
+    ) -> Option<ContractClass> {
+        let storage_contract_class_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_CONTRACT_CLASS);
+        self.query_storage::<ContractClass>(
+            block_hash,
+            &StorageKey(storage_key_build(
+                storage_contract_class_prefix,
+                &self.encode_storage_key(&contract_class_hash),
+            )),
+        )
+    }
+
+    fn nonce(&self, block_hash: <B as BlockT>::Hash, address: ContractAddress) -> Option<Nonce> {
+        let storage_nonce_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_NONCE);
+        let nonce = self.query_storage::<Nonce>(
+            block_hash,
+            &StorageKey(storage_key_build_blake2_128_concat(storage_nonce_prefix, &self.encode_storage_key(&address))),
+        );
+
+        match nonce {
+            Some(nonce) => Some(nonce),
+            None => Some(Nonce::default()),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use madara_runtime::{AuraConfig, GenesisConfig, GrandpaConfig, SealingMode, SystemConfig, WASM_BINARY};
+use madara_runtime::{
+    AccountId, AuraConfig, GenesisConfig, GrandpaConfig, SealingMode, Signature, SudoConfig, SystemConfig, WASM_BINARY,
+};
 use mp_felt::Felt252Wrapper;
 use pallet_starknet::genesis_loader::{GenesisData, GenesisLoader, HexFelt};
 use sc_service::{BasePath, ChainType};
@@ -8,7 +10,8 @@ use serde::{Deserialize, Serialize};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_consensus_grandpa::AuthorityId as GrandpaId;
 use sp_core::storage::Storage;
-use sp_core::{Pair, Public};
+use sp_core::{sr25519, Pair, Public};
+use sp_runtime::traits::{IdentifyAccount, Verify};
 use sp_state_machine::BasicExternalities;
 
 use crate::constants::DEV_CHAIN_ID;
@@ -50,6 +53,16 @@ pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Pu
     TPublic::Pair::from_string(&format!("//{seed}"), None).expect("static values are valid; qed").public()
 }
 
+type AccountPublic = <Signature as Verify>::Signer;
+
+/// Generate an account ID from seed.
+pub fn get_account_id_from_seed<TPublic: Public>(seed: &str) -> AccountId
+where
+    AccountPublic: From<<TPublic::Pair as Pair>::Public>,
+{
+    AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
+}
+
 /// Generate an Aura authority key.
 pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
     (get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
@@ -77,6 +90,8 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
                     wasm_binary,
                     // Initial PoA authorities
                     vec![authority_keys_from_seed("Alice")],
+                    // Sudo account
+                    get_account_id_from_seed::<sr25519::Public>("Alice"),
                     true,
                 ),
                 sealing: sealing.clone(),
@@ -101,10 +116,19 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
 // accounts with addresses 0x2 and 0x3 have the same PK
 pub fn print_development_accounts(genesis_loader: &GenesisLoader) {
     // TODO: this is only true by luck. It's not enforced by anything
-    let no_validate_account_address = genesis_loader.data().contracts[0].0.0;
-    let argent_account_address = genesis_loader.data().contracts[1].0.0;
-    let oz_account_address = genesis_loader.data().contracts[2].0.0;
-    let cairo_1_no_validate_account_address = genesis_loader.data().contracts[3].0.0;
+    let Some(
+        [
+            (no_validate_account_address, _),
+            (argent_account_address, _),
+            (oz_account_address, _),
+            (cairo_1_no_validate_account_address, _),
+        ],
+    ) = genesis_loader.data().contracts.get(..4)
+    else {
+        // E.g. a Starknet state imported from another chain
+        log::info!("🧪 The genesis doesn't deploy the development accounts");
+        return;
+    };
 
     let argent_pk: HexFelt =
         Felt252Wrapper::from_hex_be("0x00c1cf1490de1352865301bb8705143f3ef938f97fdf892f1090dcb5ac7bcd1d")
@@ -135,6 +159,8 @@ pub fn local_testnet_config(base_path: BasePath, chain_id: &str) -> Result<Chain
                 // Initial PoA authorities
                 // Intended to be only 2
                 vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
+                // Sudo account
+                get_account_id_from_seed::<sr25519::Public>("Alice"),
                 true,
             )
         },
@@ -166,6 +192,7 @@ fn testnet_genesis(
     genesis_loader: GenesisLoader,
     wasm_binary: &[u8],
     initial_authorities: Vec<(AuraId, GrandpaId)>,
+    root_key: AccountId,
     _enable_println: bool,
 ) -> GenesisConfig {
     let starknet_genesis_config: madara_runtime::pallet_starknet::GenesisConfig<_> = genesis_loader.into();
@@ -181,5 +208,7 @@ fn testnet_genesis(
         grandpa: GrandpaConfig { authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect() },
         /// Starknet Genesis configuration.
         starknet: starknet_genesis_config,
+        // Account allowed to change the Starknet protocol parameters
+        sudo: SudoConfig { key: Some(root_key) },
     }
 }
@@ -1,4 +1,4 @@
-use crate::commands::{ExtendedRunCmd, SetupCmd};
+use crate::commands::{ExportStarknetStateCmd, ExtendedRunCmd, ImportStarknetStateCmd, ReconstructStateCmd, SetupCmd};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -31,9 +31,15 @@ pub enum Subcommand {
     /// Export the state of a given block into a chain spec.
     ExportState(sc_cli::ExportStateCmd),
 
+    /// Export the Starknet state of a given block as a genesis file.
+    ExportStarknetState(ExportStarknetStateCmd),
+
     /// Import blocks.
     ImportBlocks(sc_cli::ImportBlocksCmd),
 
+    /// Import an exported Starknet state as the genesis of a chain.
+    ImportStarknetState(ImportStarknetStateCmd),
+
     /// Key management cli utilities
     #[command(subcommand)]
     Key(sc_cli::KeySubcommand),
@@ -41,6 +47,9 @@ pub enum Subcommand {
     /// Remove the whole chain.
     PurgeChain(sc_cli::PurgeChainCmd),
 
+    /// Reconstruct the Starknet state from the state diffs published to a DA layer.
+    ReconstructState(ReconstructStateCmd),
+
     /// Revert the chain to a previous state.
     Revert(sc_cli::RevertCmd),
 
@@ -1,6 +1,7 @@
 use frame_benchmarking_cli::{BenchmarkCmd, ExtrinsicFactory, SUBSTRATE_REFERENCE_HARDWARE};
 use madara_runtime::Block;
 use sc_cli::{ChainSpec, RuntimeVersion, SubstrateCli};
+use sp_runtime::BuildStorage;
 
 use crate::benchmarking::{inherent_benchmark_data, RemarkBuilder};
 use crate::cli::{Cli, Subcommand};
@@ -90,6 +91,13 @@ pub fn run() -> sc_cli::Result<()> {
                 Ok((cmd.run(client, config.chain_spec), task_manager))
             })
         }
+        Some(Subcommand::ExportStarknetState(ref cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.sync_run(|mut config| {
+                let (client, _, _, _, _) = service::new_chain_ops(&mut config, cli.run.cache)?;
+                cmd.run(&client)
+            })
+        }
         Some(Subcommand::ImportBlocks(ref cmd)) => {
             let runner = cli.create_runner(cmd)?;
             runner.async_run(|mut config| {
@@ -97,10 +105,20 @@ pub fn run() -> sc_cli::Result<()> {
                 Ok((cmd.run(client, import_queue), task_manager))
             })
         }
+        Some(Subcommand::ImportStarknetState(ref cmd)) => cmd.run(),
         Some(Subcommand::PurgeChain(ref cmd)) => {
             let runner = cli.create_runner(cmd)?;
             runner.sync_run(|config| cmd.run(config.database))
         }
+        Some(Subcommand::ReconstructState(ref cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.async_run(|mut config| {
+                let da_config = cmd.da_config.clone().unwrap_or_else(|| config.data_path.join("da-config.json"));
+                let genesis_storage = config.chain_spec.build_storage().map_err(sc_cli::Error::Input)?;
+                let (client, _, _, task_manager, madara_backend) = service::new_chain_ops(&mut config, cli.run.cache)?;
+                Ok((cmd.run(client, madara_backend, genesis_storage, da_config), task_manager))
+            })
+        }
         Some(Subcommand::Revert(ref cmd)) => {
             let runner = cli.create_runner(cmd)?;
             runner.async_run(|mut config| {
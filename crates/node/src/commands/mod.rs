@@ -1,5 +1,9 @@
+mod reconstruct_state;
 mod run;
 mod setup;
+mod starknet_state;
 
+pub use reconstruct_state::*;
 pub use run::*;
 pub use setup::*;
+pub use starknet_state::*;
@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use mc_data_availability::celestia::config::CelestiaConfig;
+use mc_data_availability::celestia::CelestiaClient;
+use mc_data_availability::ethereum::EthereumClient;
+use mc_data_availability::file::config::FileConfig;
+use mc_data_availability::file::FileClient;
+use mc_data_availability::state_diff::StateDiff;
+use mc_data_availability::volition::VolitionConfig;
+use mc_data_availability::{DaLayer, DaReader};
+use mp_digest_log::find_starknet_block;
+use mp_felt::Felt252Wrapper;
+use sc_cli::{CliConfiguration, DatabaseParams, Error, PruningParams, Result, SharedParams};
+use sc_client_api::HeaderBackend;
+use sp_core::storage::Storage;
+
+use super::setup::write_content_to_disk;
+use crate::service::FullClient;
+use crate::starknet::MadaraBackend;
+
+/// Reconstruct the Starknet state from the state diffs published to a DA layer.
+///
+/// The genesis state, which is not published, is built from the chain spec. The state diffs are
+/// then applied on top of it in publication order. After each of them, the root of the
+/// reconstructed state is checked against the state root of the last block it covers in the local
+/// chain. Submissions recorded in the local Madara db tell which blocks they cover, others are
+/// assumed to cover a single block, the first one being `--first-block`.
+///
+/// With `--volition`, the on-chain parts of the state diffs are also read back from Ethereum, and
+/// each of them is applied before the off-chain part of the submission covering its blocks.
+#[derive(Debug, clap::Parser)]
+pub struct ReconstructStateCmd {
+    /// DA layer to read the published state diffs from.
+    #[arg(long, value_enum, ignore_case = true)]
+    pub da_layer: DaLayer,
+
+    /// Path to the DA config. Defaults to the `da-config.json` file of the chain data directory.
+    #[arg(long, value_name = "PATH")]
+    pub da_config: Option<PathBuf>,
+
+    /// Number of the block whose state diff was published first, when the submissions are not
+    /// recorded in the local Madara db.
+    #[arg(long, value_name = "NUMBER", default_value_t = 1)]
+    pub first_block: u64,
+
+    /// Merge the on-chain parts of the state diffs, published to Ethereum in Volition mode with
+    /// the `volition` section of the DA config. Their submissions must be recorded in the local
+    /// Madara db.
+    #[arg(long)]
+    pub volition: bool,
+
+    /// File to write the reconstructed state to, as JSON.
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub pruning_params: PruningParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl CliConfiguration for ReconstructStateCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn pruning_params(&self) -> Option<&PruningParams> {
+        Some(&self.pruning_params)
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+impl ReconstructStateCmd {
+    pub async fn run(
+        &self,
+        client: Arc<FullClient>,
+        madara_backend: Arc<MadaraBackend>,
+        genesis_storage: Storage,
+        da_config: PathBuf,
+    ) -> Result<()> {
+        let da_reader: Box<dyn DaReader> = match self.da_layer {
+            DaLayer::File => {
+                let file_conf = FileConfig::try_from(&da_config).map_err(Error::Input)?;
+                Box::new(FileClient::try_from(file_conf).map_err(|e| Error::Application(e.into()))?)
+            }
+            DaLayer::Celestia => {
+                let celestia_conf = CelestiaConfig::try_from(&da_config).map_err(Error::Input)?;
+                Box::new(CelestiaClient::try_from(celestia_conf).map_err(|e| Error::Application(e.into()))?)
+            }
+            da_layer => {
+                return Err(Error::Input(format!("reading state diffs back from {da_layer:?} is not supported")));
+            }
+        };
+
+        let state_diffs = da_reader.published_state_diffs().await.map_err(|e| Error::Application(e.into()))?;
+        log::info!("Reconstructing the Starknet state from {} published state diffs", state_diffs.len());
+        let mut onchain_state_diffs = match self.volition {
+            true => {
+                let volition_conf = VolitionConfig::try_from(&da_config).map_err(Error::Input)?;
+                onchain_state_diffs(&madara_backend, volition_conf).await?
+            }
+            false => Vec::new(),
+        }
+        .into_iter()
+        .peekable();
+
+        let mut state =
+            StateDiff::from_storage(genesis_storage.top.iter().map(|(key, value)| (key.as_slice(), value.as_slice())));
+        let mut unchecked_blocks = 0;
+        if !check_state_root(&client, &state, 0)? {
+            unchecked_blocks += 1;
+        }
+        for (index, encoded_state_diff) in state_diffs.iter().enumerate() {
+            let block_number = match madara_backend.da().submission(index as u64).map_err(Error::Input)? {
+                Some((_, last_block)) => last_block,
+                None => self.first_block + index as u64,
+            };
+            // The on-chain parts of the blocks of a submission are published before its off-chain part
+            while let Some((_, onchain_state_diff)) =
+                onchain_state_diffs.next_if(|(last_block, _)| *last_block <= block_number)
+            {
+                state.merge(onchain_state_diff);
+            }
+            let state_diff = StateDiff::decode(encoded_state_diff)
+                .map_err(|e| Error::Input(format!("invalid state diff for block #{block_number}: {e}")))?;
+            state.merge(state_diff);
+
+            if !check_state_root(&client, &state, block_number)? {
+                unchecked_blocks += 1;
+            }
+        }
+
+        if onchain_state_diffs.peek().is_some() {
+            log::warn!(
+                "{} on-chain state diffs cover blocks whose off-chain part is not published, ignoring them",
+                onchain_state_diffs.count()
+            );
+        }
+        if unchecked_blocks > 0 {
+            log::warn!("{unchecked_blocks} blocks have no state root in the local chain to check the state against");
+        }
+        log::info!(
+            "Reconstructed the state of {} contracts and {} declared classes",
+            state.contracts.len(),
+            state.declared_classes.len()
+        );
+
+        if let Some(path) = &self.output {
+            let content = serde_json::to_string_pretty(&state).map_err(|e| Error::Application(Box::new(e)))?;
+            write_content_to_disk(content, path)?;
+            log::info!("Reconstructed state written to '{}'", path.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the on-chain parts of the state diffs back from Ethereum, along with the last block
+/// each of them covers
+async fn onchain_state_diffs(
+    madara_backend: &MadaraBackend,
+    volition_conf: VolitionConfig,
+) -> Result<Vec<(u64, StateDiff)>> {
+    let ethereum_client = EthereumClient::try_from(volition_conf.ethereum).map_err(|e| Error::Application(e.into()))?;
+    let state_diffs = ethereum_client.published_state_diffs().await.map_err(|e| Error::Application(e.into()))?;
+    log::info!("Merging {} on-chain state diffs published to Ethereum", state_diffs.len());
+
+    let mut onchain_state_diffs = Vec::with_capacity(state_diffs.len());
+    for (index, encoded_state_diff) in state_diffs.iter().enumerate() {
+        let last_block = match madara_backend.da().onchain_submission(index as u64).map_err(Error::Input)? {
+            Some((_, last_block)) => last_block,
+            None => {
+                return Err(Error::Input(format!(
+                    "on-chain submission #{index} is not recorded in the local Madara db"
+                )));
+            }
+        };
+        let state_diff = StateDiff::decode(encoded_state_diff)
+            .map_err(|e| Error::Input(format!("invalid on-chain state diff for block #{last_block}: {e}")))?;
+        onchain_state_diffs.push((last_block, state_diff));
+    }
+
+    Ok(onchain_state_diffs)
+}
+
+/// Check the root of the reconstructed state against the state root of the Starknet block with the
+/// given number.
+///
+/// Returns `false` if the block is not known locally, or if its header doesn't commit to a state
+/// root.
+fn check_state_root(client: &FullClient, state: &StateDiff, block_number: u64) -> Result<bool> {
+    let header_state_root = match header_state_root(client, block_number)? {
+        Some(state_root) if state_root != Felt252Wrapper::ZERO => state_root,
+        _ => return Ok(false),
+    };
+
+    let state_root = state.state_root().map_err(|e| Error::Application(e.into()))?;
+    if state_root != header_state_root {
+        return Err(Error::Input(format!(
+            "state root mismatch at block #{block_number}: reconstructed {state_root:?}, header {header_state_root:?}"
+        )));
+    }
+
+    Ok(true)
+}
+
+/// Return the state root of the Starknet block with the given number, if it is known locally.
+fn header_state_root(client: &FullClient, block_number: u64) -> Result<Option<Felt252Wrapper>> {
+    let number = block_number.try_into().map_err(|_| Error::Input(format!("invalid block number {block_number}")))?;
+    let header = match client.hash(number)? {
+        Some(block_hash) => client.header(block_hash)?,
+        None => None,
+    };
+
+    match header {
+        Some(header) => {
+            let block = find_starknet_block(&header.digest)
+                .map_err(|e| Error::Input(format!("block #{block_number} has no Starknet block: {e}")))?;
+            Ok(Some(block.header().global_state_root.into()))
+        }
+        None => Ok(None),
+    }
+}
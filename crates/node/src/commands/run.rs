@@ -5,8 +5,10 @@ use mc_data_availability::DaLayer;
 use sc_cli::{Result, RpcMethods, RunCmd, SubstrateCli};
 use sc_service::BasePath;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use crate::cli::Cli;
+use crate::l1_gas_price::L1GasPriceSource;
 use crate::service;
 
 /// Available Sealing methods.
@@ -52,6 +54,28 @@ pub struct ExtendedRunCmd {
     /// increases the memory footprint of the node.
     #[clap(long)]
     pub cache: bool,
+
+    /// A fixed L1 gas price, in wei, supplied to the blocks authored by the node.
+    #[clap(long, group = "l1_gas_price_source")]
+    pub l1_gas_price: Option<u128>,
+
+    /// A file containing the L1 gas price, in wei, supplied to the blocks authored by the node.
+    ///
+    /// The file is read again for every block.
+    #[clap(long, group = "l1_gas_price_source")]
+    pub l1_gas_price_file: Option<PathBuf>,
+
+    /// The Ethereum JSON-RPC endpoint whose `eth_gasPrice` is supplied to the blocks authored by
+    /// the node.
+    #[clap(long, group = "l1_gas_price_source")]
+    pub l1_gas_price_rpc: Option<Url>,
+
+    /// The number of blocks, before the last finalized one, whose Starknet storage is kept in the
+    /// storage history.
+    ///
+    /// The whole history is kept when none is given.
+    #[clap(long)]
+    pub storage_history_blocks: Option<u64>,
 }
 
 impl ExtendedRunCmd {
@@ -62,6 +86,19 @@ impl ExtendedRunCmd {
             .base_path()?
             .unwrap_or_else(|| BasePath::from_project("", "", &<Cli as SubstrateCli>::executable_name())))
     }
+
+    /// The source of the L1 gas price, defaulting to a fixed price when none is given.
+    pub fn l1_gas_price_source(&self) -> L1GasPriceSource {
+        if let Some(l1_gas_price) = self.l1_gas_price {
+            L1GasPriceSource::Fixed(l1_gas_price)
+        } else if let Some(path) = &self.l1_gas_price_file {
+            L1GasPriceSource::File(path.clone())
+        } else if let Some(url) = &self.l1_gas_price_rpc {
+            L1GasPriceSource::Ethereum(url.clone())
+        } else {
+            L1GasPriceSource::default()
+        }
+    }
 }
 
 pub fn run_node(mut cli: Cli) -> Result<()> {
@@ -89,7 +126,10 @@ pub fn run_node(mut cli: Cli) -> Result<()> {
     runner.run_node_until_exit(|config| async move {
         let sealing = cli.run.sealing.map(Into::into).unwrap_or_default();
         let cache = cli.run.cache;
-        service::new_full(config, sealing, da_config, cache).map_err(sc_cli::Error::Service)
+        let l1_gas_price_source = cli.run.l1_gas_price_source();
+        let storage_history_blocks = cli.run.storage_history_blocks;
+        service::new_full(config, sealing, da_config, cache, l1_gas_price_source, storage_history_blocks)
+            .map_err(sc_cli::Error::Service)
     })
 }
 
@@ -130,7 +130,7 @@ impl SetupCmd {
     }
 }
 
-fn write_content_to_disk<T: AsRef<[u8]>>(config_content: T, dest_config_file_path: &Path) -> Result<()> {
+pub(super) fn write_content_to_disk<T: AsRef<[u8]>>(config_content: T, dest_config_file_path: &Path) -> Result<()> {
     std::fs::create_dir_all(
         dest_config_file_path.parent().expect("dest_config_file_path should be the path to a file, not a dict"),
     )?;
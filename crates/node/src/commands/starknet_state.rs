@@ -0,0 +1,360 @@
+use std::path::PathBuf;
+
+use blockifier::execution::contract_class::ContractClass as StarknetContractClass;
+use madara_runtime::opaque::Block;
+use madara_runtime::Hash;
+use mp_felt::Felt252Wrapper;
+use mp_storage::{
+    StarknetStorageSchemaVersion, PALLET_STARKNET, PALLET_STARKNET_SCHEMA, STARKNET_COMPILED_CLASS_HASH,
+    STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH, STARKNET_FEE_TOKEN_ADDRESS, STARKNET_NONCE,
+    STARKNET_STORAGE, STARKNET_STRK_FEE_TOKEN_ADDRESS,
+};
+use pallet_starknet::genesis_loader::{ContractClass, GenesisData, HexFelt};
+use sc_cli::{
+    BlockNumberOrHash, CliConfiguration, DatabaseParams, Error, PruningParams, Result, SharedParams, SubstrateCli,
+};
+use sc_client_api::{HeaderBackend, StorageProvider};
+use sc_service::BasePath;
+use scale_codec::Decode;
+use sp_core::hashing::twox_128;
+use sp_core::storage::StorageKey;
+use sp_runtime::generic::BlockId;
+use starknet_api::api_core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey as StarknetStorageKey;
+
+use super::setup::write_content_to_disk;
+use crate::chain_spec::{GENESIS_ASSETS_DIR, GENESIS_ASSETS_FILE};
+use crate::cli::Cli;
+use crate::service::FullClient;
+
+const BLAKE2_128_LEN: usize = 16;
+
+/// Export the Starknet state of a given block as a genesis file.
+///
+/// The output follows the `GenesisData` format, so it can be loaded back with
+/// `import-starknet-state` to start a new chain from this state.
+#[derive(Debug, clap::Parser)]
+pub struct ExportStarknetStateCmd {
+    /// Block hash or number at which the state is exported. Defaults to the best block.
+    #[arg(value_name = "HASH or NUMBER")]
+    pub input: Option<BlockNumberOrHash>,
+
+    /// File to write the exported state to. The state is printed to stdout if not provided.
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub pruning_params: PruningParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl CliConfiguration for ExportStarknetStateCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn pruning_params(&self) -> Option<&PruningParams> {
+        Some(&self.pruning_params)
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+impl ExportStarknetStateCmd {
+    pub fn run(&self, client: &FullClient) -> Result<()> {
+        let block_hash = match &self.input {
+            Some(input) => match input.parse::<Block>().map_err(Error::Input)? {
+                BlockId::Hash(hash) => hash,
+                BlockId::Number(number) => {
+                    client.hash(number)?.ok_or_else(|| Error::Input(format!("Block number {number} not found")))?
+                }
+            },
+            None => client.info().best_hash,
+        };
+        log::info!("Exporting Starknet state at block {block_hash:?}");
+
+        let genesis_data = starknet_state(&BlockStorage { client, block_hash })?;
+        let content = serde_json::to_string_pretty(&genesis_data).map_err(|e| Error::Application(Box::new(e)))?;
+
+        match &self.output {
+            Some(path) => {
+                write_content_to_disk(content, path)?;
+                log::info!("Starknet state written to '{}'", path.display());
+            }
+            None => println!("{content}"),
+        }
+
+        Ok(())
+    }
+}
+
+/// Import a Starknet state, as exported by `export-starknet-state`, as the genesis of a chain.
+///
+/// The file is copied in the genesis assets of the chain config directory, where it will be
+/// picked up the next time the node builds the genesis block of this chain.
+#[derive(Debug, clap::Args)]
+pub struct ImportStarknetStateCmd {
+    /// Path to the exported Starknet state.
+    #[arg(value_name = "PATH")]
+    pub input: PathBuf,
+
+    #[arg(long, value_name = "CHAIN_SPEC")]
+    pub chain: Option<String>,
+
+    /// Specify custom base path.
+    #[arg(long, short = 'd', value_name = "PATH")]
+    pub base_path: Option<PathBuf>,
+
+    /// Overwrite the genesis file of the chain if there is already one.
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl ImportStarknetStateCmd {
+    pub fn run(&self) -> Result<()> {
+        let chain_id = self.chain.clone().unwrap_or_default();
+        let base_path = self
+            .base_path
+            .as_ref()
+            .map(|bp| BasePath::from(bp.clone()))
+            .unwrap_or_else(|| BasePath::from_project("", "", &Cli::executable_name()));
+        let dest_file_path = base_path.config_dir(&chain_id).join(GENESIS_ASSETS_DIR).join(GENESIS_ASSETS_FILE);
+
+        if dest_file_path.exists() && !self.force {
+            return Err(Error::Input(format!(
+                "'{}' already exists, use `--force` to overwrite it",
+                dest_file_path.display()
+            )));
+        }
+
+        let content = std::fs::read_to_string(&self.input).map_err(|e| Error::Application(Box::new(e)))?;
+        // Make sure content is valid before writing it to disk
+        let _: GenesisData = serde_json::from_str(&content)
+            .map_err(|e| Error::Input(format!("invalid Starknet state content: {}", e)))?;
+        write_content_to_disk(content, &dest_file_path)?;
+
+        log::info!(
+            "Starknet state imported at '{}'. Purge the chain database before restarting the node so that a new \
+             genesis block is built from it.",
+            dest_file_path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Read access to the storage of a block.
+trait StateStorage {
+    fn storage(&self, key: &StorageKey) -> Result<Option<Vec<u8>>>;
+
+    /// Returns all the key value pairs whose key starts with `prefix`.
+    fn storage_pairs(&self, prefix: &StorageKey) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+}
+
+/// The storage of a block of the client.
+struct BlockStorage<'a> {
+    client: &'a FullClient,
+    block_hash: Hash,
+}
+
+impl StateStorage for BlockStorage<'_> {
+    fn storage(&self, key: &StorageKey) -> Result<Option<Vec<u8>>> {
+        Ok(self.client.storage(self.block_hash, key)?.map(|data| data.0))
+    }
+
+    fn storage_pairs(&self, prefix: &StorageKey) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self
+            .client
+            .storage_pairs(self.block_hash, Some(prefix), None)?
+            .map(|(key, value)| (key.0, value.0))
+            .collect())
+    }
+}
+
+/// Read the whole Starknet state of the pallet storage.
+fn starknet_state(state: &impl StateStorage) -> Result<GenesisData> {
+    let schema_version = state
+        .storage(&StorageKey(PALLET_STARKNET_SCHEMA.to_vec()))?
+        .and_then(|data| StarknetStorageSchemaVersion::decode(&mut &data[..]).ok())
+        .unwrap_or_default();
+    // Length of the hash prefixing the encoded keys of the maps keyed by contract address
+    let contract_key_hash_len = match schema_version {
+        StarknetStorageSchemaVersion::V2 => BLAKE2_128_LEN,
+        _ => 0,
+    };
+
+    let contract_classes = storage_map::<ClassHash, StarknetContractClass>(state, STARKNET_CONTRACT_CLASS, 0)?
+        .into_iter()
+        .map(|(class_hash, class)| (felt_to_hex(class_hash), ContractClass::Class(class)))
+        .collect();
+
+    let contracts =
+        storage_map::<ContractAddress, ClassHash>(state, STARKNET_CONTRACT_CLASS_HASH, contract_key_hash_len)?
+            .into_iter()
+            .map(|(address, class_hash)| (felt_to_hex(address), felt_to_hex(class_hash)))
+            .collect();
+
+    let storage = storage_map::<(ContractAddress, StarknetStorageKey), StarkFelt>(
+        state,
+        STARKNET_STORAGE,
+        contract_key_hash_len,
+    )?
+    .into_iter()
+    .map(|((address, key), value)| ((felt_to_hex(address), felt_to_hex(key)), felt_to_hex(value)))
+    .collect();
+
+    let nonces = storage_map::<ContractAddress, Nonce>(state, STARKNET_NONCE, contract_key_hash_len)?
+        .into_iter()
+        .map(|(address, nonce)| (felt_to_hex(address), felt_to_hex(nonce)))
+        .collect();
+
+    let compiled_class_hashes = storage_map::<ClassHash, CompiledClassHash>(state, STARKNET_COMPILED_CLASS_HASH, 0)?
+        .into_iter()
+        .map(|(class_hash, compiled_class_hash)| (felt_to_hex(class_hash), felt_to_hex(compiled_class_hash)))
+        .collect();
+
+    let fee_token_address = state
+        .storage(&StorageKey(storage_prefix(STARKNET_FEE_TOKEN_ADDRESS)))?
+        .map(|data| ContractAddress::decode(&mut &data[..]))
+        .transpose()
+        .map_err(|e| Error::Input(format!("failed to decode the fee token address: {e}")))?
+        .unwrap_or_default();
+    let strk_fee_token_address = state
+        .storage(&StorageKey(storage_prefix(STARKNET_STRK_FEE_TOKEN_ADDRESS)))?
+        .map(|data| ContractAddress::decode(&mut &data[..]))
+        .transpose()
+        .map_err(|e| Error::Input(format!("failed to decode the STRK fee token address: {e}")))?;
+
+    Ok(GenesisData {
+        contract_classes,
+        contracts,
+        storage,
+        nonces,
+        compiled_class_hashes,
+        fee_token_address: felt_to_hex(fee_token_address),
+        strk_fee_token_address: strk_fee_token_address.map(felt_to_hex),
+        seq_addr_updated: true,
+    })
+}
+
+/// Returns all the entries of a `pallet-starknet` storage map using a concat hasher, such as
+/// `Identity` or `Blake2_128Concat`, whose hash is `key_hash_len` bytes long.
+fn storage_map<K: Decode, V: Decode>(
+    state: &impl StateStorage,
+    item: &[u8],
+    key_hash_len: usize,
+) -> Result<Vec<(K, V)>> {
+    let prefix = StorageKey(storage_prefix(item));
+
+    state
+        .storage_pairs(&prefix)?
+        .into_iter()
+        .map(|(key, value)| {
+            let item = String::from_utf8_lossy(item);
+            // With concat hashers, the encoded key directly follows its hash
+            let decoded_key = K::decode(&mut &key[prefix.0.len() + key_hash_len..])
+                .map_err(|e| Error::Input(format!("failed to decode a key of `{item}`: {e}")))?;
+            let decoded_value = V::decode(&mut &value[..])
+                .map_err(|e| Error::Input(format!("failed to decode a value of `{item}`: {e}")))?;
+            Ok((decoded_key, decoded_value))
+        })
+        .collect()
+}
+
+fn storage_prefix(item: &[u8]) -> Vec<u8> {
+    [twox_128(PALLET_STARKNET), twox_128(item)].concat()
+}
+
+fn felt_to_hex<F: Into<Felt252Wrapper>>(felt: F) -> HexFelt {
+    felt.into().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use madara_runtime::{AuraConfig, GenesisConfig, GrandpaConfig, Runtime, SudoConfig, SystemConfig};
+    use pallet_starknet::genesis_loader::GenesisLoader;
+    use sp_core::storage::Storage;
+    use sp_runtime::BuildStorage;
+
+    use super::*;
+
+    impl StateStorage for Storage {
+        fn storage(&self, key: &StorageKey) -> Result<Option<Vec<u8>>> {
+            Ok(self.top.get(&key.0).cloned())
+        }
+
+        fn storage_pairs(&self, prefix: &StorageKey) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            Ok(self
+                .top
+                .range(prefix.0.clone()..)
+                .take_while(|(key, _)| key.starts_with(&prefix.0))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect())
+        }
+    }
+
+    fn felt(value: &str) -> StarkFelt {
+        StarkFelt::try_from(value).unwrap()
+    }
+
+    fn genesis_storage(starknet: pallet_starknet::GenesisConfig<Runtime>) -> Storage {
+        GenesisConfig {
+            system: SystemConfig { code: vec![] },
+            aura: AuraConfig { authorities: vec![] },
+            grandpa: GrandpaConfig { authorities: vec![] },
+            starknet,
+            sudo: SudoConfig { key: None },
+        }
+        .build_storage()
+        .unwrap()
+    }
+
+    #[test]
+    fn exported_state_is_the_genesis_state() {
+        let class_hash = ClassHash(felt("0x1"));
+        let sierra_class_hash = ClassHash(felt("0x2"));
+        let compiled_class_hash = CompiledClassHash(felt("0x3"));
+        let address = ContractAddress(felt("0x4").try_into().unwrap());
+        let fee_token_address = ContractAddress(felt("0x5").try_into().unwrap());
+        let storage_key = StarknetStorageKey(felt("0x6").try_into().unwrap());
+        let contract_class = StarknetContractClass::V0(
+            serde_json::from_str(include_str!("../../../../cairo-contracts/build/ERC20.json")).unwrap(),
+        );
+
+        let exported = starknet_state(&genesis_storage(pallet_starknet::GenesisConfig {
+            contracts: vec![(address, class_hash)],
+            contract_classes: vec![(class_hash, contract_class.clone())],
+            storage: vec![((address, storage_key), felt("0x7"))],
+            nonces: vec![(address, Nonce(felt("0x8")))],
+            compiled_class_hashes: vec![(sierra_class_hash, compiled_class_hash)],
+            fee_token_address,
+            strk_fee_token_address: fee_token_address,
+            ..Default::default()
+        }))
+        .unwrap();
+
+        // The exported state is loaded back as the same genesis state
+        let imported: pallet_starknet::GenesisConfig<Runtime> = GenesisLoader::new(
+            PathBuf::new(),
+            serde_json::from_str(&serde_json::to_string(&exported).unwrap()).unwrap(),
+        )
+        .into();
+        assert_eq!(imported.contracts, vec![(address, class_hash)]);
+        assert_eq!(imported.contract_classes, vec![(class_hash, contract_class)]);
+        assert_eq!(imported.storage, vec![((address, storage_key), felt("0x7"))]);
+        assert_eq!(imported.nonces, vec![(address, Nonce(felt("0x8")))]);
+        assert_eq!(imported.compiled_class_hashes, vec![(sierra_class_hash, compiled_class_hash)]);
+        assert_eq!(imported.fee_token_address, fee_token_address);
+    }
+}
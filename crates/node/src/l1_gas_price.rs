@@ -0,0 +1,118 @@
+//! Sources of the L1 gas price supplied to the blocks authored by the node.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mp_l1_gas_price::{InherentDataProvider as L1GasPriceInherentDataProvider, InherentType, DEFAULT_L1_GAS_PRICE};
+use url::Url;
+
+/// Time after which a request to the Ethereum JSON-RPC is abandoned, so that a slow oracle does not
+/// delay the authoring of the block.
+const ETHEREUM_RPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Age, in seconds, of the parent of an imported block above which the L1 gas price of the block is
+/// not checked, as the current price says nothing of the one of a block authored in the past.
+const MAX_CHECKED_PARENT_AGE: u64 = 60;
+
+/// Where the L1 gas price, in wei, is read from.
+#[derive(Clone, Debug)]
+pub enum L1GasPriceSource {
+    /// A fixed price.
+    Fixed(InherentType),
+    /// A file containing the price, decimal or `0x`-prefixed hexadecimal. It is read again for
+    /// every block, so it can be updated while the node is running.
+    File(PathBuf),
+    /// The `eth_gasPrice` of an Ethereum JSON-RPC endpoint.
+    Ethereum(Url),
+}
+
+impl Default for L1GasPriceSource {
+    fn default() -> Self {
+        Self::Fixed(DEFAULT_L1_GAS_PRICE)
+    }
+}
+
+impl L1GasPriceSource {
+    /// Fetches the current L1 gas price.
+    pub async fn fetch(&self) -> Result<InherentType, String> {
+        match self {
+            Self::Fixed(l1_gas_price) => Ok(*l1_gas_price),
+            Self::File(path) => {
+                let content = std::fs::read_to_string(path)
+                    .map_err(|e| format!("failed to read L1 gas price file {}: {e}", path.display()))?;
+                parse_l1_gas_price(content.trim())
+            }
+            Self::Ethereum(url) => fetch_eth_gas_price(url).await,
+        }
+    }
+
+    /// Creates the inherent data provider of the L1 gas price.
+    ///
+    /// Failing to get a price does not prevent the authoring of the block: the inherent is
+    /// omitted and the block keeps the price of its parent.
+    pub async fn inherent_data_provider(&self) -> L1GasPriceInherentDataProvider {
+        match self.fetch().await {
+            Ok(0) => {
+                log::warn!("Ignoring L1 gas price of 0 wei");
+                L1GasPriceInherentDataProvider::default()
+            }
+            Ok(l1_gas_price) => L1GasPriceInherentDataProvider::new(Some(l1_gas_price)),
+            Err(e) => {
+                log::warn!("Keeping the previous L1 gas price: {e}");
+                L1GasPriceInherentDataProvider::default()
+            }
+        }
+    }
+
+    /// Creates the inherent data provider of the price the L1 gas price of an imported block is
+    /// checked against.
+    ///
+    /// `parent_timestamp` is the timestamp, in seconds, of the parent of the imported block. Only
+    /// the blocks following a recent parent are checked, and only against a price observed on L1:
+    /// a fixed price is the choice of the node operator.
+    pub async fn check_inherent_data_provider(&self, parent_timestamp: u64) -> L1GasPriceInherentDataProvider {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        if matches!(self, Self::Fixed(_)) || now.saturating_sub(parent_timestamp) > MAX_CHECKED_PARENT_AGE {
+            return L1GasPriceInherentDataProvider::default();
+        }
+
+        match self.fetch().await {
+            Ok(l1_gas_price) if l1_gas_price != 0 => L1GasPriceInherentDataProvider::new(Some(l1_gas_price)),
+            Ok(_) => L1GasPriceInherentDataProvider::default(),
+            Err(e) => {
+                log::warn!("Not checking the L1 gas price of the imported block: {e}");
+                L1GasPriceInherentDataProvider::default()
+            }
+        }
+    }
+}
+
+/// Parses a price given in decimal or in `0x`-prefixed hexadecimal.
+fn parse_l1_gas_price(value: &str) -> Result<InherentType, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => InherentType::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| format!("invalid L1 gas price {value:?}: {e}"))
+}
+
+async fn fetch_eth_gas_price(url: &Url) -> Result<InherentType, String> {
+    let body = serde_json::json!({ "jsonrpc": "2.0", "method": "eth_gasPrice", "params": [], "id": 0 });
+    let response = reqwest::Client::new()
+        .post(url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .timeout(ETHEREUM_RPC_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("eth_gasPrice request failed: {e}"))?
+        .text()
+        .await
+        .map_err(|e| format!("eth_gasPrice request failed: {e}"))?;
+
+    let response: serde_json::Value =
+        serde_json::from_str(&response).map_err(|e| format!("invalid eth_gasPrice response: {e}"))?;
+    let l1_gas_price =
+        response["result"].as_str().ok_or_else(|| format!("invalid eth_gasPrice response: {response}"))?;
+    parse_l1_gas_price(l1_gas_price)
+}
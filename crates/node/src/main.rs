@@ -11,6 +11,7 @@ mod commands;
 mod configs;
 mod constants;
 mod genesis_block;
+mod l1_gas_price;
 mod rpc;
 mod starknet;
 
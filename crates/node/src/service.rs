@@ -14,17 +14,25 @@ use madara_runtime::{self, Hash, RuntimeApi, SealingMode, StarknetHasher};
 use mc_block_proposer::ProposerFactory;
 use mc_data_availability::avail::config::AvailConfig;
 use mc_data_availability::avail::AvailClient;
+use mc_data_availability::batch::BatchConfig;
 use mc_data_availability::celestia::config::CelestiaConfig;
 use mc_data_availability::celestia::CelestiaClient;
 use mc_data_availability::ethereum::config::EthereumConfig;
 use mc_data_availability::ethereum::EthereumClient;
-use mc_data_availability::{DaClient, DaLayer, DataAvailabilityWorker};
-use mc_mapping_sync::MappingSyncWorker;
+use mc_data_availability::file::config::FileConfig;
+use mc_data_availability::file::FileClient;
+use mc_data_availability::prover::config::{ProverConfig, ProverKind};
+use mc_data_availability::prover::local::LocalProver;
+use mc_data_availability::prover::Prover;
+use mc_data_availability::volition::{Volition, VolitionConfig};
+use mc_data_availability::{DaClient, DaLayer, DaMode, DataAvailabilityWorker};
+use mc_mapping_sync::{sync_storage_history, MappingSyncWorker};
 use mc_storage::overrides_handle;
 use mc_transaction_pool::FullPool;
 use mp_sequencer_address::{
     InherentDataProvider as SeqAddrInherentDataProvider, DEFAULT_SEQUENCER_ADDRESS, SEQ_ADDR_STORAGE_KEY,
 };
+use pallet_starknet::runtime_api::StarknetRuntimeApi;
 use prometheus_endpoint::Registry;
 use sc_client_api::{Backend, BlockBackend, BlockchainEvents, HeaderBackend};
 use sc_consensus::BasicQueue;
@@ -35,13 +43,14 @@ use sc_service::error::Error as ServiceError;
 use sc_service::{new_db_backend, Configuration, TaskManager, WarpSyncParams};
 use sc_telemetry::{Telemetry, TelemetryHandle, TelemetryWorker};
 use sp_api::offchain::OffchainStorage;
-use sp_api::{ConstructRuntimeApi, TransactionFor};
+use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi, TransactionFor};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use sp_offchain::STORAGE_PREFIX;
 use sp_runtime::traits::BlakeTwo256;
 use sp_trie::PrefixedMemoryDB;
 
 use crate::genesis_block::MadaraGenesisBlockBuilder;
+use crate::l1_gas_price::L1GasPriceSource;
 use crate::rpc::StarknetDeps;
 use crate::starknet::{db_config_dir, MadaraBackend};
 // Our native executor instance.
@@ -76,6 +85,7 @@ pub fn new_partial<BIQ>(
     config: &Configuration,
     build_import_queue: BIQ,
     cache_more_things: bool,
+    l1_gas_price_source: Option<L1GasPriceSource>,
 ) -> Result<
     sc_service::PartialComponents<
         FullClient,
@@ -102,6 +112,7 @@ where
         Option<TelemetryHandle>,
         GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
         Arc<MadaraBackend>,
+        Option<L1GasPriceSource>,
     ) -> Result<(BasicImportQueue<FullClient>, BoxBlockImport<FullClient>), ServiceError>,
 {
     let telemetry = config
@@ -173,6 +184,7 @@ where
         telemetry.as_ref().map(|x| x.handle()),
         grandpa_block_import,
         madara_backend.clone(),
+        l1_gas_price_source,
     )?;
 
     Ok(sc_service::PartialComponents {
@@ -188,6 +200,9 @@ where
 }
 
 /// Build the import queue for the template runtime (aura + grandpa).
+///
+/// The L1 gas price of the imported blocks is checked against the one of `l1_gas_price_source`,
+/// if any.
 pub fn build_aura_grandpa_import_queue(
     client: Arc<FullClient>,
     config: &Configuration,
@@ -195,20 +210,35 @@ pub fn build_aura_grandpa_import_queue(
     telemetry: Option<TelemetryHandle>,
     grandpa_block_import: GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
     _madara_backend: Arc<MadaraBackend>,
+    l1_gas_price_source: Option<L1GasPriceSource>,
 ) -> Result<(BasicImportQueue<FullClient>, BoxBlockImport<FullClient>), ServiceError>
 where
     RuntimeApi: ConstructRuntimeApi<Block, FullClient>,
     RuntimeApi: Send + Sync + 'static,
 {
     let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
-
-    let create_inherent_data_providers = move |_, ()| async move {
-        let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
-        let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-            *timestamp,
-            slot_duration,
-        );
-        Ok((slot, timestamp))
+    let header_client = client.clone();
+
+    let create_inherent_data_providers = move |parent, ()| {
+        let l1_gas_price_source = l1_gas_price_source.clone();
+        let parent_timestamp = header_client
+            .header(parent)
+            .ok()
+            .flatten()
+            .and_then(|header| mp_digest_log::find_starknet_block(header.digest()).ok())
+            .map(|block| block.header().block_timestamp);
+        async move {
+            let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+            let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+                *timestamp,
+                slot_duration,
+            );
+            let l1_gas_price = match (l1_gas_price_source, parent_timestamp) {
+                (Some(source), Some(parent_timestamp)) => source.check_inherent_data_provider(parent_timestamp).await,
+                _ => Default::default(),
+            };
+            Ok((slot, timestamp, l1_gas_price))
+        }
     };
 
     let import_queue =
@@ -236,6 +266,7 @@ pub fn build_manual_seal_import_queue(
     _telemetry: Option<TelemetryHandle>,
     _grandpa_block_import: GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
     _madara_backend: Arc<MadaraBackend>,
+    _l1_gas_price_source: Option<L1GasPriceSource>,
 ) -> Result<(BasicImportQueue<FullClient>, BoxBlockImport<FullClient>), ServiceError>
 where
     RuntimeApi: ConstructRuntimeApi<Block, FullClient>,
@@ -256,11 +287,15 @@ where
 /// # Arguments
 ///
 /// - `cache`: whether more information should be cached when storing the block in the database.
+/// - `l1_gas_price_source`: where the L1 gas price of the authored blocks is read from, and the one
+///   of the imported blocks checked against.
 pub fn new_full(
     config: Configuration,
     sealing: SealingMode,
     da_layer: Option<(DaLayer, PathBuf)>,
     cache_more_things: bool,
+    l1_gas_price_source: L1GasPriceSource,
+    storage_history_blocks: Option<u64>,
 ) -> Result<TaskManager, ServiceError> {
     let build_import_queue =
         if sealing.is_default() { build_aura_grandpa_import_queue } else { build_manual_seal_import_queue };
@@ -274,7 +309,7 @@ pub fn new_full(
         select_chain,
         transaction_pool,
         other: (block_import, grandpa_link, mut telemetry, madara_backend),
-    } = new_partial(&config, build_import_queue, cache_more_things)?;
+    } = new_partial(&config, build_import_queue, cache_more_things, Some(l1_gas_price_source.clone()))?;
 
     let mut net_config = sc_network::config::FullNetworkConfiguration::new(&config.network);
 
@@ -329,7 +364,7 @@ pub fn new_full(
         _ => (None, None),
     };
 
-    let overrides = overrides_handle(client.clone());
+    let overrides = overrides_handle(client.clone(), madara_backend.clone());
     let starknet_rpc_params = StarknetDeps {
         client: client.clone(),
         madara_backend: madara_backend.clone(),
@@ -386,6 +421,12 @@ pub fn new_full(
         .for_each(|()| future::ready(())),
     );
 
+    task_manager.spawn_handle().spawn(
+        "mc-storage-history-worker",
+        Some("madara"),
+        sync_storage_history(client.clone(), madara_backend.clone(), storage_history_blocks),
+    );
+
     // initialize data availability worker
     if let Some((da_layer, da_path)) = da_layer {
         let da_client: Box<dyn DaClient + Send + Sync> = match da_layer {
@@ -401,17 +442,46 @@ pub fn new_full(
                 let avail_conf = AvailConfig::try_from(&da_path)?;
                 Box::new(AvailClient::try_from(avail_conf).map_err(|e| ServiceError::Other(e.to_string()))?)
             }
+            DaLayer::File => {
+                let file_conf = FileConfig::try_from(&da_path)?;
+                Box::new(FileClient::try_from(file_conf).map_err(|e| ServiceError::Other(e.to_string()))?)
+            }
+        };
+
+        let batch_conf = BatchConfig::try_from(&da_path)?;
+        // In Volition mode, the selected state goes on-chain to Ethereum, the rest to the DA layer
+        let volition = if da_client.get_mode() == DaMode::Volition {
+            let volition_conf = VolitionConfig::try_from(&da_path)?;
+            let client = Box::new(EthereumClient::try_from(volition_conf.ethereum.clone())?);
+            Some(Volition { client, config: volition_conf })
+        } else {
+            None
         };
 
         task_manager.spawn_essential_handle().spawn(
             "da-worker-prove",
             Some("madara"),
-            DataAvailabilityWorker::prove_current_block(da_client.get_mode(), client.clone(), madara_backend.clone()),
+            DataAvailabilityWorker::prove_current_block(client.clone(), madara_backend.clone()),
         );
+        if da_client.get_mode() == DaMode::Validity {
+            let prover_conf = ProverConfig::try_from(&da_path)?;
+            let prover: Box<dyn Prover> = match prover_conf.prover {
+                ProverKind::Local => Box::new(LocalProver::new(Duration::from_secs(prover_conf.local_proving_time))),
+                ProverKind::Sharp => {
+                    return Err(ServiceError::Other("the sharp prover isn't supported yet".to_string()));
+                }
+            };
+
+            task_manager.spawn_essential_handle().spawn(
+                "da-worker-validity",
+                Some("madara"),
+                DataAvailabilityWorker::prove_state(prover, client.clone(), madara_backend.clone(), prover_conf),
+            );
+        }
         task_manager.spawn_essential_handle().spawn(
             "da-worker-update",
             Some("madara"),
-            DataAvailabilityWorker::update_state(da_client, client.clone(), madara_backend),
+            DataAvailabilityWorker::update_state(da_client, volition, client.clone(), madara_backend, batch_conf),
         );
     };
 
@@ -429,6 +499,7 @@ pub fn new_full(
                 &task_manager,
                 prometheus_registry.as_ref(),
                 commands_stream,
+                l1_gas_price_source,
             )?;
 
             network_starter.start_network();
@@ -444,6 +515,7 @@ pub fn new_full(
         );
 
         let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+        let schedule_client = client.clone();
 
         let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(StartAuraParams {
             slot_duration,
@@ -451,8 +523,10 @@ pub fn new_full(
             select_chain,
             block_import,
             proposer_factory,
-            create_inherent_data_providers: move |_, ()| {
+            create_inherent_data_providers: move |parent, ()| {
                 let offchain_storage = backend.offchain_storage();
+                let schedule_client = schedule_client.clone();
+                let l1_gas_price_source = l1_gas_price_source.clone();
                 async move {
                     let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
 
@@ -465,7 +539,18 @@ pub fn new_full(
                     let prefix = &STORAGE_PREFIX;
                     let key = SEQ_ADDR_STORAGE_KEY;
 
-                    let sequencer_address = if let Some(storage) = ocw_storage {
+                    // The sequencer elected on-chain, if any, takes precedence over the local one. The
+                    // slot is skipped when the schedule cannot be read, as the runtime would reject a
+                    // block authored with another sequencer.
+                    let slot_number = u64::from(*slot);
+                    let elected_sequencer = schedule_client
+                        .runtime_api()
+                        .sequencer_for_slot(parent, slot_number)
+                        .map_err(|e| format!("Failed to read the sequencer elected for slot {slot_number}: {e}"))?;
+
+                    let sequencer_address = if let Some(address) = elected_sequencer {
+                        SeqAddrInherentDataProvider::new(address.0.0.0)
+                    } else if let Some(storage) = ocw_storage {
                         SeqAddrInherentDataProvider::try_from(
                             storage.get(prefix, key).unwrap_or(DEFAULT_SEQUENCER_ADDRESS.to_vec()),
                         )
@@ -474,7 +559,14 @@ pub fn new_full(
                         SeqAddrInherentDataProvider::default()
                     };
 
-                    Ok((slot, timestamp, sequencer_address))
+                    let l1_gas_price = l1_gas_price_source.inherent_data_provider().await;
+
+                    Ok::<_, Box<dyn std::error::Error + Send + Sync>>((
+                        slot,
+                        timestamp,
+                        sequencer_address,
+                        l1_gas_price,
+                    ))
                 }
             },
             force_authoring,
@@ -550,6 +642,7 @@ fn run_manual_seal_authorship(
     task_manager: &TaskManager,
     prometheus_registry: Option<&Registry>,
     commands_stream: Option<mpsc::Receiver<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>>,
+    l1_gas_price_source: L1GasPriceSource,
 ) -> Result<(), ServiceError>
 where
     RuntimeApi: ConstructRuntimeApi<Block, FullClient>,
@@ -590,9 +683,13 @@ where
         }
     }
 
-    let create_inherent_data_providers = move |_, ()| async move {
-        let timestamp = MockTimestampInherentDataProvider;
-        Ok(timestamp)
+    let create_inherent_data_providers = move |_, ()| {
+        let l1_gas_price_source = l1_gas_price_source.clone();
+        async move {
+            let timestamp = MockTimestampInherentDataProvider;
+            let l1_gas_price = l1_gas_price_source.inherent_data_provider().await;
+            Ok((timestamp, l1_gas_price))
+        }
     };
 
     let manual_seal: BoxFuture<_> = match sealing {
@@ -646,6 +743,6 @@ type ChainOpsResult = Result<
 pub fn new_chain_ops(config: &mut Configuration, cache_more_things: bool) -> ChainOpsResult {
     config.keystore = sc_service::config::KeystoreConfig::InMemory;
     let sc_service::PartialComponents { client, backend, import_queue, task_manager, other, .. } =
-        new_partial::<_>(config, build_aura_grandpa_import_queue, cache_more_things)?;
+        new_partial::<_>(config, build_aura_grandpa_import_queue, cache_more_things, None)?;
     Ok((client, backend, import_queue, task_manager, other.3))
 }
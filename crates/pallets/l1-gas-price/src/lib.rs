@@ -0,0 +1,129 @@
+//! A Substrate pallet keeping track of the price of the gas on L1.
+//!
+//! The block author supplies the current L1 gas price through the `l1_gas_price` inherent, using
+//! the oracle its node is configured with. The Starknet pallet reads it through the [Get]
+//! implementation of the pallet to compute the fees of the transactions, which pay for the L1
+//! costs of the chain.
+//!
+//! The inherent is optional: a block authored without it keeps the price of its parent. The nodes
+//! importing the block check that its price is close to the one given by their own oracle.
+// Ensure we're `no_std` when compiling for Wasm.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+use frame_support::traits::Get;
+
+#[frame_support::pallet]
+pub mod pallet {
+    use frame_support::pallet_prelude::*;
+    use frame_system::pallet_prelude::*;
+    use mp_l1_gas_price::{
+        is_within_tolerance, InherentError, InherentType, DEFAULT_L1_GAS_PRICE, INHERENT_IDENTIFIER,
+    };
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    /// Configure the pallet by specifying the parameters and types on which it depends.
+    #[pallet::config]
+    pub trait Config: frame_system::Config {}
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// The block is being finalized.
+        fn on_finalize(_n: T::BlockNumber) {
+            // The next block may update the price again.
+            L1GasPriceUpdate::<T>::kill();
+        }
+    }
+
+    #[pallet::type_value]
+    pub fn DefaultL1GasPrice() -> u128 {
+        DEFAULT_L1_GAS_PRICE
+    }
+
+    /// The L1 gas price, in wei, of the current block.
+    #[pallet::storage]
+    #[pallet::getter(fn l1_gas_price)]
+    pub type L1GasPrice<T: Config> = StorageValue<_, u128, ValueQuery, DefaultL1GasPrice>;
+
+    /// Ensure the L1 gas price is updated at most once in the block.
+    #[pallet::storage]
+    #[pallet::getter(fn l1_gas_price_update)]
+    pub type L1GasPriceUpdate<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    #[pallet::error]
+    pub enum Error<T> {
+        L1GasPriceAlreadyUpdated,
+        ZeroL1GasPrice,
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Set the L1 gas price, in wei, of the current block.
+        ///
+        /// This call can be invoked at most once per block. If it isn't, the block keeps the price
+        /// of its parent.
+        ///
+        /// The dispatch origin for this call must be `Inherent`.
+        #[pallet::call_index(0)]
+        #[pallet::weight((0, DispatchClass::Mandatory))]
+        pub fn set_l1_gas_price(origin: OriginFor<T>, l1_gas_price: u128) -> DispatchResult {
+            ensure_none(origin)?;
+            ensure!(!L1GasPriceUpdate::<T>::get(), Error::<T>::L1GasPriceAlreadyUpdated);
+            ensure!(l1_gas_price != 0, Error::<T>::ZeroL1GasPrice);
+
+            L1GasPrice::<T>::put(l1_gas_price);
+            L1GasPriceUpdate::<T>::put(true);
+            Ok(())
+        }
+    }
+
+    #[pallet::inherent]
+    impl<T: Config> ProvideInherent for Pallet<T> {
+        type Call = Call<T>;
+        type Error = InherentError;
+        const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+        fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+            let l1_gas_price = data
+                .get_data::<InherentType>(&INHERENT_IDENTIFIER)
+                .expect("L1 gas price inherent data not correctly encoded")?;
+            Some(Call::set_l1_gas_price { l1_gas_price })
+        }
+
+        fn check_inherent(call: &Self::Call, data: &InherentData) -> Result<(), Self::Error> {
+            let Call::set_l1_gas_price { l1_gas_price } = call else {
+                return Ok(());
+            };
+            if *l1_gas_price == 0 {
+                return Err(InherentError::ZeroL1GasPrice);
+            }
+
+            // The importing node provides the price of its own oracle, if it has one.
+            match data.get_data::<InherentType>(&INHERENT_IDENTIFIER).ok().flatten() {
+                Some(reference_price) if !is_within_tolerance(*l1_gas_price, reference_price) => {
+                    Err(InherentError::L1GasPriceOutOfTolerance)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        fn is_inherent(call: &Self::Call) -> bool {
+            matches!(call, Call::set_l1_gas_price { .. })
+        }
+    }
+}
+
+/// Gives the L1 gas price of the current block to the pallets using it, e.g. the Starknet pallet.
+impl<T: Config> Get<u128> for Pallet<T> {
+    fn get() -> u128 {
+        L1GasPrice::<T>::get()
+    }
+}
@@ -0,0 +1,80 @@
+use frame_support::inherent::{InherentData, ProvideInherent};
+use frame_support::traits::{Get, Hooks};
+use frame_support::{assert_err, assert_ok};
+use mp_l1_gas_price::{InherentError, DEFAULT_L1_GAS_PRICE, INHERENT_IDENTIFIER};
+
+use crate::mock::*;
+use crate::{Call, Error};
+
+#[test]
+fn l1_gas_price_defaults_until_set() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(L1GasPrice::l1_gas_price(), DEFAULT_L1_GAS_PRICE);
+        assert_eq!(<L1GasPrice as Get<u128>>::get(), DEFAULT_L1_GAS_PRICE);
+    });
+}
+
+#[test]
+fn l1_gas_price_is_set_once_per_block_and_kept_afterwards() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(L1GasPrice::set_l1_gas_price(RuntimeOrigin::none(), 30_000_000_000));
+        assert_eq!(L1GasPrice::l1_gas_price(), 30_000_000_000);
+        assert_err!(
+            L1GasPrice::set_l1_gas_price(RuntimeOrigin::none(), 40_000_000_000),
+            Error::<MockRuntime>::L1GasPriceAlreadyUpdated
+        );
+
+        // The next block does not provide a price, and keeps the one of its parent
+        L1GasPrice::on_finalize(1);
+        System::set_block_number(2);
+        assert_eq!(L1GasPrice::l1_gas_price(), 30_000_000_000);
+
+        assert_ok!(L1GasPrice::set_l1_gas_price(RuntimeOrigin::none(), 40_000_000_000));
+        assert_eq!(L1GasPrice::l1_gas_price(), 40_000_000_000);
+    });
+}
+
+#[test]
+fn l1_gas_price_must_be_set_by_an_inherent_with_a_non_zero_price() {
+    new_test_ext().execute_with(|| {
+        assert_err!(
+            L1GasPrice::set_l1_gas_price(RuntimeOrigin::signed(1), 30_000_000_000),
+            sp_runtime::traits::BadOrigin
+        );
+        assert_err!(L1GasPrice::set_l1_gas_price(RuntimeOrigin::none(), 0), Error::<MockRuntime>::ZeroL1GasPrice);
+        assert_eq!(L1GasPrice::l1_gas_price(), DEFAULT_L1_GAS_PRICE);
+    });
+}
+
+#[test]
+fn inherent_is_only_created_when_a_price_is_provided() {
+    let mut data = InherentData::new();
+    assert_eq!(L1GasPrice::create_inherent(&data), None);
+
+    data.put_data(INHERENT_IDENTIFIER, &30_000_000_000u128).unwrap();
+    let call = L1GasPrice::create_inherent(&data).unwrap();
+    assert_eq!(call, Call::set_l1_gas_price { l1_gas_price: 30_000_000_000 });
+    assert!(L1GasPrice::is_inherent(&call));
+    assert_ok!(L1GasPrice::check_inherent(&call, &data));
+
+    assert!(matches!(
+        L1GasPrice::check_inherent(&Call::set_l1_gas_price { l1_gas_price: 0 }, &data),
+        Err(InherentError::ZeroL1GasPrice)
+    ));
+}
+
+#[test]
+fn inherent_price_is_checked_against_the_price_of_the_node() {
+    let call = Call::set_l1_gas_price { l1_gas_price: 30_000_000_000 };
+
+    // Without a price, the node does not check the one of the block
+    assert_ok!(L1GasPrice::check_inherent(&call, &InherentData::new()));
+
+    let mut data = InherentData::new();
+    data.put_data(INHERENT_IDENTIFIER, &25_000_000_000u128).unwrap();
+    assert_ok!(L1GasPrice::check_inherent(&call, &data));
+
+    let mut data = InherentData::new();
+    data.put_data(INHERENT_IDENTIFIER, &20_000_000_000u128).unwrap();
+    assert!(matches!(L1GasPrice::check_inherent(&call, &data), Err(InherentError::L1GasPriceOutOfTolerance)));
+}
@@ -8,13 +8,14 @@ use blockifier::state::state_api::{State, StateReader, StateResult};
 use indexmap::IndexMap;
 use mp_felt::Felt252Wrapper;
 use mp_state::{FeeConfig, StateChanges};
+use mp_storage::{STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE};
 use sp_core::Get;
 use starknet_api::api_core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
 use starknet_crypto::FieldElement;
 
-use crate::{Config, Pallet};
+use crate::{migration, Config, Pallet};
 
 /// Empty struct that implements the traits needed by the blockifier/starknet in rust.
 ///
@@ -44,7 +45,11 @@ where
     T: Config,
 {
     fn is_transaction_fee_disabled(&self) -> bool {
-        T::DisableTransactionFee::get()
+        Pallet::<T>::protocol_parameters().disable_transaction_fee
+    }
+
+    fn strk_fee_token_address(&self) -> ContractAddress {
+        Pallet::<T>::strk_fee_token_address()
     }
 }
 
@@ -74,7 +79,12 @@ impl<T: Config> StateReader for BlockifierStateAdapter<T> {
     }
 
     fn get_compiled_contract_class(&mut self, class_hash: &ClassHash) -> StateResult<ContractClass> {
-        Pallet::<T>::contract_class_by_class_hash(class_hash).ok_or(StateError::UndeclaredClassHash(*class_hash))
+        #[cfg(feature = "std")]
+        let contract_class = crate::contract_class_cache::get_contract_class::<T>(class_hash);
+        #[cfg(not(feature = "std"))]
+        let contract_class = Pallet::<T>::contract_class_by_class_hash(class_hash);
+
+        contract_class.ok_or(StateError::UndeclaredClassHash(*class_hash))
     }
 
     fn get_compiled_class_hash(&mut self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
@@ -89,6 +99,8 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         self.storage_update.insert(contract_storage_key, value);
 
         crate::StorageView::<T>::insert(contract_storage_key, value);
+        migration::remove_schema_v1_entry::<T, _>(STARKNET_STORAGE, &contract_storage_key);
+        crate::PendingStorageChanges::<T>::append(contract_address, (key, Felt252Wrapper::from(value)));
     }
 
     fn increment_nonce(&mut self, contract_address: ContractAddress) -> StateResult<()> {
@@ -97,6 +109,8 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         let new_nonce: Nonce = Felt252Wrapper(current_nonce + FieldElement::ONE).into();
 
         crate::Nonces::<T>::insert(contract_address, new_nonce);
+        migration::remove_schema_v1_entry::<T, _>(STARKNET_NONCE, &contract_address);
+        Pallet::<T>::touch_contract(contract_address);
 
         Ok(())
     }
@@ -105,12 +119,14 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         self.class_hash_update += 1;
 
         crate::ContractClassHashes::<T>::insert(contract_address, class_hash);
+        migration::remove_schema_v1_entry::<T, _>(STARKNET_CONTRACT_CLASS_HASH, &contract_address);
+        Pallet::<T>::touch_contract(contract_address);
 
         Ok(())
     }
 
     fn set_contract_class(&mut self, class_hash: &ClassHash, contract_class: ContractClass) -> StateResult<()> {
-        crate::ContractClasses::<T>::insert(class_hash, contract_class);
+        Pallet::<T>::store_contract_class(class_hash, &contract_class);
 
         Ok(())
     }
@@ -122,6 +138,7 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
     ) -> StateResult<()> {
         self.compiled_class_hash_update += 1;
         crate::CompiledClassHashes::<T>::insert(class_hash, compiled_class_hash);
+        crate::PendingClassChanges::<T>::append((class_hash, compiled_class_hash));
 
         Ok(())
     }
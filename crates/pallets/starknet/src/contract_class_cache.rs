@@ -0,0 +1,140 @@
+//! A cache of the decoded contract classes, shared by the executions of the native runtime.
+//!
+//! Decoding a contract class from storage is a large part of the cost of a transaction, and the
+//! same few classes (accounts, ERC20) are used by most of them. The native runtime keeps the
+//! decoded classes in this cache across transactions and blocks. The wasm runtime does not: its
+//! memory doesn't outlive a runtime call, and a host function could not hand a decoded class to it
+//! without encoding it again.
+//!
+//! A cached class is only returned if the encoded class in storage is still the one it was decoded
+//! from, as told by the fingerprint stored along with the class, so that a cache hit reads 32 bytes
+//! of storage instead of the whole class. Classes declared again, on the current chain or on
+//! another fork, are therefore never served stale. Classes declared before the fingerprints were
+//! introduced are read and hashed instead. A runtime upgrade moves the execution to the new wasm
+//! runtime, and a new node binary starts with an empty cache.
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use blockifier::execution::contract_class::ContractClass;
+use parity_scale_codec::Decode;
+use starknet_api::api_core::ClassHash;
+
+use crate::{Config, ContractClassFingerprints, ContractClasses, LOG_TARGET};
+
+/// The maximum number of decoded classes kept in the cache.
+const CONTRACT_CLASS_CACHE_SIZE: usize = 128;
+
+struct CachedContractClass {
+    /// Hash of the encoded class in storage.
+    encoded_hash: [u8; 32],
+    contract_class: ContractClass,
+    last_used: u64,
+}
+
+/// A least recently used cache of the decoded contract classes, keyed by class hash.
+#[derive(Default)]
+struct ContractClassCache {
+    entries: BTreeMap<ClassHash, CachedContractClass>,
+    uses: u64,
+}
+
+impl ContractClassCache {
+    fn get(&mut self, class_hash: &ClassHash, encoded_hash: &[u8; 32]) -> Option<ContractClass> {
+        self.uses += 1;
+        let entry = self.entries.get_mut(class_hash).filter(|entry| &entry.encoded_hash == encoded_hash)?;
+        entry.last_used = self.uses;
+        Some(entry.contract_class.clone())
+    }
+
+    fn insert(&mut self, class_hash: ClassHash, encoded_hash: [u8; 32], contract_class: ContractClass) {
+        if self.entries.len() >= CONTRACT_CLASS_CACHE_SIZE && !self.entries.contains_key(&class_hash) {
+            let least_recently_used =
+                self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(class_hash, _)| *class_hash);
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+            }
+        }
+        self.entries.insert(class_hash, CachedContractClass { encoded_hash, contract_class, last_used: self.uses });
+    }
+}
+
+fn cache() -> &'static Mutex<ContractClassCache> {
+    static CACHE: OnceLock<Mutex<ContractClassCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Returns the contract class declared for the given class hash, decoding it only if it isn't
+/// cached yet.
+pub fn get_contract_class<T: Config>(class_hash: &ClassHash) -> Option<ContractClass> {
+    let fingerprint = ContractClassFingerprints::<T>::get(class_hash);
+    if let Some(contract_class) = fingerprint.and_then(|fingerprint| cached_contract_class(class_hash, &fingerprint)) {
+        return Some(contract_class);
+    }
+
+    let encoded = sp_io::storage::get(&ContractClasses::<T>::hashed_key_for(class_hash))?;
+    let fingerprint = match fingerprint {
+        Some(fingerprint) => fingerprint,
+        // Declared before the fingerprints were introduced
+        None => {
+            let fingerprint = sp_io::hashing::blake2_256(&encoded[..]);
+            if let Some(contract_class) = cached_contract_class(class_hash, &fingerprint) {
+                return Some(contract_class);
+            }
+            fingerprint
+        }
+    };
+
+    let contract_class = ContractClass::decode(&mut &encoded[..])
+        .map_err(|e| log::error!(target: LOG_TARGET, "Failed to decode contract class {class_hash:?}: {e}"))
+        .ok()?;
+    cache().lock().unwrap_or_else(PoisonError::into_inner).insert(*class_hash, fingerprint, contract_class.clone());
+
+    Some(contract_class)
+}
+
+fn cached_contract_class(class_hash: &ClassHash, encoded_hash: &[u8; 32]) -> Option<ContractClass> {
+    cache().lock().unwrap_or_else(PoisonError::into_inner).get(class_hash, encoded_hash)
+}
+
+/// Removes the class of the given class hash from the cache, e.g. when it is declared again.
+pub fn invalidate(class_hash: &ClassHash) {
+    cache().lock().unwrap_or_else(PoisonError::into_inner).entries.remove(class_hash);
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_api::hash::StarkFelt;
+
+    use super::*;
+
+    fn class_hash(n: u64) -> ClassHash {
+        ClassHash(StarkFelt::from(n as u128))
+    }
+
+    #[test]
+    fn least_recently_used_class_is_evicted() {
+        let mut cache = ContractClassCache::default();
+        let contract_class = ContractClass::V0(Default::default());
+        for n in 0..CONTRACT_CLASS_CACHE_SIZE as u64 {
+            cache.insert(class_hash(n), [0; 32], contract_class.clone());
+        }
+        // The first class is used again, the second one becomes the least recently used
+        assert!(cache.get(&class_hash(0), &[0; 32]).is_some());
+
+        cache.insert(class_hash(CONTRACT_CLASS_CACHE_SIZE as u64), [0; 32], contract_class);
+
+        assert_eq!(cache.entries.len(), CONTRACT_CLASS_CACHE_SIZE);
+        assert!(cache.get(&class_hash(0), &[0; 32]).is_some());
+        assert!(cache.get(&class_hash(1), &[0; 32]).is_none());
+        assert!(cache.get(&class_hash(2), &[0; 32]).is_some());
+        assert!(cache.get(&class_hash(CONTRACT_CLASS_CACHE_SIZE as u64), &[0; 32]).is_some());
+    }
+
+    #[test]
+    fn class_with_another_encoding_is_not_returned() {
+        let mut cache = ContractClassCache::default();
+        cache.insert(class_hash(0), [0; 32], ContractClass::V0(Default::default()));
+
+        assert!(cache.get(&class_hash(0), &[1; 32]).is_none());
+    }
+}
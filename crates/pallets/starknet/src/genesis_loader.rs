@@ -37,13 +37,24 @@ type ContractAddress = HexFelt;
 type StorageKey = HexFelt;
 type ContractStorageKey = (ContractAddress, StorageKey);
 type StorageValue = HexFelt;
+type Nonce = HexFelt;
+type CompiledClassHash = HexFelt;
 
 #[derive(Deserialize, Serialize)]
 pub struct GenesisData {
     pub contract_classes: Vec<(ClassHash, ContractClass)>,
     pub contracts: Vec<(ContractAddress, ClassHash)>,
     pub storage: Vec<(ContractStorageKey, StorageValue)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nonces: Vec<(ContractAddress, Nonce)>,
+    /// The compiled class hashes of the Cairo 1 classes, keyed by their Sierra class hash.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compiled_class_hashes: Vec<(ClassHash, CompiledClassHash)>,
     pub fee_token_address: ContractAddress,
+    /// The address of the token in which the v3 transactions pay their fee. Defaults to the fee
+    /// token address, for chains with a single fee token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strk_fee_token_address: Option<ContractAddress>,
     pub seq_addr_updated: bool,
 }
 
@@ -109,13 +120,33 @@ impl<T: crate::Config> From<GenesisLoader> for GenesisConfig<T> {
                 (key, value)
             })
             .collect::<Vec<_>>();
+        let nonces = loader
+            .data
+            .nonces
+            .into_iter()
+            .map(|(address, nonce)| (Felt252Wrapper(address.0).into(), Felt252Wrapper(nonce.0).into()))
+            .collect::<Vec<_>>();
+        let compiled_class_hashes = loader
+            .data
+            .compiled_class_hashes
+            .into_iter()
+            .map(|(hash, compiled_hash)| (Felt252Wrapper(hash.0).into(), Felt252Wrapper(compiled_hash.0).into()))
+            .collect::<Vec<_>>();
         let fee_token_address = Felt252Wrapper(loader.data.fee_token_address.0).into();
+        let strk_fee_token_address = loader
+            .data
+            .strk_fee_token_address
+            .map(|address| Felt252Wrapper(address.0).into())
+            .unwrap_or(fee_token_address);
 
         GenesisConfig {
             contracts,
             contract_classes,
             storage,
+            nonces,
+            compiled_class_hashes,
             fee_token_address,
+            strk_fee_token_address,
             seq_addr_updated: loader.data.seq_addr_updated,
             ..Default::default()
         }
@@ -180,7 +211,10 @@ mod tests {
             contract_classes: vec![(class_hash, class)],
             contracts: vec![(contract_address, class_hash)],
             storage: vec![((contract_address, storage_key), storage_value)],
+            nonces: vec![],
+            compiled_class_hashes: vec![],
             fee_token_address,
+            strk_fee_token_address: None,
             seq_addr_updated: false,
         };
 
@@ -191,4 +225,33 @@ mod tests {
         let expected = r#"{"contract_classes":[["0x1",{"path":"cairo-contracts/ERC20.json","version":0}]],"contracts":[["0x2","0x1"]],"storage":[[["0x2","0x3"],"0x4"]],"fee_token_address":"0x5","seq_addr_updated":false}"#;
         assert_eq!(expected, serialized_loader);
     }
+
+    #[test]
+    fn test_serialize_loader_with_nonces() {
+        // Given
+        let contract_address = FieldElement::from(2u8).into();
+        let nonce = FieldElement::from(7u8).into();
+        let fee_token_address = FieldElement::from(5u8).into();
+
+        let genesis_loader = GenesisData {
+            contract_classes: vec![],
+            contracts: vec![],
+            storage: vec![],
+            nonces: vec![(contract_address, nonce)],
+            compiled_class_hashes: vec![],
+            fee_token_address,
+            strk_fee_token_address: None,
+            seq_addr_updated: true,
+        };
+
+        // When
+        let serialized_loader = serde_json::to_string(&genesis_loader).unwrap();
+        let deserialized_loader: GenesisData = serde_json::from_str(&serialized_loader).unwrap();
+
+        // Then
+        let expected = r#"{"contract_classes":[],"contracts":[],"storage":[],"nonces":[["0x2","0x7"]],"fee_token_address":"0x5","seq_addr_updated":true}"#;
+        assert_eq!(expected, serialized_loader);
+        assert_eq!(1, deserialized_loader.nonces.len());
+        assert_eq!(FieldElement::from(7u8), deserialized_loader.nonces[0].1.0);
+    }
 }
@@ -47,18 +47,25 @@ pub mod genesis_loader;
 pub mod message;
 /// The Starknet pallet's runtime API
 pub mod runtime_api;
+/// Storage of the state tries, and computation of the state root.
+pub mod state_trie;
 /// Transaction validation logic.
 pub mod transaction_validation;
 /// The Starknet pallet's runtime custom types.
 pub mod types;
 
+/// A cache of the decoded contract classes, used by the native runtime
+#[cfg(feature = "std")]
+mod contract_class_cache;
+/// Migrations of the pallet storage, and of the state tries
+mod migration;
 /// Everything needed to run the pallet offchain workers
 mod offchain_worker;
 
 use blockifier::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
 use blockifier::state::cached_state::ContractStorageKey;
 use blockifier::state::state_api::State;
-use blockifier::transaction::objects::{TransactionExecutionInfo, TransactionExecutionResult};
+use blockifier::transaction::objects::{ResourcesMapping, TransactionExecutionInfo, TransactionExecutionResult};
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{Calldata, Event as StarknetEvent, Fee};
 
@@ -78,19 +85,24 @@ use blockifier::execution::entry_point::{CallInfo, ExecutionResources};
 use blockifier_state_adapter::BlockifierStateAdapter;
 use frame_support::pallet_prelude::*;
 use frame_support::traits::Time;
+use frame_system::offchain::SendTransactionTypes;
 use frame_system::pallet_prelude::*;
 use mp_block::{Block as StarknetBlock, Header as StarknetHeader};
+use mp_commitments::ProofNode;
 use mp_digest_log::MADARA_ENGINE_ID;
 use mp_fee::INITIAL_GAS;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use mp_sequencer_address::{InherentError, InherentType, DEFAULT_SEQUENCER_ADDRESS, INHERENT_IDENTIFIER};
 use mp_state::{FeeConfig, StateChanges};
-use mp_storage::{StarknetStorageSchemaVersion, PALLET_STARKNET_SCHEMA};
+use mp_storage::{
+    StarknetStorageSchemaVersion, PALLET_STARKNET_SCHEMA, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE,
+    STARKNET_STORAGE,
+};
 use mp_transactions::execution::{Execute, Validate};
 use mp_transactions::{
-    DeclareTransaction, DeployAccountTransaction, HandleL1MessageTransaction, InvokeTransaction, Transaction,
-    UserAndL1HandlerTransaction, UserTransaction,
+    DeclareTransaction, DeployAccountTransaction, DeployAccountTransactionV3, HandleL1MessageTransaction,
+    InvokeTransaction, Transaction, UserAndL1HandlerTransaction, UserTransaction,
 };
 use sp_runtime::traits::UniqueSaturatedInto;
 use sp_runtime::DigestItem;
@@ -103,13 +115,18 @@ use starknet_api::transaction::TransactionHash;
 use starknet_crypto::FieldElement;
 
 use crate::alloc::string::ToString;
-use crate::types::StorageSlot;
+use crate::types::{
+    BlockResources, MigrationStep, ProtocolParameter, ProtocolParameters, SequencerSchedule, StorageSlot,
+};
 
 pub(crate) const LOG_TARGET: &str = "runtime::starknet";
 
 pub const ETHEREUM_EXECUTION_RPC: &[u8] = b"starknet::ETHEREUM_EXECUTION_RPC";
 pub const ETHEREUM_CONSENSUS_RPC: &[u8] = b"starknet::ETHEREUM_CONSENSUS_RPC";
 pub(crate) const NONCE_DECODE_FAILURE: u8 = 1;
+/// Maximum number of `L1MessagesBlockRange` ranges the last known Ethereum block can move forward
+/// by in a single update.
+pub const MAX_L1_MESSAGES_RANGES_PER_UPDATE: u64 = 100;
 
 // syntactic sugar for logging.
 #[macro_export]
@@ -134,7 +151,7 @@ pub mod pallet {
     /// We're coupling the starknet pallet to the tx payment pallet to be able to override the fee
     /// mechanism and comply with starknet which uses an ER20 as fee token
     #[pallet::config]
-    pub trait Config: frame_system::Config {
+    pub trait Config: frame_system::Config + SendTransactionTypes<Call<Self>> {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
         /// The hashing function to use.
@@ -154,6 +171,10 @@ pub mod pallet {
         #[pallet::constant]
         type TransactionLongevity: Get<TransactionLongevity>;
         /// A bool to disable transaction fees and make all transactions free
+        ///
+        /// Initial value of the protocol parameter, which can be changed with
+        /// `set_protocol_parameter`. The same goes for `InvokeTxMaxNSteps`, `ValidateMaxNSteps`,
+        /// `ChainId` and `MaxRecursionDepth`.
         #[pallet::constant]
         type DisableTransactionFee: Get<bool>;
         /// A bool to disable Nonce validation
@@ -168,6 +189,40 @@ pub mod pallet {
         type ChainId: Get<Felt252Wrapper>;
         #[pallet::constant]
         type MaxRecursionDepth: Get<u32>;
+        /// Number of Ethereum blocks mined on top of a block before the offchain worker consumes
+        /// the L1 messages it contains.
+        #[pallet::constant]
+        type L1MessagesConfirmationDepth: Get<u64>;
+        /// Maximum number of Ethereum blocks covered by a single `eth_getLogs` query of the
+        /// offchain worker.
+        #[pallet::constant]
+        type L1MessagesBlockRange: Get<u64>;
+        /// Maximum number of Cairo steps executed by the transactions of a block.
+        #[pallet::constant]
+        type MaxBlockSteps: Get<u64>;
+        /// Maximum number of builtin applications by the transactions of a block.
+        #[pallet::constant]
+        type MaxBlockBuiltins: Get<u64>;
+        /// Maximum L1 gas consumed by the transactions of a block.
+        #[pallet::constant]
+        type MaxBlockL1Gas: Get<u64>;
+        /// The price of the L1 gas, in wei, used to compute the fees of the transactions of the
+        /// current block.
+        type L1GasPrice: Get<u128>;
+        /// Duration of a slot, in milliseconds, used to elect the sequencer of a block from its
+        /// timestamp.
+        #[pallet::constant]
+        type SlotDuration: Get<u64>;
+        /// Number of blocks whose state tries are kept, and can be proven against. The trie nodes
+        /// which are only part of older tries are deleted.
+        #[pallet::constant]
+        type StateTriesHistory: Get<u64>;
+        /// Maximum number of storage entries processed in a block by a migration running over
+        /// several blocks.
+        #[pallet::constant]
+        type MigrationEntriesPerBlock: Get<u32>;
+        /// The origin allowed to change the protocol parameters and the sequencer schedule.
+        type GovernanceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
     }
 
     /// The Starknet pallet hooks.
@@ -184,16 +239,22 @@ pub mod pallet {
             <Pallet<T>>::store_block(UniqueSaturatedInto::<u64>::unique_saturated_into(
                 frame_system::Pallet::<T>::block_number(),
             ));
+
+            // The next block starts with all its resources available.
+            BlockResourcesUsage::<T>::kill();
         }
 
         /// The block is being initialized. Implement to have something happen.
         fn on_initialize(_: T::BlockNumber) -> Weight {
-            Weight::zero()
+            // The protocol parameters changed during the previous block apply from this one on.
+            Self::apply_protocol_parameter_changes();
+
+            migration::run_migration_step::<T>()
         }
 
         /// Perform a module upgrade.
         fn on_runtime_upgrade() -> Weight {
-            Weight::zero()
+            migration::migrate_to_v2::<T>().saturating_add(migration::build_missing_state_tries::<T>())
         }
 
         /// Run offchain tasks.
@@ -204,12 +265,12 @@ pub mod pallet {
             log!(info, "Running offchain worker at block {:?}.", n);
 
             match Self::process_l1_messages() {
-                Ok(_) => log!(info, "Successfully executed L1 messages"),
+                Ok(_) => log!(info, "Successfully submitted L1 messages"),
                 Err(err) => match err {
                     offchain_worker::OffchainWorkerError::NoLastKnownEthBlock => {
-                        log!(info, "No last known Ethereum block number found. Skipping execution of L1 messages.")
+                        log!(info, "No last known Ethereum block number found. Skipping submission of L1 messages.")
                     }
-                    _ => log!(error, "Failed to execute L1 messages: {:?}", err),
+                    _ => log!(error, "Failed to submit L1 messages: {:?}", err),
                 },
             }
         }
@@ -251,12 +312,62 @@ pub mod pallet {
     /// Pending storage slot updates
     /// STORAGE
     /// Mapping storage key to storage value.
+    /// Contracts whose nonce or class hash changed have an entry too, possibly without any slot, so
+    /// that their contract state hash is updated when the block is stored.
     #[pallet::storage]
     #[pallet::unbounded]
     #[pallet::getter(fn pending_storage_changes)]
     pub(super) type PendingStorageChanges<T: Config> =
         StorageMap<_, Identity, ContractAddress, Vec<StorageSlot>, ValueQuery>;
 
+    /// Classes declared with a compiled class hash since the last block.
+    /// They are added to the classes tree when the block is stored.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn pending_class_changes)]
+    pub(super) type PendingClassChanges<T: Config> = StorageValue<_, Vec<(ClassHash, CompiledClassHash)>, ValueQuery>;
+
+    /// Nodes of the state tries, by hash: the storage tree of each contract, the contracts tree and
+    /// the classes tree. A node is kept as long as it is part of the tries of one of the last
+    /// `StateTriesHistory` blocks, so that they can be proven against.
+    /// Safe to use `Identity` as the key is already a hash.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StateTrieNodes<T: Config> = StorageMap<_, Identity, Felt252Wrapper, ProofNode, OptionQuery>;
+
+    /// Number of times each node of `StateTrieNodes` was inserted in the tries and not released
+    /// since. The node is deleted when it drops to zero.
+    /// Safe to use `Identity` as the key is already a hash.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StateTrieNodeReferences<T: Config> = StorageMap<_, Identity, Felt252Wrapper, u32, ValueQuery>;
+
+    /// Mapping from block number to the trie nodes replaced by the update of the tries of the
+    /// block. They are released once the tries of the parent block are not kept anymore.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StateTrieReleasedNodes<T: Config> = StorageMap<_, Identity, u64, Vec<Felt252Wrapper>, ValueQuery>;
+
+    /// Mapping from block number to the roots of the contracts tree and classes tree committed by
+    /// the block, for the last `StateTriesHistory` blocks.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn state_tree_roots)]
+    pub(super) type StateTreeRoots<T: Config> =
+        StorageMap<_, Identity, u64, (Felt252Wrapper, Felt252Wrapper), OptionQuery>;
+
+    /// Version of the leaves committed by the state tries. The tries are built again by a
+    /// migration when it is older than [`state_trie::STATE_TRIES_VERSION`].
+    #[pallet::storage]
+    pub(super) type StateTriesVersion<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// The migration of the pallet storage running over several blocks, if any.
+    /// Starknet transactions are not accepted until it ends, so that the state does not change
+    /// while it is migrated.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type OngoingMigration<T: Config> = StorageValue<_, MigrationStep, OptionQuery>;
+
     /// Mapping for block number and hashes.
     /// Safe to use `Identity` as the key is already a hash.
     #[pallet::storage]
@@ -265,11 +376,12 @@ pub mod pallet {
     pub(super) type BlockHash<T: Config> = StorageMap<_, Identity, u64, Felt252Wrapper, ValueQuery>;
 
     /// Mapping from Starknet contract address to the contract's class hash.
-    /// Safe to use `Identity` as the key is already a hash.
+    /// `Blake2_128Concat` is used as contract addresses can be chosen by users through the deploy
+    /// salt, which could be used to unbalance the storage trie.
     #[pallet::storage]
     #[pallet::unbounded]
-    #[pallet::getter(fn contract_class_hash_by_address)]
-    pub(super) type ContractClassHashes<T: Config> = StorageMap<_, Identity, ContractAddress, ClassHash, ValueQuery>;
+    pub(super) type ContractClassHashes<T: Config> =
+        StorageMap<_, Blake2_128Concat, ContractAddress, ClassHash, ValueQuery>;
 
     /// Mapping from Starknet class hash to contract class.
     /// Safe to use `Identity` as the key is already a hash.
@@ -278,6 +390,12 @@ pub mod pallet {
     #[pallet::getter(fn contract_class_by_class_hash)]
     pub(super) type ContractClasses<T: Config> = StorageMap<_, Identity, ClassHash, ContractClass, OptionQuery>;
 
+    /// Mapping from Starknet class hash to the blake2 hash of the encoded contract class.
+    /// Lets the native runtime check a cached decoded class against the storage without reading
+    /// the class. Classes declared before it was introduced have none.
+    #[pallet::storage]
+    pub(super) type ContractClassFingerprints<T: Config> = StorageMap<_, Identity, ClassHash, [u8; 32], OptionQuery>;
+
     /// Mapping from Starknet Sierra class hash to  Casm compiled contract class.
     /// Safe to use `Identity` as the key is already a hash.
     #[pallet::storage]
@@ -286,22 +404,35 @@ pub mod pallet {
     pub(super) type CompiledClassHashes<T: Config> = StorageMap<_, Identity, ClassHash, CompiledClassHash, OptionQuery>;
 
     /// Mapping from Starknet contract address to its nonce.
-    /// Safe to use `Identity` as the key is already a hash.
+    /// `Blake2_128Concat` is used for the same reason as in `ContractClassHashes`.
     #[pallet::storage]
     #[pallet::unbounded]
-    #[pallet::getter(fn nonce)]
-    pub(super) type Nonces<T: Config> = StorageMap<_, Identity, ContractAddress, Nonce, ValueQuery>;
+    pub(super) type Nonces<T: Config> = StorageMap<_, Blake2_128Concat, ContractAddress, Nonce, ValueQuery>;
 
     /// Mapping from Starknet contract storage key to its value.
-    /// Safe to use `Identity` as the key is already a hash.
+    /// `Blake2_128Concat` is used as storage keys can be chosen by contracts.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StorageView<T: Config> = StorageMap<_, Blake2_128Concat, ContractStorageKey, StarkFelt, ValueQuery>;
+
+    /// Nonces of the L1 messages already consumed, for replay protection.
+    /// Safe to use `Identity` as the nonces are attributed in sequence by the L1 core contract.
     #[pallet::storage]
     #[pallet::unbounded]
-    #[pallet::getter(fn storage)]
-    pub(super) type StorageView<T: Config> = StorageMap<_, Identity, ContractStorageKey, StarkFelt, ValueQuery>;
+    #[pallet::getter(fn l1_message_consumed)]
+    pub(super) type L1Messages<T: Config> = StorageMap<_, Identity, u64, bool, ValueQuery>;
+
+    /// Execution resources consumed so far by the transactions of the current block.
+    /// Cleared when the block is finalized.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn block_resources_usage)]
+    pub(super) type BlockResourcesUsage<T: Config> = StorageValue<_, BlockResources, ValueQuery>;
 
     /// The last processed Ethereum block number for L1 messages consumption.
     /// This is used to avoid re-processing the same Ethereum block multiple times.
-    /// This is used by the offchain worker.
+    /// This is used by the offchain worker, and only moves forward once all the L1 messages up to
+    /// this block have been consumed on chain.
     /// # TODO
     /// * Find a more relevant name for this.
     #[pallet::storage]
@@ -315,6 +446,12 @@ pub mod pallet {
     #[pallet::getter(fn fee_token_address)]
     pub(super) type FeeTokenAddress<T: Config> = StorageValue<_, ContractAddress, ValueQuery>;
 
+    /// The address of the STRK ERC20 contract, in which the v3 transactions pay their fee.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn strk_fee_token_address)]
+    pub(super) type StrkFeeTokenAddress<T: Config> = StorageValue<_, ContractAddress, ValueQuery>;
+
     /// Current sequencer address.
     #[pallet::storage]
     #[pallet::unbounded]
@@ -327,6 +464,37 @@ pub mod pallet {
     #[pallet::getter(fn seq_addr_update)]
     pub type SeqAddrUpdate<T: Config> = StorageValue<_, bool, ValueQuery>;
 
+    /// The sequencers allowed to author blocks and their rotation schedule.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn sequencer_schedule)]
+    pub type SequencerRotation<T: Config> = StorageValue<_, SequencerSchedule, ValueQuery>;
+
+    #[pallet::type_value]
+    pub fn DefaultProtocolParameters<T: Config>() -> ProtocolParameters {
+        ProtocolParameters {
+            chain_id: T::ChainId::get(),
+            invoke_tx_max_n_steps: T::InvokeTxMaxNSteps::get(),
+            validate_max_n_steps: T::ValidateMaxNSteps::get(),
+            max_recursion_depth: T::MaxRecursionDepth::get(),
+            disable_transaction_fee: T::DisableTransactionFee::get(),
+        }
+    }
+
+    /// The protocol parameters of the current block, initially the ones of the `Config`.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn protocol_parameters)]
+    pub type CurrentProtocolParameters<T: Config> =
+        StorageValue<_, ProtocolParameters, ValueQuery, DefaultProtocolParameters<T>>;
+
+    /// The protocol parameter changes scheduled in this block, applied at the beginning of the next
+    /// one.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn pending_protocol_parameter_changes)]
+    pub type PendingProtocolParameterChanges<T: Config> = StorageValue<_, Vec<ProtocolParameter>, ValueQuery>;
+
     /// Starknet genesis configuration.
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
@@ -343,9 +511,18 @@ pub mod pallet {
         /// contracts classes.
         pub contract_classes: Vec<(ClassHash, ContractClass)>,
         pub storage: Vec<(ContractStorageKey, StarkFelt)>,
+        /// The nonces of the contracts at genesis.
+        /// This is needed to restore accounts from an existing chain state, as their next
+        /// transaction must carry the nonce they had on the original chain.
+        pub nonces: Vec<(ContractAddress, Nonce)>,
+        /// The compiled class hashes of the Cairo 1 classes declared at genesis, keyed by their
+        /// Sierra class hash.
+        pub compiled_class_hashes: Vec<(ClassHash, CompiledClassHash)>,
         /// The address of the fee token.
         /// Must be set to the address of the fee token ERC20 contract.
         pub fee_token_address: ContractAddress,
+        /// The address of the STRK token, in which the v3 transactions pay their fee.
+        pub strk_fee_token_address: ContractAddress,
         pub _phantom: PhantomData<T>,
         pub seq_addr_updated: bool,
     }
@@ -357,7 +534,10 @@ pub mod pallet {
                 contracts: vec![],
                 contract_classes: vec![],
                 storage: vec![],
+                nonces: vec![],
+                compiled_class_hashes: vec![],
                 fee_token_address: ContractAddress::default(),
+                strk_fee_token_address: ContractAddress::default(),
                 _phantom: PhantomData,
                 seq_addr_updated: true,
             }
@@ -367,27 +547,44 @@ pub mod pallet {
     #[pallet::genesis_build]
     impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
         fn build(&self) {
-            <Pallet<T>>::store_block(0);
             frame_support::storage::unhashed::put::<StarknetStorageSchemaVersion>(
                 PALLET_STARKNET_SCHEMA,
-                &StarknetStorageSchemaVersion::V1,
+                &StarknetStorageSchemaVersion::V2,
             );
 
             for (address, class_hash) in self.contracts.iter() {
                 ContractClassHashes::<T>::insert(address, class_hash);
+                <Pallet<T>>::touch_contract(*address);
             }
 
             for (class_hash, contract_class) in self.contract_classes.iter() {
-                ContractClasses::<T>::insert(class_hash, contract_class);
+                <Pallet<T>>::store_contract_class(class_hash, contract_class);
             }
 
             for (key, value) in self.storage.iter() {
                 StorageView::<T>::insert(key, value);
+                let (address, storage_key) = key;
+                PendingStorageChanges::<T>::append(address, (*storage_key, Felt252Wrapper::from(*value)));
+            }
+
+            for (address, nonce) in self.nonces.iter() {
+                Nonces::<T>::insert(address, nonce);
+                <Pallet<T>>::touch_contract(*address);
+            }
+
+            for (class_hash, compiled_class_hash) in self.compiled_class_hashes.iter() {
+                CompiledClassHashes::<T>::insert(class_hash, compiled_class_hash);
+                PendingClassChanges::<T>::append((*class_hash, *compiled_class_hash));
             }
 
+            StateTriesVersion::<T>::put(state_trie::STATE_TRIES_VERSION);
+            // The genesis block commits to the genesis state
+            <Pallet<T>>::store_block(0);
+
             LastKnownEthBlock::<T>::set(None);
             // Set the fee token address from the genesis config.
             FeeTokenAddress::<T>::set(self.fee_token_address);
+            StrkFeeTokenAddress::<T>::set(self.strk_fee_token_address);
             SeqAddrUpdate::<T>::put(self.seq_addr_updated);
         }
     }
@@ -402,12 +599,30 @@ pub mod pallet {
         /// Regular Starknet event
         StarknetEvent(StarknetEvent),
         /// Emitted when fee token address is changed.
-        /// This is emitted by the `set_fee_token_address` extrinsic.
+        /// This is emitted when a change scheduled by the `set_protocol_parameter` extrinsic is
+        /// applied.
         /// [old_fee_token_address, new_fee_token_address]
         FeeTokenAddressChanged {
             old_fee_token_address: ContractAddress,
             new_fee_token_address: ContractAddress,
         },
+        /// Emitted when a protocol parameter change is scheduled by the `set_protocol_parameter`
+        /// extrinsic.
+        ProtocolParameterChangeScheduled {
+            parameter: ProtocolParameter,
+        },
+        /// Emitted when a protocol parameter other than the fee token address is changed, at the
+        /// beginning of the block following the one in which the change was scheduled.
+        /// [old, new]
+        ProtocolParameterChanged {
+            old: ProtocolParameter,
+            new: ProtocolParameter,
+        },
+        /// Emitted when the sequencer schedule is changed by the `set_sequencer_schedule`
+        /// extrinsic.
+        SequencerScheduleChanged {
+            schedule: SequencerSchedule,
+        },
     }
 
     /// The Starknet pallet custom errors.
@@ -432,6 +647,12 @@ pub mod pallet {
         SequencerAddressNotValid,
         InvalidContractClassForThisDeclareVersion,
         Unimplemented,
+        L1MessageAlreadyExecuted,
+        EthBlockAlreadyProcessed,
+        InvalidProtocolParameter,
+        UnexpectedSequencerAddress,
+        InvalidSequencerSchedule,
+        EthBlockTooFarAhead,
     }
 
     /// The Starknet pallet external functions.
@@ -460,6 +681,10 @@ pub mod pallet {
 
             let addr = StarkFelt::new(addr).map_err(|_| Error::<T>::SequencerAddressNotValid)?;
             let addr = ContractAddress(addr.try_into().map_err(|_| Error::<T>::SequencerAddressNotValid)?);
+            // The fees are paid to the elected sequencer, if there is one.
+            if let Some(expected_addr) = Self::expected_sequencer_address() {
+                ensure!(addr == expected_addr, Error::<T>::UnexpectedSequencerAddress);
+            }
             SequencerAddress::<T>::put(addr);
             SeqAddrUpdate::<T>::put(true);
             Ok(())
@@ -487,12 +712,12 @@ pub mod pallet {
             let chain_id = Self::chain_id();
             let transaction = input_transaction.into_executable::<T::SystemHash>(chain_id, false);
 
-            let sender_address = match &transaction.tx {
+            let sender_address = match &transaction.tx.tx {
                 starknet_api::transaction::InvokeTransaction::V0(tx) => tx.contract_address,
                 starknet_api::transaction::InvokeTransaction::V1(tx) => tx.sender_address,
             };
             // Check if contract is deployed
-            ensure!(ContractClassHashes::<T>::contains_key(sender_address), Error::<T>::AccountNotDeployed);
+            ensure!(Self::is_deployed(sender_address), Error::<T>::AccountNotDeployed);
 
             // Execute
             let tx_execution_infos = transaction
@@ -507,7 +732,9 @@ pub mod pallet {
                     Error::<T>::TransactionExecutionFailed
                 })?;
 
-            let tx_hash = transaction.tx_hash;
+            Self::record_block_resources(&tx_execution_infos.actual_resources);
+
+            let tx_hash = transaction.tx.tx_hash;
             Self::emit_and_store_tx_and_fees_events(
                 tx_hash,
                 tx_execution_infos.execute_call_info,
@@ -526,6 +753,9 @@ pub mod pallet {
         ///
         /// * `origin` - The origin of the transaction.
         /// * `transaction` - The Starknet transaction.
+        /// * `contract_class` - The class declared.
+        /// * `sierra_class` - For a Cairo 1 class, the compressed Sierra class it was compiled
+        ///   from.
         ///
         ///  # Returns
         ///
@@ -536,9 +766,13 @@ pub mod pallet {
             origin: OriginFor<T>,
             transaction: DeclareTransaction,
             contract_class: ContractClass,
+            sierra_class: Option<Vec<u8>>,
         ) -> DispatchResult {
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
+            // The Sierra class is lost when compiled, it is only carried in the block for the nodes to
+            // serve it back
+            let _ = sierra_class;
 
             let input_transaction = transaction;
             let chain_id = Self::chain_id();
@@ -548,14 +782,11 @@ pub mod pallet {
 
             // Check class hash is not already declared
             ensure!(
-                !ContractClasses::<T>::contains_key(transaction.tx().class_hash()),
+                !ContractClasses::<T>::contains_key(transaction.tx.tx().class_hash()),
                 Error::<T>::ClassHashAlreadyDeclared
             );
             // Check if contract is deployed
-            ensure!(
-                ContractClassHashes::<T>::contains_key(transaction.tx().sender_address()),
-                Error::<T>::AccountNotDeployed
-            );
+            ensure!(Self::is_deployed(transaction.tx.tx().sender_address()), Error::<T>::AccountNotDeployed);
 
             // Execute
             let tx_execution_infos = transaction
@@ -567,7 +798,9 @@ pub mod pallet {
                 )
                 .map_err(|_| Error::<T>::TransactionExecutionFailed)?;
 
-            let tx_hash = transaction.tx_hash();
+            Self::record_block_resources(&tx_execution_infos.actual_resources);
+
+            let tx_hash = transaction.tx.tx_hash();
             Self::emit_and_store_tx_and_fees_events(
                 tx_hash,
                 tx_execution_infos.execute_call_info,
@@ -598,14 +831,11 @@ pub mod pallet {
             ensure_none(origin)?;
 
             let input_transaction = transaction;
-            let chain_id = T::ChainId::get();
+            let chain_id = Self::chain_id();
             let transaction = input_transaction.into_executable::<T::SystemHash>(chain_id, false);
 
             // Check if contract is deployed
-            ensure!(
-                !ContractClassHashes::<T>::contains_key(transaction.contract_address),
-                Error::<T>::AccountAlreadyDeployed
-            );
+            ensure!(!Self::is_deployed(transaction.contract_address), Error::<T>::AccountAlreadyDeployed);
 
             // Execute
             let tx_execution_infos = transaction
@@ -616,10 +846,12 @@ pub mod pallet {
                     T::DisableNonceValidation::get(),
                 )
                 .map_err(|e| {
-                    log::error!("failed to deploy accout: {:?}", e);
+                    log::error!("failed to deploy account: {:?}", e);
                     Error::<T>::TransactionExecutionFailed
                 })?;
 
+            Self::record_block_resources(&tx_execution_infos.actual_resources);
+
             let tx_hash = transaction.tx_hash;
             Self::emit_and_store_tx_and_fees_events(
                 tx_hash,
@@ -659,6 +891,8 @@ pub mod pallet {
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
 
+            ensure!(!Self::l1_message_consumed(transaction.nonce), Error::<T>::L1MessageAlreadyExecuted);
+
             let input_transaction = transaction;
             let chain_id = Self::chain_id();
             let transaction = input_transaction.into_executable::<T::SystemHash>(chain_id, paid_fee_on_l1, false);
@@ -676,6 +910,10 @@ pub mod pallet {
                     Error::<T>::TransactionExecutionFailed
                 })?;
 
+            Self::record_block_resources(&tx_execution_infos.actual_resources);
+
+            L1Messages::<T>::insert(input_transaction.nonce, true);
+
             let tx_hash = transaction.tx_hash;
             Self::emit_and_store_tx_and_fees_events(
                 tx_hash,
@@ -691,6 +929,163 @@ pub mod pallet {
 
             Ok(())
         }
+
+        /// Record that the L1 messages emitted up to an Ethereum block have all been consumed.
+        ///
+        /// This is submitted by the offchain worker once the messages it submitted have been
+        /// included, so that it resumes from the next block.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - The origin of the transaction.
+        /// * `eth_block_number` - The new last known Ethereum block.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the transaction.
+        #[pallet::call_index(5)]
+        #[pallet::weight({0})]
+        pub fn set_last_known_eth_block(origin: OriginFor<T>, eth_block_number: u64) -> DispatchResult {
+            // This ensures that the function can only be called via unsigned transaction.
+            ensure_none(origin)?;
+
+            let last_known_eth_block = Self::last_known_eth_block()
+                .filter(|last_known_eth_block| eth_block_number > *last_known_eth_block)
+                .ok_or(Error::<T>::EthBlockAlreadyProcessed)?;
+            ensure!(
+                eth_block_number - last_known_eth_block <= Self::max_eth_blocks_per_update(),
+                Error::<T>::EthBlockTooFarAhead
+            );
+            LastKnownEthBlock::<T>::put(eth_block_number);
+
+            Ok(())
+        }
+
+        /// Schedule the change of a protocol parameter.
+        ///
+        /// The change is applied at the beginning of the next block, so that all the transactions
+        /// of a block are executed with the same parameters.
+        ///
+        /// The dispatch origin for this call must be `GovernanceOrigin`.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - The origin of the transaction.
+        /// * `parameter` - The protocol parameter and its new value.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the transaction.
+        #[pallet::call_index(6)]
+        #[pallet::weight({0})]
+        pub fn set_protocol_parameter(origin: OriginFor<T>, parameter: ProtocolParameter) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            let is_valid = match parameter {
+                ProtocolParameter::ChainId(chain_id) => chain_id != Felt252Wrapper::ZERO,
+                ProtocolParameter::InvokeTxMaxNSteps(n)
+                | ProtocolParameter::ValidateMaxNSteps(n)
+                | ProtocolParameter::MaxRecursionDepth(n) => n != 0,
+                ProtocolParameter::FeeTokenAddress(_)
+                | ProtocolParameter::DisableTransactionFee(_)
+                | ProtocolParameter::StrkFeeTokenAddress(_) => true,
+            };
+            ensure!(is_valid, Error::<T>::InvalidProtocolParameter);
+
+            PendingProtocolParameterChanges::<T>::append(parameter.clone());
+            Self::deposit_event(Event::ProtocolParameterChangeScheduled { parameter });
+
+            Ok(())
+        }
+
+        /// Set the sequencers allowed to author blocks and their rotation schedule.
+        ///
+        /// The schedule applies from the next block on, whose `set_sequencer_address` inherent
+        /// must then give the sequencer elected for its slot. An empty schedule removes the
+        /// restriction.
+        ///
+        /// The dispatch origin for this call must be `GovernanceOrigin`.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - The origin of the transaction.
+        /// * `schedule` - The sequencers, in the order of their turns, and the length of a turn.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the transaction.
+        #[pallet::call_index(7)]
+        #[pallet::weight({0})]
+        pub fn set_sequencer_schedule(origin: OriginFor<T>, schedule: SequencerSchedule) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+            ensure!(
+                schedule.sequencers.is_empty() || schedule.slots_per_sequencer != 0,
+                Error::<T>::InvalidSequencerSchedule
+            );
+
+            SequencerRotation::<T>::put(schedule.clone());
+            Self::deposit_event(Event::SequencerScheduleChanged { schedule });
+
+            Ok(())
+        }
+
+        /// The v3 deploy account transaction, which pays its fee in STRK within resource bounds.
+        /// See `https://github.com/starknet-io/SNIPs/blob/main/SNIPS/snip-8.md`.
+        ///
+        /// The v1 deploy account transaction is not an enum of its versions, so the v3 one has a
+        /// call of its own.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - The origin of the transaction.
+        /// * `transaction` - The Starknet transaction.
+        ///
+        ///  # Returns
+        ///
+        /// * `DispatchResult` - The result of the transaction.
+        #[pallet::call_index(8)]
+        #[pallet::weight({0})]
+        pub fn deploy_account_v3(origin: OriginFor<T>, transaction: DeployAccountTransactionV3) -> DispatchResult {
+            // This ensures that the function can only be called via unsigned transaction.
+            ensure_none(origin)?;
+
+            let input_transaction = transaction;
+            let chain_id = Self::chain_id();
+            let transaction = input_transaction.into_executable::<T::SystemHash>(chain_id, false);
+
+            // Check if contract is deployed
+            ensure!(!Self::is_deployed(transaction.contract_address), Error::<T>::AccountAlreadyDeployed);
+
+            // Execute
+            let tx_execution_infos = transaction
+                .execute(
+                    &mut BlockifierStateAdapter::<T>::default(),
+                    &Self::get_block_context(),
+                    false,
+                    T::DisableNonceValidation::get(),
+                )
+                .map_err(|e| {
+                    log::error!("failed to deploy account: {:?}", e);
+                    Error::<T>::TransactionExecutionFailed
+                })?;
+
+            Self::record_block_resources(&tx_execution_infos.actual_resources);
+
+            let tx_hash = transaction.tx_hash;
+            Self::emit_and_store_tx_and_fees_events(
+                tx_hash,
+                tx_execution_infos.execute_call_info,
+                tx_execution_infos.fee_transfer_call_info,
+            );
+
+            Self::store_transaction(
+                tx_hash,
+                Transaction::DeployAccountV3(input_transaction),
+                tx_execution_infos.revert_error,
+            );
+
+            Ok(())
+        }
     }
 
     #[pallet::inherent]
@@ -725,7 +1120,7 @@ pub mod pallet {
         /// By default unsigned transactions are disallowed, but implementing the validator
         /// here we make sure that some particular calls (in this case all calls)
         /// are being whitelisted and marked as valid.
-        fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        fn validate_unsigned(source: TransactionSource, call: &Self::Call) -> TransactionValidity {
             // The priority right now is the max u64 - nonce because for unsigned transactions we need to
             // determine an absolute priority. For now we use that for the benchmark (lowest nonce goes first)
             // otherwise we have a nonce error and everything fails.
@@ -737,63 +1132,67 @@ pub mod pallet {
             let mut execution_resources = ExecutionResources::default();
             let mut initial_gas = blockifier::abi::constants::INITIAL_GAS_COST;
 
-            let transaction = Self::get_call_transaction(call.clone()).map_err(|_| InvalidTransaction::Call)?;
+            if let Call::set_last_known_eth_block { eth_block_number } = call {
+                return Self::validate_last_known_eth_block_update(source, *eth_block_number);
+            }
+
+            // The transaction can be included once the state tries can be updated again
+            if !Self::state_tries_available() {
+                return Err(InvalidTransaction::Future.into());
+            }
+
+            let transaction = match Self::get_call_transaction(call.clone()).map_err(|_| InvalidTransaction::Call)? {
+                UserAndL1HandlerTransaction::User(transaction) => transaction,
+                UserAndL1HandlerTransaction::L1Handler(transaction, paid_fee_on_l1) => {
+                    return Self::validate_l1_handler_transaction(source, &transaction, paid_fee_on_l1);
+                }
+            };
 
             // Check the nonce is correct
-            let (sender_address, sender_nonce, transaction_nonce) =
-                if let UserAndL1HandlerTransaction::User(ref transaction) = transaction {
-                    let sender_address: ContractAddress = transaction.sender_address().into();
-                    let sender_nonce: Felt252Wrapper = Pallet::<T>::nonce(sender_address).into();
-                    let transaction_nonce = transaction.nonce();
-
-                    // InvokeV0 does not have a nonce
-                    if let Some(transaction_nonce) = transaction_nonce {
-                        // Reject transaction with an already used Nonce
-                        if sender_nonce > *transaction_nonce {
-                            Err(InvalidTransaction::Stale)?;
-                        }
-
-                        // A transaction with a nonce higher than the expected nonce is placed in
-                        // the future queue of the transaction pool.
-                        if sender_nonce < *transaction_nonce {
-                            log!(
-                                debug,
-                                "Nonce is too high. Expected: {:?}, got: {:?}. This transaction will be placed in the \
-                                 transaction pool and executed in the future when the nonce is reached.",
-                                sender_nonce,
-                                transaction_nonce
-                            );
-                        }
-                    };
+            let sender_address = transaction.sender_address();
+            let sender_nonce: Felt252Wrapper = Pallet::<T>::nonce(ContractAddress::from(sender_address)).into();
+            let transaction_nonce = transaction.nonce().cloned();
 
-                    (transaction.sender_address(), sender_nonce, transaction_nonce.cloned())
-                } else {
-                    // TODO: create and check L1 messages Nonce
-                    unimplemented!()
-                };
+            // InvokeV0 does not have a nonce
+            if let Some(transaction_nonce) = transaction_nonce {
+                // Reject transaction with an already used Nonce
+                if sender_nonce > transaction_nonce {
+                    Err(InvalidTransaction::Stale)?;
+                }
 
-            // Validate the user transactions
-            if let UserAndL1HandlerTransaction::User(transaction) = transaction {
-                match transaction {
-                    UserTransaction::Declare(tx, contract_class) => tx
-                        .try_into_executable::<T::SystemHash>(chain_id, contract_class, false)
-                        .map_err(|_| InvalidTransaction::BadProof)?
-                        .validate_tx(&mut state, &block_context, &mut execution_resources, &mut initial_gas, false),
-                    // There is no way to validate it before the account is actuallly deployed
-                    UserTransaction::DeployAccount(_) => Ok(None),
-                    UserTransaction::Invoke(tx) => tx.into_executable::<T::SystemHash>(chain_id, false).validate_tx(
-                        &mut state,
-                        &block_context,
-                        &mut execution_resources,
-                        &mut initial_gas,
-                        false,
-                    ),
+                // A transaction with a nonce higher than the expected nonce is placed in
+                // the future queue of the transaction pool.
+                if sender_nonce < transaction_nonce {
+                    log!(
+                        debug,
+                        "Nonce is too high. Expected: {:?}, got: {:?}. This transaction will be placed in the \
+                         transaction pool and executed in the future when the nonce is reached.",
+                        sender_nonce,
+                        transaction_nonce
+                    );
                 }
-                .map_err(|e| {
-                    log::error!("failed to validate tx: {}", e);
-                    InvalidTransaction::BadProof
-                })?;
+            };
+
+            // Validate the user transactions
+            match transaction {
+                UserTransaction::Declare(tx, contract_class, _) => tx
+                    .try_into_executable::<T::SystemHash>(chain_id, contract_class, false)
+                    .map_err(|_| InvalidTransaction::BadProof)?
+                    .validate_tx(&mut state, &block_context, &mut execution_resources, &mut initial_gas, false),
+                // There is no way to validate it before the account is actuallly deployed
+                UserTransaction::DeployAccount(_) | UserTransaction::DeployAccountV3(_) => Ok(None),
+                UserTransaction::Invoke(tx) => tx.into_executable::<T::SystemHash>(chain_id, false).validate_tx(
+                    &mut state,
+                    &block_context,
+                    &mut execution_resources,
+                    &mut initial_gas,
+                    false,
+                ),
             }
+            .map_err(|e| {
+                log::error!("failed to validate tx: {}", e);
+                InvalidTransaction::BadProof
+            })?;
 
             let nonce_for_priority: u64 = transaction_nonce
                 .unwrap_or(Felt252Wrapper::ZERO)
@@ -827,8 +1226,25 @@ pub mod pallet {
         /// In the default implementation of pre_dispatch for the ValidateUnsigned trait,
         /// this function calls the validate_unsigned function in order to verify validity
         /// before dispatch. In our case, since transaction was already validated in
-        /// `validate_unsigned` we can just return Ok.
-        fn pre_dispatch(_call: &Self::Call) -> Result<(), TransactionValidityError> {
+        /// `validate_unsigned` we only check that the block can still include Starknet
+        /// transactions. Once it can't, they are rejected as exhausting the block resources, which
+        /// makes the block producer stop filling the block.
+        fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+            let is_starknet_transaction = matches!(
+                call,
+                Call::invoke { .. }
+                    | Call::declare { .. }
+                    | Call::deploy_account { .. }
+                    | Call::deploy_account_v3 { .. }
+                    | Call::consume_l1_message { .. }
+            );
+            if is_starknet_transaction && !Self::state_tries_available() {
+                return Err(InvalidTransaction::Future.into());
+            }
+            if is_starknet_transaction && Self::block_resources_exhausted() {
+                return Err(InvalidTransaction::ExhaustsResources.into());
+            }
+
             Ok(())
         }
     }
@@ -848,10 +1264,11 @@ impl<T: Config> Pallet<T> {
     fn get_call_transaction(call: Call<T>) -> Result<UserAndL1HandlerTransaction, ()> {
         let tx = match call {
             Call::<T>::invoke { transaction } => UserTransaction::Invoke(transaction).into(),
-            Call::<T>::declare { transaction, contract_class } => {
-                UserTransaction::Declare(transaction, contract_class).into()
+            Call::<T>::declare { transaction, contract_class, sierra_class } => {
+                UserTransaction::Declare(transaction, contract_class, sierra_class).into()
             }
             Call::<T>::deploy_account { transaction } => UserTransaction::DeployAccount(transaction).into(),
+            Call::<T>::deploy_account_v3 { transaction } => UserTransaction::DeployAccountV3(transaction).into(),
             Call::<T>::consume_l1_message { transaction, paid_fee_on_l1 } => {
                 UserAndL1HandlerTransaction::L1Handler(transaction, paid_fee_on_l1)
             }
@@ -861,6 +1278,106 @@ impl<T: Config> Pallet<T> {
         Ok(tx)
     }
 
+    /// Validates an L1 handler transaction submitted to the transaction pool.
+    ///
+    /// The message and its fee are read from the L1 messaging contract by the offchain worker of
+    /// the node, so they can't be received from the network: anyone could then forge a message,
+    /// e.g. a bridge deposit. The message must not be consumed yet, its target contract must be
+    /// deployed, and the bridge must have declared a fee paid on L1 for it. Whether this fee covers
+    /// the execution of the message is checked when it is executed.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where the transaction comes from
+    /// * `transaction` - The L1 handler transaction
+    /// * `paid_fee_on_l1` - The fee paid on L1 for the message, as declared by the bridge
+    fn validate_l1_handler_transaction(
+        source: TransactionSource,
+        transaction: &HandleL1MessageTransaction,
+        paid_fee_on_l1: Fee,
+    ) -> TransactionValidity {
+        if source == TransactionSource::External {
+            return Err(InvalidTransaction::Call.into());
+        }
+        if Self::l1_message_consumed(transaction.nonce) {
+            return Err(InvalidTransaction::Stale.into());
+        }
+        if paid_fee_on_l1 == Fee(0) {
+            return Err(InvalidTransaction::Payment.into());
+        }
+        if !Self::is_deployed(ContractAddress::from(transaction.contract_address)) {
+            return Err(InvalidTransaction::Call.into());
+        }
+
+        // L1 messages are ordered by the core contract, and don't depend on each other. They go
+        // before any user transaction.
+        ValidTransaction::with_tag_prefix("starknet")
+            .priority(TransactionPriority::MAX)
+            .and_provides(("l1_message", transaction.nonce))
+            .longevity(T::TransactionLongevity::get())
+            .propagate(false)
+            .build()
+    }
+
+    /// Validates an update of the last known Ethereum block submitted to the transaction pool.
+    ///
+    /// Like the L1 messages, the update comes from the offchain worker of the node, and can't be
+    /// received from the network. It can move the last known block forward by at most
+    /// [Self::max_eth_blocks_per_update] blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where the transaction comes from
+    /// * `eth_block_number` - The new last known Ethereum block
+    fn validate_last_known_eth_block_update(source: TransactionSource, eth_block_number: u64) -> TransactionValidity {
+        if source == TransactionSource::External {
+            return Err(InvalidTransaction::Call.into());
+        }
+        match Self::last_known_eth_block() {
+            Some(last_known_eth_block) if eth_block_number > last_known_eth_block => {
+                if eth_block_number - last_known_eth_block > Self::max_eth_blocks_per_update() {
+                    return Err(InvalidTransaction::Future.into());
+                }
+            }
+            _ => return Err(InvalidTransaction::Stale.into()),
+        }
+
+        ValidTransaction::with_tag_prefix("starknet")
+            .priority(T::UnsignedPriority::get())
+            .and_provides(("last_known_eth_block", eth_block_number))
+            .longevity(T::TransactionLongevity::get())
+            .propagate(false)
+            .build()
+    }
+
+    /// Returns the maximum number of Ethereum blocks the last known Ethereum block can move forward
+    /// by in a single update.
+    pub fn max_eth_blocks_per_update() -> u64 {
+        T::L1MessagesBlockRange::get().max(1).saturating_mul(MAX_L1_MESSAGES_RANGES_PER_UPDATE)
+    }
+
+    /// Returns the limits on the execution resources consumed by the transactions of a block.
+    pub fn block_resources_limits() -> BlockResources {
+        BlockResources {
+            steps: T::MaxBlockSteps::get(),
+            builtins: T::MaxBlockBuiltins::get(),
+            l1_gas: T::MaxBlockL1Gas::get(),
+        }
+    }
+
+    /// Returns whether the current block has reached one of its resources limits.
+    ///
+    /// The transaction reaching a limit is still included, so a block exceeds its limits by at
+    /// most the resources of a single transaction.
+    pub fn block_resources_exhausted() -> bool {
+        Self::block_resources_usage().reaches(&Self::block_resources_limits())
+    }
+
+    /// Adds the resources consumed by a transaction to the ones of the current block.
+    fn record_block_resources(resources: &ResourcesMapping) {
+        BlockResourcesUsage::<T>::mutate(|usage| usage.add(resources));
+    }
+
     /// Creates a [BlockContext] object. The [BlockContext] is needed by the blockifier to execute
     /// properly the transaction. Substrate caches data so it's fine to call multiple times this
     /// function, only the first transaction/block will be "slow" to load these data.
@@ -874,8 +1391,8 @@ impl<T: Config> Pallet<T> {
         let chain_id = Self::chain_id_str();
 
         let vm_resource_fee_cost = Default::default();
-        // FIXME: https://github.com/keep-starknet-strange/madara/issues/329
-        let gas_price = 10;
+        let gas_price = T::L1GasPrice::get();
+        let protocol_parameters = Self::protocol_parameters();
         BlockContext {
             block_number: BlockNumber(block_number),
             block_timestamp: BlockTimestamp(block_timestamp),
@@ -883,17 +1400,17 @@ impl<T: Config> Pallet<T> {
             sequencer_address,
             fee_token_address,
             vm_resource_fee_cost,
-            invoke_tx_max_n_steps: T::InvokeTxMaxNSteps::get(),
-            validate_max_n_steps: T::ValidateMaxNSteps::get(),
+            invoke_tx_max_n_steps: protocol_parameters.invoke_tx_max_n_steps,
+            validate_max_n_steps: protocol_parameters.validate_max_n_steps,
             gas_price,
-            max_recursion_depth: T::MaxRecursionDepth::get(),
+            max_recursion_depth: protocol_parameters.max_recursion_depth,
         }
     }
 
     /// convert chain_id
     #[inline(always)]
     pub fn chain_id_str() -> String {
-        unsafe { from_utf8_unchecked(&T::ChainId::get().0.to_bytes_be()).to_string() }
+        unsafe { from_utf8_unchecked(&Self::chain_id().0.to_bytes_be()).to_string() }
     }
 
     /// Get the block hash of the previous block.
@@ -921,6 +1438,18 @@ impl<T: Config> Pallet<T> {
         timestamp_in_millisecond / 1000
     }
 
+    /// Get the slot of the current block, from its timestamp.
+    #[inline(always)]
+    pub fn current_slot() -> u64 {
+        let timestamp_in_millisecond: u64 = T::TimestampProvider::now().unique_saturated_into();
+        timestamp_in_millisecond / T::SlotDuration::get()
+    }
+
+    /// Get the sequencer elected for the current block, if there is a sequencer schedule.
+    pub fn expected_sequencer_address() -> Option<ContractAddress> {
+        Self::sequencer_schedule().sequencer_for_slot(Self::current_slot())
+    }
+
     /// Get the number of transactions in the block.
     #[inline(always)]
     pub fn transaction_count() -> u128 {
@@ -942,7 +1471,7 @@ impl<T: Config> Pallet<T> {
         // Get current block context
         let block_context = Self::get_block_context();
         // Get class hash
-        let class_hash = ContractClassHashes::<T>::try_get(address).map_err(|_| Error::<T>::ContractNotFound)?;
+        let class_hash = Self::contract_class_hash(address).ok_or(Error::<T>::ContractNotFound)?;
 
         let entrypoint = CallEntryPoint {
             class_hash: Some(class_hash),
@@ -978,10 +1507,52 @@ impl<T: Config> Pallet<T> {
         }
     }
 
+    /// The class hash of a deployed contract.
+    ///
+    /// While the storage is migrated to the schema V2, the contracts not moved yet are read at
+    /// their schema V1 key.
+    pub fn contract_class_hash(contract_address: ContractAddress) -> Option<ClassHash> {
+        migration::with_schema_v1_fallback::<T, _, _>(
+            STARKNET_CONTRACT_CLASS_HASH,
+            &contract_address,
+            ContractClassHashes::<T>::try_get(contract_address).ok(),
+        )
+    }
+
+    /// The class hash of a contract, or the default class hash if it is not deployed.
+    pub fn contract_class_hash_by_address(contract_address: ContractAddress) -> ClassHash {
+        Self::contract_class_hash(contract_address).unwrap_or_default()
+    }
+
+    /// Whether a contract is deployed.
+    pub fn is_deployed(contract_address: ContractAddress) -> bool {
+        Self::contract_class_hash(contract_address).is_some()
+    }
+
+    /// The nonce of a contract.
+    pub fn nonce(contract_address: ContractAddress) -> Nonce {
+        migration::with_schema_v1_fallback::<T, _, _>(
+            STARKNET_NONCE,
+            &contract_address,
+            Nonces::<T>::try_get(contract_address).ok(),
+        )
+        .unwrap_or_default()
+    }
+
+    /// The value of a storage slot of a contract.
+    pub fn storage(contract_storage_key: ContractStorageKey) -> StarkFelt {
+        migration::with_schema_v1_fallback::<T, _, _>(
+            STARKNET_STORAGE,
+            &contract_storage_key,
+            StorageView::<T>::try_get(contract_storage_key).ok(),
+        )
+        .unwrap_or_default()
+    }
+
     /// Get storage value at
     pub fn get_storage_at(contract_address: ContractAddress, key: StorageKey) -> Result<StarkFelt, DispatchError> {
         // Get state
-        ensure!(ContractClassHashes::<T>::contains_key(contract_address), Error::<T>::ContractNotFound);
+        ensure!(Self::is_deployed(contract_address), Error::<T>::ContractNotFound);
         Ok(Self::storage((contract_address, key)))
     }
 
@@ -1003,7 +1574,12 @@ impl<T: Config> Pallet<T> {
         let parent_block_hash = Self::parent_block_hash(&block_number);
         let events: Vec<StarknetEvent> = transaction_hashes.iter().flat_map(TxEvents::<T>::take).collect();
 
-        let global_state_root = Felt252Wrapper::default();
+        // A block whose state root is not available carries a zero root, as the blocks authored
+        // before the state tries were kept
+        let global_state_root = Self::commit_state(block_number).unwrap_or_else(|| {
+            log!(error, "The state root of block #{} is not available", block_number);
+            Felt252Wrapper::ZERO
+        });
 
         let sequencer_address = Self::sequencer_address();
         let block_timestamp = Self::block_timestamp();
@@ -1012,6 +1588,7 @@ impl<T: Config> Pallet<T> {
         let (transaction_commitment, event_commitment) =
             mp_commitments::calculate_commitments::<T::SystemHash>(&transactions, &events, chain_id);
         let protocol_version = T::ProtocolVersion::get();
+        let l1_gas_price = T::L1GasPrice::get();
         let extra_data = None;
 
         let block = StarknetBlock::new(
@@ -1026,6 +1603,7 @@ impl<T: Config> Pallet<T> {
                 events.len() as u128,
                 event_commitment.into(),
                 protocol_version,
+                l1_gas_price,
                 extra_data,
             ),
             transactions,
@@ -1043,6 +1621,21 @@ impl<T: Config> Pallet<T> {
         frame_system::Pallet::<T>::deposit_log(digest);
     }
 
+    /// Mark a contract as touched by the block, so that its contract state hash is updated when the
+    /// block is stored.
+    pub(crate) fn touch_contract(contract_address: ContractAddress) {
+        // `mutate` stores the default empty vector if the contract has no storage update yet
+        PendingStorageChanges::<T>::mutate(contract_address, |_| ());
+    }
+
+    /// Store a declared contract class, along with the fingerprint of its encoding.
+    pub(crate) fn store_contract_class(class_hash: &ClassHash, contract_class: &ContractClass) {
+        ContractClasses::<T>::insert(class_hash, contract_class);
+        ContractClassFingerprints::<T>::insert(class_hash, contract_class.using_encoded(sp_io::hashing::blake2_256));
+        #[cfg(feature = "std")]
+        crate::contract_class_cache::invalidate(class_hash);
+    }
+
     /// Emit events from the call info.
     ///
     /// # Arguments
@@ -1092,7 +1685,7 @@ impl<T: Config> Pallet<T> {
     }
 
     /// Estimate the fee associated with transaction
-    pub fn estimate_fee(transaction: UserTransaction) -> Result<(u64, u64), DispatchError> {
+    pub fn estimate_fee(transaction: UserTransaction) -> Result<(u128, u64), DispatchError> {
         let chain_id = Self::chain_id();
 
         fn execute_tx_and_rollback<S: State + StateChanges + FeeConfig>(
@@ -1115,7 +1708,7 @@ impl<T: Config> Pallet<T> {
         let disable_nonce_validation = T::DisableNonceValidation::get();
 
         let execution_result = match transaction {
-            UserTransaction::Declare(tx, contract_class) => execute_tx_and_rollback(
+            UserTransaction::Declare(tx, contract_class, _) => execute_tx_and_rollback(
                 tx.try_into_executable::<T::SystemHash>(chain_id, contract_class, true)
                     .map_err(|_| Error::<T>::InvalidContractClass)?,
                 &mut blockifier_state_adapter,
@@ -1134,13 +1727,19 @@ impl<T: Config> Pallet<T> {
                 &block_context,
                 disable_nonce_validation,
             ),
+            UserTransaction::DeployAccountV3(tx) => execute_tx_and_rollback(
+                tx.into_executable::<T::SystemHash>(chain_id, true),
+                &mut blockifier_state_adapter,
+                &block_context,
+                disable_nonce_validation,
+            ),
         };
 
         match execution_result {
             Ok(tx_exec_info) => {
                 log!(debug, "Successfully estimated fee: {:?}", tx_exec_info);
                 if let Some(gas_usage) = tx_exec_info.actual_resources.0.get("l1_gas_usage") {
-                    Ok((tx_exec_info.actual_fee.0 as u64, *gas_usage as u64))
+                    Ok((tx_exec_info.actual_fee.0, *gas_usage as u64))
                 } else {
                     Err(Error::<T>::TransactionExecutionFailed.into())
                 }
@@ -1172,6 +1771,28 @@ impl<T: Config> Pallet<T> {
     }
 
     pub fn chain_id() -> Felt252Wrapper {
-        T::ChainId::get()
+        Self::protocol_parameters().chain_id
+    }
+
+    /// Applies the protocol parameter changes scheduled in the previous block.
+    fn apply_protocol_parameter_changes() {
+        for new in PendingProtocolParameterChanges::<T>::take() {
+            match new {
+                ProtocolParameter::FeeTokenAddress(new_fee_token_address) => {
+                    let old_fee_token_address = FeeTokenAddress::<T>::get();
+                    FeeTokenAddress::<T>::put(new_fee_token_address);
+                    Self::deposit_event(Event::FeeTokenAddressChanged { old_fee_token_address, new_fee_token_address });
+                }
+                ProtocolParameter::StrkFeeTokenAddress(new_strk_fee_token_address) => {
+                    let old = ProtocolParameter::StrkFeeTokenAddress(StrkFeeTokenAddress::<T>::get());
+                    StrkFeeTokenAddress::<T>::put(new_strk_fee_token_address);
+                    Self::deposit_event(Event::ProtocolParameterChanged { old, new });
+                }
+                new => {
+                    let old = CurrentProtocolParameters::<T>::mutate(|parameters| parameters.set(new.clone()));
+                    Self::deposit_event(Event::ProtocolParameterChanged { old, new });
+                }
+            }
+        }
     }
 }
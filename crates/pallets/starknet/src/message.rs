@@ -2,10 +2,11 @@ use mp_felt::Felt252Wrapper;
 use mp_transactions::HandleL1MessageTransaction;
 use parity_scale_codec::{Decode, Encode};
 use serde::Deserialize;
+use starknet_api::transaction::Fee;
 
-use crate::alloc::format;
 use crate::alloc::string::String;
 use crate::alloc::vec::Vec;
+use crate::alloc::{format, vec};
 use crate::offchain_worker::OffchainWorkerError;
 
 #[derive(Deserialize, Encode, Decode, Default, Debug)]
@@ -39,46 +40,62 @@ pub fn get_messages_events(from_block: u64, to_block: u64) -> String {
 }
 
 impl Message {
-    /// Converts a `Message` into a transaction object.
-    pub fn try_into_transaction(&self) -> Result<HandleL1MessageTransaction, OffchainWorkerError> {
-        // Data at least contains a nonce and at some point the fees.
+    /// Converts a `Message` into a transaction object, along with the fee paid on L1 for it.
+    ///
+    /// The data of the `LogMessageToL2` event is the ABI encoding of `(uint256[] payload, uint256
+    /// nonce, uint256 fee)`, the payload being the calldata of the L1 handler.
+    pub fn try_into_transaction(&self) -> Result<(HandleL1MessageTransaction, Fee), OffchainWorkerError> {
+        // Data at least contains the payload offset, the nonce and the fee.
         if self.data.is_empty() {
             return Err(OffchainWorkerError::EmptyData);
         }
+        // L1 address of the sender, it is the first argument of the L1 handler.
+        let from_address = parse_felt(self.topics.get(1))?;
         // L2 contract to call.
-        let contract_address = match Felt252Wrapper::from_hex_be(self.topics[2].as_str()) {
-            Ok(f) => f,
-            Err(_) => return Err(OffchainWorkerError::ToTransactionError),
-        };
-
+        let contract_address = parse_felt(self.topics.get(2))?;
         // Function of the contract to call.
-        let entry_point_selector = match Felt252Wrapper::from_hex_be(self.topics[3].as_str()) {
-            Ok(f) => f,
-            Err(_) => return Err(OffchainWorkerError::ToTransactionError),
-        };
+        let entry_point_selector = parse_felt(self.topics.get(3))?;
+
+        // Split the data into 32 bytes words.
+        let data = self.data.trim_start_matches("0x").as_bytes();
+        if data.len() % 64 != 0 {
+            return Err(OffchainWorkerError::ToTransactionError);
+        }
+        let words = data
+            .chunks(64)
+            .map(|word| core::str::from_utf8(word).map_err(|_| OffchainWorkerError::ToTransactionError))
+            .collect::<Result<Vec<&str>, _>>()?;
+        let word = |index: usize| words.get(index).copied().ok_or(OffchainWorkerError::ToTransactionError);
 
-        // Add the from address here so it's directly in the calldata.
-        let char_vec = format!("{:}{:}", self.topics[1].trim_start_matches("0x"), self.data.trim_start_matches("0x"))
-            .chars()
-            .collect::<Vec<char>>();
-        // Split the data String into values. (The event Log(a: uin256, b: uin256, c: uin256) logs a single
-        // string which is the concatenation of those fields).
-        let data_map = char_vec.chunks(64).map(|chunk| chunk.iter().collect::<String>());
         // L1 message nonce.
-        let nonce = u64::from_str_radix(
-            data_map.clone().last().ok_or(OffchainWorkerError::ToTransactionError)?.trim_start_matches("0x"),
-            16,
-        )
-        .map_err(|_| OffchainWorkerError::ToTransactionError)?;
-        let mut calldata: Vec<Felt252Wrapper> = Vec::new();
-        for val in data_map.take(self.data.len() - 2) {
-            calldata.push(match Felt252Wrapper::from_hex_be(val.as_str()) {
-                Ok(f) => f,
-                Err(_) => return Err(OffchainWorkerError::ToTransactionError),
-            })
+        let nonce = u64::from_str_radix(word(1)?, 16).map_err(|_| OffchainWorkerError::ToTransactionError)?;
+        // Fee paid on L1 for the message.
+        let paid_fee_on_l1 = u128::from_str_radix(word(2)?, 16).map_err(|_| OffchainWorkerError::ToTransactionError)?;
+
+        // The payload is encoded as its length followed by its elements, at the given offset in bytes.
+        let payload_offset =
+            usize::from_str_radix(word(0)?, 16).map_err(|_| OffchainWorkerError::ToTransactionError)?;
+        let payload_start = payload_offset / 32;
+        let payload_len =
+            usize::from_str_radix(word(payload_start)?, 16).map_err(|_| OffchainWorkerError::ToTransactionError)?;
+
+        let payload = words
+            .get(payload_start + 1..)
+            .and_then(|words| words.get(..payload_len))
+            .ok_or(OffchainWorkerError::ToTransactionError)?;
+
+        let mut calldata = vec![from_address];
+        for word in payload {
+            calldata.push(Felt252Wrapper::from_hex_be(word).map_err(|_| OffchainWorkerError::ToTransactionError)?);
         }
         let tx = HandleL1MessageTransaction { nonce, contract_address, entry_point_selector, calldata };
 
-        Ok(tx)
+        Ok((tx, Fee(paid_fee_on_l1)))
     }
 }
+
+/// Parses a hex encoded topic of the event into a felt.
+fn parse_felt(topic: Option<&String>) -> Result<Felt252Wrapper, OffchainWorkerError> {
+    let topic = topic.ok_or(OffchainWorkerError::ToTransactionError)?;
+    Felt252Wrapper::from_hex_be(topic.as_str()).map_err(|_| OffchainWorkerError::ToTransactionError)
+}
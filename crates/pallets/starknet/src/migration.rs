@@ -0,0 +1,421 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use blockifier::state::cached_state::ContractStorageKey;
+use frame_support::pallet_prelude::*;
+use frame_support::storage::{storage_prefix, unhashed, KeyPrefixIterator, PrefixIterator, StoragePrefixedMap};
+use frame_support::traits::PalletInfoAccess;
+use mp_commitments::{calculate_class_commitment_leaf_hash, StateTrie, TrieError};
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use mp_storage::{
+    StarknetStorageSchemaVersion, PALLET_STARKNET_SCHEMA, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE,
+    STARKNET_STORAGE,
+};
+use sp_runtime::traits::UniqueSaturatedInto;
+use starknet_api::api_core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+
+use crate::state_trie::{StateTrieNodeStorage, STATE_TRIES_VERSION};
+use crate::types::MigrationStep;
+use crate::{
+    log, CompiledClassHashes, Config, ContractClassHashes, ContractsStateRoots, Nonces, OngoingMigration, Pallet,
+    StateTreeRoots, StateTrieNodeReferences, StateTrieNodes, StateTrieReleasedNodes, StateTriesVersion, StorageView,
+};
+
+/// Estimated number of trie nodes read and written to add a leaf to a state trie.
+const TRIE_NODES_PER_LEAF: u64 = 64;
+
+/// Length of an encoded contract address, and of an encoded storage key.
+const CONTRACT_ADDRESS_LEN: usize = 32;
+
+/// Schedules the migration of the pallet storage from the schema V1 to the schema V2.
+///
+/// Schema V2 stores `ContractClassHashes`, `Nonces` and `StorageView` using the
+/// `Blake2_128Concat` hasher instead of `Identity`. The entries are moved over the next blocks, see
+/// [`migrate_schema_v1_entries`], and the state tries are then built again from the migrated
+/// storage.
+pub(crate) fn migrate_to_v2<T: Config>() -> Weight {
+    let onchain_version = unhashed::get_or_default::<StarknetStorageSchemaVersion>(PALLET_STARKNET_SCHEMA);
+    if onchain_version != StarknetStorageSchemaVersion::V1 {
+        return T::DbWeight::get().reads(1);
+    }
+
+    log!(info, "Migrating the storage to the schema V2");
+    // The state tries are built again once the storage is migrated
+    OngoingMigration::<T>::put(MigrationStep::MigrateSchemaV1Entries { item: 0, cursor: None });
+    T::DbWeight::get().reads_writes(1, 1)
+}
+
+/// Moves the entries of the maps using the `Identity` hasher in the schema V1 to their schema V2
+/// key, one map after the other.
+///
+/// The schema V1 and V2 entries of a map share the same prefix, and are told apart by the length
+/// of their key. The schema version is only updated once every entry is moved, so that clients keep
+/// reading the storage with the schema V1 layout meanwhile, falling back to the schema V2 key of
+/// the entries already moved. The pallet itself reads the schema V2 key first, falling back to the
+/// schema V1 key of the entries not moved yet, see [`with_schema_v1_fallback`], so that Starknet
+/// transactions are still included while the entries are moved.
+fn migrate_schema_v1_entries<T: Config>(item: u32, cursor: Option<Vec<u8>>, limit: u32) -> StepResult {
+    let pallet_name = Pallet::<T>::name().as_bytes();
+    let (cursor, reads, moved) = match item {
+        0 => move_identity_entries::<ContractAddress, ClassHash>(
+            pallet_name,
+            STARKNET_CONTRACT_CLASS_HASH,
+            CONTRACT_ADDRESS_LEN,
+            cursor,
+            limit,
+            |k, v| ContractClassHashes::<T>::insert(k, v),
+        ),
+        1 => move_identity_entries::<ContractAddress, Nonce>(
+            pallet_name,
+            STARKNET_NONCE,
+            CONTRACT_ADDRESS_LEN,
+            cursor,
+            limit,
+            |k, v| Nonces::<T>::insert(k, v),
+        ),
+        2 => move_identity_entries::<ContractStorageKey, StarkFelt>(
+            pallet_name,
+            STARKNET_STORAGE,
+            2 * CONTRACT_ADDRESS_LEN,
+            cursor,
+            limit,
+            |k, v| StorageView::<T>::insert(k, v),
+        ),
+        _ => {
+            log!(info, "The storage is migrated to the schema V2");
+            unhashed::put::<StarknetStorageSchemaVersion>(PALLET_STARKNET_SCHEMA, &StarknetStorageSchemaVersion::V2);
+            // The state tries don't depend on the storage layout, and were updated by the blocks
+            // including transactions meanwhile
+            let next_step =
+                state_tries_need_build::<T>().then_some(MigrationStep::ClearStateTries { item: 0, cursor: None });
+            return (Ok(next_step), T::DbWeight::get().reads_writes(2, 1));
+        }
+    };
+
+    let next_step = match cursor {
+        Some(cursor) => MigrationStep::MigrateSchemaV1Entries { item, cursor: Some(cursor) },
+        None => MigrationStep::MigrateSchemaV1Entries { item: item + 1, cursor: None },
+    };
+    // Each moved entry is removed and written again
+    (Ok(Some(next_step)), T::DbWeight::get().reads_writes(reads + 1, 2 * moved))
+}
+
+/// Reads an entry of a map whose hasher changed in the schema V2, given its value at its schema V2
+/// key: while the entries are moved, an entry not moved yet is read at its schema V1 key.
+pub(crate) fn with_schema_v1_fallback<T: Config, K: Encode, V: Decode>(
+    item: &[u8],
+    key: &K,
+    value: Option<V>,
+) -> Option<V> {
+    value.or_else(|| moves_schema_v1_entries::<T>().then(|| unhashed::get(&schema_v1_key::<T, _>(item, key))).flatten())
+}
+
+/// Removes the schema V1 entry of a map whose hasher changed in the schema V2, once its value is
+/// written at its schema V2 key, so that the migration doesn't move the outdated value over it.
+pub(crate) fn remove_schema_v1_entry<T: Config, K: Encode>(item: &[u8], key: &K) {
+    if !moves_schema_v1_entries::<T>() {
+        return;
+    }
+
+    let key = schema_v1_key::<T, _>(item, key);
+    if unhashed::exists(&key) {
+        unhashed::kill(&key);
+    }
+}
+
+/// Whether the entries of the schema V1 maps are being moved to their schema V2 key.
+fn moves_schema_v1_entries<T: Config>() -> bool {
+    matches!(OngoingMigration::<T>::get(), Some(MigrationStep::MigrateSchemaV1Entries { .. }))
+}
+
+/// Raw storage key of a map entry in the schema V1 layout, using the `Identity` hasher.
+fn schema_v1_key<T: Config, K: Encode>(item: &[u8], key: &K) -> Vec<u8> {
+    [storage_prefix(Pallet::<T>::name().as_bytes(), item).as_slice(), &key.encode()].concat()
+}
+
+/// Moves up to `limit` entries of a map stored with the `Identity` hasher to their key with the
+/// hasher of the map, through `insert`, starting after the raw key `cursor`.
+///
+/// Returns the raw key to resume from, if the map may have more entries to move, along with the
+/// numbers of entries read and moved.
+fn move_identity_entries<K: Decode, V: Decode>(
+    pallet_name: &[u8],
+    item: &[u8],
+    key_len: usize,
+    cursor: Option<Vec<u8>>,
+    limit: u32,
+    insert: impl Fn(K, V),
+) -> (Option<Vec<u8>>, u64, u64) {
+    let prefix = storage_prefix(pallet_name, item);
+    let mut last_key = cursor.unwrap_or_else(|| prefix.to_vec());
+    let (mut reads, mut moved) = (0, 0);
+
+    while reads < limit as u64 {
+        let Some(key) = sp_io::storage::next_key(&last_key).filter(|key| key.starts_with(&prefix)) else {
+            return (None, reads, moved);
+        };
+        reads += 1;
+
+        // Entries already moved have a longer key, starting with the hash of the encoded key
+        if key.len() == prefix.len() + key_len {
+            if let (Ok(k), Some(v)) = (K::decode(&mut &key[prefix.len()..]), unhashed::get::<V>(&key)) {
+                unhashed::kill(&key);
+                insert(k, v);
+                moved += 1;
+            }
+        }
+        last_key = key;
+    }
+
+    (Some(last_key), reads, moved)
+}
+
+/// Schedules the build of the state tries when upgrading a chain whose runtime didn't keep them,
+/// or whose tries commit outdated leaves.
+///
+/// If the storage is being migrated to the schema V2, the tries are built once it is done.
+pub(crate) fn build_missing_state_tries<T: Config>() -> Weight {
+    if !state_tries_need_build::<T>() {
+        return T::DbWeight::get().reads(2);
+    }
+
+    log!(info, "The state tries are missing or commit outdated leaves, building them from the state");
+    schedule_state_tries_build::<T>();
+    T::DbWeight::get().reads_writes(3, 1)
+}
+
+/// Whether the state tries must be built from the state, because the tries of the parent block are
+/// missing or commit outdated leaves.
+fn state_tries_need_build<T: Config>() -> bool {
+    let tries_are_missing = block_number::<T>()
+        .checked_sub(1)
+        .map_or(false, |parent_block_number| !StateTreeRoots::<T>::contains_key(parent_block_number));
+    tries_are_missing || StateTriesVersion::<T>::get() < STATE_TRIES_VERSION
+}
+
+/// Starts building the state tries from scratch over the next blocks, unless a migration is
+/// already running.
+pub(crate) fn schedule_state_tries_build<T: Config>() {
+    if !OngoingMigration::<T>::exists() {
+        OngoingMigration::<T>::put(MigrationStep::ClearStateTries { item: 0, cursor: None });
+    }
+}
+
+/// Whether the state tries are being built from the state, so that the blocks can't update them.
+pub(crate) fn builds_state_tries<T: Config>() -> bool {
+    OngoingMigration::<T>::get().is_some_and(|step| !matches!(step, MigrationStep::MigrateSchemaV1Entries { .. }))
+}
+
+/// Runs the next step of the ongoing migration, if any.
+///
+/// The state tries are built from the state as of the end of the parent block: no Starknet
+/// transaction is included while they are built. Once they are built, they are committed as the
+/// tries of the parent block, which the current block updates.
+pub(crate) fn run_migration_step<T: Config>() -> Weight {
+    let Some(step) = OngoingMigration::<T>::get() else {
+        return T::DbWeight::get().reads(1);
+    };
+
+    let limit = T::MigrationEntriesPerBlock::get().max(1);
+    let (next_step, weight) = match step {
+        MigrationStep::MigrateSchemaV1Entries { item, cursor } => migrate_schema_v1_entries::<T>(item, cursor, limit),
+        MigrationStep::ClearStateTries { item, cursor } => clear_state_tries::<T>(item, cursor, limit),
+        MigrationStep::BuildStorageTries { cursor } => build_storage_tries::<T>(cursor, limit),
+        MigrationStep::BuildContractsTree { item, cursor, root } => {
+            build_contracts_tree::<T>(item, cursor, root, limit)
+        }
+        MigrationStep::BuildClassesTree { cursor, contracts_tree_root, root } => {
+            build_classes_tree::<T>(cursor, contracts_tree_root, root, limit)
+        }
+    };
+
+    let next_step = next_step.unwrap_or_else(|e| {
+        log!(error, "The state tries can't be built ({:?}), building them again", e);
+        Some(MigrationStep::ClearStateTries { item: 0, cursor: None })
+    });
+    match next_step {
+        Some(next_step) => OngoingMigration::<T>::put(next_step),
+        None => {
+            log!(info, "The state tries are built");
+            OngoingMigration::<T>::kill();
+        }
+    }
+
+    weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+}
+
+/// Result of a migration step: the next step, if any, and the weight of the step.
+type StepResult = (Result<Option<MigrationStep>, TrieError>, Weight);
+
+fn clear_state_tries<T: Config>(item: u32, cursor: Option<Vec<u8>>, limit: u32) -> StepResult {
+    // The roots of the tries are not deleted: the blocks keep committing the roots of their parent
+    // until the tries are built again
+    let prefixes = [
+        StateTrieNodes::<T>::final_prefix(),
+        StateTrieNodeReferences::<T>::final_prefix(),
+        StateTrieReleasedNodes::<T>::final_prefix(),
+        ContractsStateRoots::<T>::final_prefix(),
+    ];
+    let Some(prefix) = prefixes.get(item as usize) else {
+        return (Ok(Some(MigrationStep::BuildStorageTries { cursor: None })), Weight::zero());
+    };
+
+    let result = unhashed::clear_prefix(prefix, Some(limit), cursor.as_deref());
+    let next_step = match result.maybe_cursor {
+        Some(cursor) => MigrationStep::ClearStateTries { item, cursor: Some(cursor) },
+        None => MigrationStep::ClearStateTries { item: item + 1, cursor: None },
+    };
+
+    (Ok(Some(next_step)), T::DbWeight::get().writes(result.backend.into()))
+}
+
+fn build_storage_tries<T: Config>(cursor: Option<Vec<u8>>, limit: u32) -> StepResult {
+    let entries = match cursor {
+        Some(cursor) => StorageView::<T>::iter_from(cursor),
+        None => StorageView::<T>::iter(),
+    };
+    let (entries, cursor) = next_batch(entries, limit);
+    let weight = trie_update_weight::<T>(entries.len());
+
+    let mut storage_slots: BTreeMap<ContractAddress, Vec<(Felt252Wrapper, Felt252Wrapper)>> = BTreeMap::new();
+    for ((contract_address, key), value) in entries {
+        storage_slots.entry(contract_address).or_default().push((key.0.into(), value.into()));
+    }
+
+    let mut trie = StateTrie::<PedersenHasher, _>::new(StateTrieNodeStorage::<T>::for_block(block_number::<T>()));
+    for (contract_address, storage_slots) in storage_slots {
+        let storage_root = Pallet::<T>::contract_state_root_by_address(contract_address).unwrap_or_default();
+        match trie.update(storage_root, storage_slots) {
+            Ok(storage_root) => ContractsStateRoots::<T>::insert(contract_address, storage_root),
+            Err(e) => return (Err(e), weight),
+        }
+    }
+
+    let next_step = match cursor {
+        Some(cursor) => MigrationStep::BuildStorageTries { cursor: Some(cursor) },
+        None => MigrationStep::BuildContractsTree { item: 0, cursor: None, root: Felt252Wrapper::ZERO },
+    };
+    (Ok(Some(next_step)), weight)
+}
+
+fn build_contracts_tree<T: Config>(item: u32, cursor: Option<Vec<u8>>, root: Felt252Wrapper, limit: u32) -> StepResult {
+    // A contract is in the contracts tree if it has a class, a nonce or some storage. Adding it
+    // several times sets the same leaf, computed from its whole state.
+    let (contract_addresses, cursor) = match item {
+        0 => next_batch(
+            keys_from(ContractClassHashes::<T>::iter_keys, ContractClassHashes::<T>::iter_keys_from, cursor),
+            limit,
+        ),
+        1 => next_batch(keys_from(Nonces::<T>::iter_keys, Nonces::<T>::iter_keys_from, cursor), limit),
+        2 => next_batch(
+            keys_from(ContractsStateRoots::<T>::iter_keys, ContractsStateRoots::<T>::iter_keys_from, cursor),
+            limit,
+        ),
+        _ => {
+            let next_step =
+                MigrationStep::BuildClassesTree { cursor: None, contracts_tree_root: root, root: Felt252Wrapper::ZERO };
+            return (Ok(Some(next_step)), Weight::zero());
+        }
+    };
+    let weight = trie_update_weight::<T>(contract_addresses.len());
+
+    let leaves = contract_addresses
+        .into_iter()
+        .map(|contract_address| (contract_address.into(), Pallet::<T>::contract_state_hash(contract_address)));
+    let root = match StateTrie::<PedersenHasher, _>::new(StateTrieNodeStorage::<T>::for_block(block_number::<T>()))
+        .update(root, leaves)
+    {
+        Ok(root) => root,
+        Err(e) => return (Err(e), weight),
+    };
+
+    let next_step = match cursor {
+        Some(cursor) => MigrationStep::BuildContractsTree { item, cursor: Some(cursor), root },
+        None => MigrationStep::BuildContractsTree { item: item + 1, cursor: None, root },
+    };
+    (Ok(Some(next_step)), weight)
+}
+
+fn build_classes_tree<T: Config>(
+    cursor: Option<Vec<u8>>,
+    contracts_tree_root: Felt252Wrapper,
+    root: Felt252Wrapper,
+    limit: u32,
+) -> StepResult {
+    let entries = match cursor {
+        Some(cursor) => CompiledClassHashes::<T>::iter_from(cursor),
+        None => CompiledClassHashes::<T>::iter(),
+    };
+    let (entries, cursor) = next_batch(entries, limit);
+    let weight = trie_update_weight::<T>(entries.len());
+
+    let leaves = entries.into_iter().map(|(class_hash, compiled_class_hash)| {
+        (class_hash.into(), calculate_class_commitment_leaf_hash::<PoseidonHasher>(compiled_class_hash.into()))
+    });
+    let root = match StateTrie::<PoseidonHasher, _>::new(StateTrieNodeStorage::<T>::for_block(block_number::<T>()))
+        .update(root, leaves)
+    {
+        Ok(root) => root,
+        Err(e) => return (Err(e), weight),
+    };
+
+    if let Some(cursor) = cursor {
+        return (Ok(Some(MigrationStep::BuildClassesTree { cursor: Some(cursor), contracts_tree_root, root })), weight);
+    }
+
+    // The state hasn't changed since the end of the parent block, whose tries these are
+    if let Some(parent_block_number) = block_number::<T>().checked_sub(1) {
+        StateTreeRoots::<T>::insert(parent_block_number, (contracts_tree_root, root));
+    }
+    StateTriesVersion::<T>::put(STATE_TRIES_VERSION);
+    (Ok(None), weight.saturating_add(T::DbWeight::get().writes(2)))
+}
+
+fn block_number<T: Config>() -> u64 {
+    frame_system::Pallet::<T>::block_number().unique_saturated_into()
+}
+
+fn trie_update_weight<T: Config>(leaves: usize) -> Weight {
+    let leaves = leaves as u64;
+    T::DbWeight::get().reads_writes(leaves * (TRIE_NODES_PER_LEAF + 1), leaves * (TRIE_NODES_PER_LEAF + 1))
+}
+
+/// Iterates over the keys of a map, from the raw key following `cursor` if any.
+fn keys_from<K>(
+    iter_keys: impl FnOnce() -> KeyPrefixIterator<K>,
+    iter_keys_from: impl FnOnce(Vec<u8>) -> KeyPrefixIterator<K>,
+    cursor: Option<Vec<u8>>,
+) -> KeyPrefixIterator<K> {
+    match cursor {
+        Some(cursor) => iter_keys_from(cursor),
+        None => iter_keys(),
+    }
+}
+
+/// Storage iterators which can be resumed from the raw key of the last item they returned.
+trait RawKeyIterator: Iterator {
+    fn last_raw_key(&self) -> &[u8];
+}
+
+impl<K> RawKeyIterator for KeyPrefixIterator<K> {
+    fn last_raw_key(&self) -> &[u8] {
+        KeyPrefixIterator::last_raw_key(self)
+    }
+}
+
+impl<T> RawKeyIterator for PrefixIterator<T> {
+    fn last_raw_key(&self) -> &[u8] {
+        PrefixIterator::last_raw_key(self)
+    }
+}
+
+/// Returns the next `limit` items of a storage iterator, and the raw key to resume the iteration
+/// from if it may not be over.
+fn next_batch<I: RawKeyIterator>(mut iter: I, limit: u32) -> (Vec<I::Item>, Option<Vec<u8>>) {
+    let batch: Vec<I::Item> = iter.by_ref().take(limit as usize).collect();
+    let cursor = (batch.len() == limit as usize).then(|| iter.last_raw_key().to_vec());
+    (batch, cursor)
+}
@@ -3,8 +3,8 @@ use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 
-use frame_support::traits::OriginTrait;
-use frame_system::pallet_prelude::OriginFor;
+use frame_system::offchain::SubmitTransaction;
+use mp_transactions::HandleL1MessageTransaction;
 use serde_json::from_slice;
 use sp_runtime::offchain::http;
 use sp_runtime::offchain::storage::StorageValueRef;
@@ -12,40 +12,126 @@ use starknet_api::transaction::Fee;
 pub use types::*;
 
 use crate::message::get_messages_events;
-use crate::{Config, Pallet, ETHEREUM_EXECUTION_RPC};
+use crate::{log, Call, Config, Pallet, ETHEREUM_EXECUTION_RPC};
 
-pub const LAST_FINALIZED_BLOCK_QUERY: &str =
-    r#"{"jsonrpc": "2.0", "method": "eth_getBlockByNumber", "params": ["finalized", true], "id": 0}"#;
+pub const LAST_BLOCK_QUERY: &str =
+    r#"{"jsonrpc": "2.0", "method": "eth_getBlockByNumber", "params": ["latest", false], "id": 0}"#;
+
+/// Number of runs of the offchain worker submitting an L1 message, after which the message is
+/// skipped: it could not be consumed, e.g. because its fee is too low or its target isn't deployed,
+/// and must not prevent the following messages from being consumed.
+pub const MAX_L1_MESSAGE_SUBMISSIONS: u32 = 20;
+
+/// Prefix of the offchain storage keys counting the submissions of the L1 messages.
+const L1_MESSAGE_SUBMISSIONS_PREFIX: &[u8] = b"starknet::l1_message_submissions::";
 
 impl<T: Config> Pallet<T> {
-    /// Fetches L1 messages and execute them.
+    /// Fetches L1 messages and submits them to the transaction pool.
     /// This function is called by the offchain worker.
     /// It is executed in a separate thread.
+    ///
+    /// The Ethereum blocks following the last known one are queried by ranges of at most
+    /// `L1MessagesBlockRange` blocks, up to the last block with `L1MessagesConfirmationDepth`
+    /// confirmations. The messages not consumed yet are submitted as unsigned transactions. The
+    /// last known block is only moved past a range once all its messages have been included, so
+    /// that a message dropped from the transaction pool is submitted again by the next run. A
+    /// message without fee, or still not consumed after [MAX_L1_MESSAGE_SUBMISSIONS] submissions,
+    /// is skipped.
     /// # Returns
     /// The result of the offchain worker execution.
     pub(crate) fn process_l1_messages() -> Result<(), OffchainWorkerError> {
         // Get the last known block from storage.
         let last_known_eth_block = Self::last_known_eth_block().ok_or(OffchainWorkerError::NoLastKnownEthBlock)?;
-        // Query L1 for the last finalized block.
-        let raw_body = query_eth(LAST_FINALIZED_BLOCK_QUERY)?;
-        let last_finalized_block: u64 = from_slice::<EthGetBlockByNumberResponse>(&raw_body)
+        // Query L1 for the last block, and only consider the blocks with enough confirmations.
+        let raw_body = query_eth(LAST_BLOCK_QUERY)?;
+        let last_block: u64 = from_slice::<EthGetBlockByNumberResponse>(&raw_body)
             .map_err(|_| OffchainWorkerError::SerdeError)?
             .try_into()?;
-        // Check if there are new messages to be processed.
-        if last_finalized_block > last_known_eth_block {
+        // The last known block can only move forward by a bounded number of blocks per update.
+        let last_confirmed_block = last_block
+            .saturating_sub(T::L1MessagesConfirmationDepth::get())
+            .min(last_known_eth_block.saturating_add(Self::max_eth_blocks_per_update()));
+        let block_range = T::L1MessagesBlockRange::get().max(1);
+
+        let mut consumed_up_to = last_known_eth_block;
+        while consumed_up_to < last_confirmed_block {
+            let from_block = consumed_up_to + 1;
+            let to_block = last_confirmed_block.min(consumed_up_to.saturating_add(block_range));
+
             // Read the new messages from L1.
-            let raw_body = query_eth(&get_messages_events(last_known_eth_block, last_finalized_block))?;
+            let raw_body = query_eth(&get_messages_events(from_block, to_block))?;
             let res: EthLogs = from_slice(&raw_body).map_err(|_| OffchainWorkerError::SerdeError)?;
-            // Iterate over the messages and execute them.
-            res.result.iter().try_for_each(|message| {
-                // Execute the message.
-                // Fee is required but the blockifier just check it's not zero
-                Self::consume_l1_message(OriginFor::<T>::none(), message.try_into_transaction()?, Fee(1))
-                    .map_err(OffchainWorkerError::ConsumeMessageError)
-            })?;
+
+            let mut pending_messages = false;
+            for message in res.result.iter() {
+                let (transaction, paid_fee_on_l1) = message.try_into_transaction()?;
+                if Self::l1_message_consumed(transaction.nonce) {
+                    continue;
+                }
+                if paid_fee_on_l1 == Fee(0) {
+                    log!(warn, "Skipping L1 message {}: no fee was paid on L1", transaction.nonce);
+                    continue;
+                }
+                if !record_l1_message_submission(transaction.nonce) {
+                    log!(
+                        warn,
+                        "Skipping L1 message {}: not consumed after {} submissions",
+                        transaction.nonce,
+                        MAX_L1_MESSAGE_SUBMISSIONS
+                    );
+                    continue;
+                }
+                pending_messages = true;
+                Self::submit_l1_message(transaction, paid_fee_on_l1);
+            }
+
+            // The following ranges wait for the messages of this one to be included.
+            if pending_messages {
+                break;
+            }
+            consumed_up_to = to_block;
+        }
+
+        if consumed_up_to > last_known_eth_block {
+            let call = Call::<T>::set_last_known_eth_block { eth_block_number: consumed_up_to };
+            if SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).is_err() {
+                log!(debug, "Last known Ethereum block {} not submitted, it may already be pending", consumed_up_to);
+            }
         }
         Ok(())
     }
+
+    /// Submits an L1 message as an unsigned transaction.
+    ///
+    /// The submission is rejected by the transaction pool when the message is already in it, for
+    /// instance when it was submitted by a previous run of the offchain worker.
+    fn submit_l1_message(transaction: HandleL1MessageTransaction, paid_fee_on_l1: Fee) {
+        let nonce = transaction.nonce;
+        let call = Call::<T>::consume_l1_message { transaction, paid_fee_on_l1 };
+        if SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).is_err() {
+            log!(debug, "L1 message {} not submitted, it may already be pending", nonce);
+        }
+    }
+}
+
+/// Returns the offchain storage key counting the submissions of the L1 message of the given nonce.
+pub fn l1_message_submissions_key(nonce: u64) -> Vec<u8> {
+    [L1_MESSAGE_SUBMISSIONS_PREFIX, &nonce.to_be_bytes()].concat()
+}
+
+/// Records a submission of the L1 message of the given nonce.
+///
+/// Returns `false` if the message has already been submitted [MAX_L1_MESSAGE_SUBMISSIONS] times,
+/// and must be skipped.
+fn record_l1_message_submission(nonce: u64) -> bool {
+    let key = l1_message_submissions_key(nonce);
+    let submissions = StorageValueRef::persistent(&key);
+    let count = submissions.get::<u32>().ok().flatten().unwrap_or_default();
+    if count >= MAX_L1_MESSAGE_SUBMISSIONS {
+        return false;
+    }
+    submissions.set(&(count + 1));
+    true
 }
 
 /// Returns Ethereum RPC URL from Storage
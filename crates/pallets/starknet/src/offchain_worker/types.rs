@@ -6,7 +6,6 @@ use parity_scale_codec::{Decode, Encode};
 use serde::Deserialize;
 use sp_runtime::offchain::http::Error;
 use sp_runtime::offchain::HttpError;
-use sp_runtime::DispatchError;
 
 use crate::message::Message;
 
@@ -17,7 +16,6 @@ pub enum OffchainWorkerError {
     RequestError(Error),
     SerdeError,
     ToBytesError(Utf8Error),
-    ConsumeMessageError(DispatchError),
     ToTransactionError,
     U256ConversionError,
     HexDecodeError,
@@ -18,6 +18,8 @@ use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{Calldata, Event as StarknetEvent, TransactionHash};
 
+use crate::types::SequencerSchedule;
+
 #[derive(parity_scale_codec::Encode, parity_scale_codec::Decode, scale_info::TypeInfo)]
 pub enum StarknetTransactionExecutionError {
     ContractNotFound,
@@ -41,8 +43,12 @@ sp_api::decl_runtime_apis! {
         fn contract_class_by_class_hash(class_hash: ClassHash) -> Option<ContractClass>;
         /// Returns the chain id.
         fn chain_id() -> Felt252Wrapper;
+        /// Returns the sequencers allowed to author blocks and their rotation schedule.
+        fn sequencer_schedule() -> SequencerSchedule;
+        /// Returns the sequencer elected for the given slot, if there is a sequencer schedule.
+        fn sequencer_for_slot(slot: u64) -> Option<ContractAddress>;
         /// Returns fee estimate
-        fn estimate_fee(transaction: UserTransaction) -> Result<(u64, u64), DispatchError>;
+        fn estimate_fee(transaction: UserTransaction) -> Result<(u128, u64), DispatchError>;
         /// Filters extrinsic transactions to return only Starknet transactions
         ///
         /// To support runtime upgrades, the client must be unaware of the specific extrinsic
@@ -52,6 +58,9 @@ sp_api::decl_runtime_apis! {
         /// the runtime itself, accomplished through the extrinsic_filter method. This enables the
         /// client to operate seamlessly while abstracting the extrinsic complexity.
         fn extrinsic_filter(xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<Transaction>;
+        /// Returns the compressed Sierra classes carried by the declare transactions among the given
+        /// extrinsics, along with the class hash they were declared with
+        fn declared_sierra_classes(xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<(Felt252Wrapper, Vec<u8>)>;
         fn get_events_for_tx_hash(xts: Vec<<Block as BlockT>::Extrinsic>, chain_id: Felt252Wrapper, tx_hash: Felt252Wrapper) -> Option<(TxType, Vec<StarknetEvent>)>;
 
         /// Return the list of StarknetEvent evmitted during this block, along with the hash of the starknet transaction they bellong to
@@ -0,0 +1,229 @@
+use core::marker::PhantomData;
+
+use frame_support::storage::{with_transaction, TransactionOutcome};
+use mp_commitments::{
+    calculate_class_commitment_leaf_hash, calculate_contract_state_hash, calculate_global_state_root, NodeStorage,
+    ProofNode, StateTrie, TrieError,
+};
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use sp_runtime::traits::UniqueSaturatedInto;
+use sp_runtime::DispatchError;
+use starknet_api::api_core::{ClassHash, CompiledClassHash, ContractAddress};
+use starknet_api::state::StorageKey;
+
+use crate::alloc::vec::Vec;
+use crate::types::StorageSlot;
+use crate::{
+    log, migration, Config, ContractsStateRoots, Pallet, PendingClassChanges, PendingStorageChanges, StateTreeRoots,
+    StateTrieNodeReferences, StateTrieNodes, StateTrieReleasedNodes,
+};
+
+/// Version of the leaves committed by the state tries, bumped whenever the way they are computed
+/// changes.
+///
+/// * `1` - The contract state hash is `h(h(h(class_hash, storage_root), nonce), 0)`, as on
+///   Starknet.
+pub const STATE_TRIES_VERSION: u32 = 1;
+
+/// Storage of the state tries nodes in the pallet storage.
+///
+/// The nodes replaced by an update are recorded as released by the block being built, and are
+/// deleted once no kept tries contain them anymore. See [`Pallet::prune_state_tries`].
+pub struct StateTrieNodeStorage<T: Config> {
+    block_number: u64,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Config> StateTrieNodeStorage<T> {
+    /// Storage of the nodes updated by the given block.
+    pub fn for_block(block_number: u64) -> Self {
+        Self { block_number, _phantom: PhantomData }
+    }
+}
+
+impl<T: Config> Default for StateTrieNodeStorage<T> {
+    /// Storage to read the tries from, which doesn't update them.
+    fn default() -> Self {
+        Self::for_block(0)
+    }
+}
+
+impl<T: Config> NodeStorage for StateTrieNodeStorage<T> {
+    fn get_node(&self, hash: &Felt252Wrapper) -> Option<ProofNode> {
+        StateTrieNodes::<T>::get(hash)
+    }
+
+    fn insert_node(&mut self, hash: Felt252Wrapper, node: ProofNode) {
+        StateTrieNodeReferences::<T>::mutate(hash, |references| {
+            if *references == 0 {
+                StateTrieNodes::<T>::insert(hash, node);
+            }
+            *references = references.saturating_add(1);
+        });
+    }
+
+    fn release_node(&mut self, hash: Felt252Wrapper) {
+        StateTrieReleasedNodes::<T>::append(self.block_number, hash);
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Apply the state changes of the block to the state tries, and return the new global state
+    /// root, or `None` if it is not available.
+    ///
+    /// Only the nodes along the paths of the updated leaves are loaded and written, so that the
+    /// cost of the commitment is proportional to the size of the state diff.
+    ///
+    /// The tries are updated in a storage transaction, which is rolled back should a node of the
+    /// tries be missing or invalid. The tries are then built again from the state by a migration.
+    /// While they are built, the blocks don't include Starknet transactions, see
+    /// [`Pallet::state_tries_available`], and commit the same roots as their parent, whose state
+    /// they keep. The root of a block whose parent tries are not available, or whose tries update
+    /// failed, is not available: the block has no roots in `StateTreeRoots`.
+    pub(crate) fn commit_state(block_number: u64) -> Option<Felt252Wrapper> {
+        let storage_changes: Vec<_> = PendingStorageChanges::<T>::drain().collect();
+        let class_changes = PendingClassChanges::<T>::take();
+        let state_changed = !storage_changes.is_empty() || !class_changes.is_empty();
+
+        let parent_roots = match block_number.checked_sub(1) {
+            Some(parent_block_number) => StateTreeRoots::<T>::get(parent_block_number),
+            // The genesis block commits the genesis state from the empty tries
+            None => Some(Default::default()),
+        };
+        let roots = match parent_roots {
+            _ if migration::builds_state_tries::<T>() => parent_roots.filter(|_| !state_changed),
+            Some(parent_roots) => {
+                let update: Result<_, DispatchError> = with_transaction(|| {
+                    match Self::update_state_tries(block_number, parent_roots, storage_changes, class_changes) {
+                        Ok(roots) => TransactionOutcome::Commit(Ok(roots)),
+                        Err(e) => {
+                            log!(error, "The state tries can't be updated ({:?}), building them again", e);
+                            TransactionOutcome::Rollback(Err(DispatchError::Other("state tries update failed")))
+                        }
+                    }
+                });
+                if update.is_err() {
+                    migration::schedule_state_tries_build::<T>();
+                }
+                update.ok()
+            }
+            None => {
+                log!(error, "State tries of the parent block are missing, building the state tries again");
+                migration::schedule_state_tries_build::<T>();
+                None
+            }
+        };
+
+        if let Some(roots) = roots {
+            StateTreeRoots::<T>::insert(block_number, roots);
+        }
+        Self::prune_state_tries(block_number);
+
+        roots.map(|(contracts_tree_root, classes_tree_root)| {
+            calculate_global_state_root::<PoseidonHasher>(contracts_tree_root, classes_tree_root)
+        })
+    }
+
+    /// Whether the block being built can update the state tries of its parent, and thus include
+    /// Starknet transactions.
+    ///
+    /// The tries can't be updated while they are built by a migration, nor when the tries of the
+    /// parent block are missing, until they are built again.
+    pub fn state_tries_available() -> bool {
+        let block_number: u64 = frame_system::Pallet::<T>::block_number().unique_saturated_into();
+        let parent_tries_exist = block_number
+            .checked_sub(1)
+            .map_or(true, |parent_block_number| StateTreeRoots::<T>::contains_key(parent_block_number));
+        parent_tries_exist && !migration::builds_state_tries::<T>()
+    }
+
+    /// Apply state changes to the tries with the given roots, and return their new roots.
+    fn update_state_tries(
+        block_number: u64,
+        (contracts_tree_root, classes_tree_root): (Felt252Wrapper, Felt252Wrapper),
+        storage_changes: Vec<(ContractAddress, Vec<StorageSlot>)>,
+        class_changes: Vec<(ClassHash, CompiledClassHash)>,
+    ) -> Result<(Felt252Wrapper, Felt252Wrapper), TrieError> {
+        let mut trie = StateTrie::<PedersenHasher, _>::new(StateTrieNodeStorage::<T>::for_block(block_number));
+        let mut contract_state_hashes = Vec::new();
+        for (contract_address, storage_slots) in storage_changes {
+            let storage_root = Self::contract_state_root_by_address(contract_address).unwrap_or_default();
+            let storage_root =
+                trie.update(storage_root, storage_slots.into_iter().map(|(key, value)| (key.0.into(), value)))?;
+            ContractsStateRoots::<T>::insert(contract_address, storage_root);
+            contract_state_hashes.push((contract_address.into(), Self::contract_state_hash(contract_address)));
+        }
+        let contracts_tree_root = trie.update(contracts_tree_root, contract_state_hashes)?;
+
+        let class_leaves = class_changes.into_iter().map(|(class_hash, compiled_class_hash)| {
+            (class_hash.into(), calculate_class_commitment_leaf_hash::<PoseidonHasher>(compiled_class_hash.into()))
+        });
+        let classes_tree_root = StateTrie::<PoseidonHasher, _>::new(StateTrieNodeStorage::<T>::for_block(block_number))
+            .update(classes_tree_root, class_leaves)?;
+
+        Ok((contracts_tree_root, classes_tree_root))
+    }
+
+    /// The leaf of a contract in the contracts tree, from its class hash, nonce and storage root.
+    pub(crate) fn contract_state_hash(contract_address: ContractAddress) -> Felt252Wrapper {
+        calculate_contract_state_hash::<PedersenHasher>(
+            Self::contract_class_hash_by_address(contract_address).into(),
+            Self::contract_state_root_by_address(contract_address).unwrap_or_default(),
+            Self::nonce(contract_address).into(),
+        )
+    }
+
+    /// Delete the tries which are not kept anymore once a block is stored, along with the nodes
+    /// which no kept tries contain.
+    ///
+    /// A node released by the update of a block is still part of the tries of its parent. It is
+    /// dereferenced once these tries are deleted, and deleted itself if it isn't referenced by any
+    /// newer tries.
+    fn prune_state_tries(block_number: u64) {
+        let Some(pruned_block_number) = block_number.checked_sub(T::StateTriesHistory::get().max(1)) else {
+            return;
+        };
+
+        StateTreeRoots::<T>::remove(pruned_block_number);
+        for hash in StateTrieReleasedNodes::<T>::take(pruned_block_number + 1) {
+            StateTrieNodeReferences::<T>::mutate_exists(hash, |references| {
+                *references = references.and_then(|references| references.checked_sub(1)).filter(|r| *r > 0);
+                if references.is_none() {
+                    StateTrieNodes::<T>::remove(hash);
+                }
+            });
+        }
+    }
+
+    /// Proof of the contract state hash of a contract, in the contracts tree committed by a block.
+    ///
+    /// Returns `None` if the tries of the block are not kept, or are being built.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - The block whose state the proof is against.
+    /// * `contract_address` - The address of the contract.
+    pub fn contract_state_proof(block_number: u64, contract_address: ContractAddress) -> Option<Vec<ProofNode>> {
+        let (contracts_tree_root, _) = Self::state_tree_roots(block_number)?;
+        StateTrie::<PedersenHasher, _>::new(StateTrieNodeStorage::<T>::default())
+            .get_proof(contracts_tree_root, contract_address.into())
+            .ok()
+    }
+
+    /// Proof of a storage slot, in the storage tree with the given root.
+    ///
+    /// Returns `None` if the storage tree is not kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_root` - The root of the storage tree of the contract, as committed by its
+    ///   contract state hash.
+    /// * `key` - The key of the storage slot.
+    pub fn contract_storage_proof(storage_root: Felt252Wrapper, key: StorageKey) -> Option<Vec<ProofNode>> {
+        StateTrie::<PedersenHasher, _>::new(StateTrieNodeStorage::<T>::default())
+            .get_proof(storage_root, key.0.into())
+            .ok()
+    }
+}
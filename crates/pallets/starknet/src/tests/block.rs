@@ -1,18 +1,29 @@
+use alloc::collections::BTreeSet;
 use alloc::sync::Arc;
 
+use blockifier::state::state_api::State;
 use frame_support::assert_ok;
+use mp_commitments::{
+    calculate_classes_tree_root, calculate_contract_state_hash, calculate_global_state_root, calculate_state_tree_root,
+    InvalidProof, ProofNode, StateTrie,
+};
 use mp_digest_log::{ensure_log, find_starknet_block};
 use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
 use mp_sequencer_address::DEFAULT_SEQUENCER_ADDRESS;
 use starknet_api::api_core::{ChainId, ContractAddress, PatriciaKey};
 use starknet_api::block::{BlockNumber, BlockTimestamp};
 use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
 
 use super::mock::default_mock::*;
 use super::mock::*;
+use crate::blockifier_state_adapter::BlockifierStateAdapter;
+use crate::state_trie::StateTrieNodeStorage;
 use crate::tests::constants::FEE_TOKEN_ADDRESS;
 use crate::tests::get_invoke_dummy;
-use crate::{Config, SeqAddrUpdate, SequencerAddress};
+use crate::{CompiledClassHashes, Config, ContractClassHashes, Nonces, SeqAddrUpdate, SequencerAddress, StorageView};
 
 #[test]
 fn store_block_no_pending_transactions_works() {
@@ -41,6 +52,22 @@ fn store_block_no_pending_transactions_works() {
     });
 }
 
+#[test]
+fn store_block_records_the_l1_gas_price() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let header = System::finalize();
+        const BLOCK_NUMBER: u64 = 1;
+        System::initialize(&BLOCK_NUMBER, &header.hash(), &Default::default());
+
+        L1GasPrice::set(30_000_000_000);
+        Starknet::store_block(BLOCK_NUMBER);
+
+        let digest = frame_system::Pallet::<MockRuntime>::digest();
+        let block = find_starknet_block(&digest).unwrap();
+        assert_eq!(block.header().l1_gas_price, 30_000_000_000);
+    });
+}
+
 #[test]
 fn store_block_with_pending_transactions_works() {
     new_test_ext::<MockRuntime>().execute_with(|| {
@@ -82,6 +109,112 @@ fn store_block_with_pending_transactions_works() {
     });
 }
 
+#[test]
+fn store_block_commits_the_global_state_root() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let header = System::finalize();
+        System::initialize(&1, &header.hash(), &Default::default());
+
+        Starknet::store_block(1);
+        let first_root = stored_block_state_root();
+        assert_eq!(first_root, state_root_from_scratch());
+
+        // update the state of an existing contract and of a new one
+        let header = System::finalize();
+        System::initialize(&2, &header.hash(), &Default::default());
+
+        let fee_token_address = ContractAddress::try_from(StarkFelt::try_from(FEE_TOKEN_ADDRESS).unwrap()).unwrap();
+        let new_contract_address = ContractAddress(PatriciaKey(StarkFelt::from(0x1234_u64)));
+        let key = StorageKey(PatriciaKey(StarkFelt::from(42_u64)));
+        let mut state = BlockifierStateAdapter::<MockRuntime>::default();
+        state.set_storage_at(fee_token_address, key, StarkFelt::from(1_u64));
+        state.set_storage_at(new_contract_address, key, StarkFelt::from(2_u64));
+        state.increment_nonce(new_contract_address).unwrap();
+
+        Starknet::store_block(2);
+        let second_root = stored_block_state_root();
+        assert_ne!(first_root, second_root);
+        assert_eq!(second_root, state_root_from_scratch());
+
+        // the state committed by the first block can still be proven against
+        let (first_contracts_tree_root, first_classes_tree_root) = Starknet::state_tree_roots(1).unwrap();
+        let (second_contracts_tree_root, second_classes_tree_root) = Starknet::state_tree_roots(2).unwrap();
+        assert_eq!(
+            calculate_global_state_root::<PoseidonHasher>(first_contracts_tree_root, first_classes_tree_root),
+            first_root
+        );
+        assert_eq!(
+            calculate_global_state_root::<PoseidonHasher>(second_contracts_tree_root, second_classes_tree_root),
+            second_root
+        );
+
+        let first_proof = Starknet::contract_state_proof(1, new_contract_address).unwrap();
+        let second_proof = Starknet::contract_state_proof(2, new_contract_address).unwrap();
+        assert_eq!(
+            verify_contract_state_proof(first_contracts_tree_root, new_contract_address, &first_proof),
+            Ok(None)
+        );
+        assert_eq!(
+            verify_contract_state_proof(second_contracts_tree_root, new_contract_address, &second_proof),
+            Ok(Some(Starknet::contract_state_hash(new_contract_address)))
+        );
+        assert_eq!(
+            verify_contract_state_proof(first_contracts_tree_root, new_contract_address, &second_proof),
+            Err(InvalidProof)
+        );
+    });
+}
+
+/// Verify a proof returned by `contract_state_proof` against the root of a contracts tree
+fn verify_contract_state_proof(
+    contracts_tree_root: Felt252Wrapper,
+    contract_address: ContractAddress,
+    proof: &[ProofNode],
+) -> Result<Option<Felt252Wrapper>, InvalidProof> {
+    StateTrie::<PedersenHasher, StateTrieNodeStorage<MockRuntime>>::verify_proof(
+        contracts_tree_root,
+        contract_address.into(),
+        proof,
+    )
+}
+
+/// Return the state root committed by the block stored in the digest
+pub(super) fn stored_block_state_root() -> Felt252Wrapper {
+    let digest = frame_system::Pallet::<MockRuntime>::digest();
+    find_starknet_block(&digest).unwrap().header().global_state_root.into()
+}
+
+/// Compute the global state root from the whole state, without the trees kept by the pallet
+pub(super) fn state_root_from_scratch() -> Felt252Wrapper {
+    let mut addresses: BTreeSet<ContractAddress> = ContractClassHashes::<MockRuntime>::iter_keys().collect();
+    addresses.extend(Nonces::<MockRuntime>::iter_keys());
+    addresses.extend(StorageView::<MockRuntime>::iter_keys().map(|(address, _)| address));
+
+    let contracts: Vec<(Felt252Wrapper, Felt252Wrapper)> = addresses
+        .into_iter()
+        .map(|address| {
+            let storage: Vec<(Felt252Wrapper, Felt252Wrapper)> = StorageView::<MockRuntime>::iter()
+                .filter(|((contract_address, _), _)| *contract_address == address)
+                .map(|((_, key), value)| (key.0.into(), value.into()))
+                .collect();
+            let contract_state_hash = calculate_contract_state_hash::<PedersenHasher>(
+                Starknet::contract_class_hash_by_address(address).into(),
+                calculate_state_tree_root::<PedersenHasher>(&storage),
+                Starknet::nonce(address).into(),
+            );
+            (address.into(), contract_state_hash)
+        })
+        .collect();
+    let classes: Vec<(Felt252Wrapper, Felt252Wrapper)> = CompiledClassHashes::<MockRuntime>::iter()
+        .map(|(class_hash, compiled_class_hash)| (class_hash.into(), compiled_class_hash.into()))
+        .collect();
+
+    calculate_global_state_root::<PoseidonHasher>(
+        calculate_state_tree_root::<PedersenHasher>(&contracts),
+        calculate_classes_tree_root::<PoseidonHasher>(&classes),
+    )
+}
+
 #[test]
 fn get_block_context_works() {
     new_test_ext::<MockRuntime>().execute_with(|| {
@@ -0,0 +1,53 @@
+use frame_support::{assert_err, assert_ok};
+use mp_felt::Felt252Wrapper;
+use mp_transactions::InvokeTransaction;
+use sp_runtime::traits::ValidateUnsigned;
+use sp_runtime::transaction_validity::InvalidTransaction;
+
+use super::get_invoke_dummy;
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::BlockResourcesUsage;
+
+#[test]
+fn transactions_resources_are_accumulated_until_the_end_of_the_block() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let transaction: InvokeTransaction = get_invoke_dummy(Felt252Wrapper::ZERO).into();
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        let usage = Starknet::block_resources_usage();
+        assert!(usage.steps > 0);
+        assert!(usage.l1_gas > 0);
+
+        let transaction: InvokeTransaction = get_invoke_dummy(Felt252Wrapper::ONE).into();
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        let new_usage = Starknet::block_resources_usage();
+        assert!(new_usage.steps > usage.steps);
+        assert!(new_usage.l1_gas > usage.l1_gas);
+
+        run_to_block(3);
+        assert_eq!(Starknet::block_resources_usage(), Default::default());
+    });
+}
+
+#[test]
+fn starknet_transactions_exhaust_resources_once_a_limit_is_reached() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let invoke = crate::Call::invoke { transaction: get_invoke_dummy(Felt252Wrapper::ZERO).into() };
+        assert_ok!(Starknet::pre_dispatch(&invoke));
+
+        let mut usage = Starknet::block_resources_usage();
+        usage.l1_gas = Starknet::block_resources_limits().l1_gas;
+        BlockResourcesUsage::<MockRuntime>::put(usage);
+
+        assert!(Starknet::block_resources_exhausted());
+        assert_err!(Starknet::pre_dispatch(&invoke), InvalidTransaction::ExhaustsResources);
+        // Other calls are not bounded by the Starknet resources
+        assert_ok!(Starknet::pre_dispatch(&crate::Call::set_last_known_eth_block { eth_block_number: 1 }));
+    });
+}
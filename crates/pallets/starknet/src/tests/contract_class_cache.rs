@@ -0,0 +1,71 @@
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{State, StateReader};
+use parity_scale_codec::Encode;
+use starknet_api::api_core::ClassHash;
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use super::utils::get_contract_class;
+use crate::blockifier_state_adapter::BlockifierStateAdapter;
+use crate::{ContractClassFingerprints, ContractClasses, Pallet};
+
+#[test]
+fn compiled_contract_class_is_read_again_when_the_class_changes() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        // A class hash used by no other test, as the cache is shared by the whole process
+        let class_hash = ClassHash(StarkFelt::try_from("0xca11c0de").unwrap());
+        let erc20_class = get_contract_class("ERC20.json", 0);
+        let hello_starknet_class = get_contract_class("HelloStarknet.casm.json", 1);
+
+        let mut state_adapter = BlockifierStateAdapter::<MockRuntime>::default();
+        assert!(matches!(
+            state_adapter.get_compiled_contract_class(&class_hash),
+            Err(StateError::UndeclaredClassHash(_))
+        ));
+
+        state_adapter.set_contract_class(&class_hash, erc20_class.clone()).unwrap();
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), erc20_class);
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), erc20_class);
+
+        // Storage changed behind the cache, e.g. by a reorg
+        ContractClasses::<MockRuntime>::insert(class_hash, hello_starknet_class.clone());
+        ContractClassFingerprints::<MockRuntime>::insert(
+            class_hash,
+            sp_io::hashing::blake2_256(&hello_starknet_class.encode()),
+        );
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), hello_starknet_class);
+
+        ContractClasses::<MockRuntime>::remove(class_hash);
+        ContractClassFingerprints::<MockRuntime>::remove(class_hash);
+        assert!(matches!(
+            state_adapter.get_compiled_contract_class(&class_hash),
+            Err(StateError::UndeclaredClassHash(_))
+        ));
+    });
+}
+
+#[test]
+fn compiled_contract_class_without_fingerprint_is_cached() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        // A class hash used by no other test, as the cache is shared by the whole process
+        let class_hash = ClassHash(StarkFelt::try_from("0xca11c0df").unwrap());
+        let erc20_class = get_contract_class("ERC20.json", 0);
+        let hello_starknet_class = get_contract_class("HelloStarknet.casm.json", 1);
+
+        // Declared before the fingerprints were introduced
+        ContractClasses::<MockRuntime>::insert(class_hash, erc20_class.clone());
+        let mut state_adapter = BlockifierStateAdapter::<MockRuntime>::default();
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), erc20_class);
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), erc20_class);
+
+        ContractClasses::<MockRuntime>::insert(class_hash, hello_starknet_class.clone());
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), hello_starknet_class);
+
+        Pallet::<MockRuntime>::store_contract_class(&class_hash, &erc20_class);
+        assert!(ContractClassFingerprints::<MockRuntime>::get(class_hash).is_some());
+        assert_eq!(state_adapter.get_compiled_contract_class(&class_hash).unwrap(), erc20_class);
+    });
+}
@@ -36,11 +36,11 @@ fn given_contract_declare_tx_works_once_not_twice() {
             signature: vec![],
         };
 
-        assert_ok!(Starknet::declare(none_origin.clone(), transaction.clone().into(), erc20_class.clone()));
+        assert_ok!(Starknet::declare(none_origin.clone(), transaction.clone().into(), erc20_class.clone(), None));
         // TODO: Uncomment once we have ABI support
         // assert_eq!(Starknet::contract_class_by_class_hash(erc20_class_hash), erc20_class);
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::ClassHashAlreadyDeclared
         );
     });
@@ -70,7 +70,7 @@ fn given_contract_declare_tx_fails_sender_not_deployed() {
         };
 
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::AccountNotDeployed
         );
     })
@@ -90,10 +90,14 @@ fn given_contract_declare_on_openzeppelin_account_then_it_works() {
 
         assert_ok!(Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class.clone() },
+            &crate::Call::declare {
+                transaction: transaction.clone(),
+                contract_class: erc20_class.clone(),
+                sierra_class: None,
+            },
         ));
 
-        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone()));
+        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone(), None));
         assert_eq!(Starknet::contract_class_by_class_hash(ClassHash::from(erc20_class_hash)).unwrap(), erc20_class);
     });
 }
@@ -121,13 +125,17 @@ fn given_contract_declare_on_openzeppelin_account_with_incorrect_signature_then_
         assert_matches!(
             Starknet::validate_unsigned(
                 TransactionSource::InBlock,
-                &crate::Call::declare { transaction: transaction.clone().into(), contract_class: erc20_class.clone() },
+                &crate::Call::declare {
+                    transaction: transaction.clone().into(),
+                    contract_class: erc20_class.clone(),
+                    sierra_class: None,
+                },
             ),
             Err(TransactionValidityError::Invalid(_))
         );
 
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::TransactionExecutionFailed
         );
     });
@@ -147,11 +155,15 @@ fn given_contract_declare_on_braavos_account_then_it_works() {
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class.clone() },
+            &crate::Call::declare {
+                transaction: transaction.clone(),
+                contract_class: erc20_class.clone(),
+                sierra_class: None,
+            },
         );
         assert_ok!(validate_result);
 
-        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone()));
+        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone(), None));
         assert_eq!(Starknet::contract_class_by_class_hash(ClassHash::from(erc20_class_hash)).unwrap(), erc20_class);
     });
 }
@@ -179,13 +191,17 @@ fn given_contract_declare_on_braavos_account_with_incorrect_signature_then_it_fa
         assert_matches!(
             Starknet::validate_unsigned(
                 TransactionSource::InBlock,
-                &crate::Call::declare { transaction: transaction.clone().into(), contract_class: erc20_class.clone() },
+                &crate::Call::declare {
+                    transaction: transaction.clone().into(),
+                    contract_class: erc20_class.clone(),
+                    sierra_class: None,
+                },
             ),
             Err(TransactionValidityError::Invalid(_))
         );
 
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::TransactionExecutionFailed
         );
     });
@@ -205,11 +221,15 @@ fn given_contract_declare_on_argent_account_then_it_works() {
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class.clone() },
+            &crate::Call::declare {
+                transaction: transaction.clone(),
+                contract_class: erc20_class.clone(),
+                sierra_class: None,
+            },
         );
         assert_ok!(validate_result);
 
-        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone()));
+        assert_ok!(Starknet::declare(none_origin, transaction, erc20_class.clone(), None));
         assert_eq!(Starknet::contract_class_by_class_hash(ClassHash::from(erc20_class_hash)).unwrap(), erc20_class);
     });
 }
@@ -237,13 +257,17 @@ fn given_contract_declare_on_argent_account_with_incorrect_signature_then_it_fai
         assert_matches!(
             Starknet::validate_unsigned(
                 TransactionSource::InBlock,
-                &crate::Call::declare { transaction: transaction.clone().into(), contract_class: erc20_class.clone() },
+                &crate::Call::declare {
+                    transaction: transaction.clone().into(),
+                    contract_class: erc20_class.clone(),
+                    sierra_class: None,
+                },
             ),
             Err(TransactionValidityError::Invalid(_))
         );
 
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::TransactionExecutionFailed
         );
     });
@@ -280,11 +304,12 @@ fn given_contract_declare_on_cairo_1_no_validate_account_then_it_works() {
             TransactionSource::InBlock,
             &crate::Call::declare {
                 transaction: transaction.clone().into(),
-                contract_class: hello_starknet_class.clone()
+                contract_class: hello_starknet_class.clone(),
+                sierra_class: None,
             },
         ));
 
-        assert_ok!(Starknet::declare(none_origin, transaction.into(), hello_starknet_class.clone()));
+        assert_ok!(Starknet::declare(none_origin, transaction.into(), hello_starknet_class.clone(), None));
         assert_eq!(
             Starknet::contract_class_by_class_hash(ClassHash::from(hello_starknet_class_hash)).unwrap(),
             hello_starknet_class
@@ -303,7 +328,7 @@ fn test_verify_tx_longevity() {
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction, contract_class: erc20_class },
+            &crate::Call::declare { transaction, contract_class: erc20_class, sierra_class: None },
         )
         .unwrap();
 
@@ -323,7 +348,7 @@ fn test_verify_no_require_tag() {
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class },
+            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class, sierra_class: None },
         )
         .unwrap();
 
@@ -351,7 +376,7 @@ fn test_verify_require_tag() {
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
-            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class },
+            &crate::Call::declare { transaction: transaction.clone(), contract_class: erc20_class, sierra_class: None },
         )
         .unwrap();
 
@@ -382,7 +407,7 @@ fn test_verify_nonce_in_unsigned_tx() {
 
         let tx_sender = (*transaction.sender_address()).into();
         let tx_source = TransactionSource::InBlock;
-        let call = crate::Call::declare { transaction, contract_class: erc20_class };
+        let call = crate::Call::declare { transaction, contract_class: erc20_class, sierra_class: None };
 
         assert!(Starknet::validate_unsigned(tx_source, &call).is_ok());
 
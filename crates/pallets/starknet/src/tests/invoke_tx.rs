@@ -2,7 +2,10 @@ use blockifier::abi::abi_utils::get_storage_var_address;
 use frame_support::{assert_err, assert_ok};
 use mp_felt::Felt252Wrapper;
 use mp_transactions::compute_hash::ComputeTransactionHash;
-use mp_transactions::{InvokeTransaction, InvokeTransactionV1};
+use mp_transactions::{
+    DataAvailabilityMode, InvokeTransaction, InvokeTransactionV1, InvokeTransactionV3, ResourceBounds,
+    ResourceBoundsMapping, Transaction,
+};
 use pretty_assertions::assert_eq;
 use sp_runtime::traits::ValidateUnsigned;
 use sp_runtime::transaction_validity::{
@@ -11,7 +14,7 @@ use sp_runtime::transaction_validity::{
 use starknet_api::api_core::{ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::{Event as StarknetEvent, EventContent, EventData, EventKey, Fee, TransactionHash};
+use starknet_api::transaction::{Event as StarknetEvent, EventContent, EventData, EventKey, TransactionHash};
 use starknet_core::utils::get_selector_from_name;
 use starknet_crypto::FieldElement;
 
@@ -58,20 +61,29 @@ fn given_hardcoded_contract_run_invoke_tx_then_it_works() {
 
         let transaction: InvokeTransaction = get_invoke_dummy(Felt252Wrapper::ZERO).into();
 
-        let tx = Message {
+        let (tx, paid_fee_on_l1) = Message {
             topics: vec![
                 "0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b".to_owned(),
                 "0x0000000000000000000000000000000000000000000000000000000000000001".to_owned(),
                 "0x0000000000000000000000000000000000000000000000000000000000000001".to_owned(),
                 "0x01310e2c127c3b511c5ac0fd7949d544bb4d75b8bc83aaeb357e712ecf582771".to_owned(),
             ],
-            data: "0x0000000000000000000000000000000000000000000000000000000000000001".to_owned(),
+            // Payload offset, nonce, fee, payload length and payload
+            data: concat!(
+                "0x",
+                "0000000000000000000000000000000000000000000000000000000000000060",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000064",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+            )
+            .to_owned(),
         }
         .try_into_transaction()
         .unwrap();
 
         assert_ok!(Starknet::invoke(none_origin.clone(), transaction));
-        assert_ok!(Starknet::consume_l1_message(none_origin, tx, Fee(100)));
+        assert_ok!(Starknet::consume_l1_message(none_origin, tx, paid_fee_on_l1));
 
         let pending_txs = Starknet::pending();
         pretty_assertions::assert_eq!(pending_txs.len(), 2);
@@ -110,6 +122,47 @@ fn given_hardcoded_contract_run_invoke_tx_then_it_works() {
     });
 }
 
+#[test]
+fn given_hardcoded_contract_run_invoke_v3_tx_then_it_pays_its_fee_in_strk() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let none_origin = RuntimeOrigin::none();
+
+        let invoke_v1 = get_invoke_dummy(Felt252Wrapper::ZERO);
+        let transaction = InvokeTransactionV3 {
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds { max_amount: u32::MAX as u64, max_price_per_unit: u32::MAX as u128 },
+                l2_gas: ResourceBounds::default(),
+            },
+            tip: 0,
+            signature: invoke_v1.signature,
+            nonce: invoke_v1.nonce,
+            sender_address: invoke_v1.sender_address,
+            calldata: invoke_v1.calldata,
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+        };
+        let chain_id = Starknet::chain_id();
+        let tx_hash = transaction.compute_hash::<<MockRuntime as Config>::SystemHash>(chain_id, false);
+
+        assert_ok!(Starknet::invoke(none_origin, InvokeTransaction::V3(transaction.clone())));
+
+        assert_eq!(Starknet::pending(), vec![Transaction::Invoke(InvokeTransaction::V3(transaction))]);
+        assert_eq!(Starknet::pending_hashes(), vec![TransactionHash(tx_hash.into())]);
+        assert_eq!(Starknet::nonce(ContractAddress::from(invoke_v1.sender_address)), Nonce(StarkFelt::from(1u128)));
+
+        // The fee is transferred by the STRK token
+        let fee_transfer_event: Event<MockRuntime> = System::events().last().unwrap().event.clone().try_into().unwrap();
+        match fee_transfer_event {
+            Event::StarknetEvent(event) => assert_eq!(event.from_address, Starknet::strk_fee_token_address()),
+            _ => panic!("The last event should be the fee transfer"),
+        }
+    });
+}
+
 #[test]
 fn given_hardcoded_contract_run_invoke_tx_then_event_is_emitted() {
     new_test_ext::<MockRuntime>().execute_with(|| {
@@ -1,9 +1,10 @@
-use frame_support::assert_err;
+use frame_support::{assert_err, assert_ok};
 use mp_felt::Felt252Wrapper;
 use mp_transactions::{DeclareTransactionV1, HandleL1MessageTransaction};
 use sp_runtime::traits::ValidateUnsigned;
-use sp_runtime::transaction_validity::TransactionSource;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionPriority, TransactionSource};
 use starknet_api::transaction::Fee;
+use starknet_core::utils::get_selector_from_name;
 
 use super::mock::default_mock::*;
 use super::mock::*;
@@ -32,24 +33,29 @@ fn given_contract_l1_message_fails_sender_not_deployed() {
         };
 
         assert_err!(
-            Starknet::declare(none_origin, transaction.into(), erc20_class),
+            Starknet::declare(none_origin, transaction.into(), erc20_class, None),
             Error::<MockRuntime>::AccountNotDeployed
         );
     })
 }
 
+/// L1 handler of the `l1_handler` test contract deployed at genesis, which only accepts messages
+/// from address 1 carrying 1
+fn get_l1_handler_dummy(nonce: u64) -> HandleL1MessageTransaction {
+    HandleL1MessageTransaction {
+        nonce,
+        contract_address: Felt252Wrapper::ONE,
+        entry_point_selector: Felt252Wrapper::from(get_selector_from_name("assert_calldata_is_one").unwrap()),
+        calldata: vec![Felt252Wrapper::ONE, Felt252Wrapper::ONE],
+    }
+}
+
 #[test]
-#[ignore = "l1 handler validation not implemented yet"]
 fn verify_tx_longevity() {
     new_test_ext::<MockRuntime>().execute_with(|| {
         basic_test_setup(2);
 
-        let transaction = HandleL1MessageTransaction {
-            nonce: Default::default(),
-            contract_address: Default::default(),
-            entry_point_selector: Default::default(),
-            calldata: Default::default(),
-        };
+        let transaction = get_l1_handler_dummy(0);
 
         let validate_result = Starknet::validate_unsigned(
             TransactionSource::InBlock,
@@ -59,3 +65,95 @@ fn verify_tx_longevity() {
         assert!(validate_result.unwrap().longevity == TransactionLongevity::get());
     });
 }
+
+#[test]
+fn l1_handler_transactions_go_first_and_are_tagged_by_nonce() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let validate = |nonce| {
+            Starknet::validate_unsigned(
+                TransactionSource::InBlock,
+                &crate::Call::consume_l1_message { transaction: get_l1_handler_dummy(nonce), paid_fee_on_l1: Fee(100) },
+            )
+            .unwrap()
+        };
+
+        let (first, second) = (validate(0), validate(1));
+        assert_eq!(first.priority, TransactionPriority::MAX);
+        assert!(first.requires.is_empty());
+        assert_ne!(first.provides, second.provides);
+    });
+}
+
+#[test]
+fn l1_handler_transaction_without_fee_or_target_is_invalid() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let validate = |transaction, paid_fee_on_l1| {
+            Starknet::validate_unsigned(
+                TransactionSource::InBlock,
+                &crate::Call::consume_l1_message { transaction, paid_fee_on_l1 },
+            )
+        };
+
+        assert_eq!(validate(get_l1_handler_dummy(0), Fee(0)), Err(InvalidTransaction::Payment.into()));
+
+        let undeployed_target =
+            HandleL1MessageTransaction { contract_address: Felt252Wrapper::TWO, ..get_l1_handler_dummy(0) };
+        assert_eq!(validate(undeployed_target, Fee(100)), Err(InvalidTransaction::Call.into()));
+    });
+}
+
+#[test]
+fn l1_message_cannot_be_replayed() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let paid_fee_on_l1 = Fee(u128::MAX);
+        assert_ok!(Starknet::consume_l1_message(RuntimeOrigin::none(), get_l1_handler_dummy(0), paid_fee_on_l1));
+        assert!(Starknet::l1_message_consumed(0));
+
+        assert_err!(
+            Starknet::consume_l1_message(RuntimeOrigin::none(), get_l1_handler_dummy(0), paid_fee_on_l1),
+            Error::<MockRuntime>::L1MessageAlreadyExecuted
+        );
+        assert_eq!(
+            Starknet::validate_unsigned(
+                TransactionSource::InBlock,
+                &crate::Call::consume_l1_message { transaction: get_l1_handler_dummy(0), paid_fee_on_l1 },
+            ),
+            Err(InvalidTransaction::Stale.into())
+        );
+    });
+}
+
+#[test]
+fn l1_message_fails_when_the_fee_paid_on_l1_is_too_low() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_err!(
+            Starknet::consume_l1_message(RuntimeOrigin::none(), get_l1_handler_dummy(0), Fee(1)),
+            Error::<MockRuntime>::TransactionExecutionFailed
+        );
+        assert!(!Starknet::l1_message_consumed(0));
+    });
+}
+
+#[test]
+fn l1_handler_transaction_from_the_network_is_invalid() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let call = crate::Call::consume_l1_message { transaction: get_l1_handler_dummy(0), paid_fee_on_l1: Fee(100) };
+
+        assert_eq!(
+            Starknet::validate_unsigned(TransactionSource::External, &call),
+            Err(InvalidTransaction::Call.into())
+        );
+        let valid_transaction = Starknet::validate_unsigned(TransactionSource::Local, &call).unwrap();
+        assert!(!valid_transaction.propagate);
+    });
+}
@@ -1,3 +1,7 @@
+use mp_felt::Felt252Wrapper;
+use mp_transactions::HandleL1MessageTransaction;
+use starknet_api::transaction::Fee;
+
 use crate::message::Message;
 use crate::offchain_worker::OffchainWorkerError;
 
@@ -24,3 +28,47 @@ fn test_try_into_transaction_empty_data_should_fail() {
         Message { topics: vec![hex.clone(), hex.clone(), hex.clone(), hex], data: "".to_owned() };
     assert_eq!(test_message.try_into_transaction().unwrap_err(), OffchainWorkerError::EmptyData);
 }
+
+#[test]
+fn test_try_into_transaction_decodes_payload_nonce_and_fee() {
+    let word = |value: u128| format!("{value:064x}");
+    // Payload offset, nonce, fee, payload length and payload
+    let data = format!("0x{}{}{}{}{}{}", word(0x60), word(7), word(0x2386f26fc10000), word(2), word(1), word(2));
+    let test_message = Message {
+        topics: vec![
+            "0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b".to_owned(),
+            format!("0x{}", word(0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419)),
+            format!("0x{}", word(0x1)),
+            format!("0x{}", word(0x2)),
+        ],
+        data,
+    };
+
+    let (transaction, paid_fee_on_l1) = test_message.try_into_transaction().unwrap();
+
+    assert_eq!(
+        transaction,
+        HandleL1MessageTransaction {
+            nonce: 7,
+            contract_address: Felt252Wrapper::ONE,
+            entry_point_selector: Felt252Wrapper::TWO,
+            calldata: vec![
+                Felt252Wrapper::from_hex_be("0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419").unwrap(),
+                Felt252Wrapper::ONE,
+                Felt252Wrapper::TWO,
+            ],
+        }
+    );
+    assert_eq!(paid_fee_on_l1, Fee(0x2386f26fc10000));
+}
+
+#[test]
+fn test_try_into_transaction_truncated_payload_should_fail() {
+    let word = |value: u128| format!("{value:064x}");
+    // The payload is announced with 2 elements but only has 1
+    let data = format!("0x{}{}{}{}{}", word(0x60), word(7), word(1), word(2), word(1));
+    let hex = format!("0x{}", word(1));
+    let test_message = Message { topics: vec![hex.clone(), hex.clone(), hex.clone(), hex], data };
+
+    assert_eq!(test_message.try_into_transaction().unwrap_err(), OffchainWorkerError::ToTransactionError);
+}
@@ -0,0 +1,201 @@
+use blockifier::state::state_api::State;
+use frame_support::storage::migration::clear_storage_prefix;
+use frame_support::storage::{storage_prefix, unhashed};
+use frame_support::traits::{Get, Hooks};
+use frame_support::{Blake2_128Concat, StorageHasher};
+use mp_storage::{
+    StarknetStorageSchemaVersion, PALLET_STARKNET, PALLET_STARKNET_SCHEMA, STARKNET_CONTRACT_CLASS_HASH,
+    STARKNET_NONCE, STARKNET_STORAGE,
+};
+use parity_scale_codec::Encode;
+use starknet_api::api_core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use super::set_nonce;
+use crate::blockifier_state_adapter::BlockifierStateAdapter;
+use crate::{Config, OngoingMigration, StorageView};
+
+fn contract_address(value: u8) -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::from(value as u128)))
+}
+
+fn storage_key(value: u8) -> StorageKey {
+    StorageKey(PatriciaKey(StarkFelt::from(value as u128)))
+}
+
+/// Raw storage key of a map entry in the schema V1 layout, using the `Identity` hasher
+fn v1_key<K: Encode>(item: &[u8], key: &K) -> Vec<u8> {
+    [storage_prefix(PALLET_STARKNET, item).as_slice(), &key.encode()].concat()
+}
+
+fn onchain_schema() -> StarknetStorageSchemaVersion {
+    unhashed::get_or_default(PALLET_STARKNET_SCHEMA)
+}
+
+/// Replace the genesis storage by a schema V1 one
+fn setup_v1_storage(address: ContractAddress, class_hash: ClassHash, nonce: Nonce, key: StorageKey, value: StarkFelt) {
+    for item in [STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE] {
+        let _ = clear_storage_prefix(PALLET_STARKNET, item, &[], None, None);
+    }
+
+    unhashed::put(&v1_key(STARKNET_CONTRACT_CLASS_HASH, &address), &class_hash);
+    unhashed::put(&v1_key(STARKNET_NONCE, &address), &nonce);
+    unhashed::put(&v1_key(STARKNET_STORAGE, &(address, key)), &value);
+    unhashed::put(PALLET_STARKNET_SCHEMA, &StarknetStorageSchemaVersion::V1);
+}
+
+#[test]
+fn genesis_uses_schema_v2() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V2);
+    });
+}
+
+#[test]
+fn schema_v2_storage_layout() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let key = (contract_address(1), storage_key(2));
+
+        // Clients build the storage keys themselves to read the storage, so the layout must not change
+        // without a new schema version
+        assert_eq!(
+            StorageView::<MockRuntime>::hashed_key_for(key),
+            [storage_prefix(PALLET_STARKNET, STARKNET_STORAGE).as_slice(), &Blake2_128Concat::hash(&key.encode())]
+                .concat()
+        );
+    });
+}
+
+/// Runs the ongoing migration to its end, one block after the other, and returns the number of
+/// blocks it took.
+fn run_migration() -> u64 {
+    for blocks in 1..=1_000 {
+        Starknet::on_initialize(System::block_number() + blocks);
+        if !OngoingMigration::<MockRuntime>::exists() {
+            return blocks;
+        }
+    }
+    panic!("the migration did not end");
+}
+
+#[test]
+fn runtime_upgrade_migrates_schema_v1_storage() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(1);
+
+        let address = contract_address(1);
+        let class_hash = ClassHash(StarkFelt::from(3_u128));
+        let nonce = Nonce(StarkFelt::from(4_u128));
+        let key = storage_key(5);
+        let value = StarkFelt::from(6_u128);
+        setup_v1_storage(address, class_hash, nonce, key, value);
+
+        Starknet::on_runtime_upgrade();
+
+        // The storage is migrated over the next blocks
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V1);
+        run_migration();
+
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V2);
+        assert_eq!(Starknet::contract_class_hash_by_address(address), class_hash);
+        assert_eq!(Starknet::nonce(address), nonce);
+        assert_eq!(Starknet::storage((address, key)), value);
+        // The schema V1 entries are removed
+        assert!(!unhashed::exists(&v1_key(STARKNET_CONTRACT_CLASS_HASH, &address)));
+        assert!(!unhashed::exists(&v1_key(STARKNET_NONCE, &address)));
+        assert!(!unhashed::exists(&v1_key(STARKNET_STORAGE, &(address, key))));
+    });
+}
+
+#[test]
+fn schema_v1_entries_are_read_and_written_while_they_are_moved() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(1);
+
+        let address = contract_address(1);
+        let class_hash = ClassHash(StarkFelt::from(3_u128));
+        let nonce = Nonce(StarkFelt::from(4_u128));
+        let key = storage_key(5);
+        setup_v1_storage(address, class_hash, nonce, key, StarkFelt::from(6_u128));
+
+        Starknet::on_runtime_upgrade();
+        assert!(OngoingMigration::<MockRuntime>::exists());
+
+        // The entries not moved yet are read at their schema V1 key, and transactions are included
+        assert!(unhashed::exists(&v1_key(STARKNET_NONCE, &address)));
+        assert_eq!(Starknet::contract_class_hash_by_address(address), class_hash);
+        assert_eq!(Starknet::nonce(address), nonce);
+        assert_eq!(Starknet::storage((address, key)), StarkFelt::from(6_u128));
+        assert!(Starknet::state_tries_available());
+
+        // A written entry is not moved over by the migration
+        let value = StarkFelt::from(7_u128);
+        BlockifierStateAdapter::<MockRuntime>::default().set_storage_at(address, key, value);
+        assert!(!unhashed::exists(&v1_key(STARKNET_STORAGE, &(address, key))));
+        assert_eq!(Starknet::storage((address, key)), value);
+
+        run_migration();
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V2);
+        assert_eq!(Starknet::nonce(address), nonce);
+        assert_eq!(Starknet::storage((address, key)), value);
+    });
+}
+
+#[test]
+fn schema_v1_migration_is_bounded_per_block() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(1);
+
+        let address = contract_address(1);
+        setup_v1_storage(
+            address,
+            ClassHash(StarkFelt::from(3_u128)),
+            Nonce::default(),
+            storage_key(0),
+            StarkFelt::from(1_u128),
+        );
+        let n_slots = 5 * <MockRuntime as Config>::MigrationEntriesPerBlock::get() as u8;
+        for slot in 1..n_slots {
+            unhashed::put(&v1_key(STARKNET_STORAGE, &(address, storage_key(slot))), &StarkFelt::from(slot as u128));
+        }
+
+        Starknet::on_runtime_upgrade();
+        Starknet::on_initialize(System::block_number() + 1);
+        Starknet::on_initialize(System::block_number() + 2);
+        Starknet::on_initialize(System::block_number() + 3);
+
+        // The first entries are moved, and the others are still at their schema V1 key
+        let moved =
+            (0..n_slots).filter(|slot| !unhashed::exists(&v1_key(STARKNET_STORAGE, &(address, storage_key(*slot)))));
+        assert!(moved.count() < n_slots as usize);
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V1);
+
+        assert!(run_migration() > 1);
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V2);
+        for slot in 0..n_slots {
+            assert!(!unhashed::exists(&v1_key(STARKNET_STORAGE, &(address, storage_key(slot)))));
+            assert_eq!(Starknet::storage((address, storage_key(slot))), StarkFelt::from(slot.max(1) as u128));
+        }
+    });
+}
+
+#[test]
+fn runtime_upgrade_is_a_noop_on_schema_v2() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(1);
+
+        let address = contract_address(1);
+        let nonce = Nonce(StarkFelt::from(4_u128));
+        set_nonce::<MockRuntime>(&address, &nonce);
+
+        let weight = Starknet::on_runtime_upgrade();
+
+        // The schema version, the state tries version and the state tries of the parent block are read
+        assert_eq!(weight, <MockRuntime as frame_system::Config>::DbWeight::get().reads(3));
+        assert_eq!(onchain_schema(), StarknetStorageSchemaVersion::V2);
+        assert_eq!(Starknet::nonce(address), nonce);
+    });
+}
@@ -80,6 +80,14 @@ macro_rules! mock_runtime {
 				pub const ProtocolVersion: u8 = 0;
                 pub const ChainId: Felt252Wrapper = mp_chain_id::SN_GOERLI_CHAIN_ID;
                 pub const MaxRecursionDepth: u32 = 50;
+                pub const L1MessagesConfirmationDepth: u64 = 5;
+                pub const L1MessagesBlockRange: u64 = 10;
+                pub const MaxBlockSteps: u64 = 40_000_000;
+                pub const MaxBlockBuiltins: u64 = 10_000_000;
+                pub const MaxBlockL1Gas: u64 = 5_000_000;
+                pub static L1GasPrice: u128 = 10;
+                pub const StateTriesHistory: u64 = 3;
+                pub const MigrationEntriesPerBlock: u32 = 16;
             }
 
 			impl pallet_starknet::Config for MockRuntime {
@@ -95,6 +103,24 @@ macro_rules! mock_runtime {
 				type ProtocolVersion = ProtocolVersion;
                 type ChainId = ChainId;
                 type MaxRecursionDepth = MaxRecursionDepth;
+                type L1MessagesConfirmationDepth = L1MessagesConfirmationDepth;
+                type L1MessagesBlockRange = L1MessagesBlockRange;
+                type MaxBlockSteps = MaxBlockSteps;
+                type MaxBlockBuiltins = MaxBlockBuiltins;
+                type MaxBlockL1Gas = MaxBlockL1Gas;
+                type L1GasPrice = L1GasPrice;
+                type SlotDuration = ConstU64<6_000>;
+                type StateTriesHistory = StateTriesHistory;
+                type MigrationEntriesPerBlock = MigrationEntriesPerBlock;
+                type GovernanceOrigin = frame_system::EnsureRoot<u64>;
+			}
+
+			impl<C> system::offchain::SendTransactionTypes<C> for MockRuntime
+			where
+				RuntimeCall: From<C>,
+			{
+				type Extrinsic = UncheckedExtrinsic;
+				type OverarchingCall = RuntimeCall;
 			}
 
 			/// Run to block n.
@@ -14,7 +14,9 @@ use crate::tests::utils::sign_message_hash;
 use crate::{Config, Nonces};
 
 mod account_helper;
+mod block_resources;
 mod call_contract;
+mod contract_class_cache;
 mod declare_tx;
 mod deploy_account_tx;
 mod erc20;
@@ -22,9 +24,14 @@ mod events;
 mod fees_disabled;
 mod invoke_tx;
 mod l1_message;
+mod migration;
 mod no_nonce_validation;
+mod offchain_worker;
+mod protocol_parameters;
 mod query_tx;
 mod sequencer_address;
+mod sequencer_rotation;
+mod state_trie;
 
 mod block;
 mod constants;
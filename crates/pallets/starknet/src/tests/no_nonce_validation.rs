@@ -42,7 +42,7 @@ fn given_declare_tx_with_invalid_nonce_then_it_works() {
 
         let contract_class = get_contract_class("ERC20.json", 0);
 
-        assert_ok!(Starknet::declare(none_origin, transaction.clone(), contract_class.clone()));
+        assert_ok!(Starknet::declare(none_origin, transaction.clone(), contract_class.clone(), None));
         assert_eq!(Starknet::contract_class_by_class_hash(ClassHash::from(erc20_class_hash)).unwrap(), contract_class);
 
         // check nonce is still 0
@@ -0,0 +1,276 @@
+use frame_support::{assert_err, assert_ok};
+use mp_felt::Felt252Wrapper;
+use mp_transactions::HandleL1MessageTransaction;
+use parity_scale_codec::Decode;
+use sp_core::offchain::testing::{PendingRequest, TestOffchainExt, TestTransactionPoolExt};
+use sp_core::offchain::{OffchainDbExt, OffchainWorkerExt, TransactionPoolExt};
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_runtime::traits::ValidateUnsigned;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource};
+use starknet_api::transaction::Fee;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::message::get_messages_events;
+use crate::offchain_worker::{l1_message_submissions_key, LAST_BLOCK_QUERY, MAX_L1_MESSAGE_SUBMISSIONS};
+use crate::{Error, L1Messages, LastKnownEthBlock, ETHEREUM_EXECUTION_RPC};
+
+const ETH_RPC_URL: &str = "http://localhost:8545";
+const PAID_FEE_ON_L1: u128 = 0x2386f26fc10000;
+
+fn word(value: u128) -> String {
+    format!("{value:064x}")
+}
+
+/// `eth_getBlockByNumber` response of the stub
+fn block_response(block_number: u64) -> String {
+    format!(r#"{{"jsonrpc": "2.0", "id": 0, "result": {{"number": "0x{block_number:x}"}}}}"#)
+}
+
+/// `eth_getLogs` response of the stub, with a `LogMessageToL2` event per nonce
+fn logs_response(nonces: &[u64]) -> String {
+    let logs = nonces
+        .iter()
+        .map(|nonce| {
+            format!(
+                r#"{{"topics": ["0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b", "0x{}", "0x{}", "0x{}"], "data": "0x{}{}{}{}{}"}}"#,
+                word(1),
+                word(1),
+                word(2),
+                word(0x60),
+                word(*nonce as u128),
+                word(PAID_FEE_ON_L1),
+                word(1),
+                word(1),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(r#"{{"jsonrpc": "2.0", "id": 0, "result": [{logs}]}}"#)
+}
+
+/// The transaction built from the events of `logs_response`
+fn l1_handler_transaction(nonce: u64) -> HandleL1MessageTransaction {
+    HandleL1MessageTransaction {
+        nonce,
+        contract_address: Felt252Wrapper::ONE,
+        entry_point_selector: Felt252Wrapper::TWO,
+        calldata: vec![Felt252Wrapper::ONE, Felt252Wrapper::ONE],
+    }
+}
+
+/// Runs the L1 messages processing of the offchain worker against a stub of the Ethereum
+/// JSON-RPC, which expects the given requests in order and answers them with the given responses.
+///
+/// Returns the calls submitted to the transaction pool.
+fn process_l1_messages(
+    last_known_eth_block: u64,
+    consumed_nonces: &[u64],
+    requests: Vec<(String, String)>,
+) -> Vec<RuntimeCall> {
+    process_l1_messages_submitted_before(last_known_eth_block, consumed_nonces, &[], requests)
+}
+
+/// Same as [process_l1_messages], with messages already submitted the given number of times by
+/// previous runs.
+fn process_l1_messages_submitted_before(
+    last_known_eth_block: u64,
+    consumed_nonces: &[u64],
+    submitted_nonces: &[(u64, u32)],
+    requests: Vec<(String, String)>,
+) -> Vec<RuntimeCall> {
+    let (offchain, offchain_state) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = new_test_ext::<MockRuntime>();
+    ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+    ext.register_extension(OffchainDbExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+
+    for (request, response) in requests {
+        offchain_state.write().expect_request(PendingRequest {
+            method: "POST".into(),
+            uri: ETH_RPC_URL.into(),
+            headers: vec![("content-type".into(), "application/json".into())],
+            body: request.into_bytes(),
+            response: Some(response.into_bytes()),
+            sent: true,
+            ..Default::default()
+        });
+    }
+
+    ext.execute_with(|| {
+        basic_test_setup(2);
+        StorageValueRef::persistent(ETHEREUM_EXECUTION_RPC).set(&ETH_RPC_URL.as_bytes().to_vec());
+        LastKnownEthBlock::<MockRuntime>::put(last_known_eth_block);
+        for nonce in consumed_nonces {
+            L1Messages::<MockRuntime>::insert(nonce, true);
+        }
+        for (nonce, submissions) in submitted_nonces {
+            StorageValueRef::persistent(&l1_message_submissions_key(*nonce)).set(submissions);
+        }
+
+        assert_ok!(Starknet::process_l1_messages());
+    });
+
+    let transactions = pool_state.read().transactions.clone();
+    transactions
+        .into_iter()
+        .map(|tx| frame_system::mocking::MockUncheckedExtrinsic::<MockRuntime>::decode(&mut &*tx).unwrap().function)
+        .collect()
+}
+
+#[test]
+fn offchain_worker_submits_l1_messages_with_their_fee() {
+    // The last block is 32, so the blocks up to 27 have the 5 confirmations of the mock, and are
+    // queried by ranges of 10 blocks
+    let calls = process_l1_messages(
+        10,
+        &[],
+        vec![(LAST_BLOCK_QUERY.to_string(), block_response(32)), (get_messages_events(11, 20), logs_response(&[0, 1]))],
+    );
+
+    assert_eq!(
+        calls,
+        vec![
+            RuntimeCall::Starknet(crate::Call::consume_l1_message {
+                transaction: l1_handler_transaction(0),
+                paid_fee_on_l1: Fee(PAID_FEE_ON_L1),
+            }),
+            RuntimeCall::Starknet(crate::Call::consume_l1_message {
+                transaction: l1_handler_transaction(1),
+                paid_fee_on_l1: Fee(PAID_FEE_ON_L1),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn offchain_worker_only_submits_the_messages_not_consumed_yet() {
+    let calls = process_l1_messages(
+        10,
+        &[0],
+        vec![(LAST_BLOCK_QUERY.to_string(), block_response(32)), (get_messages_events(11, 20), logs_response(&[0, 1]))],
+    );
+
+    assert_eq!(
+        calls,
+        vec![RuntimeCall::Starknet(crate::Call::consume_l1_message {
+            transaction: l1_handler_transaction(1),
+            paid_fee_on_l1: Fee(PAID_FEE_ON_L1),
+        })]
+    );
+}
+
+#[test]
+fn offchain_worker_advances_last_known_eth_block_once_messages_are_consumed() {
+    let calls = process_l1_messages(
+        10,
+        &[0, 1],
+        vec![
+            (LAST_BLOCK_QUERY.to_string(), block_response(32)),
+            (get_messages_events(11, 20), logs_response(&[0, 1])),
+            (get_messages_events(21, 27), logs_response(&[])),
+        ],
+    );
+
+    assert_eq!(calls, vec![RuntimeCall::Starknet(crate::Call::set_last_known_eth_block { eth_block_number: 27 })]);
+}
+
+#[test]
+fn offchain_worker_waits_for_confirmations() {
+    let calls = process_l1_messages(27, &[], vec![(LAST_BLOCK_QUERY.to_string(), block_response(32))]);
+
+    assert_eq!(calls, vec![]);
+}
+
+#[test]
+fn set_last_known_eth_block_only_moves_forward() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let none_origin = RuntimeOrigin::none();
+
+        // The offchain worker has not started yet
+        assert_err!(
+            Starknet::set_last_known_eth_block(none_origin.clone(), 27),
+            Error::<MockRuntime>::EthBlockAlreadyProcessed
+        );
+
+        LastKnownEthBlock::<MockRuntime>::put(10);
+        assert_ok!(Starknet::set_last_known_eth_block(none_origin.clone(), 27));
+        assert_eq!(Starknet::last_known_eth_block(), Some(27));
+
+        assert_err!(
+            Starknet::validate_unsigned(
+                TransactionSource::InBlock,
+                &crate::Call::set_last_known_eth_block { eth_block_number: 27 },
+            ),
+            InvalidTransaction::Stale
+        );
+        assert_err!(
+            Starknet::set_last_known_eth_block(none_origin, 27),
+            Error::<MockRuntime>::EthBlockAlreadyProcessed
+        );
+    });
+}
+
+#[test]
+fn last_known_eth_block_update_is_local_and_bounded() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        LastKnownEthBlock::<MockRuntime>::put(10);
+
+        // 100 ranges of the 10 blocks of the mock
+        let furthest_eth_block = 10 + Starknet::max_eth_blocks_per_update();
+        assert_eq!(furthest_eth_block, 1_010);
+
+        let validate = |source, eth_block_number| {
+            Starknet::validate_unsigned(source, &crate::Call::set_last_known_eth_block { eth_block_number })
+        };
+        assert_err!(validate(TransactionSource::External, 27), InvalidTransaction::Call);
+        assert_err!(validate(TransactionSource::Local, u64::MAX), InvalidTransaction::Future);
+        assert!(!validate(TransactionSource::Local, furthest_eth_block).unwrap().propagate);
+
+        assert_err!(
+            Starknet::set_last_known_eth_block(RuntimeOrigin::none(), furthest_eth_block + 1),
+            Error::<MockRuntime>::EthBlockTooFarAhead
+        );
+        assert_ok!(Starknet::set_last_known_eth_block(RuntimeOrigin::none(), furthest_eth_block));
+    });
+}
+
+#[test]
+fn offchain_worker_skips_l1_messages_never_consumed() {
+    // Message 0 was submitted by the previous runs without ever being consumed, e.g. because its
+    // fee is too low, and the last known block moves past it
+    let calls = process_l1_messages_submitted_before(
+        10,
+        &[],
+        &[(0, MAX_L1_MESSAGE_SUBMISSIONS)],
+        vec![
+            (LAST_BLOCK_QUERY.to_string(), block_response(32)),
+            (get_messages_events(11, 20), logs_response(&[0])),
+            (get_messages_events(21, 27), logs_response(&[])),
+        ],
+    );
+
+    assert_eq!(calls, vec![RuntimeCall::Starknet(crate::Call::set_last_known_eth_block { eth_block_number: 27 })]);
+}
+
+#[test]
+fn offchain_worker_submits_l1_messages_again_until_the_limit() {
+    let calls = process_l1_messages_submitted_before(
+        10,
+        &[],
+        &[(0, MAX_L1_MESSAGE_SUBMISSIONS - 1)],
+        vec![(LAST_BLOCK_QUERY.to_string(), block_response(32)), (get_messages_events(11, 20), logs_response(&[0]))],
+    );
+
+    assert_eq!(
+        calls,
+        vec![RuntimeCall::Starknet(crate::Call::consume_l1_message {
+            transaction: l1_handler_transaction(0),
+            paid_fee_on_l1: Fee(PAID_FEE_ON_L1),
+        })]
+    );
+}
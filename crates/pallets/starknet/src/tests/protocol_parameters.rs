@@ -0,0 +1,130 @@
+use frame_support::traits::Hooks;
+use frame_support::{assert_err, assert_ok};
+use mp_felt::Felt252Wrapper;
+use sp_runtime::traits::BadOrigin;
+use starknet_api::api_core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::types::ProtocolParameter;
+use crate::{Error, Event};
+
+#[test]
+fn protocol_parameters_default_to_the_config() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let protocol_parameters = Starknet::protocol_parameters();
+        assert_eq!(protocol_parameters.chain_id, ChainId::get());
+        assert_eq!(protocol_parameters.invoke_tx_max_n_steps, InvokeTxMaxNSteps::get());
+        assert_eq!(protocol_parameters.validate_max_n_steps, ValidateMaxNSteps::get());
+        assert_eq!(protocol_parameters.max_recursion_depth, MaxRecursionDepth::get());
+        assert_eq!(protocol_parameters.disable_transaction_fee, DisableTransactionFee::get());
+    });
+}
+
+#[test]
+fn protocol_parameter_changes_apply_at_the_next_block() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_ok!(Starknet::set_protocol_parameter(RuntimeOrigin::root(), ProtocolParameter::InvokeTxMaxNSteps(42)));
+        System::assert_last_event(
+            Event::ProtocolParameterChangeScheduled { parameter: ProtocolParameter::InvokeTxMaxNSteps(42) }.into(),
+        );
+
+        // The current block keeps its parameters
+        assert_eq!(Starknet::get_block_context().invoke_tx_max_n_steps, InvokeTxMaxNSteps::get());
+
+        Starknet::on_finalize(2);
+        System::set_block_number(3);
+        Starknet::on_initialize(3);
+
+        assert_eq!(Starknet::get_block_context().invoke_tx_max_n_steps, 42);
+        assert!(Starknet::pending_protocol_parameter_changes().is_empty());
+        System::assert_last_event(
+            Event::ProtocolParameterChanged {
+                old: ProtocolParameter::InvokeTxMaxNSteps(InvokeTxMaxNSteps::get()),
+                new: ProtocolParameter::InvokeTxMaxNSteps(42),
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn fee_token_address_change_emits_fee_token_address_changed() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let old_fee_token_address = Starknet::fee_token_address();
+        let new_fee_token_address = ContractAddress(PatriciaKey(StarkFelt::from(0xfee_u128)));
+        assert_ok!(Starknet::set_protocol_parameter(
+            RuntimeOrigin::root(),
+            ProtocolParameter::FeeTokenAddress(new_fee_token_address)
+        ));
+
+        Starknet::on_finalize(2);
+        System::set_block_number(3);
+        Starknet::on_initialize(3);
+
+        assert_eq!(Starknet::get_block_context().fee_token_address, new_fee_token_address);
+        System::assert_last_event(
+            Event::FeeTokenAddressChanged { old_fee_token_address, new_fee_token_address }.into(),
+        );
+    });
+}
+
+#[test]
+fn strk_fee_token_address_change_emits_protocol_parameter_changed() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        // Without a STRK token in the genesis, the v3 transactions pay their fee in the fee token
+        let old_strk_fee_token_address = Starknet::strk_fee_token_address();
+        assert_eq!(old_strk_fee_token_address, Starknet::fee_token_address());
+
+        let new_strk_fee_token_address = ContractAddress(PatriciaKey(StarkFelt::from(0x57e_u128)));
+        assert_ok!(Starknet::set_protocol_parameter(
+            RuntimeOrigin::root(),
+            ProtocolParameter::StrkFeeTokenAddress(new_strk_fee_token_address)
+        ));
+
+        Starknet::on_finalize(2);
+        System::set_block_number(3);
+        Starknet::on_initialize(3);
+
+        assert_eq!(Starknet::strk_fee_token_address(), new_strk_fee_token_address);
+        // The fee token of the previous transaction versions is unchanged
+        assert_eq!(Starknet::fee_token_address(), old_strk_fee_token_address);
+        System::assert_last_event(
+            Event::ProtocolParameterChanged {
+                old: ProtocolParameter::StrkFeeTokenAddress(old_strk_fee_token_address),
+                new: ProtocolParameter::StrkFeeTokenAddress(new_strk_fee_token_address),
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn protocol_parameters_are_only_changed_by_root_to_valid_values() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_err!(
+            Starknet::set_protocol_parameter(RuntimeOrigin::none(), ProtocolParameter::DisableTransactionFee(true)),
+            BadOrigin
+        );
+        assert_err!(
+            Starknet::set_protocol_parameter(RuntimeOrigin::root(), ProtocolParameter::ChainId(Felt252Wrapper::ZERO)),
+            Error::<MockRuntime>::InvalidProtocolParameter
+        );
+        assert_err!(
+            Starknet::set_protocol_parameter(RuntimeOrigin::root(), ProtocolParameter::ValidateMaxNSteps(0)),
+            Error::<MockRuntime>::InvalidProtocolParameter
+        );
+        assert!(Starknet::pending_protocol_parameter_changes().is_empty());
+    });
+}
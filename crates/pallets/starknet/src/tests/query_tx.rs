@@ -30,6 +30,22 @@ fn estimates_tx_fee_successfully_no_validate() {
     });
 }
 
+#[test]
+fn estimates_tx_fee_with_the_l1_gas_price_of_the_block() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let tx = UserTransaction::Invoke(get_storage_read_write_dummy().into());
+        let (actual, l1_gas_usage) = Starknet::estimate_fee(tx.clone()).unwrap();
+
+        L1GasPrice::set(20);
+        let (actual_with_higher_price, l1_gas_usage_with_higher_price) = Starknet::estimate_fee(tx).unwrap();
+
+        assert_eq!(l1_gas_usage_with_higher_price, l1_gas_usage);
+        assert_eq!(actual_with_higher_price, 2 * actual);
+    });
+}
+
 #[test]
 fn estimates_tx_fee_with_query_version() {
     new_test_ext::<MockRuntime>().execute_with(|| {
@@ -0,0 +1,100 @@
+use frame_support::traits::Hooks;
+use frame_support::{assert_err, assert_ok};
+use sp_runtime::traits::BadOrigin;
+use starknet_api::api_core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::types::SequencerSchedule;
+use crate::{Error, Event};
+
+const FIRST_SEQUENCER_ADDRESS: [u8; 32] =
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+const SECOND_SEQUENCER_ADDRESS: [u8; 32] =
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+
+fn contract_address(bytes: [u8; 32]) -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::new(bytes).unwrap()))
+}
+
+fn two_sequencers_schedule() -> SequencerSchedule {
+    SequencerSchedule {
+        sequencers: vec![contract_address(FIRST_SEQUENCER_ADDRESS), contract_address(SECOND_SEQUENCER_ADDRESS)],
+        slots_per_sequencer: 2,
+    }
+}
+
+/// Starts the block `n`, whose slot is `n` in the mock runtime.
+fn start_block(n: u64) {
+    Starknet::on_finalize(n - 1);
+    System::set_block_number(n);
+    Timestamp::set_timestamp(n * 6_000);
+}
+
+#[test]
+fn sequencers_take_turns_every_slots_per_sequencer_slots() {
+    let schedule = two_sequencers_schedule();
+
+    assert_eq!(schedule.sequencer_for_slot(0), Some(contract_address(FIRST_SEQUENCER_ADDRESS)));
+    assert_eq!(schedule.sequencer_for_slot(1), Some(contract_address(FIRST_SEQUENCER_ADDRESS)));
+    assert_eq!(schedule.sequencer_for_slot(2), Some(contract_address(SECOND_SEQUENCER_ADDRESS)));
+    assert_eq!(schedule.sequencer_for_slot(3), Some(contract_address(SECOND_SEQUENCER_ADDRESS)));
+    assert_eq!(schedule.sequencer_for_slot(4), Some(contract_address(FIRST_SEQUENCER_ADDRESS)));
+
+    assert_eq!(SequencerSchedule::default().sequencer_for_slot(4), None);
+}
+
+#[test]
+fn sequencer_schedule_is_set_by_root_only() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_err!(Starknet::set_sequencer_schedule(RuntimeOrigin::none(), two_sequencers_schedule()), BadOrigin);
+        assert_err!(
+            Starknet::set_sequencer_schedule(
+                RuntimeOrigin::root(),
+                SequencerSchedule { slots_per_sequencer: 0, ..two_sequencers_schedule() }
+            ),
+            Error::<MockRuntime>::InvalidSequencerSchedule
+        );
+        assert_eq!(Starknet::sequencer_schedule(), SequencerSchedule::default());
+
+        assert_ok!(Starknet::set_sequencer_schedule(RuntimeOrigin::root(), two_sequencers_schedule()));
+        assert_eq!(Starknet::sequencer_schedule(), two_sequencers_schedule());
+        System::assert_last_event(Event::SequencerScheduleChanged { schedule: two_sequencers_schedule() }.into());
+    });
+}
+
+#[test]
+fn sequencer_address_must_be_the_elected_sequencer() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        assert_ok!(Starknet::set_sequencer_schedule(RuntimeOrigin::root(), two_sequencers_schedule()));
+
+        start_block(3);
+        assert_eq!(Starknet::expected_sequencer_address(), Some(contract_address(SECOND_SEQUENCER_ADDRESS)));
+        assert_err!(
+            Starknet::set_sequencer_address(RuntimeOrigin::none(), FIRST_SEQUENCER_ADDRESS),
+            Error::<MockRuntime>::UnexpectedSequencerAddress
+        );
+        assert_ok!(Starknet::set_sequencer_address(RuntimeOrigin::none(), SECOND_SEQUENCER_ADDRESS));
+        assert_eq!(Starknet::sequencer_address(), contract_address(SECOND_SEQUENCER_ADDRESS));
+
+        start_block(4);
+        assert_ok!(Starknet::set_sequencer_address(RuntimeOrigin::none(), FIRST_SEQUENCER_ADDRESS));
+        assert_eq!(Starknet::sequencer_address(), contract_address(FIRST_SEQUENCER_ADDRESS));
+    });
+}
+
+#[test]
+fn any_sequencer_address_is_accepted_without_a_schedule() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        start_block(3);
+        assert_eq!(Starknet::expected_sequencer_address(), None);
+        assert_ok!(Starknet::set_sequencer_address(RuntimeOrigin::none(), FIRST_SEQUENCER_ADDRESS));
+    });
+}
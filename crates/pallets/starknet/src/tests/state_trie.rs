@@ -0,0 +1,203 @@
+use alloc::collections::BTreeSet;
+
+use blockifier::state::state_api::State;
+use frame_support::storage::{unhashed, StoragePrefixedMap};
+use frame_support::traits::{Get, Hooks};
+use mp_commitments::ProofNode;
+use mp_felt::Felt252Wrapper;
+use sp_runtime::traits::ValidateUnsigned;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource};
+use starknet_api::api_core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+use super::block::{state_root_from_scratch, stored_block_state_root};
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::blockifier_state_adapter::BlockifierStateAdapter;
+use crate::state_trie::STATE_TRIES_VERSION;
+use crate::tests::get_invoke_dummy;
+use crate::{
+    Config, ContractsStateRoots, OngoingMigration, SeqAddrUpdate, StateTreeRoots, StateTrieNodeReferences,
+    StateTrieNodes, StateTrieReleasedNodes, StateTriesVersion,
+};
+
+fn contract_address() -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::from(0x1234_u64)))
+}
+
+fn set_storage(value: u64) {
+    let key = StorageKey(PatriciaKey(StarkFelt::from(42_u64)));
+    let mut state = BlockifierStateAdapter::<MockRuntime>::default();
+    state.set_storage_at(contract_address(), key, StarkFelt::from(value));
+}
+
+/// Initializes the next block, running the pallet hooks which run before its extrinsics.
+fn initialize_next_block(runtime_upgrade: bool) -> u64 {
+    let header = System::finalize();
+    let block_number = header.number + 1;
+    System::initialize(&block_number, &header.hash(), &Default::default());
+    if runtime_upgrade {
+        Starknet::on_runtime_upgrade();
+    }
+    Starknet::on_initialize(block_number);
+    block_number
+}
+
+fn finalize_block(block_number: u64) {
+    SeqAddrUpdate::<MockRuntime>::put(true);
+    Starknet::on_finalize(block_number);
+}
+
+fn next_block() -> u64 {
+    let block_number = initialize_next_block(false);
+    finalize_block(block_number);
+    block_number
+}
+
+/// Builds blocks until the ongoing migration ends, and returns the last one.
+fn run_migration() -> u64 {
+    let mut block_number = System::block_number();
+    for _ in 0..1_000 {
+        if !OngoingMigration::<MockRuntime>::exists() {
+            return block_number;
+        }
+        block_number = next_block();
+    }
+    panic!("the migration did not end");
+}
+
+/// Returns the hashes of the trie nodes reachable from some roots.
+fn reachable_nodes(roots: impl IntoIterator<Item = Felt252Wrapper>) -> BTreeSet<Felt252Wrapper> {
+    let mut reachable = BTreeSet::new();
+    let mut to_visit: Vec<Felt252Wrapper> = roots.into_iter().collect();
+    while let Some(hash) = to_visit.pop() {
+        match StateTrieNodes::<MockRuntime>::get(hash) {
+            Some(ProofNode::Binary(binary)) => to_visit.extend([binary.left_hash, binary.right_hash]),
+            Some(ProofNode::Edge(edge)) => to_visit.push(edge.child_hash),
+            // A leaf
+            None => continue,
+        }
+        reachable.insert(hash);
+    }
+    reachable
+}
+
+#[test]
+fn state_tries_of_old_blocks_are_pruned() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let history = <MockRuntime as Config>::StateTriesHistory::get();
+
+        let mut storage_roots = Vec::new();
+        let mut last_block_number = 0;
+        for value in 1..=10 {
+            set_storage(value);
+            last_block_number = next_block();
+            storage_roots.push(Starknet::contract_state_root_by_address(contract_address()).unwrap());
+        }
+
+        let kept_block_numbers = last_block_number - history + 1..=last_block_number;
+        assert!(Starknet::contract_state_proof(last_block_number - history, contract_address()).is_none());
+        assert!(kept_block_numbers.clone().all(|n| Starknet::contract_state_proof(n, contract_address()).is_some()));
+
+        // Only the nodes of the kept tries are stored
+        let mut roots: Vec<Felt252Wrapper> = ContractsStateRoots::<MockRuntime>::iter_values().collect();
+        roots.extend(&storage_roots[storage_roots.len() - history as usize..]);
+        for block_number in kept_block_numbers {
+            let (contracts_tree_root, classes_tree_root) = Starknet::state_tree_roots(block_number).unwrap();
+            roots.extend([contracts_tree_root, classes_tree_root]);
+        }
+        let stored_nodes: BTreeSet<Felt252Wrapper> = StateTrieNodes::<MockRuntime>::iter_keys().collect();
+        assert_eq!(stored_nodes, reachable_nodes(roots));
+        assert_eq!(StateTrieNodeReferences::<MockRuntime>::iter_keys().collect::<BTreeSet<_>>(), stored_nodes);
+    });
+}
+
+#[test]
+fn state_tries_are_built_on_upgrade_of_a_chain_without_them() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        set_storage(1);
+        next_block();
+        let state_root = stored_block_state_root();
+
+        // The state tries were not kept by the runtime before the upgrade
+        for prefix in [
+            StateTrieNodes::<MockRuntime>::final_prefix(),
+            StateTrieNodeReferences::<MockRuntime>::final_prefix(),
+            StateTrieReleasedNodes::<MockRuntime>::final_prefix(),
+            StateTreeRoots::<MockRuntime>::final_prefix(),
+            ContractsStateRoots::<MockRuntime>::final_prefix(),
+        ] {
+            let _ = unhashed::clear_prefix(&prefix, None, None);
+        }
+
+        let block_number = initialize_next_block(true);
+        assert!(OngoingMigration::<MockRuntime>::exists());
+        // The state does not change until the tries are built
+        assert_eq!(
+            Starknet::validate_unsigned(
+                TransactionSource::External,
+                &crate::Call::invoke { transaction: get_invoke_dummy(Felt252Wrapper::ZERO).into() }
+            ),
+            Err(InvalidTransaction::Future.into())
+        );
+        finalize_block(block_number);
+        assert_eq!(stored_block_state_root(), Felt252Wrapper::ZERO);
+
+        let last_block_number = run_migration();
+        assert!(last_block_number > block_number + 1);
+        assert_eq!(stored_block_state_root(), state_root);
+        assert_eq!(state_root, state_root_from_scratch());
+        assert!(Starknet::contract_state_proof(last_block_number, contract_address()).is_some());
+
+        // The tries are updated again by the following blocks
+        set_storage(2);
+        next_block();
+        assert_eq!(stored_block_state_root(), state_root_from_scratch());
+    });
+}
+
+#[test]
+fn missing_state_trie_node_builds_the_tries_again() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        next_block();
+        let state_root = stored_block_state_root();
+
+        let (contracts_tree_root, _) = Starknet::state_tree_roots(1).unwrap();
+        StateTrieNodes::<MockRuntime>::remove(contracts_tree_root);
+
+        // The root of the block is not available, and the partial update of the tries is rolled back
+        set_storage(1);
+        let block_number = next_block();
+        assert_ne!(state_root, Felt252Wrapper::ZERO);
+        assert_eq!(stored_block_state_root(), Felt252Wrapper::ZERO);
+        assert!(Starknet::state_tree_roots(block_number).is_none());
+        assert!(Starknet::contract_state_root_by_address(contract_address()).is_none());
+        assert!(StateTrieReleasedNodes::<MockRuntime>::get(block_number).is_empty());
+        assert!(OngoingMigration::<MockRuntime>::exists());
+
+        let last_block_number = run_migration();
+        assert!(Starknet::state_tree_roots(last_block_number).is_some());
+        assert_eq!(stored_block_state_root(), state_root_from_scratch());
+    });
+}
+
+#[test]
+fn state_tries_committing_outdated_leaves_are_built_again_on_upgrade() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        assert_eq!(StateTriesVersion::<MockRuntime>::get(), STATE_TRIES_VERSION);
+        set_storage(1);
+        next_block();
+
+        // The tries of a chain whose runtime computed the leaves differently
+        StateTriesVersion::<MockRuntime>::kill();
+
+        let block_number = initialize_next_block(true);
+        assert!(OngoingMigration::<MockRuntime>::exists());
+        finalize_block(block_number);
+
+        run_migration();
+        assert_eq!(StateTriesVersion::<MockRuntime>::get(), STATE_TRIES_VERSION);
+        assert_eq!(stored_block_state_root(), state_root_from_scratch());
+    });
+}
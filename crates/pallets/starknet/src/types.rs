@@ -1,6 +1,10 @@
 //! Starknet pallet custom types.
+use blockifier::abi::constants::{GAS_USAGE, N_STEPS_RESOURCE};
 use blockifier::execution::contract_class::ContractClass;
+use blockifier::transaction::objects::ResourcesMapping;
 use mp_felt::Felt252Wrapper;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
 use sp_core::ConstU32;
 use sp_std::vec::Vec;
 use starknet_api::api_core::{ClassHash, ContractAddress};
@@ -47,3 +51,131 @@ pub struct TransactionOutput {
     pub messages_sent: Vec<MessageToL1>,
     pub events: Vec<Event>,
 }
+
+/// Execution resources consumed by the transactions of a block, or limits on them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct BlockResources {
+    /// Cairo steps.
+    pub steps: u64,
+    /// Applications of the builtins, all builtins together.
+    pub builtins: u64,
+    /// Gas consumed on L1, for the state diff and the messages to L1.
+    pub l1_gas: u64,
+}
+
+impl BlockResources {
+    /// Adds the resources consumed by a transaction.
+    pub fn add(&mut self, resources: &ResourcesMapping) {
+        for (resource, amount) in resources.0.iter() {
+            let amount = *amount as u64;
+            match resource.as_str() {
+                N_STEPS_RESOURCE => self.steps = self.steps.saturating_add(amount),
+                GAS_USAGE => self.l1_gas = self.l1_gas.saturating_add(amount),
+                builtin if builtin.ends_with("_builtin") => self.builtins = self.builtins.saturating_add(amount),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns whether any of the resources has reached its limit.
+    pub fn reaches(&self, limits: &BlockResources) -> bool {
+        self.steps >= limits.steps || self.builtins >= limits.builtins || self.l1_gas >= limits.l1_gas
+    }
+}
+
+/// Protocol parameters of the pallet, which governance can change without a runtime upgrade.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct ProtocolParameters {
+    /// The chain id, used in the transaction hashes.
+    pub chain_id: Felt252Wrapper,
+    /// Maximum number of Cairo steps of the execution of a transaction.
+    pub invoke_tx_max_n_steps: u32,
+    /// Maximum number of Cairo steps of the validation of a transaction.
+    pub validate_max_n_steps: u32,
+    /// Maximum depth of the nested calls of a transaction.
+    pub max_recursion_depth: u32,
+    /// Whether transactions are free.
+    pub disable_transaction_fee: bool,
+}
+
+/// A change of a protocol parameter, applied at the beginning of the block following the one in
+/// which it is scheduled.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum ProtocolParameter {
+    ChainId(Felt252Wrapper),
+    InvokeTxMaxNSteps(u32),
+    ValidateMaxNSteps(u32),
+    MaxRecursionDepth(u32),
+    FeeTokenAddress(ContractAddress),
+    DisableTransactionFee(bool),
+    StrkFeeTokenAddress(ContractAddress),
+}
+
+impl ProtocolParameters {
+    /// Sets a parameter, and returns its previous value.
+    ///
+    /// The fee token addresses are not part of these parameters, and are returned unchanged.
+    pub fn set(&mut self, parameter: ProtocolParameter) -> ProtocolParameter {
+        match parameter {
+            ProtocolParameter::ChainId(chain_id) => {
+                ProtocolParameter::ChainId(core::mem::replace(&mut self.chain_id, chain_id))
+            }
+            ProtocolParameter::InvokeTxMaxNSteps(steps) => {
+                ProtocolParameter::InvokeTxMaxNSteps(core::mem::replace(&mut self.invoke_tx_max_n_steps, steps))
+            }
+            ProtocolParameter::ValidateMaxNSteps(steps) => {
+                ProtocolParameter::ValidateMaxNSteps(core::mem::replace(&mut self.validate_max_n_steps, steps))
+            }
+            ProtocolParameter::MaxRecursionDepth(depth) => {
+                ProtocolParameter::MaxRecursionDepth(core::mem::replace(&mut self.max_recursion_depth, depth))
+            }
+            ProtocolParameter::DisableTransactionFee(disabled) => ProtocolParameter::DisableTransactionFee(
+                core::mem::replace(&mut self.disable_transaction_fee, disabled),
+            ),
+            fee_token_address @ (ProtocolParameter::FeeTokenAddress(_) | ProtocolParameter::StrkFeeTokenAddress(_)) => {
+                fee_token_address
+            }
+        }
+    }
+}
+
+/// The sequencers allowed to author blocks, taking turns every `slots_per_sequencer` slots.
+///
+/// An empty schedule lets the block author choose the sequencer address.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct SequencerSchedule {
+    /// The sequencers, in the order of their turns.
+    pub sequencers: Vec<ContractAddress>,
+    /// The number of consecutive slots of each turn.
+    pub slots_per_sequencer: u64,
+}
+
+impl SequencerSchedule {
+    /// Returns the sequencer elected for the given slot, if any.
+    pub fn sequencer_for_slot(&self, slot: u64) -> Option<ContractAddress> {
+        if self.sequencers.is_empty() || self.slots_per_sequencer == 0 {
+            return None;
+        }
+        let turn = slot / self.slots_per_sequencer;
+        Some(self.sequencers[(turn % self.sequencers.len() as u64) as usize])
+    }
+}
+
+/// Step of a migration of the pallet storage which runs over several blocks.
+///
+/// Each step processes a bounded number of storage entries, and resumes from the raw storage key
+/// at which the previous one stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum MigrationStep {
+    /// Moves the entries of the schema V1 maps to their schema V2 key, one map after the other.
+    MigrateSchemaV1Entries { item: u32, cursor: Option<Vec<u8>> },
+    /// Deletes the nodes and roots of the state tries, one storage item after the other.
+    ClearStateTries { item: u32, cursor: Option<Vec<u8>> },
+    /// Adds the storage slots to the storage tries of their contracts.
+    BuildStorageTries { cursor: Option<Vec<u8>> },
+    /// Adds the contract state hashes of the contracts found in a storage item to the contracts
+    /// tree, one storage item after the other.
+    BuildContractsTree { item: u32, cursor: Option<Vec<u8>>, root: Felt252Wrapper },
+    /// Adds the classes with a compiled class hash to the classes tree.
+    BuildClassesTree { cursor: Option<Vec<u8>>, contracts_tree_root: Felt252Wrapper, root: Felt252Wrapper },
+}
@@ -9,12 +9,46 @@ use starknet_api::block::{BlockNumber, BlockTimestamp};
 use starknet_api::hash::StarkHash;
 use starknet_api::stdlib::collections::HashMap;
 
+/// The L1 gas price, in wei, of the blocks whose header predates the `l1_gas_price` field.
+pub const LEGACY_L1_GAS_PRICE: u128 = 10;
+
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
 // #[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
 /// Starknet header definition.
 pub struct Header {
+    /// The hash of this block’s parent.
+    pub parent_block_hash: StarkHash,
+    /// The number (height) of this block.
+    pub block_number: u64,
+    /// The state commitment after this block.
+    pub global_state_root: StarkHash,
+    /// The Starknet address of the sequencer who created this block.
+    pub sequencer_address: ContractAddress,
+    /// The time the sequencer created this block before executing transactions
+    pub block_timestamp: u64,
+    /// The number of transactions in a block
+    pub transaction_count: u128,
+    /// A commitment to the transactions included in the block
+    pub transaction_commitment: StarkHash,
+    /// The number of events
+    pub event_count: u128,
+    /// A commitment to the events produced in this block
+    pub event_commitment: StarkHash,
+    /// The version of the Starknet protocol used when creating this block
+    pub protocol_version: u8,
+    /// The price of the L1 gas, in wei, used to compute the fees of the transactions of this block
+    pub l1_gas_price: u128,
+    /// Extraneous data that might be useful for running transactions
+    pub extra_data: Option<U256>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+/// Starknet header definition before the L1 gas price was added to it, used to decode the blocks
+/// authored with it.
+pub struct LegacyHeader {
     /// The hash of this block’s parent.
     pub parent_block_hash: StarkHash,
     /// The number (height) of this block.
@@ -39,6 +73,25 @@ pub struct Header {
     pub extra_data: Option<U256>,
 }
 
+impl From<LegacyHeader> for Header {
+    fn from(header: LegacyHeader) -> Self {
+        Self {
+            parent_block_hash: header.parent_block_hash,
+            block_number: header.block_number,
+            global_state_root: header.global_state_root,
+            sequencer_address: header.sequencer_address,
+            block_timestamp: header.block_timestamp,
+            transaction_count: header.transaction_count,
+            transaction_commitment: header.transaction_commitment,
+            event_count: header.event_count,
+            event_commitment: header.event_commitment,
+            protocol_version: header.protocol_version,
+            l1_gas_price: LEGACY_L1_GAS_PRICE,
+            extra_data: header.extra_data,
+        }
+    }
+}
+
 impl Header {
     /// Creates a new header.
     #[allow(clippy::too_many_arguments)]
@@ -54,6 +107,7 @@ impl Header {
         event_count: u128,
         event_commitment: StarkHash,
         protocol_version: u8,
+        l1_gas_price: u128,
         extra_data: Option<U256>,
     ) -> Self {
         Self {
@@ -67,6 +121,7 @@ impl Header {
             event_count,
             event_commitment,
             protocol_version,
+            l1_gas_price,
             extra_data,
         }
     }
@@ -82,8 +137,7 @@ impl Header {
             fee_token_address,
             invoke_tx_max_n_steps: 1000000,
             validate_max_n_steps: 1000000,
-            // FIXME: https://github.com/keep-starknet-strange/madara/issues/329
-            gas_price: 10,
+            gas_price: self.l1_gas_price,
             max_recursion_depth: 50,
         }
     }
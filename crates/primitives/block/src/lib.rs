@@ -59,5 +59,29 @@ impl Block {
     }
 }
 
+/// Starknet block definition before the L1 gas price was added to its header, used to decode the
+/// blocks authored with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+pub struct LegacyBlock {
+    /// The block header.
+    header: LegacyHeader,
+    /// The block transactions.
+    transactions: BlockTransactions,
+}
+
+impl LegacyBlock {
+    /// Creates a new legacy block.
+    pub fn new(header: LegacyHeader, transactions: BlockTransactions) -> Self {
+        Self { header, transactions }
+    }
+}
+
+impl From<LegacyBlock> for Block {
+    fn from(block: LegacyBlock) -> Self {
+        Self { header: block.header.into(), transactions: block.transactions }
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -21,6 +21,7 @@ fn generate_dummy_header() -> Header {
         0,
         StarkFelt::from(4u128),
         1,
+        10,
         Some(U256::from(3)),
     )
 }
@@ -55,6 +56,7 @@ fn test_real_header_hash() {
     let event_commitment =
         StarkFelt::try_from("0x2043ba1ef46882ce1dbb17b501fffa4b71f87f618e8f394e9605959d92efdf6").unwrap();
     let protocol_version = 0;
+    let l1_gas_price = 29_000_000_000;
     let extra_data = None;
 
     let header = Header::new(
@@ -68,6 +70,7 @@ fn test_real_header_hash() {
         event_count,
         event_commitment,
         protocol_version,
+        l1_gas_price,
         extra_data,
     );
 
@@ -81,7 +84,8 @@ fn test_real_header_hash() {
 fn test_to_block_context() {
     let sequencer_address = ContractAddress(PatriciaKey(StarkFelt::try_from("0xFF").unwrap()));
     // Create a block header.
-    let block_header = Header { block_number: 1, block_timestamp: 1, sequencer_address, ..Default::default() };
+    let block_header =
+        Header { block_number: 1, block_timestamp: 1, sequencer_address, l1_gas_price: 20, ..Default::default() };
     // Create a fee token address.
     let fee_token_address = ContractAddress(PatriciaKey(StarkFelt::try_from("AA").unwrap()));
     // Create a chain id.
@@ -93,4 +97,5 @@ fn test_to_block_context() {
     assert_eq!(block_context.block_timestamp, BlockTimestamp(1));
     assert_eq!(block_context.sequencer_address, sequencer_address);
     assert_eq!(block_context.fee_token_address, fee_token_address);
+    assert_eq!(block_context.gas_price, 20);
 }
@@ -7,9 +7,13 @@ mod merkle_patricia_tree;
 
 use alloc::vec::Vec;
 
+use bitvec::prelude::Msb0;
 use bitvec::vec::BitVec;
-use merkle_patricia_tree::merkle_tree::{MerkleTree, NodesMapping, ProofNode};
+pub use merkle_patricia_tree::merkle_tree::{BinaryProofNode, EdgeProofNode, ProofNode};
+use merkle_patricia_tree::merkle_tree::{MerkleTree, NodesMapping};
 use merkle_patricia_tree::ref_merkle_tree::RefMerkleTree;
+use merkle_patricia_tree::storage_merkle_tree::StorageMerkleTree;
+pub use merkle_patricia_tree::storage_merkle_tree::{InvalidProof, NodeStorage, TrieError};
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use mp_transactions::compute_hash::ComputeTransactionHash;
@@ -82,8 +86,7 @@ impl<H: HasherT> StateCommitmentTree<H> {
     /// * `index` - The index of the value to set.
     /// * `value` - The value to set.
     pub fn set(&mut self, index: Felt252Wrapper, value: Felt252Wrapper) {
-        let key = &index.0.to_bytes_be()[..31];
-        self.tree.set(&BitVec::from_vec(key.to_vec()), value)
+        self.tree.set(&state_tree_key(index), value)
     }
 
     /// Get the merkle root of the tree.
@@ -93,8 +96,7 @@ impl<H: HasherT> StateCommitmentTree<H> {
 
     /// Generates a proof for `key`. See [`MerkleTree::get_proof`].
     pub fn get_proof(&self, key: Felt252Wrapper) -> Vec<ProofNode> {
-        let key = &key.0.to_bytes_be()[..31];
-        self.tree.get_proof(&BitVec::from_vec(key.to_vec()))
+        self.tree.get_proof(&state_tree_key(key))
     }
 
     /// Returns a leaf of the tree stored at key `key`
@@ -107,8 +109,7 @@ impl<H: HasherT> StateCommitmentTree<H> {
     ///
     /// `Some(value)` - Value stored at the given key.
     pub fn get(&self, key: Felt252Wrapper) -> Option<Felt252Wrapper> {
-        let key = &key.0.to_bytes_be()[..31];
-        self.tree.get(&BitVec::from_vec(key.to_vec()))
+        self.tree.get(&state_tree_key(key))
     }
 
     /// Returns the tree's nodes
@@ -117,6 +118,75 @@ impl<H: HasherT> StateCommitmentTree<H> {
     }
 }
 
+/// Height of the state trees, whose keys are field elements.
+const STATE_TREE_HEIGHT: usize = 251;
+
+/// Key of a state tree leaf, as the 251 bits of its index.
+fn state_tree_key(index: Felt252Wrapper) -> BitVec<u8, Msb0> {
+    let bits = BitVec::<u8, Msb0>::from_vec(index.0.to_bytes_be().to_vec());
+    // The indexes of the state trees, i.e. contract addresses, storage keys and class hashes, are
+    // less than 2^251, so only the 5 most significant bits, which are zero, are dropped
+    bits[bits.len() - STATE_TREE_HEIGHT..].to_bitvec()
+}
+
+/// A state tree whose nodes are persisted to a [`NodeStorage`].
+///
+/// It computes the same roots as [`StateCommitmentTree`], but only loads and writes the nodes
+/// along the paths of the updated leaves, so that updating it costs time proportional to the size
+/// of the update rather than to the size of the state. Every root it ever had can still be read
+/// and proven against.
+pub struct StateTrie<H: HasherT, S: NodeStorage> {
+    tree: StorageMerkleTree<H, S>,
+}
+
+impl<H: HasherT, S: NodeStorage> StateTrie<H, S> {
+    /// Creates a trie over the nodes of `storage`.
+    pub fn new(storage: S) -> Self {
+        Self { tree: StorageMerkleTree::new(storage, STATE_TREE_HEIGHT) }
+    }
+
+    /// Returns the storage of the trie.
+    pub fn into_storage(self) -> S {
+        self.tree.into_storage()
+    }
+
+    /// Sets the value of some leaves of the trie with the given root, and returns its new root.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The root of the trie to update, [`Felt252Wrapper::ZERO`] if it is empty.
+    /// * `leaves` - The (index, value) pairs to set. Leaves set to [`Felt252Wrapper::ZERO`] are
+    ///   deleted.
+    pub fn update(
+        &mut self,
+        root: Felt252Wrapper,
+        leaves: impl IntoIterator<Item = (Felt252Wrapper, Felt252Wrapper)>,
+    ) -> Result<Felt252Wrapper, TrieError> {
+        self.tree.update(root, leaves.into_iter().map(|(index, value)| (state_tree_key(index), value)).collect())
+    }
+
+    /// Returns the leaf stored at `key` in the trie with the given root.
+    pub fn get(&self, root: Felt252Wrapper, key: Felt252Wrapper) -> Result<Option<Felt252Wrapper>, TrieError> {
+        self.tree.get(root, &state_tree_key(key))
+    }
+
+    /// Generates a proof for `key` in the trie with the given root. See
+    /// [`StateCommitmentTree::get_proof`].
+    pub fn get_proof(&self, root: Felt252Wrapper, key: Felt252Wrapper) -> Result<Vec<ProofNode>, TrieError> {
+        self.tree.get_proof(root, &state_tree_key(key))
+    }
+
+    /// Verifies a proof generated by [`Self::get_proof`] against the root of a trie, and returns
+    /// the leaf it proves is stored at `key`, or `None` if it proves that `key` is not set.
+    pub fn verify_proof(
+        root: Felt252Wrapper,
+        key: Felt252Wrapper,
+        proof: &[ProofNode],
+    ) -> Result<Option<Felt252Wrapper>, InvalidProof> {
+        StorageMerkleTree::<H, S>::verify_proof(root, &state_tree_key(key), proof, STATE_TREE_HEIGHT)
+    }
+}
+
 /// Calculate the transaction commitment, the event commitment and the event count.
 ///
 /// # Arguments
@@ -200,7 +270,8 @@ pub fn calculate_class_commitment_leaf_hash<H: HasherT>(
 ) -> ClassCommitmentLeafHash {
     let contract_class_hash_version = Felt252Wrapper::try_from("CONTRACT_CLASS_LEAF_V0".as_bytes()).unwrap(); // Unwrap safu
 
-    let hash = H::compute_hash_on_elements(&[contract_class_hash_version.0, compiled_class_hash.0]);
+    // The leaf is H(CONTRACT_CLASS_LEAF_V0, compiled_class_hash), not the hash of a sequence
+    let hash = H::hash_elements(contract_class_hash_version.0, compiled_class_hash.0);
 
     hash.into()
 }
@@ -246,13 +317,78 @@ pub fn calculate_contract_state_hash<H: HasherT>(
     const CONTRACT_STATE_HASH_VERSION: Felt252Wrapper = Felt252Wrapper::ZERO;
 
     // The contract state hash is defined as H(H(H(hash, root), nonce), CONTRACT_STATE_HASH_VERSION)
-    let hash = H::compute_hash_on_elements(&[hash.0, root.0, nonce.0, CONTRACT_STATE_HASH_VERSION.0]);
+    let hash = H::hash_elements(hash.0, root.0);
+    let hash = H::hash_elements(hash, nonce.0);
+    let hash = H::hash_elements(hash, CONTRACT_STATE_HASH_VERSION.0);
 
     // Compare this with the HashChain construction used in the contract_hash: the number of
     // elements is not hashed to this hash, and this is supposed to be different.
     hash.into()
 }
 
+/// Calculate the root of a Patricia Merkle tree of height 251 from its leaves.
+///
+/// Used for the contract storage trees, whose leaves are the storage values, and for the contracts
+/// tree, whose leaves are the contract state hashes. Leaves with a zero value are not part of the
+/// tree.
+///
+/// # Arguments
+///
+/// * `leaves` - The (key, value) pairs of the tree.
+///
+/// # Returns
+///
+/// The merkle root of the merkle tree built from the leaves.
+pub fn calculate_state_tree_root<H: HasherT>(leaves: &[(Felt252Wrapper, Felt252Wrapper)]) -> Felt252Wrapper {
+    let mut tree = StateCommitmentTree::<H>::default();
+    leaves.iter().for_each(|(key, value)| tree.set(*key, *value));
+    tree.commit()
+}
+
+/// Calculate the root of the classes tree.
+///
+/// The classes tree maps (Cairo 1.0) class hashes to the leaf hash of their compiled class hash.
+///
+/// # Arguments
+///
+/// * `classes` - The (class hash, compiled class hash) pairs of the declared classes.
+///
+/// # Returns
+///
+/// The merkle root of the classes tree.
+pub fn calculate_classes_tree_root<H: HasherT>(classes: &[(Felt252Wrapper, Felt252Wrapper)]) -> Felt252Wrapper {
+    let mut tree = StateCommitmentTree::<H>::default();
+    classes.iter().for_each(|(class_hash, compiled_class_hash)| {
+        tree.set(*class_hash, calculate_class_commitment_leaf_hash::<H>(*compiled_class_hash))
+    });
+    tree.commit()
+}
+
+/// Calculate the global state root.
+///
+/// See: <https://docs.starknet.io/documentation/architecture_and_concepts/State/starknet-state/#state_commitment>
+///
+/// # Arguments
+///
+/// * `contracts_tree_root` - The root of the contracts tree.
+/// * `classes_tree_root` - The root of the classes tree.
+///
+/// # Returns
+///
+/// The global state root, which is the contracts tree root as long as the classes tree is empty.
+pub fn calculate_global_state_root<H: HasherT>(
+    contracts_tree_root: Felt252Wrapper,
+    classes_tree_root: Felt252Wrapper,
+) -> Felt252Wrapper {
+    if classes_tree_root == Felt252Wrapper::ZERO {
+        return contracts_tree_root;
+    }
+
+    let state_version = Felt252Wrapper::try_from("STARKNET_STATE_V0".as_bytes()).unwrap(); // Unwrap safu
+
+    H::compute_hash_on_elements(&[state_version.0, contracts_tree_root.0, classes_tree_root.0]).into()
+}
+
 /// Compute the combined hash of the transaction hash and the signature.
 ///
 /// Since the transaction hash doesn't take the signature values as its input
@@ -7,6 +7,7 @@
 use bitvec::order::Msb0;
 use bitvec::prelude::BitVec;
 use bitvec::slice::BitSlice;
+use bitvec::view::BitView;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use starknet_api::stdlib::collections::HashMap;
@@ -287,16 +288,30 @@ impl EdgeNode {
             None => unreachable!("child node not found"),
         };
 
-        let mut temp_path = self.path.clone();
-        temp_path.force_align();
+        self.hash = Some(edge_hash::<H>(child, &self.path));
+    }
+}
 
-        let path = Felt252Wrapper::try_from(temp_path.into_vec().as_slice()).unwrap();
-        let mut length = [0; 32];
-        // Safe as len() is guaranteed to be <= 251
-        length[31] = self.path.len() as u8;
+/// Calculates the hash of an edge node from the hash of its child and its path.
+pub(crate) fn edge_hash<H: HasherT>(child: Felt252Wrapper, path: &BitSlice<u8, Msb0>) -> Felt252Wrapper {
+    let path_felt = path_to_felt(path);
+    let mut length = [0; 32];
+    // Safe as len() is guaranteed to be <= 251
+    length[31] = path.len() as u8;
 
-        let length = Felt252Wrapper::try_from(&length).unwrap();
-        let hash = Felt252Wrapper(H::hash_elements(child.0, path.0) + length.0);
-        self.hash = Some(hash);
-    }
+    let length = Felt252Wrapper::try_from(&length).unwrap();
+    Felt252Wrapper(H::hash_elements(child.0, path_felt.0) + length.0)
+}
+
+/// The integer whose binary representation is the path, most significant bit first.
+///
+/// Paths are not a whole number of bytes long in general, so their bits are right aligned: the
+/// path `101010` is 42.
+pub(crate) fn path_to_felt(path: &BitSlice<u8, Msb0>) -> Felt252Wrapper {
+    let mut bytes = [0_u8; 32];
+    let bits = bytes.view_bits_mut::<Msb0>();
+    let start = bits.len() - path.len();
+    bits[start..].copy_from_bitslice(path);
+    // Safe as len() is guaranteed to be <= 251
+    Felt252Wrapper::try_from(&bytes).unwrap()
 }
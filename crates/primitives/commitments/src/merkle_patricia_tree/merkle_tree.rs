@@ -131,7 +131,7 @@ impl Serialize for ProofNode {
                 state.end()
             }
             ProofNode::Edge(edge) => {
-                let value = starknet_ff::FieldElement::from_byte_slice_be(edge.path.as_raw_slice()).unwrap();
+                let value = super::merkle_node::path_to_felt(&edge.path).0;
                 let path_wrapper = PathWrapper { value, len: edge.path.len() };
 
                 let mut state = serializer.serialize_struct_variant("ProofNode", 1, "Edge", 2)?;
@@ -4,3 +4,5 @@ pub mod ref_merkle_tree;
 // Merkle Tree implementation using nodes mapping
 pub mod merkle_node;
 pub mod merkle_tree;
+// Merkle Tree implementation persisting its nodes to a storage
+pub mod storage_merkle_tree;
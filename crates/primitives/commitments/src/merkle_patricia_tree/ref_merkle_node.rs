@@ -13,6 +13,8 @@ use bitvec::slice::BitSlice;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 
+use super::merkle_node::path_to_felt;
+
 /// A node in a Binary Merkle-Patricia Tree graph.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Node {
@@ -257,10 +259,7 @@ impl EdgeNode {
             Some(hash) => hash,
             None => unreachable!("subtree has to be committed before"),
         };
-        let mut temp_path = self.path.clone();
-        temp_path.force_align();
-
-        let path = Felt252Wrapper::try_from(temp_path.into_vec().as_slice()).unwrap();
+        let path = path_to_felt(&self.path);
         let mut length = [0; 32];
         // Safe as len() is guaranteed to be <= 251
         length[31] = self.path.len() as u8;
@@ -0,0 +1,309 @@
+//! A binary Merkle-Patricia tree whose nodes are persisted to a storage.
+//!
+//! Unlike [`MerkleTree`](super::merkle_tree::MerkleTree), the tree is never held in memory as a
+//! whole. Nodes are stored by hash, and updating the tree only loads and writes the nodes along
+//! the paths of the updated leaves. Nodes are not removed by the tree, so that the previous roots
+//! of the tree can still be read and proven against: the storage is told which nodes each update
+//! replaced, and decides when to delete them.
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use bitvec::prelude::{BitSlice, BitVec, Msb0};
+use mp_felt::Felt252Wrapper;
+use mp_hashers::HasherT;
+
+use super::merkle_node::edge_hash;
+use super::merkle_tree::{BinaryProofNode, EdgeProofNode, ProofNode};
+
+/// Storage of the nodes of a [`StorageMerkleTree`], by hash.
+///
+/// Leaves are not stored, their hash being their value.
+pub trait NodeStorage {
+    /// Returns the node with the given hash, if it is stored.
+    fn get_node(&self, hash: &Felt252Wrapper) -> Option<ProofNode>;
+    /// Stores a node under its hash.
+    fn insert_node(&mut self, hash: Felt252Wrapper, node: ProofNode);
+    /// Called when an update replaces a node of the version of the tree it applies to. The node
+    /// is still part of that version, and of the other versions it was inserted in.
+    ///
+    /// Storages keeping a bounded history of the tree count the insertions and releases of each
+    /// node, to delete it once no version they keep contains it anymore. By default, every node
+    /// is kept.
+    fn release_node(&mut self, _hash: Felt252Wrapper) {}
+}
+
+/// Error returned when the nodes reachable from a root can't be read from the storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrieError {
+    /// A node reachable from the root is not in the storage.
+    MissingNode(Felt252Wrapper),
+    /// A node is stored at a place of the tree its kind can't be at, e.g. an edge at the bottom of
+    /// another edge.
+    InvalidNode(Felt252Wrapper),
+}
+
+/// Error returned when a proof does not match the root it is verified against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidProof;
+
+/// Non empty subtree, described by the path leading from its top to its bottom node.
+///
+/// The bottom node is either a binary node or a leaf.
+struct Subtree {
+    path: BitVec<u8, Msb0>,
+    bottom: Felt252Wrapper,
+}
+
+impl Subtree {
+    fn node(bottom: Felt252Wrapper) -> Self {
+        Self { path: BitVec::new(), bottom }
+    }
+
+    /// Returns the subtree one level higher, on the `bit` side of its parent.
+    fn under(mut self, bit: bool) -> Self {
+        self.path.insert(0, bit);
+        self
+    }
+}
+
+/// A binary Merkle-Patricia tree of height `height`, whose nodes are kept in a [`NodeStorage`].
+///
+/// The tree itself is stateless: every operation takes the root of the version of the tree it
+/// applies to, [`Felt252Wrapper::ZERO`] being the root of the empty tree.
+pub struct StorageMerkleTree<H: HasherT, S: NodeStorage> {
+    storage: S,
+    height: usize,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: HasherT, S: NodeStorage> StorageMerkleTree<H, S> {
+    /// Creates a tree whose keys are `height` bits long.
+    pub fn new(storage: S, height: usize) -> Self {
+        Self { storage, height, _hasher: PhantomData }
+    }
+
+    /// Returns the storage of the tree.
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Sets the value of some leaves, and returns the new root of the tree.
+    ///
+    /// Leaves set to [`Felt252Wrapper::ZERO`] are deleted. If a key appears several times, its last
+    /// value is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The root of the tree to update.
+    /// * `leaves` - The (key, value) pairs to set.
+    pub fn update(
+        &mut self,
+        root: Felt252Wrapper,
+        mut leaves: Vec<(BitVec<u8, Msb0>, Felt252Wrapper)>,
+    ) -> Result<Felt252Wrapper, TrieError> {
+        if leaves.is_empty() {
+            return Ok(root);
+        }
+
+        // The sort is stable, so that the last value of each key is the last one of its run
+        leaves.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let subtree = self.load_root(root)?;
+        let subtree = self.update_subtree(subtree, 0, &leaves)?;
+
+        Ok(subtree.map(|subtree| self.commit_subtree(subtree)).unwrap_or(Felt252Wrapper::ZERO))
+    }
+
+    /// Returns the value stored at `key` in the tree with the given root, or `None` if it does not
+    /// exist.
+    pub fn get(&self, root: Felt252Wrapper, key: &BitSlice<u8, Msb0>) -> Result<Option<Felt252Wrapper>, TrieError> {
+        Ok(self.walk(root, key)?.1)
+    }
+
+    /// Generates a merkle-proof for `key` in the tree with the given root.
+    ///
+    /// The nodes are returned in order, root first, down to the leaf if the key exists, or to the
+    /// node which proves that it does not exist. See [`MerkleTree::get_proof`].
+    ///
+    /// [`MerkleTree::get_proof`]: super::merkle_tree::MerkleTree::get_proof
+    pub fn get_proof(&self, root: Felt252Wrapper, key: &BitSlice<u8, Msb0>) -> Result<Vec<ProofNode>, TrieError> {
+        Ok(self.walk(root, key)?.0)
+    }
+
+    /// Verifies a proof generated by [`Self::get_proof`] against the root of a tree of height
+    /// `height`, and returns the value it proves is stored at `key`, or `None` if it proves that
+    /// `key` does not exist.
+    pub fn verify_proof(
+        root: Felt252Wrapper,
+        key: &BitSlice<u8, Msb0>,
+        proof: &[ProofNode],
+        height: usize,
+    ) -> Result<Option<Felt252Wrapper>, InvalidProof> {
+        if root == Felt252Wrapper::ZERO {
+            return if proof.is_empty() { Ok(None) } else { Err(InvalidProof) };
+        }
+
+        let mut expected = root;
+        let mut current_height = 0;
+        for (i, node) in proof.iter().enumerate() {
+            if current_height >= height {
+                return Err(InvalidProof);
+            }
+            match node {
+                ProofNode::Binary(binary) => {
+                    if Felt252Wrapper(H::hash_elements(binary.left_hash.0, binary.right_hash.0)) != expected {
+                        return Err(InvalidProof);
+                    }
+                    expected = if key[current_height] { binary.right_hash } else { binary.left_hash };
+                    current_height += 1;
+                }
+                ProofNode::Edge(edge) => {
+                    let end = current_height + edge.path.len();
+                    if edge.path.is_empty() || end > height || edge_hash::<H>(edge.child_hash, &edge.path) != expected {
+                        return Err(InvalidProof);
+                    }
+                    if edge.path != key[current_height..end] {
+                        // The key diverges from the only path of the subtree, so it does not exist
+                        return if i == proof.len() - 1 { Ok(None) } else { Err(InvalidProof) };
+                    }
+                    expected = edge.child_hash;
+                    current_height = end;
+                }
+            }
+        }
+
+        if current_height == height { Ok(Some(expected)) } else { Err(InvalidProof) }
+    }
+
+    /// Walks from the root towards the leaf at `key`, returning the nodes along the path and the
+    /// value of the leaf if it is reached.
+    fn walk(
+        &self,
+        root: Felt252Wrapper,
+        key: &BitSlice<u8, Msb0>,
+    ) -> Result<(Vec<ProofNode>, Option<Felt252Wrapper>), TrieError> {
+        let mut nodes = Vec::new();
+        if root == Felt252Wrapper::ZERO {
+            return Ok((nodes, None));
+        }
+
+        let mut current = root;
+        let mut height = 0;
+        while height < self.height {
+            let node = self.storage.get_node(&current).ok_or(TrieError::MissingNode(current))?;
+            nodes.push(node.clone());
+
+            match node {
+                ProofNode::Binary(binary) => {
+                    current = if key[height] { binary.right_hash } else { binary.left_hash };
+                    height += 1;
+                }
+                ProofNode::Edge(edge) if edge.path == key[height..height + edge.path.len()] => {
+                    current = edge.child_hash;
+                    height += edge.path.len();
+                }
+                ProofNode::Edge(_) => return Ok((nodes, None)),
+            }
+        }
+
+        Ok((nodes, Some(current)))
+    }
+
+    fn load_root(&mut self, root: Felt252Wrapper) -> Result<Option<Subtree>, TrieError> {
+        if root == Felt252Wrapper::ZERO { Ok(None) } else { self.load(root, 0).map(Some) }
+    }
+
+    /// Loads the subtree with the given hash, whose top is at `height`.
+    fn load(&mut self, hash: Felt252Wrapper, height: usize) -> Result<Subtree, TrieError> {
+        if height == self.height {
+            return Ok(Subtree::node(hash));
+        }
+
+        match self.storage.get_node(&hash).ok_or(TrieError::MissingNode(hash))? {
+            ProofNode::Binary(_) => Ok(Subtree::node(hash)),
+            ProofNode::Edge(edge) => {
+                // The edge is stored again when the subtree is committed, possibly with another path
+                self.storage.release_node(hash);
+                Ok(Subtree { path: edge.path, bottom: edge.child_hash })
+            }
+        }
+    }
+
+    /// Splits a subtree whose top is at `height` into its left and right subtrees.
+    fn children(
+        &mut self,
+        subtree: Option<Subtree>,
+        height: usize,
+    ) -> Result<(Option<Subtree>, Option<Subtree>), TrieError> {
+        let Some(mut subtree) = subtree else {
+            return Ok((None, None));
+        };
+
+        if subtree.path.is_empty() {
+            return match self.storage.get_node(&subtree.bottom).ok_or(TrieError::MissingNode(subtree.bottom))? {
+                ProofNode::Binary(binary) => {
+                    // A new binary node is stored in its place
+                    self.storage.release_node(subtree.bottom);
+                    Ok((
+                        Some(self.load(binary.left_hash, height + 1)?),
+                        Some(self.load(binary.right_hash, height + 1)?),
+                    ))
+                }
+                // The child of an edge is a binary node or a leaf
+                ProofNode::Edge(_) => Err(TrieError::InvalidNode(subtree.bottom)),
+            };
+        }
+
+        let bit = subtree.path.remove(0);
+        Ok(if bit { (None, Some(subtree)) } else { (Some(subtree), None) })
+    }
+
+    /// Applies the updates of sorted leaves to a subtree whose top is at `height`.
+    ///
+    /// All the leaves share the path leading to the subtree. Subtrees without updates are returned
+    /// as they are, without loading any of their nodes.
+    fn update_subtree(
+        &mut self,
+        subtree: Option<Subtree>,
+        height: usize,
+        leaves: &[(BitVec<u8, Msb0>, Felt252Wrapper)],
+    ) -> Result<Option<Subtree>, TrieError> {
+        let Some((_, value)) = leaves.last() else {
+            return Ok(subtree);
+        };
+
+        if height == self.height {
+            return Ok((*value != Felt252Wrapper::ZERO).then(|| Subtree::node(*value)));
+        }
+
+        let (left, right) = self.children(subtree, height)?;
+        let split = leaves.partition_point(|(key, _)| !key[height]);
+        let left = self.update_subtree(left, height + 1, &leaves[..split])?;
+        let right = self.update_subtree(right, height + 1, &leaves[split..])?;
+
+        Ok(match (left, right) {
+            (Some(left), Some(right)) => {
+                let left_hash = self.commit_subtree(left);
+                let right_hash = self.commit_subtree(right);
+                let hash = Felt252Wrapper(H::hash_elements(left_hash.0, right_hash.0));
+                self.storage.insert_node(hash, ProofNode::Binary(BinaryProofNode { left_hash, right_hash }));
+                Some(Subtree::node(hash))
+            }
+            (Some(left), None) => Some(left.under(false)),
+            (None, Some(right)) => Some(right.under(true)),
+            (None, None) => None,
+        })
+    }
+
+    /// Stores the edge leading to a subtree, if any, and returns the hash of the subtree.
+    fn commit_subtree(&mut self, subtree: Subtree) -> Felt252Wrapper {
+        if subtree.path.is_empty() {
+            return subtree.bottom;
+        }
+
+        let hash = edge_hash::<H>(subtree.bottom, &subtree.path);
+        self.storage
+            .insert_node(hash, ProofNode::Edge(EdgeProofNode { path: subtree.path, child_hash: subtree.bottom }));
+        hash
+    }
+}
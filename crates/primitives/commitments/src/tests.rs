@@ -1,11 +1,16 @@
+use bitvec::prelude::{bits, bitvec, Msb0};
 use mp_felt::Felt252Wrapper;
 use mp_hashers::pedersen::PedersenHasher;
 use mp_hashers::HasherT;
-use starknet_api::stdlib::collections::HashMap;
+use starknet_api::stdlib::collections::{HashMap, HashSet};
 use starknet_core::crypto::compute_hash_on_elements;
 use starknet_crypto::FieldElement;
 
-use super::merkle_patricia_tree::merkle_node::{BinaryNode, Direction, Node, NodeId};
+use super::merkle_patricia_tree::merkle_node::{path_to_felt, BinaryNode, Direction, EdgeNode, Node, NodeId};
+use super::{
+    calculate_contract_state_hash, calculate_global_state_root, calculate_state_tree_root, InvalidProof, NodeStorage,
+    ProofNode, StateCommitmentTree, StateTrie, TrieError,
+};
 
 pub const PEDERSEN_ZERO_HASH: &str = "0x49EE3EBA8C1600700EE1B87EB599F16716B0B1022947733551FDE4050CA6804";
 
@@ -98,6 +103,63 @@ fn test_binary_node_implementations() {
     assert_eq!(debug_string, "BinaryNode { hash: None, height: 0, left: NodeId(0), right: NodeId(1) }");
 }
 
+// Test vectors of the node hashes from the Starknet reference implementation, see
+// https://github.com/starkware-libs/cairo-lang/blob/v0.11.0/src/starkware/starkware_utils/commitment_tree/patricia_tree/nodes_test.py
+#[test]
+fn test_binary_node_hash_matches_starknet() {
+    let mut nodes: HashMap<NodeId, Node> = HashMap::new();
+    nodes.insert(NodeId(0), Node::Leaf(Felt252Wrapper::from_hex_be("0x1234").unwrap()));
+    nodes.insert(NodeId(1), Node::Leaf(Felt252Wrapper::from_hex_be("0xabcd").unwrap()));
+
+    let mut binary_node = BinaryNode { hash: None, height: 0, left: NodeId(0), right: NodeId(1) };
+    binary_node.calculate_hash::<PedersenHasher>(&nodes);
+
+    let expected =
+        Felt252Wrapper::from_hex_be("0x615bb8d47888d2987ad0c63fc06e9e771930986a4dd8adc55617febfcf3639e").unwrap();
+    assert_eq!(binary_node.hash, Some(expected));
+}
+
+#[test]
+fn test_edge_node_hash_matches_starknet() {
+    let mut nodes: HashMap<NodeId, Node> = HashMap::new();
+    nodes.insert(NodeId(0), Node::Leaf(Felt252Wrapper::from_hex_be("0x1234abcd").unwrap()));
+
+    // The path is 42, on 6 bits: it is not a whole number of bytes
+    let path = bitvec![u8, Msb0; 1, 0, 1, 0, 1, 0];
+    let mut edge_node = EdgeNode { hash: None, height: 0, path, child: NodeId(0) };
+    edge_node.calculate_hash::<PedersenHasher>(&nodes);
+
+    let expected =
+        Felt252Wrapper::from_hex_be("0x1d937094c09b5f8e26a662d21911871e3cbc6858d55cc49af9848ea6fed4e9").unwrap();
+    assert_eq!(edge_node.hash, Some(expected));
+}
+
+#[test]
+fn test_edge_paths_are_right_aligned() {
+    assert_eq!(path_to_felt(bits![u8, Msb0; 1, 0, 1, 0, 1, 0]), Felt252Wrapper::from(42_u64));
+
+    // The longest path, whose bits would overflow the field if they were left aligned
+    let path = bitvec![u8, Msb0; 1; 251];
+    let expected =
+        Felt252Wrapper::from_hex_be("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap();
+    assert_eq!(path_to_felt(&path), expected);
+}
+
+// The state of a contract of Starknet, see
+// https://github.com/eqlabs/pathfinder/blob/v0.5.6/crates/merkle-tree/src/contract_state.rs
+#[test]
+fn test_contract_state_hash_matches_starknet() {
+    let class_hash =
+        Felt252Wrapper::from_hex_be("0x2ff4903e17f87b298ded00c44bfeb22874c5f73be2ced8f1d9d9556fb509779").unwrap();
+    let storage_root =
+        Felt252Wrapper::from_hex_be("0x4fb440e8ca9b74fc12a22ebffe0bc0658206337897226117b985434c239c028").unwrap();
+    let nonce = Felt252Wrapper::ZERO;
+
+    let expected =
+        Felt252Wrapper::from_hex_be("0x7161b591c893836263a64f2a7e0d829c92f6956148a60ce5e99a3f55c7973f3").unwrap();
+    assert_eq!(calculate_contract_state_hash::<PedersenHasher>(class_hash, storage_root, nonce), expected);
+}
+
 #[test]
 fn test_pedersen_hash_elements_zero() {
     let elements = vec![Felt252Wrapper::ZERO, Felt252Wrapper::ONE];
@@ -116,4 +178,194 @@ fn test_pedersen_hash_elements_empty() {
     );
 }
 
+#[test]
+fn test_state_tree_root_ignores_zero_leaves() {
+    let leaves = [(Felt252Wrapper::ONE, Felt252Wrapper::TWO)];
+    let with_zero_leaf = [(Felt252Wrapper::ONE, Felt252Wrapper::TWO), (Felt252Wrapper::TWO, Felt252Wrapper::ZERO)];
+
+    assert_eq!(
+        calculate_state_tree_root::<PedersenHasher>(&leaves),
+        calculate_state_tree_root::<PedersenHasher>(&with_zero_leaf)
+    );
+}
+
+#[test]
+fn test_global_state_root_without_classes() {
+    let contracts_tree_root = Felt252Wrapper::from(42_u64);
+
+    assert_eq!(
+        calculate_global_state_root::<TestHasher>(contracts_tree_root, Felt252Wrapper::ZERO),
+        contracts_tree_root
+    );
+    assert_ne!(
+        calculate_global_state_root::<TestHasher>(contracts_tree_root, Felt252Wrapper::ONE),
+        contracts_tree_root
+    );
+}
+
+/// Keeps every node, and counts how many times each one is referenced by the last version of the
+/// trie.
+#[derive(Default)]
+struct MemoryNodeStorage {
+    nodes: HashMap<Felt252Wrapper, ProofNode>,
+    references: HashMap<Felt252Wrapper, i64>,
+}
+
+impl MemoryNodeStorage {
+    /// Returns the nodes referenced by the last version of the trie.
+    fn referenced_nodes(&self) -> HashSet<Felt252Wrapper> {
+        assert!(self.references.values().all(|references| *references >= 0));
+        self.references.iter().filter(|(_, references)| **references > 0).map(|(hash, _)| *hash).collect()
+    }
+
+    /// Returns the nodes reachable from a root.
+    fn reachable_nodes(&self, root: Felt252Wrapper) -> HashSet<Felt252Wrapper> {
+        let mut reachable = HashSet::new();
+        let mut to_visit = vec![root];
+        while let Some(hash) = to_visit.pop() {
+            match self.nodes.get(&hash) {
+                Some(ProofNode::Binary(binary)) => to_visit.extend([binary.left_hash, binary.right_hash]),
+                Some(ProofNode::Edge(edge)) => to_visit.push(edge.child_hash),
+                // A leaf
+                None => continue,
+            }
+            reachable.insert(hash);
+        }
+        reachable
+    }
+}
+
+impl NodeStorage for MemoryNodeStorage {
+    fn get_node(&self, hash: &Felt252Wrapper) -> Option<ProofNode> {
+        self.nodes.get(hash).cloned()
+    }
+
+    fn insert_node(&mut self, hash: Felt252Wrapper, node: ProofNode) {
+        self.nodes.insert(hash, node);
+        *self.references.entry(hash).or_default() += 1;
+    }
+
+    fn release_node(&mut self, hash: Felt252Wrapper) {
+        *self.references.entry(hash).or_default() -= 1;
+    }
+}
+
+fn leaves(pairs: &[(u64, u64)]) -> Vec<(Felt252Wrapper, Felt252Wrapper)> {
+    pairs.iter().map(|(key, value)| (Felt252Wrapper::from(*key), Felt252Wrapper::from(*value))).collect()
+}
+
+#[test]
+fn test_state_trie_updates_incrementally() {
+    let first = leaves(&[(1, 10), (2, 20), (3, 30), (1000, 40), (u64::MAX, 50)]);
+    let update = leaves(&[(2, 21), (3, 0), (4, 60), (2, 22)]);
+    let last = leaves(&[(1, 10), (2, 22), (1000, 40), (u64::MAX, 50), (4, 60)]);
+
+    let mut trie = StateTrie::<PedersenHasher, _>::new(MemoryNodeStorage::default());
+    let first_root = trie.update(Felt252Wrapper::ZERO, first.clone()).unwrap();
+    assert_eq!(first_root, calculate_state_tree_root::<PedersenHasher>(&first));
+
+    let last_root = trie.update(first_root, update).unwrap();
+    assert_eq!(last_root, calculate_state_tree_root::<PedersenHasher>(&last));
+
+    // The previous version of the trie can still be read and proven against
+    let (key, value) = first[2];
+    assert_eq!(trie.get(first_root, key).unwrap(), Some(value));
+    assert_eq!(trie.get(last_root, key).unwrap(), None);
+
+    let mut tree = StateCommitmentTree::<PedersenHasher>::default();
+    first.iter().for_each(|(key, value)| tree.set(*key, *value));
+    tree.commit();
+    assert_eq!(trie.get_proof(first_root, key).unwrap(), tree.get_proof(key));
+
+    // Deleting every leaf empties the trie
+    let empty = last.iter().map(|(key, _)| (*key, Felt252Wrapper::ZERO));
+    assert_eq!(trie.update(last_root, empty).unwrap(), Felt252Wrapper::ZERO);
+}
+
+#[test]
+fn test_state_trie_releases_the_nodes_it_replaces() {
+    let mut trie = StateTrie::<PedersenHasher, _>::new(MemoryNodeStorage::default());
+    let mut root = Felt252Wrapper::ZERO;
+    for update in [
+        leaves(&[(1, 10), (2, 20), (3, 30), (1000, 40), (u64::MAX, 50)]),
+        leaves(&[(2, 21), (3, 0), (4, 60)]),
+        // Setting a leaf back to a previous value stores the same nodes again
+        leaves(&[(3, 30), (4, 0)]),
+        leaves(&[(1, 0), (2, 0), (3, 0)]),
+    ] {
+        root = trie.update(root, update).unwrap();
+    }
+
+    let storage = trie.into_storage();
+    assert_eq!(storage.referenced_nodes(), storage.reachable_nodes(root));
+}
+
+#[test]
+fn test_state_trie_rejects_invalid_nodes() {
+    let mut trie = StateTrie::<PedersenHasher, _>::new(MemoryNodeStorage::default());
+    let root = trie.update(Felt252Wrapper::ZERO, leaves(&[(1, 10), (2, 20)])).unwrap();
+
+    // The keys share a long prefix: the root is an edge leading to a binary node, replaced by
+    // another edge
+    let mut storage = trie.into_storage();
+    let Some(ProofNode::Edge(edge)) = storage.nodes.get(&root).cloned() else { panic!("the root should be an edge") };
+    storage.nodes.insert(edge.child_hash, ProofNode::Edge(edge.clone()));
+    let mut trie = StateTrie::<PedersenHasher, _>::new(storage);
+    assert_eq!(trie.update(root, leaves(&[(3, 30)])), Err(TrieError::InvalidNode(edge.child_hash)));
+
+    let mut trie = StateTrie::<PedersenHasher, _>::new(MemoryNodeStorage::default());
+    assert_eq!(trie.update(root, leaves(&[(3, 30)])), Err(TrieError::MissingNode(root)));
+}
+
+#[test]
+fn test_state_trie_proofs_are_verified_against_the_root() {
+    let pairs = leaves(&[(1, 10), (2, 20), (3, 30), (1000, 40), (u64::MAX, 50)]);
+    let mut trie = StateTrie::<PedersenHasher, _>::new(MemoryNodeStorage::default());
+    let root = trie.update(Felt252Wrapper::ZERO, pairs.clone()).unwrap();
+
+    for (key, value) in pairs {
+        let proof = trie.get_proof(root, key).unwrap();
+        assert_eq!(StateTrie::<PedersenHasher, MemoryNodeStorage>::verify_proof(root, key, &proof), Ok(Some(value)));
+    }
+
+    // The key does not exist
+    let missing_key = Felt252Wrapper::from(5_u64);
+    let proof = trie.get_proof(root, missing_key).unwrap();
+    assert_eq!(StateTrie::<PedersenHasher, MemoryNodeStorage>::verify_proof(root, missing_key, &proof), Ok(None));
+
+    // A proof is only valid for its own root and key
+    let (key, _) = pairs[0];
+    let proof = trie.get_proof(root, key).unwrap();
+    assert_eq!(
+        StateTrie::<PedersenHasher, MemoryNodeStorage>::verify_proof(Felt252Wrapper::ONE, key, &proof),
+        Err(InvalidProof)
+    );
+    assert_eq!(
+        StateTrie::<PedersenHasher, MemoryNodeStorage>::verify_proof(root, missing_key, &proof),
+        Err(InvalidProof)
+    );
+    assert_eq!(StateTrie::<PedersenHasher, MemoryNodeStorage>::verify_proof(root, key, &proof[1..]), Err(InvalidProof));
+}
+
+#[test]
+fn test_state_tree_keys_use_every_bit_of_the_index() {
+    // e.g. the low and high words of an ERC20 Uint256 balance
+    let low = leaves(&[(1, 10)]);
+    let high = leaves(&[(2, 10)]);
+    assert_ne!(calculate_state_tree_root::<PedersenHasher>(&low), calculate_state_tree_root::<PedersenHasher>(&high));
+
+    let mut tree = StateCommitmentTree::<PedersenHasher>::default();
+    tree.set(Felt252Wrapper::ONE, Felt252Wrapper::from(10_u64));
+    assert_eq!(tree.get(Felt252Wrapper::ONE), Some(Felt252Wrapper::from(10_u64)));
+    assert_eq!(tree.get(Felt252Wrapper::TWO), None);
+
+    // The most significant bit of the largest index is kept too
+    let max_index =
+        Felt252Wrapper::from_hex_be("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap();
+    let mut tree = StateCommitmentTree::<PedersenHasher>::default();
+    tree.set(max_index, Felt252Wrapper::ONE);
+    assert_eq!(tree.get(max_index), Some(Felt252Wrapper::ONE));
+    assert_eq!(tree.get(Felt252Wrapper(max_index.0 - FieldElement::ONE)), None);
+}
+
 // TODO: add tests to poseidon hasher too
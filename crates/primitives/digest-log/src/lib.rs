@@ -20,7 +20,7 @@ mod error;
 mod tests;
 
 pub use error::FindLogError;
-use mp_block::Block as StarknetBlock;
+use mp_block::{Block as StarknetBlock, LegacyBlock as LegacyStarknetBlock};
 use parity_scale_codec::{Decode, Encode};
 use sp_runtime::generic::{Digest, OpaqueDigestItemId};
 use sp_runtime::ConsensusEngineId;
@@ -33,13 +33,17 @@ pub const MADARA_ENGINE_ID: ConsensusEngineId = [b'm', b'a', b'd', b'a'];
 /// but other usecases may appears later on.
 #[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
 pub enum Log {
+    /// A block authored before the L1 gas price was added to the header.
     #[codec(index = 0)]
+    LegacyBlock(LegacyStarknetBlock),
+    #[codec(index = 1)]
     Block(StarknetBlock),
 }
 
 /// Return the wrapped [StarknetBlock] contained in a given [Digest]
 pub fn find_starknet_block(digest: &Digest) -> Result<StarknetBlock, FindLogError> {
     find_log(digest).map(|log| match log {
+        Log::LegacyBlock(b) => b.into(),
         Log::Block(b) => b,
     })
 }
@@ -15,6 +15,20 @@ fn log_is_found() {
     assert_eq!(find_starknet_block(&digest).unwrap(), block);
 }
 
+#[test]
+fn legacy_log_is_found() {
+    let mut digest = Digest::default();
+    let header = mp_block::LegacyHeader { block_number: 1, ..Default::default() };
+    let block = LegacyStarknetBlock::new(header, vec![]);
+
+    digest.push(DigestItem::Consensus(MADARA_ENGINE_ID, Log::LegacyBlock(block.clone()).encode()));
+
+    let starknet_block = find_starknet_block(&digest).unwrap();
+    assert_eq!(starknet_block, StarknetBlock::from(block));
+    assert_eq!(starknet_block.header().block_number, 1);
+    assert_eq!(starknet_block.header().l1_gas_price, mp_block::LEGACY_L1_GAS_PRICE);
+}
+
 #[test]
 fn multiple_logs() {
     let mut digest = Digest::default();
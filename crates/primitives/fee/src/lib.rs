@@ -25,7 +25,7 @@ use hashbrown::HashMap;
 use mp_state::{FeeConfig, StateChanges};
 use sp_arithmetic::fixed_point::{FixedPointNumber, FixedU128};
 use sp_arithmetic::traits::Zero;
-use starknet_api::api_core::EntryPointSelector;
+use starknet_api::api_core::{ContractAddress, EntryPointSelector};
 use starknet_api::calldata;
 use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
@@ -37,6 +37,10 @@ pub const INITIAL_GAS: u64 = u64::MAX;
 pub const FEE_TRANSFER_N_STORAGE_CHANGES: u8 = 2; // Sender and sequencer balance update.
 /// Number of storage updates to actually charge for the fee transfer tx.
 pub const FEE_TRANSFER_N_STORAGE_CHANGES_TO_CHARGE: u8 = FEE_TRANSFER_N_STORAGE_CHANGES - 1; // Exclude the sequencer balance update, since it's charged once throughout the batch.
+/// Version of the v3 transactions, which pay their fee in STRK.
+const V3_VERSION: &str = "0x3";
+/// Version of the v3 transactions when simulated.
+const QUERY_V3_VERSION: &str = "0x100000000000000000000000000000003";
 
 pub static VM_RESOURCE_FEE_COSTS: [(&str, FixedU128); 7] = [
     ("n_steps", FixedU128::from_inner(10_000_000_000_000_000)),
@@ -111,15 +115,36 @@ pub fn charge_fee<S: State + StateChanges + FeeConfig>(
         return Ok((actual_fee, None));
     }
 
-    let fee_transfer_call_info = execute_fee_transfer(state, block_context, account_tx_context, actual_fee)?;
+    let fee_token_address = fee_token_address(&*state, block_context, &account_tx_context);
+    let fee_transfer_call_info =
+        execute_fee_transfer(state, block_context, fee_token_address, account_tx_context, actual_fee)?;
 
     Ok((actual_fee, Some(fee_transfer_call_info)))
 }
 
+/// Returns the address of the token in which the fee of a transaction is paid.
+///
+/// The v3 transactions pay their fee in STRK, and the previous versions in ETH, the fee token of
+/// the block context.
+pub fn fee_token_address<S: FeeConfig>(
+    state: &S,
+    block_context: &BlockContext,
+    account_tx_context: &AccountTransactionContext,
+) -> ContractAddress {
+    let version = account_tx_context.version.0;
+    if version == StarkFelt::try_from(V3_VERSION).unwrap() || version == StarkFelt::try_from(QUERY_V3_VERSION).unwrap()
+    {
+        state.strk_fee_token_address()
+    } else {
+        block_context.fee_token_address
+    }
+}
+
 /// Executes the fee transfer tx
 fn execute_fee_transfer(
     state: &mut dyn State,
     block_context: &BlockContext,
+    fee_token_address: ContractAddress,
     account_tx_context: AccountTransactionContext,
     actual_fee: Fee,
 ) -> TransactionExecutionResult<CallInfo> {
@@ -134,7 +159,7 @@ fn execute_fee_transfer(
     // The most significant 128 bits of the amount transferred.
     let msb_amount = StarkFelt::from(0_u64);
 
-    let storage_address = block_context.fee_token_address;
+    let storage_address = fee_token_address;
     let fee_transfer_call = CallEntryPoint {
         class_hash: None,
         code_address: None,
@@ -166,6 +191,9 @@ fn execute_fee_transfer(
 }
 
 /// Computes the fees from the execution resources.
+///
+/// All the resources are converted into L1 gas, paid at the `gas_price` of the block context, which
+/// is the L1 gas price of the block.
 pub fn calculate_tx_fee(resources: &ResourcesMapping, block_context: &BlockContext) -> TransactionExecutionResult<Fee> {
     let (l1_gas_usage, vm_resources) = extract_l1_gas_and_vm_usage(resources);
     let l1_gas_by_vm_usage = calculate_l1_gas_by_vm_usage(block_context, &vm_resources)?;
@@ -0,0 +1,117 @@
+//! The price of the gas on L1, used to compute the fees of the transactions
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_inherents::{InherentData, InherentIdentifier, IsFatalError};
+use thiserror_no_std::Error;
+
+/// The identifier for the `l1_gas_price` inherent.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"l1gaspr0";
+
+/// Default value, in wei, in case the L1 gas price was never set.
+pub const DEFAULT_L1_GAS_PRICE: u128 = 10;
+
+/// The inherent type for the L1 gas price, in wei.
+pub type InherentType = u128;
+
+/// Maximum deviation, in percent, of the L1 gas price of an imported block from the one given by
+/// the oracle of the importing node.
+pub const L1_GAS_PRICE_TOLERANCE_PERCENT: u128 = 25;
+
+/// Returns whether the `l1_gas_price` of a block deviates from the `reference_price` of the
+/// importing node by at most [L1_GAS_PRICE_TOLERANCE_PERCENT].
+pub fn is_within_tolerance(l1_gas_price: InherentType, reference_price: InherentType) -> bool {
+    let tolerance = reference_price.saturating_mul(L1_GAS_PRICE_TOLERANCE_PERCENT) / 100;
+    l1_gas_price.abs_diff(reference_price) <= tolerance
+}
+
+#[derive(Error, sp_core::RuntimeDebug)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+/// Error types when working with the L1 gas price.
+pub enum InherentError {
+    /// Submitted price must not be zero.
+    #[error("L1 gas price is zero")]
+    ZeroL1GasPrice,
+    /// Submitted price deviates too much from the one of the importing node.
+    #[error("L1 gas price is too far from the one of the node")]
+    L1GasPriceOutOfTolerance,
+}
+
+impl IsFatalError for InherentError {
+    fn is_fatal_error(&self) -> bool {
+        match self {
+            InherentError::ZeroL1GasPrice | InherentError::L1GasPriceOutOfTolerance => true,
+        }
+    }
+}
+
+/// Auxiliary trait to extract L1 gas price inherent data.
+pub trait L1GasPriceInherentData {
+    /// Get L1 gas price inherent data.
+    fn l1_gas_price_inherent_data(&self) -> Result<Option<InherentType>, sp_inherents::Error>;
+}
+
+impl L1GasPriceInherentData for InherentData {
+    fn l1_gas_price_inherent_data(&self) -> Result<Option<InherentType>, sp_inherents::Error> {
+        self.get_data(&INHERENT_IDENTIFIER)
+    }
+}
+
+#[cfg(feature = "client")]
+mod reexport_for_client_only {
+    use std::boxed::Box;
+
+    use parity_scale_codec::{Decode, Encode};
+
+    use super::*;
+
+    impl InherentError {
+        /// Try to create an instance out of the given identifier and data.
+        pub fn try_from(id: &InherentIdentifier, mut data: &[u8]) -> Option<Self> {
+            if id == &INHERENT_IDENTIFIER { <InherentError as Decode>::decode(&mut data).ok() } else { None }
+        }
+    }
+
+    #[derive(Copy, Clone, Default, Decode, Encode, sp_core::RuntimeDebug)]
+    /// The inherent data provider for the L1 gas price.
+    ///
+    /// When no price is available, no inherent data is provided and the block keeps the price of
+    /// its parent. When importing a block, the price provided is the one its price is checked
+    /// against, and none means it is not checked.
+    pub struct InherentDataProvider {
+        /// The L1 gas price field.
+        pub l1_gas_price: Option<InherentType>,
+    }
+
+    impl InherentDataProvider {
+        /// Create `Self` using the given `l1_gas_price`.
+        pub fn new(l1_gas_price: Option<InherentType>) -> Self {
+            Self { l1_gas_price }
+        }
+
+        /// Returns the L1 gas price of this inherent data provider.
+        pub fn l1_gas_price(&self) -> Option<InherentType> {
+            self.l1_gas_price
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl sp_inherents::InherentDataProvider for InherentDataProvider {
+        async fn provide_inherent_data(&self, inherent_data: &mut InherentData) -> Result<(), sp_inherents::Error> {
+            match self.l1_gas_price {
+                Some(l1_gas_price) => inherent_data.put_data(INHERENT_IDENTIFIER, &l1_gas_price),
+                None => Ok(()),
+            }
+        }
+
+        async fn try_handle_error(
+            &self,
+            identifier: &InherentIdentifier,
+            error: &[u8],
+        ) -> Option<Result<(), sp_inherents::Error>> {
+            Some(Err(sp_inherents::Error::Application(Box::from(InherentError::try_from(identifier, error)?))))
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+pub use reexport_for_client_only::*;
@@ -34,6 +34,14 @@ pub trait FeeConfig {
     ///
     /// * `bool` - Is the fee disabled
     fn is_transaction_fee_disabled(&self) -> bool;
+
+    /// This function reads the StrkFeeTokenAddress from the pallet, the address of the token in
+    /// which the v3 transactions pay their fee.
+    ///
+    /// # Returns
+    ///
+    /// * `ContractAddress` - The address of the STRK fee token
+    fn strk_fee_token_address(&self) -> ContractAddress;
 }
 
 /// A simple implementation of `StateReader` using `HashMap`s as storage.
@@ -23,6 +23,12 @@ pub const STARKNET_CONTRACT_CLASS: &[u8] = b"ContractClasses";
 pub const STARKNET_NONCE: &[u8] = b"Nonces";
 /// Starknet storage
 pub const STARKNET_STORAGE: &[u8] = b"StorageView";
+/// Starknet compiled class hash storage item.
+pub const STARKNET_COMPILED_CLASS_HASH: &[u8] = b"CompiledClassHashes";
+/// Starknet fee token address storage item.
+pub const STARKNET_FEE_TOKEN_ADDRESS: &[u8] = b"FeeTokenAddress";
+/// Starknet STRK fee token address storage item.
+pub const STARKNET_STRK_FEE_TOKEN_ADDRESS: &[u8] = b"StrkFeeTokenAddress";
 
 /// The schema version for Pallet Starknet's storage.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -33,6 +39,11 @@ pub enum StarknetStorageSchemaVersion {
     Undefined,
     /// Schema V1.
     V1,
+    /// Schema V2.
+    ///
+    /// `ContractClassHashes`, `Nonces` and `StorageView` use the `Blake2_128Concat` hasher
+    /// instead of `Identity`.
+    V2,
 }
 
 impl Default for StarknetStorageSchemaVersion {
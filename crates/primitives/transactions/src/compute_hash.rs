@@ -3,18 +3,21 @@ use alloc::vec::Vec;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use starknet_core::crypto::compute_hash_on_elements;
-use starknet_crypto::FieldElement;
+use starknet_crypto::{poseidon_hash_many, FieldElement};
 
 use super::{
-    DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2, DeployAccountTransaction,
-    HandleL1MessageTransaction, InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1, Transaction,
-    UserTransaction, SIMULATE_TX_VERSION_OFFSET,
+    DataAvailabilityMode, DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2,
+    DeclareTransactionV3, DeployAccountTransaction, DeployAccountTransactionV3, HandleL1MessageTransaction,
+    InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1, InvokeTransactionV3, ResourceBounds,
+    ResourceBoundsMapping, Transaction, UserTransaction, SIMULATE_TX_VERSION_OFFSET,
 };
 
 const DECLARE_PREFIX: &[u8] = b"declare";
 const DEPLOY_ACCOUNT_PREFIX: &[u8] = b"deploy_account";
 const INVOKE_PREFIX: &[u8] = b"invoke";
 const L1_HANDLER_PREFIX: &[u8] = b"l1_handler";
+const L1_GAS: &[u8] = b"L1_GAS";
+const L2_GAS: &[u8] = b"L2_GAS";
 
 pub trait ComputeTransactionHash {
     fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper;
@@ -79,6 +82,7 @@ impl ComputeTransactionHash for InvokeTransaction {
         match self {
             InvokeTransaction::V0(tx) => tx.compute_hash::<H>(chain_id, is_query),
             InvokeTransaction::V1(tx) => tx.compute_hash::<H>(chain_id, is_query),
+            InvokeTransaction::V3(tx) => tx.compute_hash::<H>(chain_id, is_query),
         }
     }
 }
@@ -166,6 +170,7 @@ impl ComputeTransactionHash for DeclareTransaction {
             DeclareTransaction::V0(tx) => tx.compute_hash::<H>(chain_id, is_query),
             DeclareTransaction::V1(tx) => tx.compute_hash::<H>(chain_id, is_query),
             DeclareTransaction::V2(tx) => tx.compute_hash::<H>(chain_id, is_query),
+            DeclareTransaction::V3(tx) => tx.compute_hash::<H>(chain_id, is_query),
         }
     }
 }
@@ -236,6 +241,113 @@ impl DeployAccountTransaction {
     }
 }
 
+/// Packs the bounds of a resource in a single felt: its name, then its max amount on 64 bits and
+/// its max price per unit on 128 bits.
+fn resource_bounds_felt(resource_name: &[u8], bounds: &ResourceBounds) -> FieldElement {
+    let mut bytes = [0u8; 32];
+    bytes[8 - resource_name.len()..8].copy_from_slice(resource_name);
+    bytes[8..16].copy_from_slice(&bounds.max_amount.to_be_bytes());
+    bytes[16..].copy_from_slice(&bounds.max_price_per_unit.to_be_bytes());
+    FieldElement::from_bytes_be(&bytes).unwrap()
+}
+
+fn tip_and_resource_bounds_hash(tip: u64, resource_bounds: &ResourceBoundsMapping) -> FieldElement {
+    poseidon_hash_many(&[
+        tip.into(),
+        resource_bounds_felt(L1_GAS, &resource_bounds.l1_gas),
+        resource_bounds_felt(L2_GAS, &resource_bounds.l2_gas),
+    ])
+}
+
+fn data_availability_modes(
+    nonce_data_availability_mode: DataAvailabilityMode,
+    fee_data_availability_mode: DataAvailabilityMode,
+) -> FieldElement {
+    let mode_bits = |mode| match mode {
+        DataAvailabilityMode::L1 => 0u64,
+        DataAvailabilityMode::L2 => 1u64,
+    };
+    FieldElement::from((mode_bits(nonce_data_availability_mode) << 32) + mode_bits(fee_data_availability_mode))
+}
+
+fn v3_version(is_query: bool) -> FieldElement {
+    let version = FieldElement::THREE;
+    if is_query { SIMULATE_TX_VERSION_OFFSET + version } else { version }
+}
+
+// The v3 transaction hashes are always computed with Poseidon, whatever the hasher of the chain.
+impl ComputeTransactionHash for InvokeTransactionV3 {
+    fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper {
+        let prefix = FieldElement::from_byte_slice_be(INVOKE_PREFIX).unwrap();
+
+        poseidon_hash_many(&[
+            prefix,
+            v3_version(is_query),
+            self.sender_address.into(),
+            tip_and_resource_bounds_hash(self.tip, &self.resource_bounds),
+            poseidon_hash_many(convert_calldata(&self.paymaster_data)),
+            chain_id.into(),
+            self.nonce.into(),
+            data_availability_modes(self.nonce_data_availability_mode, self.fee_data_availability_mode),
+            poseidon_hash_many(convert_calldata(&self.account_deployment_data)),
+            poseidon_hash_many(convert_calldata(&self.calldata)),
+        ])
+        .into()
+    }
+}
+
+impl ComputeTransactionHash for DeclareTransactionV3 {
+    fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper {
+        let prefix = FieldElement::from_byte_slice_be(DECLARE_PREFIX).unwrap();
+
+        poseidon_hash_many(&[
+            prefix,
+            v3_version(is_query),
+            self.sender_address.into(),
+            tip_and_resource_bounds_hash(self.tip, &self.resource_bounds),
+            poseidon_hash_many(convert_calldata(&self.paymaster_data)),
+            chain_id.into(),
+            self.nonce.into(),
+            data_availability_modes(self.nonce_data_availability_mode, self.fee_data_availability_mode),
+            poseidon_hash_many(convert_calldata(&self.account_deployment_data)),
+            self.class_hash.into(),
+            self.compiled_class_hash.into(),
+        ])
+        .into()
+    }
+}
+
+impl ComputeTransactionHash for DeployAccountTransactionV3 {
+    fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper {
+        let prefix = FieldElement::from_byte_slice_be(DEPLOY_ACCOUNT_PREFIX).unwrap();
+
+        poseidon_hash_many(&[
+            prefix,
+            v3_version(is_query),
+            self.get_account_address(),
+            tip_and_resource_bounds_hash(self.tip, &self.resource_bounds),
+            poseidon_hash_many(convert_calldata(&self.paymaster_data)),
+            chain_id.into(),
+            self.nonce.into(),
+            data_availability_modes(self.nonce_data_availability_mode, self.fee_data_availability_mode),
+            poseidon_hash_many(convert_calldata(&self.constructor_calldata)),
+            self.class_hash.into(),
+            self.contract_address_salt.into(),
+        ])
+        .into()
+    }
+}
+
+impl DeployAccountTransactionV3 {
+    pub fn get_account_address(&self) -> FieldElement {
+        DeployAccountTransaction::calculate_contract_address(
+            self.contract_address_salt.into(),
+            self.class_hash.into(),
+            convert_calldata(&self.constructor_calldata),
+        )
+    }
+}
+
 impl ComputeTransactionHash for HandleL1MessageTransaction {
     fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper {
         let prefix = FieldElement::from_byte_slice_be(L1_HANDLER_PREFIX).unwrap();
@@ -266,6 +378,7 @@ impl ComputeTransactionHash for Transaction {
             Transaction::DeployAccount(tx) => tx.compute_hash::<H>(chain_id, is_query),
             Transaction::Invoke(tx) => tx.compute_hash::<H>(chain_id, is_query),
             Transaction::L1Handler(tx) => tx.compute_hash::<H>(chain_id, is_query),
+            Transaction::DeployAccountV3(tx) => tx.compute_hash::<H>(chain_id, is_query),
         }
     }
 }
@@ -273,9 +386,10 @@ impl ComputeTransactionHash for Transaction {
 impl ComputeTransactionHash for UserTransaction {
     fn compute_hash<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> Felt252Wrapper {
         match self {
-            UserTransaction::Declare(tx, _) => tx.compute_hash::<H>(chain_id, is_query),
+            UserTransaction::Declare(tx, _, _) => tx.compute_hash::<H>(chain_id, is_query),
             UserTransaction::DeployAccount(tx) => tx.compute_hash::<H>(chain_id, is_query),
             UserTransaction::Invoke(tx) => tx.compute_hash::<H>(chain_id, is_query),
+            UserTransaction::DeployAccountV3(tx) => tx.compute_hash::<H>(chain_id, is_query),
         }
     }
 }
@@ -3,16 +3,18 @@ use alloc::sync::Arc;
 use blockifier::execution::contract_class::ContractClass;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
 use starknet_api::api_core::{calculate_contract_address, ContractAddress, PatriciaKey};
 use starknet_api::hash::StarkFelt;
 use starknet_api::transaction::Calldata;
 use starknet_crypto::FieldElement;
 
+use super::{data_availability_modes, resource_bounds_felt, L1_GAS};
 use crate::compute_hash::ComputeTransactionHash;
 use crate::{
-    DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2, DeployAccountTransaction,
-    HandleL1MessageTransaction, InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1, Transaction,
-    UserTransaction,
+    DataAvailabilityMode, DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2,
+    DeployAccountTransaction, HandleL1MessageTransaction, InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1,
+    InvokeTransactionV3, ResourceBounds, ResourceBoundsMapping, Transaction, UserTransaction,
 };
 
 #[test]
@@ -97,7 +99,8 @@ fn test_declare_v0_tx_hash() {
     let tx_hash = generic_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 
-    let user_transaction = UserTransaction::Declare(declare_v0_transaction, ContractClass::V0(Default::default()));
+    let user_transaction =
+        UserTransaction::Declare(declare_v0_transaction, ContractClass::V0(Default::default()), None);
     let tx_hash = user_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 }
@@ -130,7 +133,8 @@ fn test_declare_v1_tx_hash() {
     let tx_hash = generic_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 
-    let user_transaction = UserTransaction::Declare(declare_v1_transaction, ContractClass::V0(Default::default()));
+    let user_transaction =
+        UserTransaction::Declare(declare_v1_transaction, ContractClass::V0(Default::default()), None);
     let tx_hash = user_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 }
@@ -164,7 +168,8 @@ fn test_declare_v2_tx_hash() {
     let tx_hash = generic_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 
-    let user_transaction = UserTransaction::Declare(declare_v2_transaction, ContractClass::V1(Default::default()));
+    let user_transaction =
+        UserTransaction::Declare(declare_v2_transaction, ContractClass::V1(Default::default()), None);
     let tx_hash = user_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 }
@@ -258,3 +263,64 @@ fn test_handle_l1_message_tx_hash() {
     let tx_hash = wrapped_transaction.compute_hash::<PedersenHasher>(chain_id, false);
     assert_eq!(tx_hash, expected_tx_hash);
 }
+
+#[test]
+fn resource_bounds_are_packed_with_their_name() {
+    let bounds = ResourceBounds { max_amount: 0x186a0, max_price_per_unit: 0x5af3107a4000 };
+
+    assert_eq!(
+        resource_bounds_felt(L1_GAS, &bounds),
+        FieldElement::from_hex_be("0x4c315f47415300000000000186a0000000000000000000005af3107a4000").unwrap()
+    );
+}
+
+#[test]
+fn data_availability_modes_are_packed_nonce_first() {
+    assert_eq!(data_availability_modes(DataAvailabilityMode::L1, DataAvailabilityMode::L1), FieldElement::ZERO);
+    assert_eq!(data_availability_modes(DataAvailabilityMode::L1, DataAvailabilityMode::L2), FieldElement::ONE);
+    assert_eq!(
+        data_availability_modes(DataAvailabilityMode::L2, DataAvailabilityMode::L1),
+        FieldElement::from(1u64 << 32)
+    );
+}
+
+#[test]
+fn test_invoke_v3_tx_hash_commits_to_the_fee_fields() {
+    let chain_id = Felt252Wrapper(FieldElement::from_byte_slice_be(b"SN_GOERLI").unwrap());
+
+    let transaction = InvokeTransactionV3 {
+        resource_bounds: ResourceBoundsMapping {
+            l1_gas: ResourceBounds { max_amount: 0x186a0, max_price_per_unit: 0x5af3107a4000 },
+            l2_gas: ResourceBounds::default(),
+        },
+        tip: 0,
+        signature: vec![],
+        nonce: Felt252Wrapper::ONE,
+        sender_address: Felt252Wrapper::from(19911991_u128),
+        calldata: vec![Felt252Wrapper::ONE, Felt252Wrapper::TWO, Felt252Wrapper::THREE],
+        nonce_data_availability_mode: DataAvailabilityMode::L1,
+        fee_data_availability_mode: DataAvailabilityMode::L1,
+        paymaster_data: vec![],
+        account_deployment_data: vec![],
+    };
+    let tx_hash = transaction.compute_hash::<PedersenHasher>(chain_id, false);
+
+    // Computed following SNIP-8 with an independent implementation of Poseidon, checked against the
+    // Poseidon vectors of `mp-hashers`
+    let expected_tx_hash =
+        Felt252Wrapper::from_hex_be("0x05fd7af9b361dcc49d6d40f48c3aaed18a47d814914696a523ed4c428905d25d").unwrap();
+    assert_eq!(tx_hash, expected_tx_hash);
+
+    // The hash of v3 transactions does not depend on the hasher of the chain
+    assert_eq!(transaction.compute_hash::<PoseidonHasher>(chain_id, false), tx_hash);
+    assert_ne!(transaction.compute_hash::<PedersenHasher>(chain_id, true), tx_hash);
+
+    let with_tip = InvokeTransactionV3 { tip: 1, ..transaction.clone() };
+    assert_ne!(with_tip.compute_hash::<PedersenHasher>(chain_id, false), tx_hash);
+
+    let with_paymaster = InvokeTransactionV3 { paymaster_data: vec![Felt252Wrapper::ONE], ..transaction.clone() };
+    assert_ne!(with_paymaster.compute_hash::<PedersenHasher>(chain_id, false), tx_hash);
+
+    let on_l2 = InvokeTransactionV3 { fee_data_availability_mode: DataAvailabilityMode::L2, ..transaction };
+    assert_ne!(on_l2.compute_hash::<PedersenHasher>(chain_id, false), tx_hash);
+}
@@ -11,9 +11,11 @@ use starknet_api::transaction as sttx;
 use starknet_api::transaction::{Fee, TransactionVersion};
 
 use super::compute_hash::ComputeTransactionHash;
+use super::execution::VersionedTransaction;
 use super::{
-    DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2, DeployAccountTransaction,
-    HandleL1MessageTransaction, InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1,
+    DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2, DeclareTransactionV3,
+    DeployAccountTransaction, DeployAccountTransactionV3, HandleL1MessageTransaction, InvokeTransaction,
+    InvokeTransactionV0, InvokeTransactionV1, InvokeTransactionV3,
 };
 
 impl DeclareTransactionV0 {
@@ -86,18 +88,47 @@ impl DeclareTransactionV2 {
     }
 }
 
+impl DeclareTransactionV3 {
+    /// Converts the transaction to a blockifier v2 declare transaction, which carries the same
+    /// fields, with the fee bound by its L1 gas resource bounds.
+    fn try_into_executable<H: HasherT>(
+        &self,
+        chain_id: Felt252Wrapper,
+        contract_class: ContractClass,
+        is_query: bool,
+    ) -> TransactionExecutionResult<btx::DeclareTransaction> {
+        let transaction_hash = self.compute_hash::<H>(chain_id, is_query);
+
+        btx::DeclareTransaction::new(
+            sttx::DeclareTransaction::V2(sttx::DeclareTransactionV2 {
+                max_fee: sttx::Fee(self.resource_bounds.max_fee()),
+                signature: vec_of_felt_to_signature(&self.signature),
+                nonce: self.nonce.into(),
+                class_hash: self.class_hash.into(),
+                compiled_class_hash: self.compiled_class_hash.into(),
+                sender_address: self.sender_address.into(),
+            }),
+            transaction_hash.into(),
+            contract_class,
+        )
+    }
+}
+
 impl DeclareTransaction {
     pub fn try_into_executable<H: HasherT>(
         &self,
         chain_id: Felt252Wrapper,
         contract_class: ContractClass,
         is_query: bool,
-    ) -> TransactionExecutionResult<btx::DeclareTransaction> {
-        match self {
+    ) -> TransactionExecutionResult<VersionedTransaction<btx::DeclareTransaction>> {
+        let tx = match self {
             DeclareTransaction::V0(tx) => tx.try_into_executable::<H>(chain_id, contract_class, is_query),
             DeclareTransaction::V1(tx) => tx.try_into_executable::<H>(chain_id, contract_class, is_query),
             DeclareTransaction::V2(tx) => tx.try_into_executable::<H>(chain_id, contract_class, is_query),
-        }
+            DeclareTransaction::V3(tx) => tx.try_into_executable::<H>(chain_id, contract_class, is_query),
+        }?;
+
+        Ok(VersionedTransaction { tx, version: TransactionVersion(StarkFelt::from(self.version())) })
     }
 }
 
@@ -135,12 +166,38 @@ impl InvokeTransactionV1 {
     }
 }
 
-impl InvokeTransaction {
+impl InvokeTransactionV3 {
+    /// Converts the transaction to a blockifier v1 invoke transaction, which carries the same
+    /// fields, with the fee bound by its L1 gas resource bounds.
     pub fn into_executable<H: HasherT>(&self, chain_id: Felt252Wrapper, is_query: bool) -> btx::InvokeTransaction {
-        match self {
+        let transaction_hash = self.compute_hash::<H>(chain_id, is_query);
+
+        btx::InvokeTransaction {
+            tx: sttx::InvokeTransaction::V1(sttx::InvokeTransactionV1 {
+                max_fee: sttx::Fee(self.resource_bounds.max_fee()),
+                signature: vec_of_felt_to_signature(&self.signature),
+                nonce: self.nonce.into(),
+                calldata: vec_of_felt_to_calldata(&self.calldata),
+                sender_address: self.sender_address.into(),
+            }),
+            tx_hash: transaction_hash.into(),
+        }
+    }
+}
+
+impl InvokeTransaction {
+    pub fn into_executable<H: HasherT>(
+        &self,
+        chain_id: Felt252Wrapper,
+        is_query: bool,
+    ) -> VersionedTransaction<btx::InvokeTransaction> {
+        let tx = match self {
             InvokeTransaction::V0(tx) => tx.into_executable::<H>(chain_id, is_query),
             InvokeTransaction::V1(tx) => tx.into_executable::<H>(chain_id, is_query),
-        }
+            InvokeTransaction::V3(tx) => tx.into_executable::<H>(chain_id, is_query),
+        };
+
+        VersionedTransaction { tx, version: TransactionVersion(StarkFelt::from(self.version())) }
     }
 }
 
@@ -171,6 +228,33 @@ impl DeployAccountTransaction {
     }
 }
 
+impl DeployAccountTransactionV3 {
+    /// Converts the transaction to a blockifier deploy account transaction of version 3, with the
+    /// fee bound by its L1 gas resource bounds.
+    pub fn into_executable<H: HasherT>(
+        &self,
+        chain_id: Felt252Wrapper,
+        is_query: bool,
+    ) -> btx::DeployAccountTransaction {
+        let transaction_hash = self.compute_hash::<H>(chain_id, is_query);
+        let contract_address: Felt252Wrapper = self.get_account_address().into();
+
+        btx::DeployAccountTransaction {
+            tx: sttx::DeployAccountTransaction {
+                max_fee: sttx::Fee(self.resource_bounds.max_fee()),
+                version: sttx::TransactionVersion(StarkFelt::from(3u128)),
+                signature: vec_of_felt_to_signature(&self.signature),
+                nonce: self.nonce.into(),
+                class_hash: self.class_hash.into(),
+                contract_address_salt: self.contract_address_salt.into(),
+                constructor_calldata: vec_of_felt_to_calldata(&self.constructor_calldata),
+            },
+            tx_hash: transaction_hash.into(),
+            contract_address: contract_address.into(),
+        }
+    }
+}
+
 impl HandleL1MessageTransaction {
     pub fn into_executable<H: HasherT>(
         &self,
@@ -22,7 +22,7 @@ use blockifier::transaction::transaction_utils::{update_remaining_gas, verify_no
 use blockifier::transaction::transactions::{
     DeclareTransaction, DeployAccountTransaction, Executable, InvokeTransaction, L1HandlerTransaction,
 };
-use mp_fee::{calculate_tx_fee, charge_fee, compute_transaction_resources};
+use mp_fee::{calculate_tx_fee, charge_fee, compute_transaction_resources, fee_token_address};
 use mp_felt::Felt252Wrapper;
 use mp_state::{FeeConfig, StateChanges};
 use starknet_api::api_core::{ContractAddress, EntryPointSelector, Nonce};
@@ -50,6 +50,18 @@ impl ValidateExecuteCallInfo {
     }
 }
 
+/// A blockifier transaction, executed with the version of the transaction it was converted from.
+///
+/// The blockifier version we depend on has no v3 invoke and declare transactions, so they are
+/// converted to the transaction of the previous version carrying the same fields. They are then
+/// executed with version 3 in their context, so that their account sees their actual version, and
+/// their fee is paid in STRK.
+#[derive(Clone, Debug)]
+pub struct VersionedTransaction<T> {
+    pub tx: T,
+    pub version: TransactionVersion,
+}
+
 pub trait GetAccountTransactionContext {
     fn get_account_transaction_context(&self, is_query: bool) -> AccountTransactionContext;
 }
@@ -149,6 +161,17 @@ impl GetAccountTransactionContext for L1HandlerTransaction {
     }
 }
 
+impl<T: GetAccountTransactionContext> GetAccountTransactionContext for VersionedTransaction<T> {
+    fn get_account_transaction_context(&self, is_query: bool) -> AccountTransactionContext {
+        let mut version = self.version;
+        if is_query {
+            version = version.apply_simulate_tx_version_offset();
+        }
+
+        AccountTransactionContext { version, ..self.tx.get_account_transaction_context(is_query) }
+    }
+}
+
 pub trait GetTransactionCalldata {
     fn calldata(&self) -> Calldata;
 }
@@ -181,6 +204,12 @@ impl GetTransactionCalldata for L1HandlerTransaction {
     }
 }
 
+impl<T: GetTransactionCalldata> GetTransactionCalldata for VersionedTransaction<T> {
+    fn calldata(&self) -> Calldata {
+        self.tx.calldata()
+    }
+}
+
 pub trait GetTxType {
     fn tx_type() -> TransactionType;
 }
@@ -205,6 +234,11 @@ impl GetTxType for L1HandlerTransaction {
         TransactionType::L1Handler
     }
 }
+impl<T: GetTxType> GetTxType for VersionedTransaction<T> {
+    fn tx_type() -> TransactionType {
+        T::tx_type()
+    }
+}
 
 pub trait Validate: GetAccountTransactionContext + GetTransactionCalldata {
     const VALIDATE_TX_ENTRY_POINT_NAME: &'static str;
@@ -300,9 +334,10 @@ pub trait Execute: Sized + GetAccountTransactionContext + GetTransactionCalldata
         Ok(())
     }
 
-    /// Handles nonce and checks that the account's balance covers max fee.
-    fn handle_nonce_and_check_fee_balance(
-        state: &mut dyn State,
+    /// Handles nonce and checks that the account's balance covers max fee, in the token the fee is
+    /// paid in.
+    fn handle_nonce_and_check_fee_balance<S: State + FeeConfig>(
+        state: &mut S,
         block_context: &BlockContext,
         account_tx_context: &AccountTransactionContext,
         disable_nonce_validation: bool,
@@ -315,8 +350,10 @@ pub trait Execute: Sized + GetAccountTransactionContext + GetTransactionCalldata
 
         // Check fee balance.
         if account_tx_context.max_fee != Fee(0) {
+            let fee_token_address = fee_token_address(&*state, block_context, account_tx_context);
+            let fee_block_context = BlockContext { fee_token_address, ..block_context.clone() };
             let (balance_low, balance_high) =
-                state.get_fee_token_balance(block_context, &account_tx_context.sender_address)?;
+                state.get_fee_token_balance(&fee_block_context, &account_tx_context.sender_address)?;
 
             if balance_high <= StarkFelt::from(0_u8) && balance_low < StarkFelt::from(account_tx_context.max_fee.0) {
                 return Err(TransactionExecutionError::MaxFeeExceedsBalance {
@@ -400,6 +437,23 @@ pub trait Execute: Sized + GetAccountTransactionContext + GetTransactionCalldata
     }
 }
 
+impl<T: Validate> Validate for VersionedTransaction<T> {
+    const VALIDATE_TX_ENTRY_POINT_NAME: &'static str = T::VALIDATE_TX_ENTRY_POINT_NAME;
+}
+
+impl<T: Execute> Execute for VersionedTransaction<T> {
+    fn execute_inner<S: State + StateChanges + FeeConfig>(
+        &self,
+        state: &mut S,
+        block_context: &BlockContext,
+        resources: &mut ExecutionResources,
+        remaining_gas: &mut u64,
+        account_tx_context: &AccountTransactionContext,
+    ) -> TransactionExecutionResult<ValidateExecuteCallInfo> {
+        self.tx.execute_inner(state, block_context, resources, remaining_gas, account_tx_context)
+    }
+}
+
 impl Validate for InvokeTransaction {
     const VALIDATE_TX_ENTRY_POINT_NAME: &'static str = VALIDATE_ENTRY_POINT_NAME;
 }
@@ -561,10 +615,10 @@ impl Execute for L1HandlerTransaction {
 
         let actual_fee = calculate_tx_fee(&actual_resources, block_context)?;
 
+        // The fee declared by the L1 bridge must cover the execution of the message, unless fees are
+        // disabled, in which case any amount of fee is enough.
         let paid_fee = self.paid_fee_on_l1;
-        // For now, assert only that any amount of fee was paid.
-        // The error message still indicates the required fee.
-        if paid_fee == Fee(0) {
+        if paid_fee == Fee(0) || (!state.is_transaction_fee_disabled() && paid_fee < actual_fee) {
             return Err(TransactionExecutionError::InsufficientL1Fee { paid_fee, actual_fee });
         }
 
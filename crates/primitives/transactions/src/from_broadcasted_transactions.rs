@@ -10,11 +10,15 @@ use cairo_lang_starknet::contract_class_into_casm_contract_class::StarknetSierra
 use cairo_lang_utils::bigint::BigUintAsHex;
 use cairo_vm::types::program::Program;
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use mp_felt::Felt252Wrapper;
 use num_bigint::{BigInt, BigUint, Sign};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 use starknet_api::api_core::EntryPointSelector;
 use starknet_api::deprecated_contract_class::{EntryPoint, EntryPointOffset, EntryPointType};
 use starknet_api::hash::StarkFelt;
+use starknet_core::serde::unsigned_field_element::UfeHex;
 use starknet_core::types::contract::legacy::{
     LegacyContractClass, LegacyEntrypointOffset, RawLegacyEntryPoint, RawLegacyEntryPoints,
 };
@@ -28,7 +32,11 @@ use starknet_core::types::{
 use starknet_crypto::FieldElement;
 use thiserror::Error;
 
-use super::{DeclareTransaction, DeclareTransactionV1, DeclareTransactionV2, UserTransaction};
+use super::{
+    DataAvailabilityMode, DeclareTransaction, DeclareTransactionV1, DeclareTransactionV2, DeclareTransactionV3,
+    DeployAccountTransactionV3, InvokeTransactionV3, ResourceBounds, ResourceBoundsMapping, UserTransaction,
+    SIMULATE_TX_VERSION_OFFSET,
+};
 
 #[derive(Debug, Error)]
 pub enum BroadcastedTransactionConversionError {
@@ -46,8 +54,14 @@ pub enum BroadcastedTransactionConversionError {
     InvalidCompiledClassHash,
     #[error("Failed to compile to Sierra")]
     SierraCompilationFailed,
+    #[error("Failed to compress the Sierra class")]
+    SierraClassCompressionFailed,
     #[error("This transaction version is not supported")]
     UnsuportedTransactionVersion,
+    #[error("Resource bounds should not be greater than u64::MAX for the amount and u128::MAX for the price")]
+    ResourceBoundsTooBig,
+    #[error("Tip should not be greater than u64::MAX")]
+    TipTooBig,
 }
 
 impl TryFrom<BroadcastedTransaction> for UserTransaction {
@@ -116,7 +130,7 @@ impl TryFrom<BroadcastedDeclareTransaction> for UserTransaction {
 
                 let contract_class = instantiate_blockifier_contract_class(contract_class, decompressed_bytes)?;
 
-                UserTransaction::Declare(tx, contract_class)
+                UserTransaction::Declare(tx, contract_class, None)
             }
             BroadcastedDeclareTransaction::V2(BroadcastedDeclareTransactionV2 {
                 max_fee,
@@ -136,20 +150,11 @@ impl TryFrom<BroadcastedDeclareTransaction> for UserTransaction {
                     compiled_class_hash: compiled_class_hash.into(),
                 });
 
-                let casm_contract_class = flattened_sierra_to_casm_contract_class(contract_class)
-                    .map_err(|_| BroadcastedTransactionConversionError::SierraCompilationFailed)?;
-
-                // ensure that the user has sign the correct class hash
-                if get_casm_cotract_class_hash(&casm_contract_class) != compiled_class_hash {
-                    return Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash);
-                }
-
-                let contract_class = ContractClass::V1(
-                    ContractClassV1::try_from(casm_contract_class)
-                        .map_err(|_| BroadcastedTransactionConversionError::CasmContractClassConversionFailed)?,
-                );
+                let sierra_class = compress_sierra_class(&contract_class)
+                    .map_err(|_| BroadcastedTransactionConversionError::SierraClassCompressionFailed)?;
+                let contract_class = compile_sierra_contract_class(contract_class, compiled_class_hash)?;
 
-                UserTransaction::Declare(tx, contract_class)
+                UserTransaction::Declare(tx, contract_class, Some(sierra_class))
             }
         };
 
@@ -157,6 +162,39 @@ impl TryFrom<BroadcastedDeclareTransaction> for UserTransaction {
     }
 }
 
+/// Returns the compressed JSON serialization of a [FlattenedSierraClass], as carried by the
+/// declare transactions and stored in the Madara db
+pub fn compress_sierra_class(sierra_class: &FlattenedSierraClass) -> std::io::Result<Vec<u8>> {
+    let mut gzip_encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    serde_json::to_writer(&mut gzip_encoder, sierra_class)?;
+    gzip_encoder.finish()
+}
+
+/// Returns a [FlattenedSierraClass] from its compressed JSON serialization
+pub fn decompress_sierra_class(compressed_sierra_class: &[u8]) -> serde_json::Result<FlattenedSierraClass> {
+    serde_json::from_reader(GzDecoder::new(compressed_sierra_class))
+}
+
+/// Compiles a Sierra class to the blockifier class it is executed as, checking that it compiles to
+/// the compiled class hash signed by the user.
+fn compile_sierra_contract_class(
+    contract_class: Arc<FlattenedSierraClass>,
+    compiled_class_hash: FieldElement,
+) -> Result<ContractClass, BroadcastedTransactionConversionError> {
+    let casm_contract_class = flattened_sierra_to_casm_contract_class(contract_class)
+        .map_err(|_| BroadcastedTransactionConversionError::SierraCompilationFailed)?;
+
+    // ensure that the user has sign the correct class hash
+    if get_casm_cotract_class_hash(&casm_contract_class) != compiled_class_hash {
+        return Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash);
+    }
+
+    Ok(ContractClass::V1(
+        ContractClassV1::try_from(casm_contract_class)
+            .map_err(|_| BroadcastedTransactionConversionError::CasmContractClassConversionFailed)?,
+    ))
+}
+
 fn instantiate_blockifier_contract_class(
     contract_class: Arc<CompressedLegacyContractClass>,
     program_decompressed_bytes: Vec<u8>,
@@ -342,6 +380,268 @@ impl TryFrom<BroadcastedDeployAccountTransaction> for UserTransaction {
     }
 }
 
+/// The maximum amount and price per unit of a resource, as broadcasted to the RPC.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BroadcastedResourceBounds {
+    #[serde_as(as = "UfeHex")]
+    pub max_amount: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub max_price_per_unit: FieldElement,
+}
+
+/// The resource bounds of a v3 transaction, as broadcasted to the RPC.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BroadcastedResourceBoundsMapping {
+    pub l1_gas: BroadcastedResourceBounds,
+    pub l2_gas: BroadcastedResourceBounds,
+}
+
+/// The data availability mode of a v3 transaction, as broadcasted to the RPC.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BroadcastedDataAvailabilityMode {
+    L1,
+    L2,
+}
+
+/// A v3 invoke transaction, as broadcasted to the RPC.
+///
+/// The starknet-core version we depend on predates the v3 transactions, so they are defined here.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BroadcastedInvokeTransactionV3 {
+    #[serde_as(as = "UfeHex")]
+    pub version: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub sender_address: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub calldata: Vec<FieldElement>,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub signature: Vec<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub nonce: FieldElement,
+    pub resource_bounds: BroadcastedResourceBoundsMapping,
+    #[serde_as(as = "UfeHex")]
+    pub tip: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub paymaster_data: Vec<FieldElement>,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub account_deployment_data: Vec<FieldElement>,
+    pub nonce_data_availability_mode: BroadcastedDataAvailabilityMode,
+    pub fee_data_availability_mode: BroadcastedDataAvailabilityMode,
+}
+
+/// A v3 declare transaction, as broadcasted to the RPC.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BroadcastedDeclareTransactionV3 {
+    #[serde_as(as = "UfeHex")]
+    pub version: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub sender_address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub compiled_class_hash: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub signature: Vec<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub nonce: FieldElement,
+    pub contract_class: Arc<FlattenedSierraClass>,
+    pub resource_bounds: BroadcastedResourceBoundsMapping,
+    #[serde_as(as = "UfeHex")]
+    pub tip: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub paymaster_data: Vec<FieldElement>,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub account_deployment_data: Vec<FieldElement>,
+    pub nonce_data_availability_mode: BroadcastedDataAvailabilityMode,
+    pub fee_data_availability_mode: BroadcastedDataAvailabilityMode,
+}
+
+/// A v3 deploy account transaction, as broadcasted to the RPC.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BroadcastedDeployAccountTransactionV3 {
+    #[serde_as(as = "UfeHex")]
+    pub version: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub signature: Vec<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub nonce: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub contract_address_salt: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub constructor_calldata: Vec<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub class_hash: FieldElement,
+    pub resource_bounds: BroadcastedResourceBoundsMapping,
+    #[serde_as(as = "UfeHex")]
+    pub tip: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub paymaster_data: Vec<FieldElement>,
+    pub nonce_data_availability_mode: BroadcastedDataAvailabilityMode,
+    pub fee_data_availability_mode: BroadcastedDataAvailabilityMode,
+}
+
+impl TryFrom<BroadcastedResourceBounds> for ResourceBounds {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(value: BroadcastedResourceBounds) -> Result<Self, Self::Error> {
+        Ok(ResourceBounds {
+            max_amount: value
+                .max_amount
+                .try_into()
+                .map_err(|_| BroadcastedTransactionConversionError::ResourceBoundsTooBig)?,
+            max_price_per_unit: value
+                .max_price_per_unit
+                .try_into()
+                .map_err(|_| BroadcastedTransactionConversionError::ResourceBoundsTooBig)?,
+        })
+    }
+}
+
+impl TryFrom<BroadcastedResourceBoundsMapping> for ResourceBoundsMapping {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(value: BroadcastedResourceBoundsMapping) -> Result<Self, Self::Error> {
+        Ok(ResourceBoundsMapping { l1_gas: value.l1_gas.try_into()?, l2_gas: value.l2_gas.try_into()? })
+    }
+}
+
+impl From<BroadcastedDataAvailabilityMode> for DataAvailabilityMode {
+    fn from(value: BroadcastedDataAvailabilityMode) -> Self {
+        match value {
+            BroadcastedDataAvailabilityMode::L1 => DataAvailabilityMode::L1,
+            BroadcastedDataAvailabilityMode::L2 => DataAvailabilityMode::L2,
+        }
+    }
+}
+
+/// A v3 transaction, as broadcasted to the RPC.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum BroadcastedTransactionV3 {
+    #[serde(rename = "INVOKE")]
+    Invoke(BroadcastedInvokeTransactionV3),
+    #[serde(rename = "DECLARE")]
+    Declare(BroadcastedDeclareTransactionV3),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(BroadcastedDeployAccountTransactionV3),
+}
+
+impl BroadcastedTransactionV3 {
+    /// Whether the transaction is only meant to be simulated, which its version tells for the v3
+    /// transactions.
+    pub fn is_query(&self) -> bool {
+        let version = match self {
+            BroadcastedTransactionV3::Invoke(tx) => tx.version,
+            BroadcastedTransactionV3::Declare(tx) => tx.version,
+            BroadcastedTransactionV3::DeployAccount(tx) => tx.version,
+        };
+
+        version == query_v3_version()
+    }
+}
+
+impl TryFrom<BroadcastedTransactionV3> for UserTransaction {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(tx: BroadcastedTransactionV3) -> Result<Self, Self::Error> {
+        match tx {
+            BroadcastedTransactionV3::Invoke(tx) => tx.try_into(),
+            BroadcastedTransactionV3::Declare(tx) => tx.try_into(),
+            BroadcastedTransactionV3::DeployAccount(tx) => tx.try_into(),
+        }
+    }
+}
+
+/// Version of the v3 transactions when simulated.
+fn query_v3_version() -> FieldElement {
+    FieldElement::THREE + SIMULATE_TX_VERSION_OFFSET
+}
+
+/// Checks that a broadcasted v3 transaction is of version 3, simulated or not, and converts its
+/// tip.
+fn check_v3_version_and_convert_tip(
+    version: FieldElement,
+    tip: FieldElement,
+) -> Result<u64, BroadcastedTransactionConversionError> {
+    if version != FieldElement::THREE && version != query_v3_version() {
+        return Err(BroadcastedTransactionConversionError::UnsuportedTransactionVersion);
+    }
+
+    tip.try_into().map_err(|_| BroadcastedTransactionConversionError::TipTooBig)
+}
+
+impl TryFrom<BroadcastedInvokeTransactionV3> for UserTransaction {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(value: BroadcastedInvokeTransactionV3) -> Result<Self, Self::Error> {
+        let tip = check_v3_version_and_convert_tip(value.version, value.tip)?;
+
+        Ok(UserTransaction::Invoke(super::InvokeTransaction::V3(InvokeTransactionV3 {
+            resource_bounds: value.resource_bounds.try_into()?,
+            tip,
+            signature: cast_vec_of_field_elements(value.signature),
+            nonce: value.nonce.into(),
+            sender_address: value.sender_address.into(),
+            calldata: cast_vec_of_field_elements(value.calldata),
+            nonce_data_availability_mode: value.nonce_data_availability_mode.into(),
+            fee_data_availability_mode: value.fee_data_availability_mode.into(),
+            paymaster_data: cast_vec_of_field_elements(value.paymaster_data),
+            account_deployment_data: cast_vec_of_field_elements(value.account_deployment_data),
+        })))
+    }
+}
+
+impl TryFrom<BroadcastedDeclareTransactionV3> for UserTransaction {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(value: BroadcastedDeclareTransactionV3) -> Result<Self, Self::Error> {
+        let tip = check_v3_version_and_convert_tip(value.version, value.tip)?;
+
+        let tx = DeclareTransaction::V3(DeclareTransactionV3 {
+            resource_bounds: value.resource_bounds.try_into()?,
+            tip,
+            signature: cast_vec_of_field_elements(value.signature),
+            nonce: value.nonce.into(),
+            class_hash: value.contract_class.class_hash().into(),
+            compiled_class_hash: value.compiled_class_hash.into(),
+            sender_address: value.sender_address.into(),
+            nonce_data_availability_mode: value.nonce_data_availability_mode.into(),
+            fee_data_availability_mode: value.fee_data_availability_mode.into(),
+            paymaster_data: cast_vec_of_field_elements(value.paymaster_data),
+            account_deployment_data: cast_vec_of_field_elements(value.account_deployment_data),
+        });
+
+        let sierra_class = compress_sierra_class(&value.contract_class)
+            .map_err(|_| BroadcastedTransactionConversionError::SierraClassCompressionFailed)?;
+        let contract_class = compile_sierra_contract_class(value.contract_class, value.compiled_class_hash)?;
+
+        Ok(UserTransaction::Declare(tx, contract_class, Some(sierra_class)))
+    }
+}
+
+impl TryFrom<BroadcastedDeployAccountTransactionV3> for UserTransaction {
+    type Error = BroadcastedTransactionConversionError;
+
+    fn try_from(value: BroadcastedDeployAccountTransactionV3) -> Result<Self, Self::Error> {
+        let tip = check_v3_version_and_convert_tip(value.version, value.tip)?;
+
+        Ok(UserTransaction::DeployAccountV3(DeployAccountTransactionV3 {
+            resource_bounds: value.resource_bounds.try_into()?,
+            tip,
+            signature: cast_vec_of_field_elements(value.signature),
+            nonce: value.nonce.into(),
+            contract_address_salt: value.contract_address_salt.into(),
+            constructor_calldata: cast_vec_of_field_elements(value.constructor_calldata),
+            class_hash: value.class_hash.into(),
+            nonce_data_availability_mode: value.nonce_data_availability_mode.into(),
+            fee_data_availability_mode: value.fee_data_availability_mode.into(),
+            paymaster_data: cast_vec_of_field_elements(value.paymaster_data),
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -349,6 +649,7 @@ mod tests {
     use starknet_core::types::FlattenedSierraClass;
 
     use super::*;
+    use crate::compute_hash::ComputeTransactionHash;
 
     const CAIRO_1_NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH: &str =
         "0xdf4d3042eec107abe704619f13d92bbe01a58029311b7a1886b23dcbb4ea87";
@@ -426,14 +727,19 @@ mod tests {
             max_fee: FieldElement::default(),
             signature: vec![FieldElement::default()],
             nonce: FieldElement::default(),
-            contract_class: Arc::new(flattened_contract_class),
+            contract_class: Arc::new(flattened_contract_class.clone()),
             sender_address: FieldElement::default(),
             compiled_class_hash: FieldElement::from_hex_be(CAIRO_1_NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH).unwrap(),
             is_query: false,
         };
 
         let input: BroadcastedDeclareTransaction = BroadcastedDeclareTransaction::V2(txn);
-        assert!(UserTransaction::try_from(input).is_ok());
+        // The Sierra class is carried along with the compiled class, for the nodes to serve it back
+        assert_matches!(
+            UserTransaction::try_from(input),
+            Ok(UserTransaction::Declare(_, ContractClass::V1(_), Some(sierra_class)))
+                if decompress_sierra_class(&sierra_class).unwrap() == flattened_contract_class
+        );
     }
 
     #[test]
@@ -457,4 +763,59 @@ mod tests {
             Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash)
         );
     }
+
+    #[test]
+    fn try_into_invoke_transaction_v3() {
+        let input: BroadcastedInvokeTransactionV3 = serde_json::from_value(serde_json::json!({
+            "type": "INVOKE",
+            "version": "0x3",
+            "sender_address": "0x12fd537",
+            "calldata": ["0x1", "0x2", "0x3"],
+            "signature": [],
+            "nonce": "0x1",
+            "resource_bounds": {
+                "l1_gas": { "max_amount": "0x186a0", "max_price_per_unit": "0x5af3107a4000" },
+                "l2_gas": { "max_amount": "0x0", "max_price_per_unit": "0x0" }
+            },
+            "tip": "0x0",
+            "paymaster_data": [],
+            "account_deployment_data": [],
+            "nonce_data_availability_mode": "L1",
+            "fee_data_availability_mode": "L1"
+        }))
+        .unwrap();
+
+        let tx = UserTransaction::try_from(input.clone()).unwrap();
+        assert_eq!(tx.version(), 3);
+        assert_eq!(tx.max_fee(), 0x186a0 * 0x5af3107a4000);
+
+        // Same transaction as in `test_invoke_v3_tx_hash_commits_to_the_fee_fields`
+        let chain_id = Felt252Wrapper(FieldElement::from_byte_slice_be(b"SN_GOERLI").unwrap());
+        assert_eq!(
+            tx.compute_hash::<mp_hashers::pedersen::PedersenHasher>(chain_id, false),
+            Felt252Wrapper::from_hex_be("0x05fd7af9b361dcc49d6d40f48c3aaed18a47d814914696a523ed4c428905d25d").unwrap()
+        );
+
+        let not_v3 = BroadcastedInvokeTransactionV3 { version: FieldElement::ONE, ..input.clone() };
+        assert_matches!(
+            UserTransaction::try_from(not_v3),
+            Err(BroadcastedTransactionConversionError::UnsuportedTransactionVersion)
+        );
+
+        let too_big = BroadcastedInvokeTransactionV3 {
+            resource_bounds: BroadcastedResourceBoundsMapping {
+                l1_gas: BroadcastedResourceBounds {
+                    // u64::MAX + 1
+                    max_amount: FieldElement::from_hex_be("0x10000000000000000").unwrap(),
+                    max_price_per_unit: FieldElement::ONE,
+                },
+                ..input.resource_bounds
+            },
+            ..input
+        };
+        assert_matches!(
+            UserTransaction::try_from(too_big),
+            Err(BroadcastedTransactionConversionError::ResourceBoundsTooBig)
+        );
+    }
 }
@@ -2,7 +2,10 @@ use alloc::vec::Vec;
 
 use mp_felt::Felt252Wrapper;
 
-use super::{DeclareTransaction, DeployAccountTransaction, InvokeTransaction, Transaction, UserTransaction};
+use super::{
+    DeclareTransaction, DeployAccountTransaction, DeployAccountTransactionV3, InvokeTransaction, ResourceBoundsMapping,
+    Transaction, UserTransaction,
+};
 
 impl Transaction {
     pub fn signature(&self) -> Vec<Felt252Wrapper> {
@@ -11,6 +14,7 @@ impl Transaction {
             Transaction::DeployAccount(tx) => tx.signature().clone(),
             Transaction::Invoke(tx) => tx.signature().clone(),
             Transaction::L1Handler(_) => Vec::new(),
+            Transaction::DeployAccountV3(tx) => tx.signature().clone(),
         }
     }
 }
@@ -18,25 +22,28 @@ impl Transaction {
 impl UserTransaction {
     pub fn sender_address(&self) -> Felt252Wrapper {
         match self {
-            UserTransaction::Declare(tx, _) => *tx.sender_address(),
+            UserTransaction::Declare(tx, _, _) => *tx.sender_address(),
             UserTransaction::DeployAccount(tx) => tx.account_address(),
             UserTransaction::Invoke(tx) => *tx.sender_address(),
+            UserTransaction::DeployAccountV3(tx) => tx.account_address(),
         }
     }
 
     pub fn signature(&self) -> &Vec<Felt252Wrapper> {
         match self {
-            UserTransaction::Declare(tx, _) => tx.signature(),
+            UserTransaction::Declare(tx, _, _) => tx.signature(),
             UserTransaction::DeployAccount(tx) => tx.signature(),
             UserTransaction::Invoke(tx) => tx.signature(),
+            UserTransaction::DeployAccountV3(tx) => tx.signature(),
         }
     }
 
-    pub fn max_fee(&self) -> &u128 {
+    pub fn max_fee(&self) -> u128 {
         match self {
-            UserTransaction::Declare(tx, _) => tx.max_fee(),
+            UserTransaction::Declare(tx, _, _) => tx.max_fee(),
             UserTransaction::DeployAccount(tx) => tx.max_fee(),
             UserTransaction::Invoke(tx) => tx.max_fee(),
+            UserTransaction::DeployAccountV3(tx) => tx.max_fee(),
         }
     }
 
@@ -45,22 +52,25 @@ impl UserTransaction {
             UserTransaction::Declare(..) => None,
             UserTransaction::DeployAccount(tx) => Some(tx.calldata()),
             UserTransaction::Invoke(tx) => Some(tx.calldata()),
+            UserTransaction::DeployAccountV3(tx) => Some(tx.calldata()),
         }
     }
 
     pub fn nonce(&self) -> Option<&Felt252Wrapper> {
         match self {
-            UserTransaction::Declare(tx, _) => Some(tx.nonce()),
+            UserTransaction::Declare(tx, _, _) => Some(tx.nonce()),
             UserTransaction::DeployAccount(tx) => Some(tx.nonce()),
             UserTransaction::Invoke(tx) => tx.nonce(),
+            UserTransaction::DeployAccountV3(tx) => Some(tx.nonce()),
         }
     }
 
     pub fn version(&self) -> u8 {
         match self {
-            UserTransaction::Declare(tx, _) => tx.version(),
+            UserTransaction::Declare(tx, _, _) => tx.version(),
             UserTransaction::DeployAccount(tx) => tx.version(),
             UserTransaction::Invoke(tx) => tx.version(),
+            UserTransaction::DeployAccountV3(tx) => tx.version(),
         }
     }
 }
@@ -71,6 +81,7 @@ impl DeclareTransaction {
             DeclareTransaction::V0(tx) => &tx.sender_address,
             DeclareTransaction::V1(tx) => &tx.sender_address,
             DeclareTransaction::V2(tx) => &tx.sender_address,
+            DeclareTransaction::V3(tx) => &tx.sender_address,
         }
     }
 
@@ -79,14 +90,16 @@ impl DeclareTransaction {
             DeclareTransaction::V0(tx) => &tx.signature,
             DeclareTransaction::V1(tx) => &tx.signature,
             DeclareTransaction::V2(tx) => &tx.signature,
+            DeclareTransaction::V3(tx) => &tx.signature,
         }
     }
 
-    pub fn max_fee(&self) -> &u128 {
+    pub fn max_fee(&self) -> u128 {
         match self {
-            DeclareTransaction::V0(tx) => &tx.max_fee,
-            DeclareTransaction::V1(tx) => &tx.max_fee,
-            DeclareTransaction::V2(tx) => &tx.max_fee,
+            DeclareTransaction::V0(tx) => tx.max_fee,
+            DeclareTransaction::V1(tx) => tx.max_fee,
+            DeclareTransaction::V2(tx) => tx.max_fee,
+            DeclareTransaction::V3(tx) => tx.resource_bounds.max_fee(),
         }
     }
 
@@ -95,6 +108,7 @@ impl DeclareTransaction {
             DeclareTransaction::V0(tx) => &tx.nonce,
             DeclareTransaction::V1(tx) => &tx.nonce,
             DeclareTransaction::V2(tx) => &tx.nonce,
+            DeclareTransaction::V3(tx) => &tx.nonce,
         }
     }
 
@@ -103,6 +117,7 @@ impl DeclareTransaction {
             DeclareTransaction::V0(_) => 0,
             DeclareTransaction::V1(_) => 1,
             DeclareTransaction::V2(_) => 2,
+            DeclareTransaction::V3(_) => 3,
         }
     }
 
@@ -111,6 +126,7 @@ impl DeclareTransaction {
             DeclareTransaction::V0(tx) => &tx.class_hash,
             DeclareTransaction::V1(tx) => &tx.class_hash,
             DeclareTransaction::V2(tx) => &tx.class_hash,
+            DeclareTransaction::V3(tx) => &tx.class_hash,
         }
     }
 
@@ -119,6 +135,7 @@ impl DeclareTransaction {
             DeclareTransaction::V0(_) => None,
             DeclareTransaction::V1(_) => None,
             DeclareTransaction::V2(tx) => Some(&tx.compiled_class_hash),
+            DeclareTransaction::V3(tx) => Some(&tx.compiled_class_hash),
         }
     }
 }
@@ -128,8 +145,8 @@ impl DeployAccountTransaction {
         &self.signature
     }
 
-    pub fn max_fee(&self) -> &u128 {
-        &self.max_fee
+    pub fn max_fee(&self) -> u128 {
+        self.max_fee
     }
 
     pub fn calldata(&self) -> &Vec<Felt252Wrapper> {
@@ -158,6 +175,7 @@ impl InvokeTransaction {
         match self {
             InvokeTransaction::V0(tx) => &tx.contract_address,
             InvokeTransaction::V1(tx) => &tx.sender_address,
+            InvokeTransaction::V3(tx) => &tx.sender_address,
         }
     }
 
@@ -165,13 +183,15 @@ impl InvokeTransaction {
         match self {
             InvokeTransaction::V0(tx) => &tx.signature,
             InvokeTransaction::V1(tx) => &tx.signature,
+            InvokeTransaction::V3(tx) => &tx.signature,
         }
     }
 
-    pub fn max_fee(&self) -> &u128 {
+    pub fn max_fee(&self) -> u128 {
         match self {
-            InvokeTransaction::V0(tx) => &tx.max_fee,
-            InvokeTransaction::V1(tx) => &tx.max_fee,
+            InvokeTransaction::V0(tx) => tx.max_fee,
+            InvokeTransaction::V1(tx) => tx.max_fee,
+            InvokeTransaction::V3(tx) => tx.resource_bounds.max_fee(),
         }
     }
 
@@ -179,6 +199,7 @@ impl InvokeTransaction {
         match self {
             InvokeTransaction::V0(tx) => &tx.calldata,
             InvokeTransaction::V1(tx) => &tx.calldata,
+            InvokeTransaction::V3(tx) => &tx.calldata,
         }
     }
 
@@ -186,6 +207,7 @@ impl InvokeTransaction {
         match self {
             InvokeTransaction::V0(_) => None,
             InvokeTransaction::V1(tx) => Some(&tx.nonce),
+            InvokeTransaction::V3(tx) => Some(&tx.nonce),
         }
     }
 
@@ -193,6 +215,46 @@ impl InvokeTransaction {
         match self {
             InvokeTransaction::V0(_) => 0,
             InvokeTransaction::V1(_) => 1,
+            InvokeTransaction::V3(_) => 3,
         }
     }
 }
+
+impl DeployAccountTransactionV3 {
+    pub fn signature(&self) -> &Vec<Felt252Wrapper> {
+        &self.signature
+    }
+
+    pub fn max_fee(&self) -> u128 {
+        self.resource_bounds.max_fee()
+    }
+
+    pub fn calldata(&self) -> &Vec<Felt252Wrapper> {
+        &self.constructor_calldata
+    }
+
+    pub fn nonce(&self) -> &Felt252Wrapper {
+        &self.nonce
+    }
+
+    pub fn version(&self) -> u8 {
+        3
+    }
+
+    pub fn account_address(&self) -> Felt252Wrapper {
+        Felt252Wrapper(self.get_account_address())
+    }
+
+    pub fn class_hash(&self) -> &Felt252Wrapper {
+        &self.class_hash
+    }
+}
+
+impl ResourceBoundsMapping {
+    /// The maximum fee of a v3 transaction, in STRK.
+    ///
+    /// Only the L1 gas is charged, so the fee is bounded by its max amount at its max price.
+    pub fn max_fee(&self) -> u128 {
+        (self.l1_gas.max_amount as u128).saturating_mul(self.l1_gas.max_price_per_unit)
+    }
+}
@@ -61,9 +61,12 @@ impl From<TxType> for TransactionType {
 #[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
 #[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
 pub enum UserTransaction {
-    Declare(DeclareTransaction, ContractClass),
+    /// A declare transaction, along with the class it declares and, for a Cairo 1 class, the
+    /// compressed Sierra class it was compiled from
+    Declare(DeclareTransaction, ContractClass, Option<Vec<u8>>),
     DeployAccount(DeployAccountTransaction),
     Invoke(InvokeTransaction),
+    DeployAccountV3(DeployAccountTransactionV3),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, From)]
@@ -74,6 +77,7 @@ pub enum Transaction {
     DeployAccount(DeployAccountTransaction),
     Invoke(InvokeTransaction),
     L1Handler(HandleL1MessageTransaction),
+    DeployAccountV3(DeployAccountTransactionV3),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, From)]
@@ -90,6 +94,7 @@ pub enum UserAndL1HandlerTransaction {
 pub enum InvokeTransaction {
     V0(InvokeTransactionV0),
     V1(InvokeTransactionV1),
+    V3(InvokeTransactionV3),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -121,6 +126,7 @@ pub enum DeclareTransaction {
     V0(DeclareTransactionV0),
     V1(DeclareTransactionV1),
     V2(DeclareTransactionV2),
+    V3(DeclareTransactionV3),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -169,6 +175,92 @@ pub struct DeployAccountTransaction {
     pub class_hash: Felt252Wrapper,
 }
 
+// The deploy account transaction isn't an enum of its versions, so the v3 one is a variant of
+// `Transaction` and `UserTransaction` of its own, which keeps the encoding of the v1 ones.
+
+/// The layer on which the nonce or the fee of a v3 transaction are stored.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub enum DataAvailabilityMode {
+    #[default]
+    L1,
+    L2,
+}
+
+/// The maximum amount of a resource a v3 transaction may consume, and the maximum price it pays
+/// per unit of it.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct ResourceBounds {
+    pub max_amount: u64,
+    pub max_price_per_unit: u128,
+}
+
+/// The bounds of the resources a v3 transaction pays for.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct ResourceBoundsMapping {
+    pub l1_gas: ResourceBounds,
+    pub l2_gas: ResourceBounds,
+}
+
+/// An invoke transaction paying its fee in STRK, within resource bounds.
+///
+/// See `https://github.com/starknet-io/SNIPs/blob/main/SNIPS/snip-8.md`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct InvokeTransactionV3 {
+    pub resource_bounds: ResourceBoundsMapping,
+    pub tip: u64,
+    pub signature: Vec<Felt252Wrapper>,
+    pub nonce: Felt252Wrapper,
+    pub sender_address: Felt252Wrapper,
+    pub calldata: Vec<Felt252Wrapper>,
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    pub fee_data_availability_mode: DataAvailabilityMode,
+    pub paymaster_data: Vec<Felt252Wrapper>,
+    pub account_deployment_data: Vec<Felt252Wrapper>,
+}
+
+/// A declare transaction paying its fee in STRK, within resource bounds.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct DeclareTransactionV3 {
+    pub resource_bounds: ResourceBoundsMapping,
+    pub tip: u64,
+    pub signature: Vec<Felt252Wrapper>,
+    pub nonce: Felt252Wrapper,
+    pub class_hash: Felt252Wrapper,
+    pub compiled_class_hash: Felt252Wrapper,
+    pub sender_address: Felt252Wrapper,
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    pub fee_data_availability_mode: DataAvailabilityMode,
+    pub paymaster_data: Vec<Felt252Wrapper>,
+    pub account_deployment_data: Vec<Felt252Wrapper>,
+}
+
+/// A deploy account transaction paying its fee in STRK, within resource bounds.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct DeployAccountTransactionV3 {
+    pub resource_bounds: ResourceBoundsMapping,
+    pub tip: u64,
+    pub signature: Vec<Felt252Wrapper>,
+    pub nonce: Felt252Wrapper,
+    pub contract_address_salt: Felt252Wrapper,
+    pub constructor_calldata: Vec<Felt252Wrapper>,
+    pub class_hash: Felt252Wrapper,
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    pub fee_data_availability_mode: DataAvailabilityMode,
+    pub paymaster_data: Vec<Felt252Wrapper>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
 #[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
@@ -10,11 +10,15 @@ fn cast_vec_of_felt_252_wrappers(data: Vec<Felt252Wrapper>) -> Vec<FieldElement>
     unsafe { alloc::vec::Vec::from_raw_parts(data.as_mut_ptr() as *mut FieldElement, data.len(), data.capacity()) }
 }
 
+/// Converts a transaction to its starknet-core representation.
+///
+/// Returns `None` for the v3 transactions, which the starknet-core version we depend on cannot
+/// represent.
 pub fn to_starknet_core_tx(
     tx: super::Transaction,
     transaction_hash: FieldElement,
-) -> starknet_core::types::Transaction {
-    match tx {
+) -> Option<starknet_core::types::Transaction> {
+    let tx = match tx {
         super::Transaction::Declare(tx) => {
             let tx = match tx {
                 super::DeclareTransaction::V0(super::DeclareTransactionV0 {
@@ -60,6 +64,7 @@ pub fn to_starknet_core_tx(
                     sender_address: sender_address.into(),
                     compiled_class_hash: compiled_class_hash.into(),
                 }),
+                super::DeclareTransaction::V3(_) => return None,
             };
 
             starknet_core::types::Transaction::Declare(tx)
@@ -107,6 +112,7 @@ pub fn to_starknet_core_tx(
                     sender_address: sender_address.into(),
                     calldata: cast_vec_of_felt_252_wrappers(calldata),
                 }),
+                super::InvokeTransaction::V3(_) => return None,
             };
 
             starknet_core::types::Transaction::Invoke(tx)
@@ -123,5 +129,8 @@ pub fn to_starknet_core_tx(
 
             starknet_core::types::Transaction::L1Handler(tx)
         }
-    }
+        super::Transaction::DeployAccountV3(_) => return None,
+    };
+
+    Some(tx)
 }
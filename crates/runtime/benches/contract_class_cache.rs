@@ -0,0 +1,35 @@
+//! Compares reading a declared contract class through the decoded class cache of the native
+//! runtime with decoding it from storage.
+use blockifier::execution::contract_class::ContractClass;
+use blockifier::state::state_api::{State, StateReader};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use madara_runtime::Runtime;
+use pallet_starknet::blockifier_state_adapter::BlockifierStateAdapter;
+use pallet_starknet::Pallet;
+use starknet_api::api_core::ClassHash;
+use starknet_api::hash::StarkFelt;
+
+fn contract_class_cache(c: &mut Criterion) {
+    let class_hash = ClassHash(StarkFelt::try_from("0xca11c0de").unwrap());
+    let contract_class = ContractClass::V0(
+        serde_json::from_str(include_str!("../../../cairo-contracts/build/ERC20.json"))
+            .expect("ERC20.json should be a valid contract class"),
+    );
+
+    sp_io::TestExternalities::default().execute_with(|| {
+        let mut state_adapter = BlockifierStateAdapter::<Runtime>::default();
+        state_adapter.set_contract_class(&class_hash, contract_class).unwrap();
+
+        let mut group = c.benchmark_group("contract_class");
+        group.bench_function("decoded", |b| {
+            b.iter(|| Pallet::<Runtime>::contract_class_by_class_hash(black_box(&class_hash)).unwrap())
+        });
+        group.bench_function("cached", |b| {
+            b.iter(|| state_adapter.get_compiled_contract_class(black_box(&class_hash)).unwrap())
+        });
+        group.finish();
+    });
+}
+
+criterion_group!(benches, contract_class_cache);
+criterion_main!(benches);
@@ -35,7 +35,8 @@ use pallet_grandpa::{fg_primitives, AuthorityId as GrandpaId, AuthorityList as G
 pub use pallet_starknet;
 use pallet_starknet::pallet::Error as PalletError;
 use pallet_starknet::runtime_api::StarknetTransactionExecutionError;
-use pallet_starknet::Call::{consume_l1_message, declare, deploy_account, invoke};
+use pallet_starknet::types::SequencerSchedule;
+use pallet_starknet::Call::{consume_l1_message, declare, deploy_account, deploy_account_v3, invoke};
 use pallet_starknet::{Config, Event};
 pub use pallet_timestamp::Call as TimestampCall;
 use sp_api::impl_runtime_apis;
@@ -69,8 +70,11 @@ construct_runtime!(
         Timestamp: pallet_timestamp,
         Aura: pallet_aura,
         Grandpa: pallet_grandpa,
+        // Include the L1 gas price pallet before the Starknet pallet, which uses its price.
+        L1GasPrice: pallet_l1_gas_price,
         // Include Starknet pallet.
         Starknet: pallet_starknet,
+        Sudo: pallet_sudo,
     }
 );
 
@@ -263,7 +267,15 @@ impl_runtime_apis! {
             Starknet::chain_id()
         }
 
-        fn estimate_fee(transaction: UserTransaction) -> Result<(u64, u64), DispatchError> {
+        fn sequencer_schedule() -> SequencerSchedule {
+            Starknet::sequencer_schedule()
+        }
+
+        fn sequencer_for_slot(slot: u64) -> Option<ContractAddress> {
+            Starknet::sequencer_schedule().sequencer_for_slot(slot)
+        }
+
+        fn estimate_fee(transaction: UserTransaction) -> Result<(u128, u64), DispatchError> {
             Starknet::estimate_fee(transaction)
         }
 
@@ -284,6 +296,7 @@ impl_runtime_apis! {
                     RuntimeCall::Starknet( invoke { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( declare { transaction, .. }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( deploy_account { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
+                    RuntimeCall::Starknet( deploy_account_v3 { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( consume_l1_message { transaction, .. }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     _ => return None,
                 };
@@ -297,11 +310,19 @@ impl_runtime_apis! {
                 RuntimeCall::Starknet( invoke { transaction }) => Some(Transaction::Invoke(transaction)),
                 RuntimeCall::Starknet( declare { transaction, .. }) => Some(Transaction::Declare(transaction)),
                 RuntimeCall::Starknet( deploy_account { transaction }) => Some(Transaction::DeployAccount(transaction)),
+                RuntimeCall::Starknet( deploy_account_v3 { transaction }) => Some(Transaction::DeployAccountV3(transaction)),
                 RuntimeCall::Starknet( consume_l1_message { transaction, .. }) => Some(Transaction::L1Handler(transaction)),
                 _ => None
             }).collect::<Vec<Transaction>>()
         }
 
+        fn declared_sierra_classes(xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<(Felt252Wrapper, Vec<u8>)> {
+            xts.into_iter().filter_map(|xt| match xt.function {
+                RuntimeCall::Starknet( declare { transaction, sierra_class: Some(sierra_class), .. }) => Some((*transaction.class_hash(), sierra_class)),
+                _ => None
+            }).collect()
+        }
+
         fn get_events_for_tx_hash(extrinsics: Vec<<Block as BlockT>::Extrinsic>, chain_id: Felt252Wrapper, tx_hash: Felt252Wrapper) -> Option<(TxType, Vec<StarknetEvent>)> {
             // Find our tx and it's index
             let (tx_index, tx) =  extrinsics.into_iter().enumerate().find(|(_, xt)| {
@@ -309,6 +330,7 @@ impl_runtime_apis! {
                     RuntimeCall::Starknet( invoke { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( declare { transaction, .. }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( deploy_account { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
+                    RuntimeCall::Starknet( deploy_account_v3 { transaction }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     RuntimeCall::Starknet( consume_l1_message { transaction, .. }) => transaction.compute_hash::<<Runtime as Config>::SystemHash>(chain_id, false),
                     _ => return false
                 };
@@ -321,6 +343,7 @@ impl_runtime_apis! {
                 RuntimeCall::Starknet( invoke { .. }) => TxType::Invoke,
                 RuntimeCall::Starknet( declare { .. }) => TxType::Declare,
                 RuntimeCall::Starknet( deploy_account { .. }) => TxType::DeployAccount,
+                RuntimeCall::Starknet( deploy_account_v3 { .. }) => TxType::DeployAccount,
                 RuntimeCall::Starknet( consume_l1_message { .. }) => TxType::L1Handler,
                 _ => panic!("The previous match made sure that at this point tx is one of those starknet calls"),
             };
@@ -364,8 +387,8 @@ impl_runtime_apis! {
     impl pallet_starknet::runtime_api::ConvertTransactionRuntimeApi<Block> for Runtime {
         fn convert_transaction(transaction: UserTransaction) -> Result<UncheckedExtrinsic, DispatchError> {
             let call = match transaction {
-                UserTransaction::Declare(tx, contract_class) => {
-                    pallet_starknet::Call::declare { transaction: tx, contract_class  }
+                UserTransaction::Declare(tx, contract_class, sierra_class) => {
+                    pallet_starknet::Call::declare { transaction: tx, contract_class, sierra_class }
                 }
                 UserTransaction::DeployAccount(tx) => {
                     pallet_starknet::Call::deploy_account { transaction: tx  }
@@ -373,6 +396,9 @@ impl_runtime_apis! {
                 UserTransaction::Invoke(tx) => {
                     pallet_starknet::Call::invoke { transaction: tx  }
                 }
+                UserTransaction::DeployAccountV3(tx) => {
+                    pallet_starknet::Call::deploy_account_v3 { transaction: tx  }
+                }
             };
 
             Ok(UncheckedExtrinsic::new_unsigned(call.into()))
@@ -10,6 +10,7 @@ pub use frame_support::weights::constants::{
 pub use frame_support::weights::{IdentityFee, Weight};
 pub use frame_support::{construct_runtime, parameter_types, StorageValue};
 pub use frame_system::Call as SystemCall;
+use frame_system::EnsureRoot;
 pub use mp_chain_id::SN_GOERLI_CHAIN_ID;
 /// Import the StarkNet pallet.
 pub use pallet_starknet;
@@ -47,6 +48,28 @@ impl pallet_starknet::Config for Runtime {
     type ProtocolVersion = ProtocolVersion;
     type ChainId = ChainId;
     type MaxRecursionDepth = MaxRecursionDepth;
+    type L1MessagesConfirmationDepth = L1MessagesConfirmationDepth;
+    type L1MessagesBlockRange = L1MessagesBlockRange;
+    type MaxBlockSteps = MaxBlockSteps;
+    type MaxBlockBuiltins = MaxBlockBuiltins;
+    type MaxBlockL1Gas = MaxBlockL1Gas;
+    type L1GasPrice = L1GasPrice;
+    type SlotDuration = ConstU64<SLOT_DURATION>;
+    type StateTriesHistory = StateTriesHistory;
+    type MigrationEntriesPerBlock = MigrationEntriesPerBlock;
+    type GovernanceOrigin = EnsureRoot<AccountId>;
+}
+
+/// Configure the L1 gas price pallet in pallets/l1-gas-price.
+impl pallet_l1_gas_price::Config for Runtime {}
+
+/// Allows the Starknet pallet offchain worker to submit unsigned transactions.
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+    RuntimeCall: From<C>,
+{
+    type Extrinsic = UncheckedExtrinsic;
+    type OverarchingCall = RuntimeCall;
 }
 
 /// --------------------------------------
@@ -152,6 +175,14 @@ impl pallet_timestamp::Config for Runtime {
     type WeightInfo = ();
 }
 
+/// Dispatches calls as `Root`, the governance origin of the Starknet pallet, on behalf of the sudo
+/// key set in the genesis config.
+impl pallet_sudo::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeCall = RuntimeCall;
+    type WeightInfo = pallet_sudo::weights::SubstrateWeight<Runtime>;
+}
+
 parameter_types! {
     pub const UnsignedPriority: u64 = 1 << 20;
     pub const TransactionLongevity: u64 = u64::MAX;
@@ -160,6 +191,15 @@ parameter_types! {
     pub const ProtocolVersion: u8 = 0;
     pub const ChainId: Felt252Wrapper = SN_GOERLI_CHAIN_ID;
     pub const MaxRecursionDepth: u32 = 50;
+    /// About the number of blocks before an Ethereum block is finalized.
+    pub const L1MessagesConfirmationDepth: u64 = 64;
+    pub const L1MessagesBlockRange: u64 = 1_000;
+    pub const MaxBlockSteps: u64 = 40_000_000;
+    pub const MaxBlockBuiltins: u64 = 10_000_000;
+    pub const MaxBlockL1Gas: u64 = 5_000_000;
+    /// About a day of blocks.
+    pub const StateTriesHistory: u64 = 14_400;
+    pub const MigrationEntriesPerBlock: u32 = 1_000;
 }
 
 /// Implement the OnTimestampSet trait to override the default Aura.
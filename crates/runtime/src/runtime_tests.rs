@@ -4,7 +4,11 @@
 mod tests {
     use std::collections::HashSet;
 
+    use frame_support::traits::GenesisBuild;
+    use pallet_starknet::types::{ProtocolParameter, SequencerSchedule};
     use sp_core::hexdisplay::HexDisplay;
+    use sp_runtime::traits::Dispatchable;
+    use starknet_api::api_core::ContractAddress;
 
     use crate::sp_api_hidden_includes_construct_runtime::hidden_include::traits::WhitelistedStorageKeys;
     use crate::*;
@@ -24,4 +28,65 @@ mod tests {
         // System Events
         assert!(whitelist.contains("26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7"));
     }
+
+    fn new_test_ext(sudo_key: AccountId) -> sp_io::TestExternalities {
+        let mut storage = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+        GenesisBuild::<Runtime>::assimilate_storage(
+            &pallet_sudo::GenesisConfig::<Runtime> { key: Some(sudo_key) },
+            &mut storage,
+        )
+        .unwrap();
+        storage.into()
+    }
+
+    fn set_protocol_parameter_call(parameter: ProtocolParameter) -> RuntimeCall {
+        RuntimeCall::Starknet(pallet_starknet::Call::set_protocol_parameter { parameter })
+    }
+
+    #[test]
+    fn sudo_key_sets_protocol_parameter() {
+        let sudo_key = AccountId::from([1; 32]);
+        new_test_ext(sudo_key.clone()).execute_with(|| {
+            let parameter = ProtocolParameter::MaxRecursionDepth(10);
+            let call = RuntimeCall::Sudo(pallet_sudo::Call::sudo {
+                call: Box::new(set_protocol_parameter_call(parameter.clone())),
+            });
+
+            assert!(call.dispatch(RuntimeOrigin::signed(sudo_key)).is_ok());
+            assert_eq!(Starknet::pending_protocol_parameter_changes(), vec![parameter]);
+        });
+    }
+
+    #[test]
+    fn other_accounts_cannot_set_protocol_parameter() {
+        let other = AccountId::from([2; 32]);
+        new_test_ext(AccountId::from([1; 32])).execute_with(|| {
+            let parameter = ProtocolParameter::MaxRecursionDepth(10);
+            let sudo_call = RuntimeCall::Sudo(pallet_sudo::Call::sudo {
+                call: Box::new(set_protocol_parameter_call(parameter.clone())),
+            });
+
+            assert!(set_protocol_parameter_call(parameter).dispatch(RuntimeOrigin::signed(other.clone())).is_err());
+            assert!(sudo_call.dispatch(RuntimeOrigin::signed(other)).is_err());
+            assert!(Starknet::pending_protocol_parameter_changes().is_empty());
+        });
+    }
+
+    #[test]
+    fn sudo_key_sets_sequencer_schedule() {
+        let sudo_key = AccountId::from([1; 32]);
+        new_test_ext(sudo_key.clone()).execute_with(|| {
+            let schedule = SequencerSchedule { sequencers: vec![ContractAddress::default()], slots_per_sequencer: 1 };
+            let set_schedule_call =
+                RuntimeCall::Starknet(pallet_starknet::Call::set_sequencer_schedule { schedule: schedule.clone() });
+
+            assert!(set_schedule_call.clone().dispatch(RuntimeOrigin::signed(sudo_key.clone())).is_err());
+            assert!(
+                RuntimeCall::Sudo(pallet_sudo::Call::sudo { call: Box::new(set_schedule_call) })
+                    .dispatch(RuntimeOrigin::signed(sudo_key))
+                    .is_ok()
+            );
+            assert_eq!(Starknet::sequencer_schedule(), schedule);
+        });
+    }
 }
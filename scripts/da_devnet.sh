@@ -1,6 +1,7 @@
 #!/bin/bash
 
-# [ethereum, celestia, avail]
+# [ethereum, celestia, avail, file]
+# set DA_SUBMISSION=blob to submit the Ethereum state diffs as EIP-4844 blobs
 DA_LAYER=$1
 MADARA_PATH=$2
 
@@ -22,6 +23,15 @@ if [ "$DA_LAYER" = "ethereum" ]; then
 
     echo -e "\t anvil logs -> target/anvil.log"
     echo -e "\t to kill anvil -> ./target/zaun/scripts/sn-base-kill.sh target"
+
+    # EIP-4844 blobs require anvil to run with `--hardfork cancun`
+    if [ "$DA_SUBMISSION" = "blob" ]; then
+        # Pinned to the release of the c-kzg bindings the DA client is built with
+        curl -sSfL -o $MADARA_PATH/trusted_setup.txt https://raw.githubusercontent.com/ethereum/c-kzg-4844/v0.4.0/src/trusted_setup.txt
+        jq -r '.submission = "blob" | .trusted_setup = "trusted_setup.txt"' $MADARA_PATH/da-config.json > $MADARA_PATH/da-config-tmp.json
+        mv $MADARA_PATH/da-config-tmp.json $MADARA_PATH/da-config.json
+        echo -e "\t state diffs are submitted as blobs"
+    fi
 elif [ "$DA_LAYER" = "celestia" ]; then
     if ! command -v celestia > /dev/null
     then
@@ -70,6 +80,9 @@ elif [ "$DA_LAYER" = "avail" ]; then
     cd ..
 
     sleep 5
+elif [ "$DA_LAYER" = "file" ]; then
+    echo "File DA Test:"
+    echo -e "\t state diffs -> $MADARA_PATH/da"
 fi
 
 echo "Launching Madara with DA $DA_LAYER"